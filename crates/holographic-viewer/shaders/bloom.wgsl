@@ -0,0 +1,75 @@
+// Bloom shader, run once per stage selected by UBO.mode:
+//   0 = bright pass: keep the part of tSrc above `threshold` luminance
+//   1 = horizontal Gaussian blur of tSrc
+//   2 = vertical Gaussian blur of tSrc
+//   3 = composite: tSrc + intensity * tAux (tAux = blurred bright pass)
+// NOTE: Uses textureSampleLevel(..., 0.0) per NonFiltering sampler binding.
+
+struct Uniforms {
+    inv_size:  vec2<f32>,
+    threshold: f32,
+    intensity: f32,
+    mode:      u32,
+    _pad0:     u32,
+    _pad1:     u32,
+    _pad2:     u32,
+};
+
+@group(0) @binding(0) var tSrc: texture_2d<f32>;
+@group(0) @binding(1) var tAux: texture_2d<f32>;
+@group(0) @binding(2) var samp: sampler;
+@group(0) @binding(3) var<uniform> UBO: Uniforms;
+
+struct VSOut {
+    @builtin(position) clip: vec4<f32>,
+    @location(0)      uv:   vec2<f32>,
+};
+
+@vertex
+fn vs_main(@location(0) pos: vec2<f32>) -> VSOut {
+    var out: VSOut;
+    out.clip = vec4<f32>(pos, 0.0, 1.0);
+    // Clip space Y is up, UV space Y is down; flip.
+    out.uv = vec2<f32>(0.5 * (pos.x + 1.0), 0.5 * (-pos.y + 1.0));
+    return out;
+}
+
+// 9-tap Gaussian, sigma = 2 taps, taps spaced 2 px apart for a wider glow.
+fn blur(uv: vec2<f32>, dir: vec2<f32>) -> vec4<f32> {
+    var weights = array<f32, 5>(0.2042, 0.1802, 0.1238, 0.0663, 0.0276);
+    var acc = textureSampleLevel(tSrc, samp, uv, 0.0) * weights[0];
+    for (var i = 1; i < 5; i = i + 1) {
+        let off = dir * f32(i) * 2.0;
+        let lo = clamp(uv - off, vec2<f32>(0.0), vec2<f32>(1.0));
+        let hi = clamp(uv + off, vec2<f32>(0.0), vec2<f32>(1.0));
+        acc += (textureSampleLevel(tSrc, samp, lo, 0.0)
+              + textureSampleLevel(tSrc, samp, hi, 0.0)) * weights[i];
+    }
+    return acc;
+}
+
+@fragment
+fn fs_main(in: VSOut) -> @location(0) vec4<f32> {
+    let uv = clamp(in.uv, vec2<f32>(0.0, 0.0), vec2<f32>(1.0, 1.0));
+
+    switch UBO.mode {
+        case 0u: {
+            let c = textureSampleLevel(tSrc, samp, uv, 0.0).rgb;
+            let lum = dot(c, vec3<f32>(0.2126, 0.7152, 0.0722));
+            // Scale rather than clip so bright pixels keep their hue.
+            let k = max(lum - UBO.threshold, 0.0) / max(lum, 1e-4);
+            return vec4<f32>(c * k, 1.0);
+        }
+        case 1u: {
+            return blur(uv, vec2<f32>(UBO.inv_size.x, 0.0));
+        }
+        case 2u: {
+            return blur(uv, vec2<f32>(0.0, UBO.inv_size.y));
+        }
+        default: {
+            let src = textureSampleLevel(tSrc, samp, uv, 0.0);
+            let glow = textureSampleLevel(tAux, samp, uv, 0.0).rgb;
+            return vec4<f32>(src.rgb + UBO.intensity * glow, src.a);
+        }
+    }
+}
@@ -45,9 +45,6 @@ fn fs_main(in: VSOut) -> @location(0) vec4<f32> {
 
     // Depth texture information
 
-    // Base background colour (dark gray)
-    let bg_base = vec3<f32>(0.01, 0.01, 0.01);
-
     // --------------------------------------------------------------------
     // Scanline effect
     // --------------------------------------------------------------------
@@ -82,7 +79,8 @@ fn fs_main(in: VSOut) -> @location(0) vec4<f32> {
     let bg_mask = max(is_far, is_grid);
 
     // Combine colours
-    let bg_color = (bg_base * 0.85 + grain) * scan_bg;
+    // The background gradient (and grid) come through, dimmed by the scanlines.
+    let bg_color = (src_color * 0.85 + grain) * scan_bg;
     let fg_color = (src_color + grain * 0.4) * scan_fg;
 
     let final_rgb = mix(fg_color, bg_color, bg_mask) * vignette;
@@ -63,6 +63,12 @@ fn fs_main(in: VSOut) -> @location(0) vec4<f32> {
         // Tag: points=white (a>=0.5), grid/background=black
         let v = step(0.5, dl.a);
         return vec4<f32>(v, v, v, 1.0);
+    } else if (U.mode == 4u) {
+        // Tile: per-tile tint from the hologram pass, background dimmed
+        if (dl.r >= 0.9999) {
+            return vec4<f32>(0.05, 0.05, 0.05, 1.0);
+        }
+        return vec4<f32>(c.r, c.g, c.b, 1.0);
     }
 
     // Passthrough
@@ -3,9 +3,21 @@
 // NOTE: preserves incoming alpha so SMC1 labels (in RT0.a) survive the next pass.
 
 struct Uniforms {
-    inv_size  : vec2<f32>,
-    strength  : f32,
-    radius_px : f32,
+    inv_size    : vec2<f32>,
+    strength    : f32,
+    radius_px   : f32,
+    // Linear eye depth (m) from RT1.r: d = proj_b / (z + proj_a),
+    // or d = (proj_b - z) / proj_a for an orthographic projection
+    proj_a      : f32,
+    proj_b      : f32,
+    // Focal length in pixels; pixels per metre when orthographic
+    focal_px    : f32,
+    radius_m    : f32,
+    // 0 = pixel radius on NDC depth, 1 = world radius on linear depth
+    world_space : u32,
+    ortho       : u32,
+    _pad0       : u32,
+    _pad1       : u32,
 }
 
 @group(0) @binding(0) var tColor    : texture_2d<f32>;
@@ -30,6 +42,17 @@ fn vs_main(@location(0) pos : vec2<f32>) -> VSOut {
     return out;
 }
 
+// Depth used for the log comparison: NDC z in pixel mode, eye-space metres in world mode.
+fn edl_depth(z: f32) -> f32 {
+    if (UBO.world_space != 0u) {
+        if (UBO.ortho != 0u) {
+            return (UBO.proj_b - z) / UBO.proj_a;
+        }
+        return UBO.proj_b / (z + UBO.proj_a);
+    }
+    return z;
+}
+
 // Helper: accumulate only from valid neighbor (tag>=0.5 AND z<1)
 fn acc(uv_n: vec2<f32>, lz0: f32) -> f32 {
     let dl = textureSampleLevel(tDepthLin, samp, uv_n, 0.0);
@@ -37,7 +60,7 @@ fn acc(uv_n: vec2<f32>, lz0: f32) -> f32 {
     let a  = dl.a;
     // mask: 1 for real geometry neighbor, else 0
     let m  = select(0.0, 1.0, a >= 0.5 && z < 0.9999);
-    return m * max(0.0, log(z + 1e-6) - lz0);
+    return m * max(0.0, log(edl_depth(z) + 1e-6) - lz0);
 }
 
 @fragment
@@ -60,9 +83,16 @@ fn fs_main(in : VSOut) -> @location(0) vec4<f32> {
     }
 
     let px  = UBO.inv_size;
-    let r   = UBO.radius_px;
     let eps = 1e-6;
-    let lz0 = log(z0 + eps);
+    let d0  = edl_depth(z0);
+    let lz0 = log(d0 + eps);
+
+    // World mode: project the metre radius to pixels at this fragment's depth.
+    var r = UBO.radius_px;
+    if (UBO.world_space != 0u) {
+        let px_per_m = select(UBO.focal_px / max(d0, eps), UBO.focal_px, UBO.ortho != 0u);
+        r = clamp(UBO.radius_m * px_per_m, 0.5, 32.0);
+    }
 
     let offsets = array<vec2<f32>, 8>(
         vec2<f32>( 1.0,  0.0), vec2<f32>(-1.0,  0.0),
@@ -1,6 +1,7 @@
  // HYPC camera-relative point renderer with MRT and billboarding.
  // RT0: color (rgb = base, a = coverage alpha)
  // RT1: depth-linear proxy (r = z_ndc [0..1], g = semantic label / 255, a = tag)
+ // RT2: point ID (r = tile slot + 1, g = instance index) for picking
 
  struct TileUniform {
      delta_hi      : vec3<f32>,
@@ -8,9 +9,15 @@
      delta_lo      : vec3<f32>,
      _pad1         : f32,
      view_proj     : mat4x4<f32>,
+     model         : mat4x4<f32>,
      viewport_size : vec2<f32>,
      point_size_px : f32,
-     _pad2         : f32,
+     // Tile slot + 1, written to RT2.
+     pick_id       : u32,
+     // Per-class point-size multipliers; label l lives at class_size[l / 4][l % 4].
+     class_size    : array<vec4<f32>, 4>,
+     // Per-tile debug tint; a = 1 replaces the base color.
+     tile_color    : vec4<f32>,
  };
 
  @group(0) @binding(0) var<uniform> U : TileUniform;
@@ -21,6 +28,7 @@
      @location(1)       zndc     : f32,
      @location(2)       local_uv : vec2<f32>,
      @location(3)       visible  : u32,
+     @location(4) @interpolate(flat) instance : u32,
  };
 
  @vertex
@@ -28,8 +36,9 @@
      @location(0) corner : vec2<f32>,
      @location(1) ofs_m  : vec3<f32>,
      @location(2) label  : u32,
+     @builtin(instance_index) instance : u32,
  ) -> VSOut {
-     let world_rel   = (U.delta_hi + U.delta_lo) + ofs_m;
+     let world_rel   = (U.delta_hi + U.delta_lo) + (U.model * vec4<f32>(ofs_m, 0.0)).xyz;
      let clip_center = U.view_proj * vec4<f32>(world_rel, 1.0);
 
      // 🚫 Hard-kill billboards whose center is behind the camera.
@@ -41,11 +50,16 @@
          o.zndc     = 1.0;
          o.local_uv = vec2<f32>(2.0, 2.0);
          o.visible  = 0u;
+         o.instance = instance;
          return o;
      }
 
      // Normal billboarding path
-     let point_size_ndc   = (U.point_size_px / U.viewport_size) * 2.0;
+     var size_mult = 1.0;
+     if (label < 16u) {
+         size_mult = U.class_size[label / 4u][label % 4u];
+     }
+     let point_size_ndc   = (U.point_size_px * size_mult / U.viewport_size) * 2.0;
      let perspective_scale = clip_center.w; // w > 0 guaranteed here
      let offset = vec2<f32>(corner.x * point_size_ndc.x,
                             corner.y * point_size_ndc.y) * perspective_scale;
@@ -56,6 +70,7 @@
      o.zndc     = clamp(o.clip.z / o.clip.w, 0.0, 1.0);
      o.local_uv = corner;
      o.visible  = 1u;
+     o.instance = instance;
      return o;
  }
 
@@ -98,6 +113,7 @@
  struct FSOut {
      @location(0) color : vec4<f32>,
      @location(1) dlin  : vec4<f32>,
+     @location(2) pick  : vec2<u32>,
  };
 
  fn base_color(_label : u32) -> vec3<f32> {
@@ -121,11 +137,16 @@
 
      var out : FSOut;
      // Color carries only coverage alpha (for blending-based AA).
-     out.color = vec4<f32>(base_color(in.label), alpha);
+     var rgb = base_color(in.label);
+     if (U.tile_color.a > 0.5) {
+         rgb = U.tile_color.rgb;
+     }
+     out.color = vec4<f32>(rgb, alpha);
      // Depth-linear proxy + semantic label + tag (1 = not grid).
      out.dlin = vec4<f32>(clamp(in.zndc, 0.0, 1.0),
                          f32(in.label) / 255.0,
                          0.0,
                          1.0);
+     out.pick = vec2<u32>(U.pick_id, in.instance);
      return out;
  }
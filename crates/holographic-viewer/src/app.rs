@@ -1,14 +1,31 @@
 use crate::{
-    camera::{Camera, CameraController},
-    data::{point_cloud::load_hypc_tile, types::TileGpu},
-    renderer::Renderer,
+    camera::{self, Camera, CameraController},
+    data::{
+        loader::{DatasetExtent, LoadEvent, TileLoader},
+        point_cloud::{upload_tile, DecodedTile},
+        types::TileGpu,
+        TileLoadStats,
+    },
+    renderer::{
+        picking::{DepthPick, PickPoll, PickedPoint, PointPick},
+        pipelines::post_stack::DEBUG_MODE_TILE,
+        screenshot::{self, PendingScreenshot},
+        Renderer,
+    },
     ui,
 };
-use anyhow::Result;
+use anyhow::{bail, Result};
 use glam::Mat4;
-use std::sync::Arc;
-use walkdir::WalkDir;
-use winit::{event::WindowEvent, window::Window};
+use std::{
+    collections::VecDeque,
+    path::Path,
+    sync::{mpsc, Arc},
+    time::{Duration, Instant},
+};
+use winit::{
+    event::{ElementState, MouseButton, WindowEvent},
+    window::Window,
+};
 
 // --- Geodetic Helpers for Grid Convergence ---
 
@@ -28,6 +45,85 @@ fn meridian_convergence_rad(lat_deg: f64, lon_deg: f64) -> f64 {
     ((lam - lam0).tan() * phi.sin()).atan()
 }
 
+/// Frames averaged for the HUD frame time.
+const FRAME_TIME_WINDOW: usize = 120;
+
+/// Recent frame-to-frame intervals, for the HUD performance readout.
+#[derive(Default)]
+pub struct FrameTimes {
+    last: Option<Instant>,
+    deltas: VecDeque<Duration>,
+}
+
+impl FrameTimes {
+    /// Records the start of a frame.
+    pub fn tick(&mut self) {
+        let now = Instant::now();
+        if let Some(last) = self.last.replace(now) {
+            if self.deltas.len() == FRAME_TIME_WINDOW {
+                self.deltas.pop_front();
+            }
+            self.deltas.push_back(now - last);
+        }
+    }
+
+    /// Frames per second from the latest interval.
+    pub fn fps(&self) -> f64 {
+        self.deltas
+            .back()
+            .map_or(0.0, |d| 1.0 / d.as_secs_f64().max(1e-6))
+    }
+
+    /// Mean interval over the window, in milliseconds.
+    pub fn average_ms(&self) -> f64 {
+        if self.deltas.is_empty() {
+            return 0.0;
+        }
+        let total: Duration = self.deltas.iter().sum();
+        total.as_secs_f64() * 1e3 / self.deltas.len() as f64
+    }
+}
+
+/// Vertical field of view of the perspective projection.
+const FOV_Y: f32 = std::f32::consts::FRAC_PI_3;
+
+/// Depth (meters) below the orbit target that the orthographic view still draws.
+const ORTHO_DEPTH_BELOW_TARGET_M: f32 = 100_000.0;
+
+/// Builds the camera projection. The orthographic box is sized so the orbit
+/// target's surroundings frame as they would in perspective at `radius_m`.
+fn projection(ortho: bool, aspect: f32, radius_m: f64) -> Mat4 {
+    if ortho {
+        let half_h = radius_m as f32 * (0.5 * FOV_Y).tan();
+        let half_w = half_h * aspect;
+        Mat4::orthographic_rh(
+            -half_w,
+            half_w,
+            -half_h,
+            half_h,
+            0.0,
+            radius_m as f32 + ORTHO_DEPTH_BELOW_TARGET_M,
+        )
+    } else {
+        // WebGPU/wgpu uses 0..1 depth; glam::Mat4::perspective_rh is RH, depth in [0,1].
+        Mat4::perspective_rh(FOV_Y, aspect, 10.0, 20_000_000.0)
+    }
+}
+
+/// How far (physical pixels) the cursor may move between press and release
+/// for a left click to pick rather than orbit.
+const CLICK_SLOP_PX: u32 = 4;
+
+/// Smallest and largest point sprite radius, in pixels.
+const MIN_POINT_SIZE_PX: f32 = 0.6;
+const MAX_POINT_SIZE_PX: f32 = 3.0;
+
+/// Time per frame spent uploading freshly decoded tiles.
+const TILE_UPLOAD_BUDGET: Duration = Duration::from_millis(8);
+
+/// JSON file holding the numbered camera poses, in the working directory.
+pub const CAMERA_POSES_PATH: &str = "camera-poses.json";
+
 pub struct App {
     pub renderer: Renderer,
     pub camera: Camera,
@@ -35,6 +131,35 @@ pub struct App {
     pub egui_ctx: egui::Context,
     pub egui_state: egui_winit::State,
     pub tiles: Vec<TileGpu>,
+    /// Outcome counts of the current or last tile load.
+    pub load_stats: TileLoadStats,
+    /// The background load in progress, if any.
+    tile_loader: Option<TileLoader>,
+    /// Union of the semantic classes of the loaded tiles, sorted.
+    pub legend_classes: Vec<u8>,
+    pub frame_times: FrameTimes,
+    /// Set by [`App::request_screenshot`]; the next `render` captures its frame.
+    screenshot_requested: bool,
+    /// A captured frame still being read back.
+    pending_screenshot: Option<PendingScreenshot>,
+    /// Cursor position in physical pixels, while it is over the window.
+    cursor_px: Option<[u32; 2]>,
+    /// The depth texel under the cursor, while it is being read back.
+    pending_pick: Option<DepthPick>,
+    /// Geodetic (lat°, lon°, height m) under the cursor, from the last
+    /// completed pick; `None` over empty space.
+    hover_geodetic: Option<(f64, f64, f64)>,
+    /// Where the left button went down, while it is held.
+    click_start_px: Option<[u32; 2]>,
+    /// A click to pick the point under, on the next `render`.
+    click_requested: Option<[u32; 2]>,
+    /// The point pick of the last click, while it is being read back.
+    pending_point_pick: Option<PointPick>,
+    /// Result of the last completed point pick; `Some(None)` when the click
+    /// hit no point.
+    picked: Option<Option<PickedPoint>>,
+    /// Elevation to restore when leaving the orthographic view, while it is on.
+    elevation_before_ortho: Option<f64>,
 }
 
 impl App {
@@ -42,13 +167,7 @@ impl App {
         let renderer = Renderer::new(window.clone()).await?;
         let size = renderer.gfx.size;
 
-        // WebGPU/wgpu uses 0..1 depth; glam::Mat4::perspective_rh is RH, depth in [0,1].
-        let proj = Mat4::perspective_rh(
-            60f32.to_radians(),
-            size.width as f32 / size.height.max(1) as f32,
-            10.0,
-            20_000_000.0,
-        );
+        let proj = projection(false, size.width as f32 / size.height.max(1) as f32, 5000.0);
 
         // Default camera, orbiting a point over Berlin at a 5km radius.
         let camera = Camera::new(52.52, 13.40, 5000.0, proj);
@@ -70,30 +189,133 @@ impl App {
             egui_ctx,
             egui_state,
             tiles: Vec::new(),
+            load_stats: TileLoadStats::default(),
+            tile_loader: None,
+            legend_classes: Vec::new(),
+            frame_times: FrameTimes::default(),
+            screenshot_requested: false,
+            pending_screenshot: None,
+            cursor_px: None,
+            pending_pick: None,
+            hover_geodetic: None,
+            click_start_px: None,
+            click_requested: None,
+            pending_point_pick: None,
+            picked: None,
+            elevation_before_ortho: None,
         })
     }
 
     pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
         if new_size.width > 0 && new_size.height > 0 {
             self.renderer.resize(new_size);
-            self.camera.proj = Mat4::perspective_rh(
-                // Field of view
-                60f32.to_radians(),
-                // Aspect ratio
-                new_size.width as f32 / new_size.height as f32,
-                // Near plane distance
-                10.0,
-                20_000_000.0,
-            );
+            self.update_projection();
         }
     }
 
+    /// Switches between the perspective and the top-down orthographic view.
+    pub fn toggle_projection(&mut self) {
+        let params = &mut self.renderer.post_stack.params;
+        params.ortho = !params.ortho;
+        log::info!(
+            "Projection: {}",
+            if params.ortho { "orthographic" } else { "perspective" }
+        );
+    }
+
+    /// Rebuilds `camera.proj` for the current mode, window size and orbit radius.
+    /// The orthographic view looks straight down; the elevation it replaced is
+    /// restored when switching back.
+    fn update_projection(&mut self) {
+        let ortho = self.renderer.post_stack.params.ortho;
+        match (ortho, self.elevation_before_ortho) {
+            (true, None) => self.elevation_before_ortho = Some(self.camera.elevation_rad),
+            (false, Some(elevation)) => {
+                self.camera.elevation_rad = elevation;
+                self.elevation_before_ortho = None;
+                self.camera.update();
+            }
+            _ => {}
+        }
+        if ortho && self.camera.elevation_rad != std::f64::consts::FRAC_PI_2 {
+            self.camera.elevation_rad = std::f64::consts::FRAC_PI_2;
+            self.camera.update();
+        }
+
+        let size = self.renderer.gfx.size;
+        let aspect = size.width as f32 / size.height.max(1) as f32;
+        self.camera.proj = projection(ortho, aspect, self.camera.radius_m);
+    }
+
+    /// Saves the camera pose to numbered `slot` of `CAMERA_POSES_PATH`.
+    pub fn save_camera_slot(&self, slot: u8) {
+        let path = Path::new(CAMERA_POSES_PATH);
+        match camera::save_pose_slot(path, slot, self.camera.pose()) {
+            Ok(()) => log::info!("Saved camera to slot {} in {}", slot, path.display()),
+            Err(e) => log::error!("Failed to save camera slot {}: {:#}", slot, e),
+        }
+    }
+
+    /// Restores the camera pose from numbered `slot` of `CAMERA_POSES_PATH`.
+    pub fn load_camera_slot(&mut self, slot: u8) {
+        match camera::load_pose_slot(Path::new(CAMERA_POSES_PATH), slot) {
+            Ok(Some(pose)) => {
+                self.camera.set_pose(pose);
+                log::info!("Restored camera from slot {}", slot);
+            }
+            Ok(None) => log::warn!("Camera slot {} is empty", slot),
+            Err(e) => log::error!("Failed to load camera slot {}: {:#}", slot, e),
+        }
+    }
+
+    /// Saves the next rendered frame as a timestamped PNG. Ignored while an
+    /// earlier capture is still being read back.
+    pub fn request_screenshot(&mut self) {
+        if self.pending_screenshot.is_some() {
+            log::warn!("Screenshot already in progress");
+            return;
+        }
+        self.screenshot_requested = true;
+    }
+
     pub fn handle_event(&mut self, window: &Window, event: &WindowEvent) -> bool {
+        // Track the cursor for the HUD readout even when egui takes the event.
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor_px = Some([position.x.max(0.0) as u32, position.y.max(0.0) as u32]);
+            }
+            WindowEvent::CursorLeft { .. } => {
+                self.cursor_px = None;
+                self.hover_geodetic = None;
+            }
+            _ => {}
+        }
+
         let response = self.egui_state.on_window_event(window, event);
         if response.consumed {
             return true;
         }
 
+        // A left click without a drag picks the point under the cursor.
+        if let WindowEvent::MouseInput {
+            state,
+            button: MouseButton::Left,
+            ..
+        } = event
+        {
+            match state {
+                ElementState::Pressed => self.click_start_px = self.cursor_px,
+                ElementState::Released => {
+                    if let (Some(start), Some(end)) = (self.click_start_px.take(), self.cursor_px) {
+                        let moved = start[0].abs_diff(end[0]).max(start[1].abs_diff(end[1]));
+                        if moved <= CLICK_SLOP_PX {
+                            self.click_requested = Some(end);
+                        }
+                    }
+                }
+            }
+        }
+
         self.camera_controller.handle_event(event, &mut self.camera);
 
         if let WindowEvent::Resized(physical_size) = event {
@@ -103,161 +325,207 @@ impl App {
         false
     }
 
-    pub fn build_all_tiles(&mut self, root: &str) -> Result<()> {
-        let paths: Vec<_> = WalkDir::new(root)
-            .into_iter()
-            .filter_map(Result::ok)
-            .filter(|e| e.path().extension().and_then(|s| s.to_str()) == Some("hypc"))
-            .map(|e| e.path().to_path_buf())
-            .collect();
-
-        if paths.is_empty() {
-            log::warn!("No .hypc files found in '{}'", root);
-            return Ok(());
+    /// Starts loading the `.hypc` file at `root`, or every `.hypc` under it
+    /// when it is a directory, on a background thread; see [`TileLoader`].
+    /// Tiles appear as `render` uploads them. Version-mismatched tiles are
+    /// skipped and counted; truncated or corrupt ones are logged as errors.
+    pub fn start_loading_tiles(&mut self, root: &Path, max_points: Option<u64>) -> Result<()> {
+        if !root.exists() {
+            bail!("tile path '{}' does not exist", root.display());
         }
 
-        log::info!("Loading {} tiles...", paths.len());
-
-        let mut loaded_tiles = Vec::new();
-        let mut total_points: u64 = 0;
-        let mut sum_anchor_w = [0.0f64; 3]; // Σ (anchor_m * weight)
-        let mut sum_w = 0.0f64; // Σ weight
-        let mut anchors_m: Vec<[f64; 3]> = Vec::new();
-        let mut min_upm: u32 = u32::MAX;
-        let mut max_upm: u32 = 0;
-
-        let viewport_size = [
-            self.renderer.gfx.size.width as f32,
-            self.renderer.gfx.size.height as f32,
-        ];
-
-        for path in paths {
-            match load_hypc_tile(
-                &self.renderer.gfx.device,
-                &self.renderer.holo.tile_layout,
-                &self.camera,
-                &path,
-                viewport_size,
-            ) {
-                Ok(tile) => {
-                    // Convert this tile's anchor to meters using ITS UPM.
-                    let upm = tile.units_per_meter as f64;
-
-                    let a_m = [
-                        tile.anchor_units[0] as f64 / upm,
-                        tile.anchor_units[1] as f64 / upm,
-                        tile.anchor_units[2] as f64 / upm,
-                    ];
-
-                    anchors_m.push(a_m);
-
-                    let w = tile.instances_len as f64; // weight by points
+        self.tiles.clear();
+        // Tile slots are about to be reused.
+        self.pending_point_pick = None;
+        self.picked = None;
+        self.legend_classes.clear();
+        self.load_stats = TileLoadStats::default();
+        self.tile_loader = Some(TileLoader::spawn(root.to_path_buf(), max_points)?);
+        Ok(())
+    }
 
-                    sum_anchor_w[0] += a_m[0] * w;
-                    sum_anchor_w[1] += a_m[1] * w;
-                    sum_anchor_w[2] += a_m[2] * w;
+    /// Uploads the tiles the loader has decoded, for at most
+    /// `TILE_UPLOAD_BUDGET` per frame, and wraps up once it is done.
+    fn poll_tile_loader(&mut self) {
+        let Some(mut loader) = self.tile_loader.take() else {
+            return;
+        };
 
-                    sum_w += w;
+        let start = Instant::now();
+        while start.elapsed() < TILE_UPLOAD_BUDGET {
+            match loader.try_recv() {
+                Ok(LoadEvent::Started { total, extent }) => {
+                    log::info!("Loading {} tiles...", total);
+                    if let Some(extent) = extent {
+                        self.frame_dataset(&extent);
+                    }
+                }
+                Ok(LoadEvent::Tile(_, Ok(decoded))) => self.add_tile(*decoded),
+                Ok(LoadEvent::Tile(path, Err(e))) => {
+                    if e.is_skippable() {
+                        log::debug!("Skipping tile {}: {}", path.display(), e);
+                    } else {
+                        log::error!("Failed to load tile {}: {}", path.display(), e);
+                    }
+                    self.load_stats.record(&e);
+                }
+                Err(mpsc::TryRecvError::Empty) => break,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    self.finish_loading();
+                    return;
+                }
+            }
+        }
+        self.tile_loader = Some(loader);
+    }
 
-                    min_upm = min_upm.min(tile.units_per_meter);
-                    max_upm = max_upm.max(tile.units_per_meter);
+    /// Points the camera and grid at a dataset before its tiles arrive.
+    fn frame_dataset(&mut self, extent: &DatasetExtent) {
+        let center_ecef_m = extent.center_ecef_m;
 
-                    total_points += tile.instances_len as u64;
+        // Plausibility check for WGS‑84 ECEF surface vicinity
+        let r = (center_ecef_m[0] * center_ecef_m[0]
+            + center_ecef_m[1] * center_ecef_m[1]
+            + center_ecef_m[2] * center_ecef_m[2])
+            .sqrt();
 
-                    log::debug!(
-                        "Tile {:?}: upm={}, anchor_ecef_m=({:.3},{:.3},{:.3}), points={}",
-                        tile.key
-                            .map(|k| String::from_utf8_lossy(&k).trim_end_matches('\0').to_string()),
-                        tile.units_per_meter,
-                        a_m[0],
-                        a_m[1],
-                        a_m[2],
-                        tile.instances_len
-                    );
+        // Accept only ~6.2–6.5 Mm (allows terrain + a few km)
+        let plausible = (6_200_000.0..=6_500_000.0).contains(&r);
 
-                    loaded_tiles.push(tile);
-                }
-                Err(e) => {
-                    log::error!("Failed to load tile {}: {}", path.display(), e);
-                }
-            }
+        if !plausible {
+            log::warn!(
+                "Anchor centroid radius {:.3} Mm not plausible for WGS‑84; skipping recenter. \
+                 (Check input CS and HYPC anchors.)",
+                r * 1e-6
+            );
         }
 
-        if !loaded_tiles.is_empty() && sum_w > 0.0 {
-            // Weighted centroid in "claimed ECEF" meters
-            let center_ecef_m = [
-                sum_anchor_w[0] / sum_w,
-                sum_anchor_w[1] / sum_w,
-                sum_anchor_w[2] / sum_w,
-            ];
+        // Choose a starting orbit radius to fit the dataset in view from an angle.
+        let start_radius_m = (extent.radius_m * 2.0).clamp(100.0, 50_000.0);
 
-            // Plausibility check for WGS‑84 ECEF surface vicinity
-            let r = (center_ecef_m[0] * center_ecef_m[0]
-                + center_ecef_m[1] * center_ecef_m[1]
-                + center_ecef_m[2] * center_ecef_m[2])
-                .sqrt();
+        if plausible {
+            self.camera
+                .set_target_and_radius(center_ecef_m, start_radius_m);
+        }
 
-            // Accept only ~6.2–6.5 Mm (allows terrain + a few km)
-            let plausible = (6_200_000.0..=6_500_000.0).contains(&r);
+        // Propagate grid world anchor so the grid is stable in EN.
+        self.renderer.grid.set_origin(center_ecef_m);
+
+        let (lat, lon, _) =
+            hypc::ecef_to_geodetic(center_ecef_m[0], center_ecef_m[1], center_ecef_m[2]);
+
+        log::info!(
+            "Dataset center ECEF(m)=({:.3},{:.3},{:.3}) -> geodetic ({:.6}°, {:.6}°). \
+             Anchor spread radius ~{:.1} m. Start orbit radius set to {:.1} m. \
+             UPM range [{}..{}].",
+            center_ecef_m[0],
+            center_ecef_m[1],
+            center_ecef_m[2],
+            lat,
+            lon,
+            extent.radius_m,
+            start_radius_m,
+            extent.min_upm,
+            extent.max_upm
+        );
+    }
 
-            if !plausible {
-                log::warn!(
-                    "Anchor centroid radius {:.3} Mm not plausible for WGS‑84; skipping recenter. \
-                     (Check input CS and HYPC anchors.)",
-                    r * 1e-6
-                );
-            }
+    /// Uploads a decoded tile and adds it to the scene and the legend.
+    fn add_tile(&mut self, decoded: DecodedTile) {
+        let viewport_size = [
+            self.renderer.gfx.size.width as f32,
+            self.renderer.gfx.size.height as f32,
+        ];
+        let tile = upload_tile(
+            &self.renderer.gfx.device,
+            &self.renderer.holo.tile_layout,
+            &self.camera,
+            decoded,
+            viewport_size,
+        );
 
-            // Approximate dataset radius from anchor spread
-            let mut r2_max = 0.0f64;
-            for a in &anchors_m {
-                let dx = a[0] - center_ecef_m[0];
-                let dy = a[1] - center_ecef_m[1];
-                let dz = a[2] - center_ecef_m[2];
-                r2_max = r2_max.max(dx * dx + dy * dy + dz * dz);
-            }
-            let radius_m = r2_max.sqrt();
+        let upm = tile.units_per_meter as f64;
+        log::debug!(
+            "Tile {}: upm={}, anchor_ecef_m=({:.3},{:.3},{:.3}), points={}",
+            tile.name(),
+            tile.units_per_meter,
+            tile.anchor_units[0] as f64 / upm,
+            tile.anchor_units[1] as f64 / upm,
+            tile.anchor_units[2] as f64 / upm,
+            tile.instances_len
+        );
 
-            // Choose a starting orbit radius to fit the dataset in view from an angle.
-            let start_radius_m = (radius_m * 2.0).clamp(100.0, 50_000.0);
+        self.legend_classes.extend_from_slice(&tile.classes);
+        self.legend_classes.sort_unstable();
+        self.legend_classes.dedup();
 
-            if plausible {
-                self.camera
-                    .set_target_and_radius(center_ecef_m, start_radius_m);
-            }
+        self.tiles.push(tile);
+        self.load_stats.loaded += 1;
+    }
 
-            // Propagate grid world anchor so the grid is stable in EN.
-            self.renderer.grid.set_origin(center_ecef_m);
+    /// Logs the outcome of a finished load.
+    fn finish_loading(&self) {
+        let stats = self.load_stats;
+        let total_points: u64 = self.tiles.iter().map(|t| t.instances_len as u64).sum();
+        log::info!("Loaded {} tiles | points={}.", stats.loaded, total_points);
 
-            log::info!(
-                "Loaded {} tiles | points={} | UPM range [{}..{}].",
-                loaded_tiles.len(),
-                total_points,
-                min_upm,
-                max_upm
+        if stats.skipped_version > 0 {
+            log::warn!(
+                "Skipped {} tiles with an unsupported HYPC version.",
+                stats.skipped_version
             );
-
-            let (lat, lon, _) =
-                hypc::ecef_to_geodetic(center_ecef_m[0], center_ecef_m[1], center_ecef_m[2]);
-
-            log::info!(
-                "Dataset center ECEF(m)=({:.3},{:.3},{:.3}) -> geodetic ({:.6}°, {:.6}°). \
-                 Anchor spread radius ~{:.1} m. Start orbit radius set to {:.1} m.",
-                center_ecef_m[0],
-                center_ecef_m[1],
-                center_ecef_m[2],
-                lat,
-                lon,
-                radius_m,
-                start_radius_m
+        }
+        if stats.failed() > 0 {
+            log::error!(
+                "{} tiles failed to load: {} truncated, {} corrupt, {} I/O errors.",
+                stats.failed(),
+                stats.truncated,
+                stats.corrupt,
+                stats.io
             );
         }
-
-        self.tiles = loaded_tiles;
-        Ok(())
     }
 
     pub fn render(&mut self, window: &Window) -> Result<(), wgpu::SurfaceError> {
+        self.frame_times.tick();
+        self.poll_tile_loader();
+        // Ortho extents follow the orbit radius, so rebuild after zooming.
+        self.update_projection();
+
+        if let Some(pending) = self.pending_screenshot.take() {
+            self.pending_screenshot = pending.poll(&self.renderer.gfx.device);
+        }
+        if let Some(pick) = &self.pending_pick {
+            if let Some(hit) = pick.poll(&self.renderer.gfx.device) {
+                self.hover_geodetic = hit.map(|p| hypc::ecef_to_geodetic(p[0], p[1], p[2]));
+                self.pending_pick = None;
+            }
+        }
+        if let Some(pick) = self.pending_point_pick.take() {
+            let gfx = &self.renderer.gfx;
+            match pick.poll(&gfx.device, &gfx.queue, &self.tiles) {
+                PickPoll::InFlight(pick) => self.pending_point_pick = Some(pick),
+                PickPoll::Done(picked) => {
+                    match &picked {
+                        Some(p) => log::info!(
+                            "Picked label {} in tile {} at ECEF ({:.3}, {:.3}, {:.3}) m, \
+                             ({:.7}°, {:.7}°, {:.2} m)",
+                            p.label,
+                            p.tile_name,
+                            p.ecef_m[0],
+                            p.ecef_m[1],
+                            p.ecef_m[2],
+                            p.geodetic.0,
+                            p.geodetic.1,
+                            p.geodetic.2
+                        ),
+                        None => log::info!("Picked nothing"),
+                    }
+                    self.picked = Some(picked);
+                }
+            }
+        }
+
         let frame = self.renderer.gfx.surface.get_current_texture()?;
         let swap_view = frame
             .texture
@@ -268,22 +536,33 @@ impl App {
             self.renderer.gfx.size.height as f32,
         ];
 
-        // Dynamically adjust point size based on altitude - larger points at lower altitudes
-        const MAX_POINT_SIZE: f32 = 3.0;
-        const MIN_POINT_SIZE: f32 = 0.6;
-        const MAX_ALT_M: f32 = 1000.0;
-        let clamped_alt = (self.camera.h_m as f32).clamp(1.0, MAX_ALT_M);
-
-        // Normalize altitude to [0, 1] where 1.0 is max altitude
-        let normalized_alt = (clamped_alt.ln() / MAX_ALT_M.ln()).clamp(0.0, 1.0);
-
-        // Inverse relationship: point size decreases as altitude increases
-        // At normalized_alt = 0 (low altitude), point_size = MAX_POINT_SIZE
-        // At normalized_alt = 1 (high altitude), point_size = MIN_POINT_SIZE
-        let point_size = MAX_POINT_SIZE - normalized_alt * (MAX_POINT_SIZE - MIN_POINT_SIZE);
+        // Skip tiles whose bounding sphere lies outside the view frustum.
+        let visible_slots: Vec<usize> = (0..self.tiles.len())
+            .filter(|&slot| {
+                let t = &self.tiles[slot];
+                self.camera
+                    .sphere_in_frustum(t.bounds_center_m, t.bounds_radius_m)
+            })
+            .collect();
+        let visible: Vec<&TileGpu> = visible_slots.iter().map(|&slot| &self.tiles[slot]).collect();
 
-        for tile in &self.tiles {
-            let ubo_data = tile.make_uniform(&self.camera, viewport_size, point_size);
+        let params = &self.renderer.post_stack.params;
+        let tile_tint = params.debug_mode == DEBUG_MODE_TILE;
+        for (&slot, tile) in visible_slots.iter().zip(&visible) {
+            let point_size = tile.point_size_px(
+                &self.camera,
+                viewport_size[1],
+                params.ortho,
+                params.point_size_bias,
+            );
+            let mut ubo_data = tile.make_uniform(
+                &self.camera,
+                viewport_size,
+                point_size,
+                &params.class_point_size,
+                tile_tint,
+            );
+            ubo_data.pick_id = slot as u32 + 1;
 
             self.renderer
                 .gfx
@@ -291,13 +570,37 @@ impl App {
                 .write_buffer(&tile.ubo, 0, bytemuck::bytes_of(&ubo_data));
         }
 
-        self.renderer.render(&swap_view, &self.tiles, &self.camera);
+        // Footprints ignore culling: a misplaced tile's points and bbox can
+        // be far apart.
+        if self.renderer.post_stack.params.tile_bounds_on {
+            self.renderer.tile_bounds.prepare(
+                &self.renderer.gfx.device,
+                &self.renderer.gfx.queue,
+                &self.tiles,
+                &self.camera,
+            );
+        }
+
+        self.renderer.render(&swap_view, &visible, &self.camera);
+        let hover = self.cursor_px.map(|_| self.hover_geodetic);
 
         let total_points = self.tiles.iter().map(|t| t.instances_len).sum();
         let egui_input = self.egui_state.take_egui_input(window);
         self.egui_ctx.begin_frame(egui_input);
 
-        ui::draw_hud(&self.egui_ctx, self.camera.h_m as i32, total_points);
+        ui::draw_hud(
+            &self.egui_ctx,
+            self.camera.h_m as i32,
+            total_points,
+            (visible.len(), self.tiles.len()),
+            self.tile_loader.as_ref().map(TileLoader::progress),
+            hover,
+            (self.frame_times.fps(), self.frame_times.average_ms()),
+        );
+
+        if let Some(picked) = &self.picked {
+            ui::draw_pick_panel(&self.egui_ctx, picked.as_ref());
+        }
 
         if true {
             let gamma_deg =
@@ -310,6 +613,12 @@ impl App {
             );
         }
 
+        if self.renderer.post_stack.params.legend_on {
+            ui::draw_class_legend(&self.egui_ctx, &self.legend_classes);
+        }
+
+        ui::draw_tile_panel(&self.egui_ctx, &mut self.tiles);
+
         let egui_output = self.egui_ctx.end_frame();
         let shapes = self
             .egui_ctx
@@ -360,7 +669,7 @@ impl App {
                     },
                 })],
                 depth_stencil_attachment: None,
-                timestamp_writes: None,
+                timestamp_writes: self.renderer.pass_timestamps("egui"),
                 occlusion_query_set: None,
             });
 
@@ -373,10 +682,70 @@ impl App {
             self.renderer.egui_renderer.free_texture(id);
         }
 
+        // Read back the depth under the cursor, one pick in flight at a time.
+        let mut picked = None;
+        if let (Some(pixel), None) = (self.cursor_px, &self.pending_pick) {
+            picked = DepthPick::capture(
+                &self.renderer.gfx.device,
+                &mut encoder,
+                &self.renderer.targets.depth_tex,
+                pixel,
+                &self.camera,
+            );
+        }
+
+        let mut point_picked = None;
+        if let Some(pixel) = self.click_requested.take() {
+            if self.pending_point_pick.is_some() {
+                log::debug!("Point pick already in progress; ignoring click");
+            } else {
+                point_picked = PointPick::capture(
+                    &self.renderer.gfx.device,
+                    &mut encoder,
+                    &self.renderer.targets.pick_tex,
+                    pixel,
+                );
+            }
+        }
+
+        let mut captured = None;
+        if std::mem::take(&mut self.screenshot_requested) {
+            match PendingScreenshot::capture(
+                &self.renderer.gfx.device,
+                &mut encoder,
+                &frame.texture,
+                screenshot::timestamped_path(),
+            ) {
+                Ok(pending) => captured = Some(pending),
+                Err(e) => log::error!("Screenshot failed: {:#}", e),
+            }
+        }
+
         self.renderer
             .gfx
             .queue
             .submit(std::iter::once(encoder.finish()));
+
+        if let Some(mut pick) = picked {
+            pick.map();
+            self.pending_pick = Some(pick);
+        }
+        if let Some(mut pick) = point_picked {
+            pick.map();
+            self.pending_point_pick = Some(pick);
+        }
+        if let Some(mut pending) = captured {
+            pending.map();
+            self.pending_screenshot = Some(pending);
+        }
+
+        if let Some(profiler) = &self.renderer.profiler {
+            if let Err(err) =
+                profiler.end_frame(&self.renderer.gfx.device, &self.renderer.gfx.queue)
+            {
+                log::error!("GPU profiling failed: {:#}", err);
+            }
+        }
         frame.present();
 
         Ok(())
@@ -384,17 +753,51 @@ impl App {
 }
 
 impl TileGpu {
+    /// Sprite radius (pixels) that makes the points about as wide on screen
+    /// as their mean spacing, times `bias`, at the tile's nearest distance
+    /// from the camera.
+    pub fn point_size_px(
+        &self,
+        cam: &Camera,
+        viewport_height_px: f32,
+        ortho: bool,
+        bias: f32,
+    ) -> f32 {
+        // Pixels per meter at unit distance; at any distance when orthographic.
+        let mut px_per_m = cam.proj.y_axis.y as f64 * 0.5 * viewport_height_px as f64;
+        if !ortho {
+            let c = cam.ecef_m();
+            let d: f64 = (0..3)
+                .map(|k| (self.bounds_center_m[k] - c[k]).powi(2))
+                .sum::<f64>()
+                .sqrt();
+            px_per_m /= (d - self.bounds_radius_m).max(1.0);
+        }
+        // The sprite size is a radius: half the spacing.
+        let size = (0.5 * self.point_spacing_m * px_per_m) as f32 * bias;
+        size.clamp(MIN_POINT_SIZE_PX, MAX_POINT_SIZE_PX)
+    }
+
     pub fn make_uniform(
         &self,
         cam: &Camera,
         viewport_size: [f32; 2],
         point_size_px: f32,
+        class_point_size: &[f32; crate::data::types::CLASS_SIZE_SLOTS],
+        tile_tint: bool,
     ) -> crate::data::types::TileUniformStd140 {
-        cam.make_tile_uniform(
+        let mut ubo = cam.make_tile_uniform(
             self.anchor_units,
             self.units_per_meter,
+            self.transform.as_ref(),
             viewport_size,
             point_size_px,
-        )
+            class_point_size,
+        );
+        if tile_tint {
+            let [r, g, b] = self.debug_color;
+            ubo.tile_color = [r, g, b, 1.0];
+        }
+        ubo
     }
 }
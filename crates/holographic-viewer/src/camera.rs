@@ -1,7 +1,16 @@
-use crate::data::types::TileUniformStd140 as TileUniform;
-use glam::{DMat3, DVec3, Mat3, Mat4, Vec3};
-use hypc::{ecef_to_geodetic, geodetic_to_ecef, split_f64_to_f32_pair};
-use winit::event::{ElementState, MouseButton, MouseScrollDelta, WindowEvent};
+use crate::data::types::{TileUniformStd140 as TileUniform, CLASS_SIZE_SLOTS};
+use anyhow::{Context, Result};
+use glam::{DMat3, DVec3, DVec4, Mat3, Mat4, Vec3};
+use hypc::{
+    ecef_to_enu_matrix, ecef_to_geodetic, geodetic_to_ecef, split_f64_to_f32_pair, wgs84,
+    GeoExtentQ7, RigidTransform,
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, path::Path};
+use winit::{
+    event::{ElementState, KeyEvent, MouseButton, MouseScrollDelta, WindowEvent},
+    keyboard::{KeyCode, PhysicalKey},
+};
 
 /// This matrix converts clip-space coordinates from OpenGL conventions (Y-up, Z in [-1, 1])
 /// to WebGPU conventions (Y-down, Z in [0, 1]).
@@ -13,6 +22,69 @@ pub const OPENGL_TO_WGPU_MATRIX: Mat4 = Mat4::from_cols_array(&[
     0.0,  0.0, 0.5, 1.0,
 ]);
 
+/// Farthest ground distance (meters) considered by `Camera::ground_footprint`; rays
+/// that reach the horizon or beyond are clamped to this range.
+pub const FOOTPRINT_MAX_RANGE_M: f64 = 20_000.0;
+
+/// Rays per axis sampled across the view when estimating the ground footprint.
+const FOOTPRINT_SAMPLES: usize = 5;
+
+/// Default keyboard pan distance per key press or repeat, as a fraction of the orbit radius.
+pub const DEFAULT_PAN_STEP: f64 = 0.05;
+
+/// Pan keys and the (right, forward, up) direction each contributes.
+const PAN_KEYS: [(KeyCode, [f64; 3]); 6] = [
+    (KeyCode::KeyW, [0.0, 1.0, 0.0]),
+    (KeyCode::KeyS, [0.0, -1.0, 0.0]),
+    (KeyCode::KeyD, [1.0, 0.0, 0.0]),
+    (KeyCode::KeyA, [-1.0, 0.0, 0.0]),
+    (KeyCode::KeyE, [0.0, 0.0, 1.0]),
+    (KeyCode::KeyQ, [0.0, 0.0, -1.0]),
+];
+
+/// Rotation from the local ENU frame at `ecef` to ECEF; columns are east, north, up.
+fn enu_to_ecef_at(ecef: DVec3) -> DMat3 {
+    let (lat, lon, _) = ecef_to_geodetic(ecef.x, ecef.y, ecef.z);
+    // The rows of the ECEF -> ENU rotation become columns: its transpose.
+    DMat3::from_cols_array_2d(&ecef_to_enu_matrix(lat, lon))
+}
+
+/// The orbit parameters of a [`Camera`], which determine everything else about it
+/// except the projection.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CameraPose {
+    pub target_ecef: [f64; 3],
+    pub radius_m: f64,
+    pub azimuth_rad: f64,
+    pub elevation_rad: f64,
+}
+
+/// Numbered camera poses as stored on disk, keyed by slot.
+type CameraPoseSlots = BTreeMap<u8, CameraPose>;
+
+/// Reads the pose saved in `slot` of the JSON file at `path`, if any. A
+/// missing file holds no poses.
+pub fn load_pose_slot(path: &Path, slot: u8) -> Result<Option<CameraPose>> {
+    Ok(read_pose_slots(path)?.get(&slot).copied())
+}
+
+/// Stores `pose` in `slot` of the JSON file at `path`, keeping the other slots.
+pub fn save_pose_slot(path: &Path, slot: u8, pose: CameraPose) -> Result<()> {
+    let mut slots = read_pose_slots(path)?;
+    slots.insert(slot, pose);
+    let json = serde_json::to_string_pretty(&slots)?;
+    std::fs::write(path, json).with_context(|| format!("writing {}", path.display()))
+}
+
+fn read_pose_slots(path: &Path) -> Result<CameraPoseSlots> {
+    if !path.exists() {
+        return Ok(CameraPoseSlots::new());
+    }
+    let json =
+        std::fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+    serde_json::from_str(&json).with_context(|| format!("parsing {}", path.display()))
+}
+
 #[derive(Debug, Clone)]
 pub struct Camera {
     // --- Orbital Parameters (Primary State) ---
@@ -63,17 +135,8 @@ impl Camera {
     /// Recalculates the camera's ECEF position and geodetic coordinates from its
     /// orbital parameters. This must be called after any orbital parameter changes.
     pub fn update(&mut self) {
-        // 1. Get the geodetic coordinates of the target to define its local tangent plane.
-        let (target_lat, target_lon, _) =
-            ecef_to_geodetic(self.target_ecef.x, self.target_ecef.y, self.target_ecef.z);
-        let (sin_lat, cos_lat) = target_lat.to_radians().sin_cos();
-        let (sin_lon, cos_lon) = target_lon.to_radians().sin_cos();
-
-        // 2. Create the rotation matrix from the local ENU frame at the target back to ECEF.
-        let east = DVec3::new(-sin_lon, cos_lon, 0.0);
-        let north = DVec3::new(-sin_lat * cos_lon, -sin_lat * sin_lon, cos_lat);
-        let up = DVec3::new(cos_lat * cos_lon, cos_lat * sin_lon, sin_lat);
-        let enu_to_ecef = DMat3::from_cols(east, north, up);
+        // 1-2. The rotation from the local ENU frame at the target back to ECEF.
+        let enu_to_ecef = enu_to_ecef_at(self.target_ecef);
 
         // 3. Calculate the camera's offset from the target in the local ENU frame
         //    using spherical coordinates (azimuth, elevation, radius).
@@ -104,6 +167,41 @@ impl Camera {
         self.update();
     }
 
+    /// Returns the orbit parameters, for saving the viewpoint.
+    pub fn pose(&self) -> CameraPose {
+        CameraPose {
+            target_ecef: self.target_ecef.into(),
+            radius_m: self.radius_m,
+            azimuth_rad: self.azimuth_rad,
+            elevation_rad: self.elevation_rad,
+        }
+    }
+
+    /// Restores orbit parameters saved by [`Camera::pose`], then updates the
+    /// camera state. The projection is left as is.
+    pub fn set_pose(&mut self, pose: CameraPose) {
+        self.target_ecef = DVec3::from(pose.target_ecef);
+        self.radius_m = pose.radius_m;
+        self.azimuth_rad = pose.azimuth_rad;
+        self.elevation_rad = pose.elevation_rad;
+        self.update();
+    }
+
+    /// Moves the orbit target by `right_m`/`forward_m` along the ground, relative
+    /// to the view heading, and `up_m` along the local vertical, then updates
+    /// the camera state.
+    pub fn pan(&mut self, right_m: f64, forward_m: f64, up_m: f64) {
+        // The camera sits at (sin az, cos az) from the target in EN, so it
+        // looks along the opposite heading.
+        let (sin_az, cos_az) = self.azimuth_rad.sin_cos();
+        let forward = DVec3::new(-sin_az, -cos_az, 0.0);
+        let right = DVec3::new(forward.y, -forward.x, 0.0);
+        let offset_enu = right * right_m + forward * forward_m + DVec3::Z * up_m;
+
+        self.target_ecef += enu_to_ecef_at(self.target_ecef) * offset_enu;
+        self.update();
+    }
+
     /// Returns camera position in ECEF meters.
     #[inline]
     pub fn ecef_m(&self) -> [f64; 3] {
@@ -112,25 +210,9 @@ impl Camera {
 
     /// Returns rotation matrix from ECEF to ENU for the camera position.
     pub fn ecef_to_enu_matrix(&self) -> Mat3 {
-        let lat_rad = self.lat_deg.to_radians();
-        let lon_rad = self.lon_deg.to_radians();
-        let (sin_lat, cos_lat) = lat_rad.sin_cos();
-        let (sin_lon, cos_lon) = lon_rad.sin_cos();
-
-        // East, North, Up basis vectors.
-        let east = Vec3::new(-sin_lon as f32, cos_lon as f32, 0.0);
-        let north = Vec3::new(
-            (-sin_lat * cos_lon) as f32,
-            (-sin_lat * sin_lon) as f32,
-            cos_lat as f32,
-        );
-        let up = Vec3::new(
-            (cos_lat * cos_lon) as f32,
-            (cos_lat * sin_lon) as f32,
-            sin_lat as f32,
-        );
-
-        Mat3::from_cols(east, north, up).transpose()
+        DMat3::from_cols_array_2d(&ecef_to_enu_matrix(self.lat_deg, self.lon_deg))
+            .transpose()
+            .as_mat3()
     }
 
     /// Returns combined view‑projection matrix in ECEF meters.
@@ -153,8 +235,17 @@ impl Camera {
         let world_up = Vec3::new(cos_lat * cos_lon, cos_lat * sin_lon, sin_lat);
 
         // The "side" vector is orthogonal to forward and world_up.
-        // f.cross(world_up) gives the "left" vector.
-        let s = f.cross(world_up).normalize();
+        // f.cross(world_up) gives the "left" vector. Looking straight down the
+        // two are parallel; take the side from the azimuth's horizontal heading
+        // instead, which is the limit of the general case.
+        let side = f.cross(world_up);
+        let s = if side.length_squared() > 1e-6 {
+            side.normalize()
+        } else {
+            let (sin_az, cos_az) = self.azimuth_rad.sin_cos();
+            let heading = enu_to_ecef_at(self.target_ecef) * DVec3::new(-sin_az, -cos_az, 0.0);
+            heading.as_vec3().cross(world_up).normalize()
+        };
 
         // The camera's local "up" vector is orthogonal to the side and forward vectors.
         // s.cross(f) gives the "down" vector.
@@ -167,25 +258,125 @@ impl Camera {
         Mat4::from_mat3(rot_mat)
     }
 
+    /// Returns whether a sphere (absolute ECEF meters) intersects the view frustum.
+    ///
+    /// The sphere is tested against the six clip planes of `view_proj_ecef`, taken
+    /// camera-relative in f64 so distant tiles keep their precision. Conservative:
+    /// a sphere outside the frustum but straddling two planes near a corner is kept.
+    pub fn sphere_in_frustum(&self, center_ecef_m: [f64; 3], radius_m: f64) -> bool {
+        let m = self.view_proj_ecef().as_dmat4();
+        let c = DVec3::from(center_ecef_m) - self.position_ecef;
+        let (x, y, z, w) = (m.row(0), m.row(1), m.row(2), m.row(3));
+
+        // WebGPU clip space: -w <= x, y <= w and 0 <= z <= w.
+        [w + x, w - x, w + y, w - y, z, w - z].into_iter().all(|plane| {
+            let n = plane.truncate();
+            n.dot(c) + plane.w >= -radius_m * n.length()
+        })
+    }
+
+    /// Returns the approximate lon/lat box of the ground visible in the view frustum.
+    ///
+    /// A grid of view rays is intersected with the WGS84 ellipsoid; rays that miss it
+    /// or hit beyond `FOOTPRINT_MAX_RANGE_M` (looking at the horizon) are clamped to that
+    /// range. Returns `None` when no ray reaches the ground, i.e. the camera is
+    /// pointing at the sky.
+    pub fn ground_footprint(&self) -> Option<GeoExtentQ7> {
+        // The view matrix is rotation-only, so unprojected points are camera-relative.
+        let inv_view_proj = (self.proj * self.view_ecef()).as_dmat4().inverse();
+        let origin = self.position_ecef;
+
+        // Work in a frame where the ellipsoid is the unit sphere.
+        let b = wgs84::A * (1.0 - wgs84::F);
+        let to_unit = DVec3::new(1.0 / wgs84::A, 1.0 / wgs84::A, 1.0 / b);
+        let o = origin * to_unit;
+
+        let mut any_hit = false;
+        let (mut lon_min, mut lon_max) = (f64::INFINITY, f64::NEG_INFINITY);
+        let (mut lat_min, mut lat_max) = (f64::INFINITY, f64::NEG_INFINITY);
+
+        for iy in 0..FOOTPRINT_SAMPLES {
+            for ix in 0..FOOTPRINT_SAMPLES {
+                let step = 2.0 / (FOOTPRINT_SAMPLES - 1) as f64;
+                let ndc = DVec4::new(-1.0 + ix as f64 * step, -1.0 + iy as f64 * step, 0.0, 1.0);
+                let near = inv_view_proj * ndc;
+                let dir = (near.truncate() / near.w).normalize();
+
+                // Nearest intersection with the unit sphere along o + t*d.
+                let d = dir * to_unit;
+                let (qa, qb, qc) = (d.dot(d), 2.0 * o.dot(d), o.dot(o) - 1.0);
+                let disc = qb * qb - 4.0 * qa * qc;
+                let hit_t = (disc >= 0.0)
+                    .then(|| (-qb - disc.sqrt()) / (2.0 * qa))
+                    .filter(|&t| t > 0.0);
+
+                let t = match hit_t {
+                    Some(t) => {
+                        any_hit = true;
+                        t.min(FOOTPRINT_MAX_RANGE_M)
+                    }
+                    None => FOOTPRINT_MAX_RANGE_M,
+                };
+
+                let p = origin + dir * t;
+                let (lat, lon, _) = ecef_to_geodetic(p.x, p.y, p.z);
+
+                // Unwrap longitude around the camera so boxes near the antimeridian stay compact.
+                let lon = self.lon_deg + (lon - self.lon_deg + 540.0).rem_euclid(360.0) - 180.0;
+                lon_min = lon_min.min(lon);
+                lon_max = lon_max.max(lon);
+                lat_min = lat_min.min(lat);
+                lat_max = lat_max.max(lat);
+            }
+        }
+
+        any_hit.then(|| {
+            GeoExtentQ7::from_deg(
+                lon_min.max(-180.0),
+                lon_max.min(180.0),
+                lat_min.max(-90.0),
+                lat_max.min(90.0),
+            )
+        })
+    }
+
     /// Builds a per‑tile uniform buffer.
     pub fn make_tile_uniform(
         &self,
         tile_anchor_units: [i64; 3],
         units_per_meter: u32,
+        transform: Option<&RigidTransform>,
         viewport_size: [f32; 2],
         point_size_px: f32,
+        class_point_size: &[f32; CLASS_SIZE_SLOTS],
     ) -> TileUniform {
         // Camera position in ECEF (meters).
         let cam_ecef = self.ecef_m();
 
         // Convert tile anchor from integer units to meters.
         let upm = units_per_meter as f64;
-        let anchor_m = [
+        let mut anchor_m = [
             tile_anchor_units[0] as f64 / upm,
             tile_anchor_units[1] as f64 / upm,
             tile_anchor_units[2] as f64 / upm,
         ];
 
+        // The tile correction's translation moves the anchor (in f64); its rotation
+        // is applied to the small per-point offsets on the GPU.
+        let mut model = Mat4::IDENTITY;
+        if let Some(xf) = transform {
+            for (a, t) in anchor_m.iter_mut().zip(xf.translation_m) {
+                *a += t;
+            }
+
+            let r = xf.rotation;
+            model = Mat4::from_mat3(Mat3::from_cols_array_2d(&[
+                [r[0][0] as f32, r[1][0] as f32, r[2][0] as f32],
+                [r[0][1] as f32, r[1][1] as f32, r[2][1] as f32],
+                [r[0][2] as f32, r[1][2] as f32, r[2][2] as f32],
+            ]));
+        }
+
         // Difference between tile anchor and camera position.
         let dx = anchor_m[0] - cam_ecef[0];
         let dy = anchor_m[1] - cam_ecef[1];
@@ -203,9 +394,12 @@ impl Camera {
             delta_lo: [lox, loy, loz],
             _pad1: 0.0,
             view_proj: self.view_proj_ecef().to_cols_array_2d(),
+            model: model.to_cols_array_2d(),
             viewport_size,
             point_size_px,
-            _pad2: 0.0,
+            pick_id: 0,
+            class_size: bytemuck::cast(*class_point_size),
+            tile_color: [0.0; 4],
         }
     }
 }
@@ -213,6 +407,10 @@ impl Camera {
 pub struct CameraController {
     mouse_down: bool,
     last_mouse: Option<(f64, f64)>,
+    /// Held state of each of `PAN_KEYS`.
+    pan_held: [bool; PAN_KEYS.len()],
+    /// Keyboard pan distance per key press or repeat, as a fraction of the orbit radius.
+    pub pan_step: f64,
 }
 
 impl CameraController {
@@ -221,6 +419,8 @@ impl CameraController {
         Self {
             mouse_down: false,
             last_mouse: None,
+            pan_held: [false; PAN_KEYS.len()],
+            pan_step: DEFAULT_PAN_STEP,
         }
     }
 
@@ -235,6 +435,7 @@ impl CameraController {
             WindowEvent::CursorMoved { position, .. } => {
                 self.handle_cursor_orbit((position.x, position.y), camera);
             }
+            WindowEvent::KeyboardInput { event, .. } => self.handle_pan_key(event, camera),
             WindowEvent::MouseWheel { delta, .. } => {
                 let scroll = match delta {
                     MouseScrollDelta::LineDelta(_, y) => *y,
@@ -247,6 +448,36 @@ impl CameraController {
         }
     }
 
+    /// Pans the orbit target with WASD (ground plane) and Q/E (down/up).
+    ///
+    /// Every press or key repeat moves `pan_step * radius_m` along the sum of
+    /// the held directions, normalized so diagonals are no faster.
+    fn handle_pan_key(&mut self, event: &KeyEvent, camera: &mut Camera) {
+        let PhysicalKey::Code(code) = event.physical_key else {
+            return;
+        };
+        let Some(index) = PAN_KEYS.iter().position(|&(key, _)| key == code) else {
+            return;
+        };
+        let pressed = event.state.is_pressed();
+        self.pan_held[index] = pressed;
+        if !pressed {
+            return;
+        }
+
+        let dir = PAN_KEYS
+            .iter()
+            .zip(self.pan_held)
+            .filter(|&(_, held)| held)
+            .fold(DVec3::ZERO, |acc, ((_, d), _)| acc + DVec3::from(*d));
+        let Some(dir) = dir.try_normalize() else {
+            return;
+        };
+
+        let step = dir * self.pan_step * camera.radius_m;
+        camera.pan(step.x, step.y, step.z);
+    }
+
     /// Adjusts camera orbit radius based on scroll input.
     fn handle_scroll(&mut self, delta: f32, camera: &mut Camera) {
         // Positive delta = scroll up = zoom in = decrease radius.
@@ -278,3 +509,99 @@ impl CameraController {
         self.last_mouse = Some(xy);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pose_round_trips_through_json() {
+        let mut camera = Camera::new(48.137, 11.575, 850.0, Mat4::IDENTITY);
+        camera.azimuth_rad = 0.7;
+        camera.elevation_rad = 0.4;
+        camera.update();
+
+        let json = serde_json::to_string(&camera.pose()).unwrap();
+        let mut restored = Camera::new(0.0, 0.0, 1.0, Mat4::IDENTITY);
+        restored.set_pose(serde_json::from_str(&json).unwrap());
+
+        assert_eq!(restored.pose(), camera.pose());
+        assert_eq!(restored.position_ecef, camera.position_ecef);
+        assert_eq!(
+            (restored.lat_deg, restored.lon_deg, restored.h_m),
+            (camera.lat_deg, camera.lon_deg, camera.h_m)
+        );
+        // 400 m up over Munich.
+        assert!((48.0..48.3).contains(&camera.lat_deg), "{}", camera.lat_deg);
+        assert!((300.0..500.0).contains(&camera.h_m), "{}", camera.h_m);
+    }
+
+    #[test]
+    fn pose_slots_keep_each_other() {
+        let path = std::env::temp_dir().join(format!("viewer-poses-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(load_pose_slot(&path, 1).unwrap(), None);
+
+        let a = Camera::new(48.137, 11.575, 850.0, Mat4::IDENTITY).pose();
+        let b = Camera::new(-33.86, 151.21, 120.0, Mat4::IDENTITY).pose();
+        save_pose_slot(&path, 1, a).unwrap();
+        save_pose_slot(&path, 9, b).unwrap();
+        assert_eq!(load_pose_slot(&path, 1).unwrap(), Some(a));
+        assert_eq!(load_pose_slot(&path, 9).unwrap(), Some(b));
+        assert_eq!(load_pose_slot(&path, 5).unwrap(), None);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    /// A camera orbiting a ground target over Munich with the viewer's
+    /// perspective projection.
+    fn orbit(radius_m: f64, elevation_deg: f64) -> Camera {
+        let proj = Mat4::perspective_rh(std::f32::consts::FRAC_PI_3, 1.5, 10.0, 20_000_000.0);
+        let mut camera = Camera::new(48.137, 11.575, radius_m, proj);
+        camera.elevation_rad = elevation_deg.to_radians();
+        camera.update();
+        camera
+    }
+
+    #[test]
+    fn footprint_looking_straight_down_surrounds_the_target() {
+        let camera = orbit(1_000.0, 90.0);
+        let (lon_min, lon_max, lat_min, lat_max) = camera.ground_footprint().unwrap().to_deg();
+
+        let spans = ((lon_min, lon_max), (lat_min, lat_max));
+        assert!((lon_min..lon_max).contains(&11.575), "{:?}", spans);
+        assert!((lat_min..lat_max).contains(&48.137), "{:?}", spans);
+        // 1 km up with a 60° field of view sees well under 2 km either way.
+        assert!(lat_max - lat_min < 0.02, "{}", lat_max - lat_min);
+        assert!(lon_max - lon_min < 0.03, "{}", lon_max - lon_min);
+    }
+
+    #[test]
+    fn footprint_towards_the_horizon_is_clamped() {
+        // 175 m up, looking north at a target 2 km away: the upper rays pass
+        // over the horizon.
+        let camera = orbit(2_000.0, 5.0);
+        let (lon_min, lon_max, _, lat_max) = camera.ground_footprint().unwrap().to_deg();
+
+        // The far edge lies at the clamp range from the camera, not beyond.
+        let far_edge = DVec3::from(geodetic_to_ecef(lat_max, camera.lon_deg, 0.0));
+        let reach_m = far_edge.distance(DVec3::from(camera.ecef_m()));
+        assert!(
+            (FOOTPRINT_MAX_RANGE_M - 500.0..=FOOTPRINT_MAX_RANGE_M + 10.0).contains(&reach_m),
+            "{}",
+            reach_m
+        );
+        let west = DVec3::from(geodetic_to_ecef(camera.lat_deg, lon_min, 0.0));
+        let east = DVec3::from(geodetic_to_ecef(camera.lat_deg, lon_max, 0.0));
+        assert!(west.distance(east) <= 2.0 * FOOTPRINT_MAX_RANGE_M);
+    }
+
+    #[test]
+    fn footprint_of_the_sky_is_none() {
+        // 5 km below a target 10 km up, looking up at it.
+        let mut camera = orbit(5_000.0, -60.0);
+        camera.pan(0.0, 0.0, 10_000.0);
+        assert!(camera.h_m > 5_000.0, "{}", camera.h_m);
+        assert_eq!(camera.ground_footprint(), None);
+    }
+}
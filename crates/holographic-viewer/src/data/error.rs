@@ -0,0 +1,68 @@
+//! Error type for the viewer's data layer.
+
+use hypc::HypcError;
+use std::io;
+use thiserror::Error;
+
+/// Why a HYPC tile could not be turned into a `TileGpu`.
+///
+/// Version mismatches are expected when a directory mixes encoder
+/// generations and are skipped; truncation and corruption point at a bad
+/// file and are reported loudly.
+#[derive(Error, Debug)]
+pub enum TileLoadError {
+    #[error("unsupported HYPC version: {0}")]
+    UnsupportedVersion(io::Error),
+    #[error("truncated tile: {0}")]
+    Truncated(io::Error),
+    #[error("corrupt tile: {0}")]
+    Corrupt(io::Error),
+    #[error("I/O error: {0}")]
+    Io(io::Error),
+}
+
+impl TileLoadError {
+    /// True for failures that are safe to skip without flagging the dataset.
+    pub fn is_skippable(&self) -> bool {
+        matches!(self, Self::UnsupportedVersion(_))
+    }
+}
+
+impl From<io::Error> for TileLoadError {
+    /// Classifies the `io::Error`s produced by the `hypc` reader by the
+    /// [`HypcError`] they carry.
+    fn from(e: io::Error) -> Self {
+        match e.get_ref().and_then(|inner| inner.downcast_ref::<HypcError>()) {
+            Some(HypcError::UnsupportedVersion(_)) => Self::UnsupportedVersion(e),
+            Some(HypcError::Truncated { .. } | HypcError::RleTruncated) => Self::Truncated(e),
+            Some(_) => Self::Corrupt(e),
+            None => Self::Io(e),
+        }
+    }
+}
+
+/// Per-outcome counts from loading a tile directory.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TileLoadStats {
+    pub loaded: usize,
+    pub skipped_version: usize,
+    pub truncated: usize,
+    pub corrupt: usize,
+    pub io: usize,
+}
+
+impl TileLoadStats {
+    pub fn record(&mut self, err: &TileLoadError) {
+        match err {
+            TileLoadError::UnsupportedVersion(_) => self.skipped_version += 1,
+            TileLoadError::Truncated(_) => self.truncated += 1,
+            TileLoadError::Corrupt(_) => self.corrupt += 1,
+            TileLoadError::Io(_) => self.io += 1,
+        }
+    }
+
+    /// Tiles that failed for a reason other than a version mismatch.
+    pub fn failed(&self) -> usize {
+        self.truncated + self.corrupt + self.io
+    }
+}
@@ -0,0 +1,183 @@
+//! Background tile loading.
+//!
+//! A worker thread finds the tiles, reads their headers to frame the dataset
+//! and size the point budget, then decodes them one by one and streams the
+//! results back over a channel. GPU uploads stay with the receiver, on the
+//! render thread.
+
+use crate::data::{
+    error::TileLoadError,
+    point_cloud::{decode_hypc_tile, DecodedTile},
+};
+use anyhow::{Context, Result};
+use hypc::HypcHeader;
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc,
+    thread,
+};
+use walkdir::WalkDir;
+
+/// Where a dataset sits, from its tile headers.
+#[derive(Debug, Clone, Copy)]
+pub struct DatasetExtent {
+    /// Point-weighted centroid of the tile anchors, ECEF meters.
+    pub center_ecef_m: [f64; 3],
+    /// Largest distance of a tile anchor from the centroid, meters.
+    pub radius_m: f64,
+    pub min_upm: u32,
+    pub max_upm: u32,
+}
+
+impl DatasetExtent {
+    /// Returns `None` when there are no headers or no points to weight them by.
+    fn from_headers(headers: &[HypcHeader]) -> Option<Self> {
+        let anchors_m: Vec<([f64; 3], f64)> = headers
+            .iter()
+            .map(|h| {
+                let upm = h.units_per_meter as f64;
+                let a = h.anchor_ecef_units;
+                let a_m = [a[0] as f64 / upm, a[1] as f64 / upm, a[2] as f64 / upm];
+                (a_m, h.points_count as f64)
+            })
+            .collect();
+
+        let sum_w: f64 = anchors_m.iter().map(|&(_, w)| w).sum();
+        if sum_w <= 0.0 {
+            return None;
+        }
+        let center_ecef_m: [f64; 3] = std::array::from_fn(|k| {
+            anchors_m.iter().map(|&(a, w)| a[k] * w).sum::<f64>() / sum_w
+        });
+
+        let radius_m = anchors_m
+            .iter()
+            .map(|(a, _)| {
+                let d: [f64; 3] = std::array::from_fn(|k| a[k] - center_ecef_m[k]);
+                d[0] * d[0] + d[1] * d[1] + d[2] * d[2]
+            })
+            .fold(0.0f64, f64::max)
+            .sqrt();
+
+        Some(Self {
+            center_ecef_m,
+            radius_m,
+            min_upm: headers.iter().map(|h| h.units_per_meter).min()?,
+            max_upm: headers.iter().map(|h| h.units_per_meter).max()?,
+        })
+    }
+}
+
+/// Messages from the loader thread, in this order.
+pub enum LoadEvent {
+    /// Sent once, before any tile.
+    Started {
+        total: usize,
+        /// `None` when no header could be read.
+        extent: Option<DatasetExtent>,
+    },
+    /// One tile, decoded or not.
+    Tile(PathBuf, Result<Box<DecodedTile>, TileLoadError>),
+}
+
+/// Receiving end of a loader thread. The channel disconnects once every tile
+/// has been sent.
+pub struct TileLoader {
+    rx: mpsc::Receiver<LoadEvent>,
+    total: usize,
+    received: usize,
+}
+
+impl TileLoader {
+    /// Starts loading the `.hypc` file at `root`, or every `.hypc` under it
+    /// when it is a directory.
+    ///
+    /// With a `max_points` budget, the tile headers are summed first and, if
+    /// the total exceeds it, every tile keeps the same fraction of its points,
+    /// so each tile's share of the budget matches its share of the dataset.
+    pub fn spawn(root: PathBuf, max_points: Option<u64>) -> Result<Self> {
+        let (tx, rx) = mpsc::channel();
+        thread::Builder::new()
+            .name("tile-loader".into())
+            .spawn(move || run(&root, max_points, tx))
+            .context("spawning the tile loader thread")?;
+        Ok(Self {
+            rx,
+            total: 0,
+            received: 0,
+        })
+    }
+
+    /// Returns the next event without blocking.
+    pub fn try_recv(&mut self) -> Result<LoadEvent, mpsc::TryRecvError> {
+        let event = self.rx.try_recv()?;
+        match &event {
+            LoadEvent::Started { total, .. } => self.total = *total,
+            LoadEvent::Tile(..) => self.received += 1,
+        }
+        Ok(event)
+    }
+
+    /// Tiles received so far, and the number found.
+    pub fn progress(&self) -> (usize, usize) {
+        (self.received, self.total)
+    }
+}
+
+fn run(root: &Path, max_points: Option<u64>, tx: mpsc::Sender<LoadEvent>) {
+    let paths: Vec<_> = if root.is_dir() {
+        WalkDir::new(root)
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|e| e.path().extension().and_then(|s| s.to_str()) == Some("hypc"))
+            .map(|e| e.path().to_path_buf())
+            .collect()
+    } else {
+        vec![root.to_path_buf()]
+    };
+
+    if paths.is_empty() {
+        log::warn!("No .hypc files found in '{}'", root.display());
+    }
+
+    // Unreadable headers are left out here; decoding reports them.
+    let headers: Vec<HypcHeader> = paths
+        .iter()
+        .filter_map(|p| hypc::read_header(p).ok())
+        .collect();
+
+    let keep = match max_points {
+        Some(budget) => {
+            let header_points: u64 = headers.iter().map(|h| h.points_count as u64).sum();
+            let keep = if header_points > budget {
+                budget as f64 / header_points as f64
+            } else {
+                1.0
+            };
+            log::info!(
+                "Point budget {}: retaining {:.1}% of {} points",
+                budget,
+                keep * 100.0,
+                header_points
+            );
+            keep
+        }
+        None => 1.0,
+    };
+
+    let started = LoadEvent::Started {
+        total: paths.len(),
+        extent: DatasetExtent::from_headers(&headers),
+    };
+    if tx.send(started).is_err() {
+        return;
+    }
+
+    for path in paths {
+        let result = decode_hypc_tile(&path, keep).map(Box::new);
+        // The viewer has gone away.
+        if tx.send(LoadEvent::Tile(path, result)).is_err() {
+            return;
+        }
+    }
+}
@@ -3,10 +3,18 @@
 //!
 //! This module provides functionality for:
 //! - Loading HYPC point clouds and preparing them for the GPU.
+//! - Decoding tiles on a background thread.
 //! - Defining the data structures for GPU buffers.
+//! - Classifying tile load failures.
 
+pub mod error;
+pub mod loader;
 pub mod point_cloud;
 pub mod types;
 
 // Re-export commonly used types for convenience.
-pub use self::types::{PointInstance, TileGpu, TileKey32, TileUniformStd140};
+pub use self::error::{TileLoadError, TileLoadStats};
+pub use self::types::{
+    PointInstance, TileGpu, TileKey32, TileUniformStd140, CLASS_SIZE_SLOTS, SEM_CLASS_COLORS,
+    SEM_UNKNOWN_COLOR,
+};
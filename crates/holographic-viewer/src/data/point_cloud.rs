@@ -1,55 +1,21 @@
 use crate::camera::Camera;
-use crate::data::types::{PointInstance, TileGpu};
-use anyhow::Result;
+use crate::data::{
+    error::TileLoadError,
+    types::{tile_debug_color, PointInstance, TileGpu, TileKey32},
+};
 use hypc::{
-    ecef_to_geodetic, read_file, smc1_decode_rle, wgs84, HypcTile, Smc1CoordSpace, Smc1Encoding,
+    ecef_to_enu_matrix, ecef_to_geodetic, geodetic_to_ecef, read_file, wgs84, GeoExtentQ7,
+    HypcTile,
 };
 use rayon::prelude::*;
 use std::path::Path;
 
-// ASSUMPTION: The `PointInstance` struct is defined in `crate::data::types`.
-// For this file to be self-contained and compilable, we define the module and the
-// struct here.
-pub mod data {
-    pub mod types {
-        #[repr(C)]
-        #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
-        pub struct PointInstance {
-            pub ofs_m: [f32; 3],
-            pub label: u32,
-        }
-
-        // Unchanged struct, provided for context.
-        pub struct TileGpu {
-            pub key: u64,
-            pub units_per_meter: f32,
-            pub anchor_units: [i64; 3],
-            pub instances_len: u32,
-            pub vtx: wgpu::Buffer,
-            pub ubo: wgpu::Buffer,
-            pub bind: wgpu::BindGroup,
-        }
-    }
-}
-
 // wgpu::util::DeviceExt is a trait, so we need to bring it into scope.
 mod wgpu_util {
     pub use wgpu::util::DeviceExt;
 }
 use wgpu_util::*;
 
-#[inline(always)]
-fn build_ecef_to_enu(lat_rad: f64, lon_rad: f64) -> [[f64; 3]; 3] {
-    let (sφ, cφ) = lat_rad.sin_cos();
-    let (sλ, cλ) = lon_rad.sin_cos();
-    // Rows are ê^T, n̂^T, û^T in ECEF components.
-    [
-        [-sλ, cλ, 0.0],
-        [-sφ * cλ, -sφ * sλ, cφ],
-        [cφ * cλ, cφ * sλ, sφ],
-    ]
-}
-
 #[inline(always)]
 fn mul_mat3_vec3(m: &[[f64; 3]; 3], v: [f64; 3]) -> [f64; 3] {
     [
@@ -59,34 +25,74 @@ fn mul_mat3_vec3(m: &[[f64; 3]; 3], v: [f64; 3]) -> [f64; 3] {
     ]
 }
 
-/// Read one HYPC tile from disk and upload to GPU (instances + per-tile UBO).
-pub fn load_hypc_tile(
-    device: &wgpu::Device,
-    layout: &wgpu::BindGroupLayout,
-    camera: &Camera,
-    path: &Path,
-    viewport_size: [f32; 2], // Initial viewport size
-) -> Result<TileGpu> {
+/// Keeps `floor(len * keep)` of `instances`, spread evenly over the tile by a
+/// fractional stride, so the thinning is deterministic and never overshoots.
+fn thin_instances(instances: Vec<PointInstance>, keep: f64) -> Vec<PointInstance> {
+    if keep >= 1.0 {
+        return instances;
+    }
+    instances
+        .into_iter()
+        .enumerate()
+        .filter(|&(i, _)| ((i + 1) as f64 * keep).floor() > (i as f64 * keep).floor())
+        .map(|(_, p)| p)
+        .collect()
+}
+
+/// Segments per edge of a GEOT footprint, so long edges follow their
+/// parallel or meridian instead of cutting through the ellipsoid.
+const FOOTPRINT_EDGE_SEGMENTS: usize = 8;
+
+/// The GEOT bbox as a closed ring of ECEF points (meters) at height 0, going
+/// counter-clockwise from the south-west corner.
+fn geot_footprint_ecef_m(geot: GeoExtentQ7) -> Vec<[f64; 3]> {
+    let (lon_min, lon_max, lat_min, lat_max) = geot.to_deg();
+    let corners = [
+        (lat_min, lon_min),
+        (lat_min, lon_max),
+        (lat_max, lon_max),
+        (lat_max, lon_min),
+    ];
+    let n = FOOTPRINT_EDGE_SEGMENTS;
+    (0..4)
+        .flat_map(|edge| {
+            let (lat0, lon0) = corners[edge];
+            let (lat1, lon1) = corners[(edge + 1) % 4];
+            (0..n).map(move |i| {
+                let t = i as f64 / n as f64;
+                geodetic_to_ecef(lat0 + (lat1 - lat0) * t, lon0 + (lon1 - lon0) * t, 0.0)
+            })
+        })
+        .collect()
+}
+
+/// A tile decoded and labelled on the CPU, ready for [`upload_tile`].
+pub struct DecodedTile {
+    pub key: Option<TileKey32>,
+    pub units_per_meter: u32,
+    pub anchor_units: [i64; 3],
+    pub transform: Option<hypc::RigidTransform>,
+    pub geot: Option<GeoExtentQ7>,
+    pub instances: Vec<PointInstance>,
+    /// See [`TileGpu::bounds_center_m`].
+    pub bounds_center_m: [f64; 3],
+    pub bounds_radius_m: f64,
+    /// See [`TileGpu::classes`].
+    pub classes: Vec<u8>,
+    /// See [`TileGpu::point_spacing_m`].
+    pub point_spacing_m: f64,
+}
+
+/// Read one HYPC tile from disk and build its point instances. Touches no GPU
+/// state, so it can run on a loader thread.
+///
+/// Only a `keep` fraction (0..=1) of the points is kept; see
+/// [`thin_instances`]. Decode failures come back classified so callers can
+/// skip version mismatches and flag truncated or corrupt files.
+pub fn decode_hypc_tile(path: &Path, keep: f64) -> Result<DecodedTile, TileLoadError> {
     let tile: HypcTile = read_file(path)?;
     let upm_f32 = tile.units_per_meter as f32;
     let inv_upm_f32 = upm_f32.recip();
-    let inv_upm_f64 = (tile.units_per_meter as f64).recip();
-
-    // SMC1 decode (only if needed)
-    let (smc_w, smc_h, smc_raw): (u32, u32, Option<Vec<u8>>) =
-        if let Some(smc1) = tile.smc1.as_ref() {
-            if smc1.coord_space == Smc1CoordSpace::Crs84BboxNorm && tile.geot.is_some() {
-                let data = match smc1.encoding {
-                    Smc1Encoding::Raw => smc1.data.clone(),
-                    Smc1Encoding::Rle => smc1_decode_rle(&smc1.data)?,
-                };
-                (smc1.width as u32, smc1.height as u32, Some(data))
-            } else {
-                (0, 0, None)
-            }
-        } else {
-            (0, 0, None)
-        };
 
     // GEOT in degrees
     let geot_deg = tile.geot.map(|g| g.to_deg());
@@ -99,74 +105,62 @@ pub fn load_hypc_tile(
         tile.anchor_ecef_units[2] as f64 / upm64,
     ];
 
-    // Fast path: either direct labels, or SMC path with linearized geodesy
+    // Labels: stored per point if present, else sampled from the SMC1 mask.
     let has_direct_labels = tile
         .labels
         .as_ref()
         .map_or(false, |v| v.len() == tile.points_units.len());
+    let smc_labels = if has_direct_labels {
+        None
+    } else {
+        let t_label = std::time::Instant::now();
+        let labels = tile.smc1_point_labels()?;
+        if labels.is_some() {
+            log::debug!(
+                "SMC labeling of {} points took {:.1} ms",
+                tile.points_units.len(),
+                t_label.elapsed().as_secs_f64() * 1e3
+            );
+        }
+        labels
+    };
+    let labels = if has_direct_labels {
+        tile.labels.as_deref()
+    } else {
+        smc_labels.as_deref()
+    };
 
     // Prepare instance buffer in parallel
-    let instances: Vec<PointInstance> =
-        if has_direct_labels || smc_raw.is_none() || geot_deg.is_none() {
-            // No geodesy work; just scale offsets and copy labels if present.
-            let labels = tile.labels.as_deref();
-            tile.points_units
-                .par_iter()
-                .enumerate()
-                .map(|(i, p)| {
-                    let ofs_m = [
-                        (p[0] as f32) * inv_upm_f32,
-                        (p[1] as f32) * inv_upm_f32,
-                        (p[2] as f32) * inv_upm_f32,
-                    ];
-                    let label = labels.map(|ls| ls[i]).unwrap_or(0) as u32;
-                    PointInstance { ofs_m, label }
-                })
-                .collect()
-        } else {
-            let (lon_min, lon_max, lat_min, lat_max) = geot_deg.unwrap();
-            let inv_dlon = 1.0 / (lon_max - lon_min + 1e-12);
-            let inv_dlat = 1.0 / (lat_max - lat_min + 1e-12);
-
-            let smc = smc_raw.as_ref().unwrap();
-            let sw = smc_w as usize;
-            let sh = smc_h as usize;
-
-            tile.points_units
-                .par_iter()
-                .map(|p| {
-                    // 1. Reconstruct the point's full ECEF coordinate in meters (f64 for precision).
-                    let point_ecef_m = [
-                        anchor_m[0] + (p[0] as f64 * inv_upm_f64),
-                        anchor_m[1] + (p[1] as f64 * inv_upm_f64),
-                        anchor_m[2] + (p[2] as f64 * inv_upm_f64),
-                    ];
-
-                    // 2. Convert the ECEF coordinate to a precise geodetic coordinate.
-                    let (lat_deg, lon_deg, _h) =
-                        ecef_to_geodetic(point_ecef_m[0], point_ecef_m[1], point_ecef_m[2]);
-
-                    // 3. Normalize the geodetic coordinate into a [0,1] UV coordinate using the tile's GEOT bbox.
-                    let u = ((lon_deg - lon_min) * inv_dlon).clamp(0.0, 1.0);
-                    let v = ((lat_deg - lat_min) * inv_dlat).clamp(0.0, 1.0);
-
-                    // 4. Sample the semantic mask texture.
-                    let ix = (u * (smc_w.saturating_sub(1)) as f64).round() as usize;
-                    let iy = (v * (smc_h.saturating_sub(1)) as f64).round() as usize;
-                    let label = smc[iy * sw + ix] as u32;
-
-                    // 5. Create the PointInstance. The offset is still the original ECEF offset for rendering.
-                    PointInstance {
-                        ofs_m: [
-                            (p[0] as f32) * inv_upm_f32,
-                            (p[1] as f32) * inv_upm_f32,
-                            (p[2] as f32) * inv_upm_f32,
-                        ],
-                        label,
-                    }
-                })
-                .collect()
-        };
+    let instances: Vec<PointInstance> = tile
+        .points_units
+        .par_iter()
+        .enumerate()
+        .map(|(i, p)| {
+            let ofs_m = [
+                (p[0] as f32) * inv_upm_f32,
+                (p[1] as f32) * inv_upm_f32,
+                (p[2] as f32) * inv_upm_f32,
+            ];
+            let label = labels.map(|ls| ls[i]).unwrap_or(0) as u32;
+            PointInstance { ofs_m, label }
+        })
+        .collect();
+
+    // Classes for the legend: the per-point labels if present, else the SMC1
+    // palette when the mask was sampled.
+    let mut classes: Vec<u8> = match (&tile.labels, &tile.smc1, &smc_labels) {
+        (Some(labels), _, _) if has_direct_labels => {
+            let mut seen = [false; 256];
+            labels.iter().for_each(|&l| seen[l as usize] = true);
+            (0..=255u8).filter(|&l| seen[l as usize]).collect()
+        }
+        (_, Some(smc1), Some(_)) => smc1.palette.iter().map(|&(class, _)| class).collect(),
+        _ => Vec::new(),
+    };
+    classes.sort_unstable();
+    classes.dedup();
+
+    let instances = thin_instances(instances, keep);
 
     // Tile-level analysis and logging is confined to debug builds.
     #[cfg(debug_assertions)]
@@ -176,11 +170,9 @@ pub fn load_hypc_tile(
         // 1. Get geodetic coordinates of the anchor to define the local tangent plane.
         let (anchor_lat_deg, anchor_lon_deg, _) =
             ecef_to_geodetic(anchor_m[0], anchor_m[1], anchor_m[2]);
-        let anchor_lat_rad = anchor_lat_deg.to_radians();
-        let anchor_lon_rad = anchor_lon_deg.to_radians();
 
         // 2. Build the transformation matrix from ECEF to the local ENU frame.
-        let ecef_to_enu_mat = build_ecef_to_enu(anchor_lat_rad, anchor_lon_rad);
+        let ecef_to_enu_mat = ecef_to_enu_matrix(anchor_lat_deg, anchor_lon_deg);
 
         // 3. Calculate PCA-based orientation. This requires iterating through points to build covariance matrix.
         let num_points = instances.len() as f64;
@@ -221,28 +213,16 @@ pub fn load_hypc_tile(
 
         // --- End: Tile-level orientation calculation via PCA ---
 
-        // AABB calculation for logging purposes.
-        use std::f32::{INFINITY, NEG_INFINITY};
-        let (min, max) = instances
-            .par_iter()
-            .map(|pi| (pi.ofs_m, pi.ofs_m))
-            .reduce(
-                || ([INFINITY; 3], [NEG_INFINITY; 3]),
-                |(a_min, a_max), (b_min, b_max)| {
-                    (
-                        [
-                            a_min[0].min(b_min[0]),
-                            a_min[1].min(b_min[1]),
-                            a_min[2].min(b_min[2]),
-                        ],
-                        [
-                            a_max[0].max(b_max[0]),
-                            a_max[1].max(b_max[1]),
-                            a_max[2].max(b_max[2]),
-                        ],
-                    )
-                },
-            );
+        // AABB (relative to the anchor) for logging purposes.
+        let (min, max) = tile
+            .bounding_box_ecef_m()
+            .map(|(lo, hi)| {
+                (
+                    [lo[0] - anchor_m[0], lo[1] - anchor_m[1], lo[2] - anchor_m[2]],
+                    [hi[0] - anchor_m[0], hi[1] - anchor_m[1], hi[2] - anchor_m[2]],
+                )
+            })
+            .unwrap_or_default();
 
         log::debug!(
             "HYPC {:?}: pts={}, upm={}, anchor_ecef_m=({:.3},{:.3},{:.3}), ofs_AABB_m=min({:.2},{:.2},{:.2}) max({:.2},{:.2},{:.2}), pca_orientation_deg_from_N={:.1}",
@@ -256,18 +236,72 @@ pub fn load_hypc_tile(
         );
     }
 
-    // GPU upload
+    // Bounding sphere around the point AABB, for frustum culling. An empty tile
+    // collapses to its anchor.
+    let (bounds_center_m, bounds_radius_m) = match tile.bounding_box_ecef_m() {
+        Some((lo, hi)) => {
+            let center: [f64; 3] = std::array::from_fn(|k| 0.5 * (lo[k] + hi[k]));
+            let radius = (0..3)
+                .map(|k| (hi[k] - center[k]) * (hi[k] - center[k]))
+                .sum::<f64>()
+                .sqrt();
+            (center, radius)
+        }
+        None => (anchor_m, 0.0),
+    };
+
+    // Mean spacing of the kept points over the ground footprint: the GEOT bbox,
+    // else ~2r² from the bounding sphere (a flat square tile of side L has
+    // r ≈ L / √2).
+    let footprint_m2 = match geot_deg {
+        Some((lon_min, lon_max, lat_min, lat_max)) => {
+            let lat_mid = (0.5 * (lat_min + lat_max)).to_radians();
+            let dy = (lat_max - lat_min).to_radians() * wgs84::A;
+            let dx = (lon_max - lon_min).to_radians() * wgs84::A * lat_mid.cos();
+            dx * dy
+        }
+        None => 2.0 * bounds_radius_m * bounds_radius_m,
+    };
+    let point_spacing_m = (footprint_m2 / instances.len().max(1) as f64).sqrt();
+
+    Ok(DecodedTile {
+        key: tile.tile_key,
+        units_per_meter: tile.units_per_meter,
+        anchor_units: tile.anchor_ecef_units,
+        transform: tile.transform,
+        geot: tile.geot,
+        instances,
+        bounds_center_m,
+        bounds_radius_m,
+        classes,
+        point_spacing_m,
+    })
+}
+
+/// Upload a decoded tile to the GPU (instances + per-tile UBO).
+pub fn upload_tile(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    camera: &Camera,
+    tile: DecodedTile,
+    viewport_size: [f32; 2], // Initial viewport size
+) -> TileGpu {
     let vtx = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
         label: Some("HYPC Instances"),
-        contents: bytemuck::cast_slice(&instances),
-        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        contents: bytemuck::cast_slice(&tile.instances),
+        // COPY_SRC: picked points are read back from here.
+        usage: wgpu::BufferUsages::VERTEX
+            | wgpu::BufferUsages::COPY_DST
+            | wgpu::BufferUsages::COPY_SRC,
     });
 
     let tile_ubo_data = camera.make_tile_uniform(
-        tile.anchor_ecef_units,
+        tile.anchor_units,
         tile.units_per_meter,
+        tile.transform.as_ref(),
         viewport_size,
         1.0, // Default point size
+        &[1.0; crate::data::types::CLASS_SIZE_SLOTS],
     );
 
     let ubo = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -285,13 +319,22 @@ pub fn load_hypc_tile(
         }],
     });
 
-    Ok(TileGpu {
-        key: tile.tile_key,
+    TileGpu {
+        key: tile.key,
         units_per_meter: tile.units_per_meter,
-        anchor_units: tile.anchor_ecef_units,
-        instances_len: instances.len() as u32,
+        anchor_units: tile.anchor_units,
+        transform: tile.transform,
+        geot: tile.geot,
+        footprint_ecef_m: tile.geot.map(geot_footprint_ecef_m).unwrap_or_default(),
+        instances_len: tile.instances.len() as u32,
+        debug_color: tile_debug_color(tile.key.as_ref(), tile.anchor_units),
+        bounds_center_m: tile.bounds_center_m,
+        bounds_radius_m: tile.bounds_radius_m,
+        classes: tile.classes,
+        point_spacing_m: tile.point_spacing_m,
+        visible: true,
         vtx,
         ubo,
         bind,
-    })
+    }
 }
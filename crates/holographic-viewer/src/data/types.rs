@@ -24,23 +24,108 @@ pub struct TileUniformStd140 {
     pub _pad1: f32,
     /// Combined view-projection matrix for camera-relative ECEF rendering.
     pub view_proj: [[f32; 4]; 4],
+    /// Per-tile rotation applied to point offsets (identity unless the tile carries XFRM).
+    pub model: [[f32; 4]; 4],
     /// Size of the viewport in physical pixels.
     pub viewport_size: [f32; 2],
     /// Base size of the point sprite in pixels.
     pub point_size_px: f32,
-    pub _pad2: f32,
+    /// Tile slot + 1, written to the pick target with each point's instance index.
+    pub pick_id: u32,
+    /// Per-class point-size multipliers, packed four per vec4 (label = 4 * i + j).
+    pub class_size: [[f32; 4]; 4],
+    /// Per-tile debug tint (rgb); `a` = 1 replaces the base color with it.
+    pub tile_color: [f32; 4],
 }
 
+/// Number of semantic classes with a configurable point-size multiplier.
+pub const CLASS_SIZE_SLOTS: usize = 16;
+
+/// Semantic class colors (linear rgb), indexed by label like [`hypc::CLASS_NAMES`].
+/// Must match `class_color` in `sem_post.wgsl`; labels past the end use
+/// [`SEM_UNKNOWN_COLOR`].
+pub const SEM_CLASS_COLORS: [[f32; 3]; hypc::CLASS_NAMES.len()] = [
+    [0.85, 0.85, 0.85],
+    [1.00, 0.82, 0.40],
+    [1.00, 0.92, 0.20],
+    [0.80, 0.80, 0.80],
+    [0.70, 0.70, 0.70],
+    [0.20, 0.55, 0.95],
+    [0.40, 0.85, 0.40],
+    [0.17, 0.55, 0.30],
+    [0.85, 0.30, 0.55],
+    [0.55, 0.55, 0.95],
+];
+
+/// Color `sem_post.wgsl` gives labels without an entry in [`SEM_CLASS_COLORS`].
+pub const SEM_UNKNOWN_COLOR: [f32; 3] = [0.85, 0.85, 0.85];
+
 /// A 32-byte, zero-padded UTF-8 tile identifier.
 pub type TileKey32 = [u8; 32];
 
+/// Distinct, stable color for a tile, derived from an FNV-1a hash of its key
+/// (or of its anchor when the tile has no key). Hues step by the golden ratio
+/// so neighbouring hashes still land far apart.
+pub fn tile_debug_color(key: Option<&TileKey32>, anchor_units: [i64; 3]) -> [f32; 3] {
+    let mut h: u64 = 0xcbf2_9ce4_8422_2325;
+    let mut eat = |bytes: &[u8]| {
+        for &b in bytes {
+            h ^= b as u64;
+            h = h.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    };
+    match key {
+        Some(k) => eat(k),
+        None => anchor_units.iter().for_each(|a| eat(&a.to_le_bytes())),
+    }
+
+    let hue = ((h >> 40) as f32 / (1u64 << 24) as f32 * 0.618_034).fract();
+    let sat = 0.65 + 0.25 * ((h & 0xff) as f32 / 255.0);
+    hsv_to_rgb(hue, sat, 0.95)
+}
+
+fn hsv_to_rgb(h: f32, s: f32, v: f32) -> [f32; 3] {
+    let h6 = h * 6.0;
+    let f = h6.fract();
+    let (p, q, t) = (v * (1.0 - s), v * (1.0 - s * f), v * (1.0 - s * (1.0 - f)));
+    match h6 as u32 % 6 {
+        0 => [v, t, p],
+        1 => [q, v, p],
+        2 => [p, v, t],
+        3 => [p, q, v],
+        4 => [t, p, v],
+        _ => [v, p, q],
+    }
+}
+
 /// Holds all GPU resources and metadata for a single, renderable HYPC tile.
 #[derive(Debug)]
 pub struct TileGpu {
     pub key: Option<TileKey32>,
     pub units_per_meter: u32,
     pub anchor_units: [i64; 3],
+    /// Optional rigid correction from the tile's XFRM chunk.
+    pub transform: Option<hypc::RigidTransform>,
+    /// CRS:84 bbox from the tile's GEOT chunk, if any.
+    pub geot: Option<hypc::GeoExtentQ7>,
+    /// The GEOT bbox at height 0 as a closed ring of ECEF points (meters), for
+    /// the footprint wireframe; empty without a GEOT.
+    pub footprint_ecef_m: Vec<[f64; 3]>,
     pub instances_len: u32,
+    /// Tint used by the per-tile debug view; see [`tile_debug_color`].
+    pub debug_color: [f32; 3],
+    /// Center (absolute ECEF meters) of a sphere enclosing every point, for culling.
+    pub bounds_center_m: [f64; 3],
+    /// Radius of that sphere in meters.
+    pub bounds_radius_m: f64,
+    /// Semantic classes the tile's points can carry, sorted: its direct labels,
+    /// or the SMC1 palette when labels come from the mask.
+    pub classes: Vec<u8>,
+    /// Mean distance between neighbouring points over the tile's ground
+    /// footprint, meters; sizes the points on screen.
+    pub point_spacing_m: f64,
+    /// Whether the tile is drawn; toggled from the tile list panel.
+    pub visible: bool,
 
     /// Vertex buffer containing `PointInstance` data.
     pub vtx: wgpu::Buffer,
@@ -49,3 +134,13 @@ pub struct TileGpu {
     /// Bind group connecting the UBO to the pipeline.
     pub bind: wgpu::BindGroup,
 }
+
+impl TileGpu {
+    /// Display name: the key's UTF-8 prefix, or the anchor for keyless tiles.
+    pub fn name(&self) -> String {
+        match &self.key {
+            Some(k) => String::from_utf8_lossy(k).trim_end_matches('\0').to_string(),
+            None => format!("anchor {:?}", self.anchor_units),
+        }
+    }
+}
@@ -1,23 +1,122 @@
 //! Entry point for the Holographic Viewer application.
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use holographic_viewer::app::App;
 use std::{
+    path::PathBuf,
     sync::Arc,
 };
 use winit::{
     event::{Event, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
-    keyboard::{KeyCode, PhysicalKey},
+    keyboard::{KeyCode, ModifiersState, PhysicalKey},
     window::WindowBuilder,
 };
 
+/// Tile directory loaded when no path is given.
+const DEFAULT_TILE_PATH: &str = "hypc";
+/// Frames recorded by `--profile` when no count is given.
+const DEFAULT_PROFILE_FRAMES: u32 = 300;
+/// Trace file written by `--profile` unless `--profile-out` is given.
+const DEFAULT_PROFILE_OUT: &str = "viewer-profile.json";
+
+/// Command-line arguments: `[TILES] [--max-points N] [--pan-step F] [--profile ...]`.
+struct Args {
+    /// A `.hypc` file or a directory searched recursively for them.
+    tile_path: PathBuf,
+    /// Most points uploaded to the GPU; tiles are thinned to fit.
+    max_points: Option<u64>,
+    /// Keyboard pan step as a fraction of the orbit radius.
+    pan_step: Option<f64>,
+    profile: Option<ProfileArgs>,
+}
+
+/// `--profile [FRAMES]` / `--profile=FRAMES`, optionally with `--profile-out PATH`.
+struct ProfileArgs {
+    frames: u32,
+    out_path: PathBuf,
+}
+
+fn parse_args() -> Result<Args> {
+    let mut args = std::env::args().skip(1).peekable();
+    let mut tile_path = None;
+    let mut max_points = None;
+    let mut pan_step = None;
+    let mut frames = None;
+    let mut out_path = PathBuf::from(DEFAULT_PROFILE_OUT);
+
+    while let Some(arg) = args.next() {
+        if let Some(n) = arg.strip_prefix("--profile=") {
+            frames = Some(
+                n.parse()
+                    .with_context(|| format!("invalid --profile frame count '{n}'"))?,
+            );
+        } else if arg == "--profile" {
+            let n = match args.peek() {
+                Some(next) if !next.starts_with("--") => {
+                    let n = next
+                        .parse()
+                        .with_context(|| format!("invalid --profile frame count '{next}'"))?;
+                    args.next();
+                    n
+                }
+                _ => DEFAULT_PROFILE_FRAMES,
+            };
+            frames = Some(n);
+        } else if arg == "--max-points" {
+            let n = args.next().context("--max-points needs a count")?;
+            max_points = Some(
+                n.parse()
+                    .with_context(|| format!("invalid --max-points count '{n}'"))?,
+            );
+        } else if arg == "--pan-step" {
+            let f = args.next().context("--pan-step needs a fraction")?;
+            pan_step = Some(
+                f.parse()
+                    .with_context(|| format!("invalid --pan-step fraction '{f}'"))?,
+            );
+        } else if arg == "--profile-out" {
+            out_path = args.next().context("--profile-out needs a path")?.into();
+        } else if !arg.starts_with("--") && tile_path.is_none() {
+            tile_path = Some(PathBuf::from(arg));
+        } else {
+            log::warn!("Ignoring unknown argument '{arg}'");
+        }
+    }
+
+    Ok(Args {
+        tile_path: tile_path.unwrap_or_else(|| PathBuf::from(DEFAULT_TILE_PATH)),
+        max_points,
+        pan_step,
+        profile: frames.map(|frames| ProfileArgs { frames, out_path }),
+    })
+}
+
+/// The camera slot bound to a digit key, 1..=9.
+fn camera_slot(code: KeyCode) -> Option<u8> {
+    let slot = match code {
+        KeyCode::Digit1 => 1,
+        KeyCode::Digit2 => 2,
+        KeyCode::Digit3 => 3,
+        KeyCode::Digit4 => 4,
+        KeyCode::Digit5 => 5,
+        KeyCode::Digit6 => 6,
+        KeyCode::Digit7 => 7,
+        KeyCode::Digit8 => 8,
+        KeyCode::Digit9 => 9,
+        _ => return None,
+    };
+    Some(slot)
+}
+
 fn main() -> Result<()> {
     // Initialize logging; default to "info" if RUST_LOG is unset.
     env_logger::Builder::from_env(
         env_logger::Env::default().default_filter_or("info")
     ).init();
 
+    let args = parse_args()?;
+
     // Create the event loop and window.
     let event_loop = EventLoop::new()?;
     let window = Arc::new(
@@ -30,12 +129,27 @@ fn main() -> Result<()> {
     // Initialise the application (async → sync).
     let mut app = pollster::block_on(App::new(window.clone()))?;
 
+    if let Some(pan_step) = args.pan_step {
+        app.camera_controller.pan_step = pan_step;
+    }
+
+    if let Some(profile) = args.profile {
+        log::info!(
+            "Profiling {} frames to {}",
+            profile.frames,
+            profile.out_path.display()
+        );
+        app.renderer
+            .enable_profiler(profile.frames, profile.out_path)?;
+    }
+
     // Load tiles; log any errors.
-    if let Err(err) = app.build_all_tiles("hypc") {
-        log::error!("Failed to build tiles: {}", err);
+    if let Err(err) = app.start_loading_tiles(&args.tile_path, args.max_points) {
+        log::error!("Failed to load tiles: {:#}", err);
     }
 
     // Run the winit event loop.
+    let mut modifiers = ModifiersState::empty();
     event_loop.run(move |event, elwt| {
         elwt.set_control_flow(ControlFlow::Poll);
 
@@ -45,13 +159,38 @@ fn main() -> Result<()> {
                 if !app.handle_event(&window, &event) {
                     match event {
                         WindowEvent::CloseRequested => elwt.exit(),
+                        WindowEvent::ModifiersChanged(m) => modifiers = m.state(),
                         WindowEvent::KeyboardInput { event, .. } => {
-                            if event.physical_key == PhysicalKey::Code(KeyCode::Escape) {
-                                elwt.exit();
+                            match event.physical_key {
+                                PhysicalKey::Code(KeyCode::Escape) => elwt.exit(),
+                                PhysicalKey::Code(KeyCode::F12)
+                                    if event.state.is_pressed() && !event.repeat =>
+                                {
+                                    app.request_screenshot();
+                                }
+                                PhysicalKey::Code(KeyCode::KeyO)
+                                    if event.state.is_pressed() && !event.repeat =>
+                                {
+                                    app.toggle_projection();
+                                }
+                                // 1..9 recall a camera slot, Ctrl+1..9 save one.
+                                PhysicalKey::Code(code)
+                                    if event.state.is_pressed() && !event.repeat =>
+                                {
+                                    if let Some(slot) = camera_slot(code) {
+                                        if modifiers.control_key() {
+                                            app.save_camera_slot(slot);
+                                        } else {
+                                            app.load_camera_slot(slot);
+                                        }
+                                    }
+                                }
+                                _ => {}
                             }
                         }
                         WindowEvent::RedrawRequested => {
                             match app.render(&window) {
+                                Ok(_) if app.renderer.profile_finished() => elwt.exit(),
                                 Ok(_) => {}
                                 Err(wgpu::SurfaceError::Lost) => {
                                     app.resize(app.renderer.gfx.size);
@@ -30,12 +30,16 @@ impl GfxContext {
             .await
             .ok_or_else(|| anyhow!("Failed to find a suitable GPU adapter."))?;
 
+        // Timestamp queries back the `--profile` mode; request them whenever
+        // the adapter has them so profiling needs no separate device path.
+        let required_features = adapter.features() & wgpu::Features::TIMESTAMP_QUERY;
+
         // Request a device and its command queue.
         let (device, queue) = adapter
             .request_device(
                 &wgpu::DeviceDescriptor {
                     label:            Some("Device"),
-                    required_features: wgpu::Features::empty(),
+                    required_features,
                     // Use default limits for broad compatibility.
                     required_limits:   wgpu::Limits::default(),
                 },
@@ -52,9 +56,13 @@ impl GfxContext {
             .find(|f| f.is_srgb())
             .unwrap_or(caps.formats[0]);
 
+        // Allow copying frames out for screenshots where the surface supports it.
+        let usage = wgpu::TextureUsages::RENDER_ATTACHMENT
+            | (caps.usages & wgpu::TextureUsages::COPY_SRC);
+
         // Configure the surface.
         let config = wgpu::SurfaceConfiguration {
-            usage:                       wgpu::TextureUsages::RENDER_ATTACHMENT,
+            usage,
             format:                      surface_format,
             width:                       size.width.max(1),
             height:                      size.height.max(1),
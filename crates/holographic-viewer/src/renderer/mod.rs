@@ -2,16 +2,23 @@
 //! and all the individual render pass pipelines.
 
 pub mod context;
+pub mod picking;
 pub mod pipelines;
+pub mod profiler;
+pub mod screenshot;
 pub mod targets;
 
 use self::{
     context::GfxContext,
-    pipelines::{ground_grid::GroundGridPipeline, hologram::HologramPipeline, post_stack::PostStack},
+    pipelines::{
+        background::BackgroundPipeline, ground_grid::GroundGridPipeline, hologram::HologramPipeline, post_stack::PostStack,
+        tile_bounds::TileBoundsPipeline,
+    },
+    profiler::GpuProfiler,
     targets::Targets,
 };
 use crate::{camera::Camera, data::types::TileGpu};
-use std::sync::Arc;
+use std::{path::PathBuf, rc::Rc, sync::Arc};
 use winit::window::Window;
 
 /// Owns all rendering-related state.
@@ -19,9 +26,17 @@ pub struct Renderer {
     pub gfx: GfxContext,
     pub targets: Targets,
     pub holo: HologramPipeline,
+    /// Gradient drawn behind everything; colors are in [`PostParams`].
+    ///
+    /// [`PostParams`]: pipelines::post_stack::PostParams
+    pub background: BackgroundPipeline,
     pub grid: GroundGridPipeline,
+    /// GEOT footprint wireframes; see [`TileBoundsPipeline::prepare`].
+    pub tile_bounds: TileBoundsPipeline,
     pub post_stack: PostStack,
     pub egui_renderer: egui_wgpu::Renderer,
+    /// Per-pass GPU timing, present only in `--profile` mode.
+    pub profiler: Option<Rc<GpuProfiler>>,
 }
 
 impl Renderer {
@@ -35,12 +50,28 @@ impl Renderer {
             targets.color_fmt,
             targets.depth_fmt,
             targets.dlin_fmt,
+            targets.pick_fmt,
+        );
+        let background = BackgroundPipeline::new(
+            &gfx.device,
+            targets.color_fmt,
+            targets.dlin_fmt,
+            targets.depth_fmt,
+            targets.pick_fmt,
         );
         let grid = GroundGridPipeline::new(
             &gfx.device,
             targets.color_fmt,
             targets.dlin_fmt,
             targets.depth_fmt,
+            targets.pick_fmt,
+        );
+        let tile_bounds = TileBoundsPipeline::new(
+            &gfx.device,
+            targets.color_fmt,
+            targets.dlin_fmt,
+            targets.depth_fmt,
+            targets.pick_fmt,
         );
         let post_stack = PostStack::new(&gfx.device, gfx.config.format, size.width, size.height);
 
@@ -51,12 +82,42 @@ impl Renderer {
             gfx,
             targets,
             holo,
+            background,
             grid,
+            tile_bounds,
             post_stack,
             egui_renderer,
+            profiler: None,
         })
     }
 
+    /// Starts timing every render pass for `frames` frames, after which the
+    /// trace is written to `out_path`.
+    pub fn enable_profiler(&mut self, frames: u32, out_path: PathBuf) -> anyhow::Result<()> {
+        let profiler = Rc::new(GpuProfiler::new(
+            &self.gfx.device,
+            &self.gfx.queue,
+            frames,
+            out_path,
+        )?);
+        self.post_stack.set_profiler(Some(profiler.clone()));
+        self.profiler = Some(profiler);
+        Ok(())
+    }
+
+    /// Timestamp writes for a pass named `name`, if profiling.
+    pub fn pass_timestamps(
+        &self,
+        name: &'static str,
+    ) -> Option<wgpu::RenderPassTimestampWrites<'_>> {
+        self.profiler.as_ref().and_then(|p| p.pass(name))
+    }
+
+    /// True once a profiling run has recorded all its frames.
+    pub fn profile_finished(&self) -> bool {
+        self.profiler.as_ref().is_some_and(|p| p.is_finished())
+    }
+
     pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
         if new_size.width > 0 && new_size.height > 0 {
             self.gfx.resize(new_size);
@@ -68,7 +129,7 @@ impl Renderer {
     pub fn render(
         &mut self,
         swap_view: &wgpu::TextureView,
-        tiles: &[TileGpu],
+        tiles: &[&TileGpu],
         camera: &Camera,
     ) {
         let mut encoder = self
@@ -99,6 +160,14 @@ impl Renderer {
                             store: wgpu::StoreOp::Store,
                         },
                     }),
+                    Some(wgpu::RenderPassColorAttachment {
+                        view: &self.targets.pick,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                            store: wgpu::StoreOp::Store,
+                        },
+                    }),
                 ],
                 depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
                     view: &self.targets.depth,
@@ -108,10 +177,15 @@ impl Renderer {
                     }),
                     stencil_ops: None,
                 }),
-                timestamp_writes: None,
+                timestamp_writes: self.pass_timestamps("geometry"),
                 occlusion_query_set: None,
             });
 
+            // The background goes under everything and leaves depth cleared
+            let params = &self.post_stack.params;
+            self.background
+                .draw(&mut pass, &self.gfx.queue, params.bg_top, params.bg_bottom);
+
             // Draw the grid first, so it's behind the points
             if self.post_stack.params.grid_on {
                 self.grid.draw(
@@ -122,13 +196,19 @@ impl Renderer {
                 );
             }
 
-            // Draw all point cloud tiles
-            for tile in tiles {
+            // Draw the point cloud tiles that survived culling and aren't hidden
+            for tile in tiles.iter().filter(|t| t.visible) {
                 self.holo.draw_tile(&mut pass, tile);
             }
+
+            // Tile footprints go over the points
+            if self.post_stack.params.tile_bounds_on {
+                self.tile_bounds.draw(&mut pass);
+            }
         }
 
         // Pass 2..N: Post-processing stack
+        self.post_stack.set_camera(camera);
         self.post_stack.run(
             &self.gfx.device,
             &self.gfx.queue,
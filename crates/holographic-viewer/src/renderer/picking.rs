@@ -0,0 +1,322 @@
+//! Readback of what lies under the mouse cursor.
+//!
+//! [`DepthPick`] copies one pixel of the geometry pass depth target out per
+//! pick and rebuilds the ECEF position with the inverse view-projection of
+//! the frame it came from. The `dlin` target carries the same clip depth, but
+//! as f16, which is far too coarse at city distances; the Depth32Float target
+//! is exact.
+//!
+//! [`PointPick`] identifies the point itself: the hologram pass writes each
+//! point's tile slot and instance index to the pick target, and the picked
+//! instance is then read back from its tile's vertex buffer for its exact
+//! offset and label.
+
+use crate::{
+    camera::Camera,
+    data::types::{PointInstance, TileGpu},
+};
+use glam::{DMat4, DVec3, DVec4};
+use std::sync::mpsc;
+
+/// `bytes_per_row` for the single-texel copy; one aligned row.
+const PICK_ROW_BYTES: u32 = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+
+/// A buffer being mapped for reading.
+struct Readback {
+    buffer: wgpu::Buffer,
+    /// Result of `map_async`, once `map` has been called.
+    mapped: Option<mpsc::Receiver<Result<(), wgpu::BufferAsyncError>>>,
+}
+
+impl Readback {
+    fn new(device: &wgpu::Device, label: &str, size: u64) -> Self {
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        Self {
+            buffer,
+            mapped: None,
+        }
+    }
+
+    /// Records a copy of the texel at `pixel` of `texture`. Returns `false` if
+    /// the pixel lies outside the texture.
+    fn copy_texel(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        texture: &wgpu::Texture,
+        aspect: wgpu::TextureAspect,
+        pixel: [u32; 2],
+    ) -> bool {
+        if pixel[0] >= texture.width() || pixel[1] >= texture.height() {
+            return false;
+        }
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d {
+                    x: pixel[0],
+                    y: pixel[1],
+                    z: 0,
+                },
+                aspect,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &self.buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(PICK_ROW_BYTES),
+                    rows_per_image: Some(1),
+                },
+            },
+            wgpu::Extent3d {
+                width: 1,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+        );
+        true
+    }
+
+    /// Starts mapping the buffer. Call after the copy has been submitted.
+    fn map(&mut self) {
+        let (tx, rx) = mpsc::channel();
+        self.buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |r| {
+                let _ = tx.send(r);
+            });
+        self.mapped = Some(rx);
+    }
+
+    /// Checks, without blocking, whether the copy has landed. Returns `None`
+    /// while it is in flight, then the first `N` bytes, or `Some(None)` if
+    /// mapping failed.
+    fn poll<const N: usize>(&self, device: &wgpu::Device) -> Option<Option<[u8; N]>> {
+        device.poll(wgpu::Maintain::Poll);
+        match self.mapped.as_ref()?.try_recv() {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => {
+                log::warn!("Failed to map pick buffer: {}", e);
+                return Some(None);
+            }
+            Err(mpsc::TryRecvError::Empty) => return None,
+            Err(mpsc::TryRecvError::Disconnected) => return Some(None),
+        }
+
+        let bytes = {
+            let view = self.buffer.slice(..).get_mapped_range();
+            std::array::from_fn(|i| view[i])
+        };
+        self.buffer.unmap();
+        Some(Some(bytes))
+    }
+}
+
+/// A depth texel on its way back from the GPU, with the camera it was rendered with.
+pub struct DepthPick {
+    readback: Readback,
+    /// Inverse of the frame's camera-relative view-projection.
+    inv_view_proj: DMat4,
+    camera_ecef: DVec3,
+    /// NDC x/y of the picked pixel's center.
+    ndc: [f64; 2],
+}
+
+impl DepthPick {
+    /// Records a copy of the depth texel at `pixel` (physical pixels, origin
+    /// top-left) into a new readback buffer. Returns `None` if the pixel lies
+    /// outside the texture. Submit `encoder` and then call [`DepthPick::map`].
+    pub fn capture(
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        depth: &wgpu::Texture,
+        pixel: [u32; 2],
+        camera: &Camera,
+    ) -> Option<Self> {
+        let readback = Readback::new(device, "Depth Pick Readback Buffer", PICK_ROW_BYTES as u64);
+        if !readback.copy_texel(encoder, depth, wgpu::TextureAspect::DepthOnly, pixel) {
+            return None;
+        }
+
+        // Pixel centers to NDC; framebuffer y grows downwards.
+        let (width, height) = (depth.width(), depth.height());
+        let ndc = [
+            (pixel[0] as f64 + 0.5) / width as f64 * 2.0 - 1.0,
+            1.0 - (pixel[1] as f64 + 0.5) / height as f64 * 2.0,
+        ];
+
+        Some(Self {
+            readback,
+            inv_view_proj: camera.view_proj_ecef().as_dmat4().inverse(),
+            camera_ecef: DVec3::from(camera.ecef_m()),
+            ndc,
+        })
+    }
+
+    /// Starts mapping the buffer. Call after the copy has been submitted.
+    pub fn map(&mut self) {
+        self.readback.map();
+    }
+
+    /// Checks, without blocking, whether the texel has landed.
+    ///
+    /// Returns `None` while the readback is in flight. Once done, returns the
+    /// ECEF position (meters) under the cursor, or `Some(None)` when the pixel
+    /// holds the cleared depth, i.e. empty space.
+    pub fn poll(&self, device: &wgpu::Device) -> Option<Option<[f64; 3]>> {
+        let Some(bytes) = self.readback.poll::<4>(device)? else {
+            return Some(None);
+        };
+        let depth = f32::from_le_bytes(bytes);
+
+        if depth >= 1.0 {
+            return Some(None);
+        }
+        let p = self.inv_view_proj * DVec4::new(self.ndc[0], self.ndc[1], depth as f64, 1.0);
+        Some(Some((self.camera_ecef + p.truncate() / p.w).into()))
+    }
+}
+
+/// A point found by [`PointPick`].
+#[derive(Debug, Clone)]
+pub struct PickedPoint {
+    /// Display name of the tile it belongs to; see [`TileGpu::name`].
+    pub tile_name: String,
+    pub ecef_m: [f64; 3],
+    /// Geodetic (lat°, lon°, height m).
+    pub geodetic: (f64, f64, f64),
+    /// Semantic label.
+    pub label: u32,
+}
+
+/// A click pick in flight: first the point ID under the cursor, then the
+/// point's instance data.
+pub struct PointPick {
+    stage: PickStage,
+}
+
+enum PickStage {
+    /// The pick target texel is on its way back.
+    Id(Readback),
+    /// The picked instance is on its way back from tile `slot`'s vertex buffer.
+    Instance { slot: usize, readback: Readback },
+}
+
+/// Outcome of [`PointPick::poll`].
+pub enum PickPoll {
+    InFlight(PointPick),
+    /// The picked point, or `None` when no point was drawn under the cursor.
+    Done(Option<PickedPoint>),
+}
+
+impl PointPick {
+    /// Records a copy of the pick target texel at `pixel` (physical pixels,
+    /// origin top-left). Returns `None` if the pixel lies outside the texture.
+    /// Submit `encoder` and then call [`PointPick::map`].
+    pub fn capture(
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        pick: &wgpu::Texture,
+        pixel: [u32; 2],
+    ) -> Option<Self> {
+        let readback = Readback::new(device, "Point Pick Readback Buffer", PICK_ROW_BYTES as u64);
+        readback
+            .copy_texel(encoder, pick, wgpu::TextureAspect::All, pixel)
+            .then_some(Self {
+                stage: PickStage::Id(readback),
+            })
+    }
+
+    /// Starts mapping the current stage's buffer. Call after its copy has been
+    /// submitted.
+    pub fn map(&mut self) {
+        match &mut self.stage {
+            PickStage::Id(readback) | PickStage::Instance { readback, .. } => readback.map(),
+        }
+    }
+
+    /// Advances the pick without blocking. `tiles` must be the tiles the pick
+    /// target was rendered from.
+    pub fn poll(self, device: &wgpu::Device, queue: &wgpu::Queue, tiles: &[TileGpu]) -> PickPoll {
+        match self.stage {
+            PickStage::Id(readback) => {
+                let Some(bytes) = readback.poll::<8>(device) else {
+                    return PickPoll::InFlight(Self {
+                        stage: PickStage::Id(readback),
+                    });
+                };
+                let Some(bytes) = bytes else {
+                    return PickPoll::Done(None);
+                };
+                let pick_id = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+                let instance = u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]);
+
+                // 0 is the cleared background.
+                let Some(slot) = (pick_id as usize).checked_sub(1) else {
+                    return PickPoll::Done(None);
+                };
+                let Some(tile) = tiles.get(slot).filter(|t| instance < t.instances_len) else {
+                    return PickPoll::Done(None);
+                };
+
+                let size = std::mem::size_of::<PointInstance>() as u64;
+                let readback = Readback::new(device, "Point Instance Readback Buffer", size);
+                let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("Point Pick Encoder"),
+                });
+                encoder.copy_buffer_to_buffer(
+                    &tile.vtx,
+                    instance as u64 * size,
+                    &readback.buffer,
+                    0,
+                    size,
+                );
+                queue.submit(std::iter::once(encoder.finish()));
+
+                let mut next = Self {
+                    stage: PickStage::Instance { slot, readback },
+                };
+                next.map();
+                PickPoll::InFlight(next)
+            }
+            PickStage::Instance { slot, readback } => {
+                const SIZE: usize = std::mem::size_of::<PointInstance>();
+                let Some(bytes) = readback.poll::<SIZE>(device) else {
+                    return PickPoll::InFlight(Self {
+                        stage: PickStage::Instance { slot, readback },
+                    });
+                };
+                let (Some(bytes), Some(tile)) = (bytes, tiles.get(slot)) else {
+                    return PickPoll::Done(None);
+                };
+                let point: PointInstance = bytemuck::cast(bytes);
+                PickPoll::Done(Some(tile.picked_point(&point)))
+            }
+        }
+    }
+}
+
+impl TileGpu {
+    /// Absolute position, geodetic coordinate and label of one of this
+    /// tile's points, placed as the hologram pass draws it.
+    fn picked_point(&self, point: &PointInstance) -> PickedPoint {
+        let upm = self.units_per_meter as f64;
+        let ofs_m = point.ofs_m.map(|v| v as f64);
+        let ofs_m = match &self.transform {
+            Some(xf) => xf.apply_offset(ofs_m),
+            None => ofs_m,
+        };
+        let ecef_m: [f64; 3] = std::array::from_fn(|k| self.anchor_units[k] as f64 / upm + ofs_m[k]);
+        PickedPoint {
+            tile_name: self.name(),
+            ecef_m,
+            geodetic: hypc::ecef_to_geodetic(ecef_m[0], ecef_m[1], ecef_m[2]),
+            label: point.label,
+        }
+    }
+}
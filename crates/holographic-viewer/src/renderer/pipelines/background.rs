@@ -0,0 +1,182 @@
+//! Vertical color gradient behind the scene, in place of a flat clear.
+//!
+//! Drawn first in the geometry pass as a full-screen triangle. It writes the
+//! same depth-linear texel the clear does (background depth, overlay tag), so
+//! the post passes keep treating it as empty space, and it leaves the depth
+//! buffer and point IDs untouched.
+
+/// Gradient colors, linear RGB; `w` is unused padding.
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct BackgroundUniforms {
+    top: [f32; 4],
+    bottom: [f32; 4],
+}
+
+pub struct BackgroundPipeline {
+    pipeline: wgpu::RenderPipeline,
+    bind_group: wgpu::BindGroup,
+    uniform_buffer: wgpu::Buffer,
+}
+
+impl BackgroundPipeline {
+    pub fn new(
+        device: &wgpu::Device,
+        color_fmt: wgpu::TextureFormat,
+        dlin_fmt: wgpu::TextureFormat,
+        depth_fmt: wgpu::TextureFormat,
+        pick_fmt: wgpu::TextureFormat,
+    ) -> Self {
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Background Uniform Buffer"),
+            size: std::mem::size_of::<BackgroundUniforms>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Background BGL"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Background Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Background WGSL"),
+            source: wgpu::ShaderSource::Wgsl(BACKGROUND_WGSL.into()),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Background Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Background Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[
+                    Some(wgpu::ColorTargetState {
+                        format: color_fmt,
+                        blend: None,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    }),
+                    Some(wgpu::ColorTargetState {
+                        format: dlin_fmt,
+                        blend: None,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    }),
+                    // Leaves the point IDs alone.
+                    Some(wgpu::ColorTargetState {
+                        format: pick_fmt,
+                        blend: None,
+                        write_mask: wgpu::ColorWrites::empty(),
+                    }),
+                ],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            // Never occludes anything: the grid and points depth-test against
+            // the cleared buffer as if nothing had been drawn.
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: depth_fmt,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Always,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        Self {
+            pipeline,
+            bind_group,
+            uniform_buffer,
+        }
+    }
+
+    /// Fills the targets with a gradient from `top` at the top edge of the
+    /// screen to `bottom` at the bottom edge (linear RGB).
+    pub fn draw<'a>(
+        &'a self,
+        rpass: &mut wgpu::RenderPass<'a>,
+        queue: &wgpu::Queue,
+        top: [f32; 3],
+        bottom: [f32; 3],
+    ) {
+        let uniforms = BackgroundUniforms {
+            top: [top[0], top[1], top[2], 0.0],
+            bottom: [bottom[0], bottom[1], bottom[2], 0.0],
+        };
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::bytes_of(&uniforms));
+
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_bind_group(0, &self.bind_group, &[]);
+        rpass.draw(0..3, 0..1);
+    }
+}
+
+pub const BACKGROUND_WGSL: &str = r#"
+struct BackgroundUniforms {
+    top: vec4<f32>,
+    bottom: vec4<f32>,
+}
+@group(0) @binding(0) var<uniform> U: BackgroundUniforms;
+
+struct VSOut {
+    @builtin(position) clip: vec4<f32>,
+    // 0 at the bottom edge, 1 at the top.
+    @location(0) t: f32,
+}
+
+// One triangle covering the screen, from the vertex index alone.
+@vertex
+fn vs_main(@builtin(vertex_index) i: u32) -> VSOut {
+    let pos = vec2<f32>(f32(i & 1u) * 4.0 - 1.0, f32(i >> 1u) * 4.0 - 1.0);
+    var out: VSOut;
+    out.clip = vec4<f32>(pos, 1.0, 1.0);
+    out.t = 0.5 * (pos.y + 1.0);
+    return out;
+}
+
+struct FSOut {
+    @location(0) color: vec4<f32>,
+    @location(1) dlin:  vec4<f32>,
+}
+
+@fragment
+fn fs_main(in: VSOut) -> FSOut {
+    var out: FSOut;
+    // Alpha 0, as cleared: this is not geometry.
+    out.color = vec4<f32>(mix(U.bottom.rgb, U.top.rgb, clamp(in.t, 0.0, 1.0)), 0.0);
+    // Background depth (r=1) and overlay tag (alpha=0), as the clear writes.
+    out.dlin = vec4<f32>(1.0, 0.0, 0.0, 0.0);
+    return out;
+}
+"#;
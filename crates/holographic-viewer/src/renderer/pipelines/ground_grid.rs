@@ -56,6 +56,7 @@ impl GroundGridPipeline {
         color_fmt:  wgpu::TextureFormat,
         dlin_fmt:   wgpu::TextureFormat,
         depth_fmt:  wgpu::TextureFormat,
+        pick_fmt:   wgpu::TextureFormat,
     ) -> Self {
         // Uniform buffer
         let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
@@ -146,6 +147,12 @@ impl GroundGridPipeline {
                         blend:      None, // Direct tag write
                         write_mask: wgpu::ColorWrites::ALL,
                     }),
+                    // Leaves the point IDs alone.
+                    Some(wgpu::ColorTargetState {
+                        format:     pick_fmt,
+                        blend:      None,
+                        write_mask: wgpu::ColorWrites::empty(),
+                    }),
                 ],
                 compilation_options: wgpu::PipelineCompilationOptions::default(),
             }),
@@ -13,6 +13,7 @@ impl HologramPipeline {
         color_fmt: wgpu::TextureFormat,
         depth_fmt: wgpu::TextureFormat,
         dlin_fmt: wgpu::TextureFormat,
+        pick_fmt: wgpu::TextureFormat,
     ) -> Self {
         // Uniform buffer layout for tile data
         let tile_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
@@ -130,6 +131,11 @@ impl HologramPipeline {
                         blend: None,
                         write_mask: wgpu::ColorWrites::ALL,
                     }),
+                    Some(wgpu::ColorTargetState {
+                        format: pick_fmt,
+                        blend: None,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    }),
                 ],
                 compilation_options: wgpu::PipelineCompilationOptions::default(),
             }),
@@ -1,5 +1,7 @@
 //! Rendering pipeline implementations.
 
+pub mod background;
 pub mod ground_grid;
 pub mod hologram;
 pub mod post_stack;
+pub mod tile_bounds;
@@ -1,4 +1,8 @@
-use std::time::Instant;
+use crate::camera::{Camera, OPENGL_TO_WGPU_MATRIX};
+use crate::data::types::CLASS_SIZE_SLOTS;
+use crate::renderer::profiler::GpuProfiler;
+use glam::Mat4;
+use std::{rc::Rc, time::Instant};
 use wgpu::util::DeviceExt;
 
 /// Intermediate texture format
@@ -98,6 +102,15 @@ struct UboEdl {
     inv_size: [f32; 2],
     strength: f32,
     radius_px: f32,
+    // Linear depth reconstruction: d = proj_b / (z_ndc + proj_a)
+    proj_a: f32,
+    proj_b: f32,
+    focal_px: f32,
+    radius_m: f32,
+    world_space: u32,
+    /// Orthographic projection: depth is affine in NDC z, and `focal_px` is pixels per meter.
+    ortho: u32,
+    _pad: [u32; 2],
 }
 
 #[repr(C)]
@@ -115,6 +128,17 @@ struct UboRgb {
     angle: f32,
 }
 
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable, Default)]
+struct UboBloom {
+    inv_size: [f32; 2],
+    threshold: f32,
+    intensity: f32,
+    /// Stage index, see [`BLOOM_STAGES`].
+    mode: u32,
+    _pad: [u32; 3],
+}
+
 #[repr(C)]
 #[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable, Default)]
 struct UboCrt {
@@ -159,6 +183,19 @@ struct RgbShiftPass {
     fs_vbo: wgpu::Buffer,
 }
 
+/// One stage of the bloom chain. Each stage owns its pipeline and UBO, since a
+/// single UBO rewritten between passes would only hold the last write.
+struct BloomPass {
+    pipeline: wgpu::RenderPipeline,
+    layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    ubo: wgpu::Buffer,
+    fs_vbo: wgpu::Buffer,
+}
+
+/// Bloom stages in execution order; the index is the shader's `mode`.
+const BLOOM_STAGES: [&str; 4] = ["bloom_bright", "bloom_blur_h", "bloom_blur_v", "bloom"];
+
 struct CrtPass {
     pipeline: wgpu::RenderPipeline,
     layout: wgpu::BindGroupLayout,
@@ -188,7 +225,12 @@ struct BlitPass {
 pub struct PostParams {
     pub edl_strength: f32,
     pub edl_radius_px: f32,
+    /// Neighbour radius in metres when `edl_world_space` is set.
+    pub edl_radius_m: f32,
     pub sem_amount: f32,
+    /// Luminance above which pixels contribute to bloom.
+    pub bloom_threshold: f32,
+    pub bloom_intensity: f32,
     pub rgb_amount: f32,
     pub rgb_angle: f32,
     pub crt_intensity: f32,
@@ -196,36 +238,74 @@ pub struct PostParams {
 
     // 🔧 Debug toggles
     pub edl_on: bool,
+    /// Compare linear eye-space depths over a world-space radius instead of pixels.
+    pub edl_world_space: bool,
     pub sem_on: bool,
+    pub bloom_on: bool,
     pub rgb_on: bool,
     pub crt_on: bool,
     pub grid_on: bool,
     pub grid_utm_align: bool,
+    /// Outline each tile's GEOT bbox on the ellipsoid.
+    pub tile_bounds_on: bool,
+    /// Show the semantic class legend.
+    pub legend_on: bool,
+    /// Top-down orthographic projection instead of perspective.
+    pub ortho: bool,
+
+    /// Background gradient at the top and bottom of the screen, linear RGB.
+    pub bg_top: [f32; 3],
+    pub bg_bottom: [f32; 3],
+
+    /// Scales the density-based point size before clamping; 1 = points as
+    /// wide as their mean spacing.
+    pub point_size_bias: f32,
+    /// Point-size multiplier per semantic label (consumed by the hologram pass).
+    pub class_point_size: [f32; CLASS_SIZE_SLOTS],
 
     /// 0 = Off (normal path)
     /// 1 = Depth (RT1.r) grayscale
     /// 2 = Labels (class color)
     /// 3 = Tag (RT1.a) monocrome
+    /// 4 = Tile (per-tile color, see [`DEBUG_MODE_TILE`])
     pub debug_mode: u32,
 }
 
+/// Debug view tinting each tile with its own color. The hologram pass writes
+/// the tint as base color, so the semantic and RGB passes are skipped.
+pub const DEBUG_MODE_TILE: u32 = 4;
+
 impl Default for PostParams {
     fn default() -> Self {
         Self {
             edl_strength: 1.4,
             edl_radius_px: 1.0,
+            edl_radius_m: 2.0,
             sem_amount: 0.80,
+            bloom_threshold: 0.6,
+            bloom_intensity: 0.8,
             rgb_amount: 0.0007,
             rgb_angle: 1.4,
             crt_intensity: 1.0,
             crt_vignette: 0.8,
 
             edl_on:  true,
+            edl_world_space: false,
             sem_on:  true,
+            bloom_on: true,
             rgb_on:  true,
             crt_on:  true,
             grid_on: true,
             grid_utm_align: false,
+            tile_bounds_on: false,
+            legend_on: true,
+            ortho: false,
+
+            bg_top: [0.010, 0.022, 0.040],
+            bg_bottom: [0.0, 0.0, 0.0],
+
+            point_size_bias: 1.0,
+            class_point_size: [1.0; CLASS_SIZE_SLOTS],
 
             debug_mode: 0,
         }
@@ -234,14 +314,20 @@ impl Default for PostParams {
 
 pub struct PostStack {
     pingpong: PingPong,
+    /// Bright-pass and blur scratch for bloom, separate from the main chain.
+    bloom_pingpong: PingPong,
     edl: EdlPass,
     sem: SemPost,
+    bloom: [BloomPass; 4],
     rgb: RgbShiftPass,
     crt: CrtPass,
     blit: BlitPass,
     dbg: DebugPass,
     pub params: PostParams,
+    /// Final (wgpu clip-space) projection of the current frame, for depth linearization.
+    proj: Mat4,
     start: Instant,
+    profiler: Option<Rc<GpuProfiler>>,
 }
 
 impl PostStack {
@@ -254,6 +340,7 @@ impl PostStack {
         let pingpong = PingPong::new(device, width, height);
         let edl = EdlPass::new(device, INTERMEDIATE_FMT);
         let sem = SemPost::new(device, INTERMEDIATE_FMT);
+        let bloom = std::array::from_fn(|_| BloomPass::new(device, INTERMEDIATE_FMT));
         let rgb = RgbShiftPass::new(device, INTERMEDIATE_FMT);
         let crt = CrtPass::new(device, out_fmt);
         let blit = BlitPass::new(device, out_fmt);
@@ -261,22 +348,45 @@ impl PostStack {
 
         Self {
             pingpong,
+            bloom_pingpong: PingPong::new(device, width, height),
             edl,
             sem,
+            bloom,
             rgb,
             crt,
             blit,
             dbg,
             params: PostParams::default(),
+            proj: Mat4::IDENTITY,
             start: Instant::now(),
+            profiler: None,
         }
     }
 
     pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
         self.pingpong.resize(device, width, height);
+        self.bloom_pingpong.resize(device, width, height);
     }
 
-    /// Run the post‑processing chain: EDL → Semantic → RGB shift → CRT
+    /// Records the camera projection used by depth-aware passes this frame.
+    pub fn set_camera(&mut self, camera: &Camera) {
+        self.proj = OPENGL_TO_WGPU_MATRIX * camera.proj;
+    }
+
+    /// Attaches a profiler; each executed pass then records GPU timestamps.
+    pub fn set_profiler(&mut self, profiler: Option<Rc<GpuProfiler>>) {
+        self.profiler = profiler;
+    }
+
+    /// Wraps `view` as a pass target, reserving timestamps when profiling.
+    fn target<'a>(&'a self, view: &'a wgpu::TextureView, name: &'static str) -> PassTarget<'a> {
+        PassTarget {
+            view,
+            timestamps: self.profiler.as_ref().and_then(|p| p.pass(name)),
+        }
+    }
+
+    /// Run the post‑processing chain: EDL → Semantic → Bloom → RGB shift → CRT
     pub fn run(
         &self,
         device: &wgpu::Device,
@@ -299,29 +409,44 @@ impl PostStack {
 
         // Pass 1: Eye-Dome Lighting
         if self.params.edl_on {
+            // RT1.r holds post-projection depth; with w = -z_eye it maps back to
+            // linear eye depth via the projection's z row.
+            let proj = self.proj;
+            let ubo = UboEdl {
+                inv_size,
+                strength: self.params.edl_strength,
+                radius_px: self.params.edl_radius_px,
+                proj_a: proj.z_axis.z,
+                proj_b: proj.w_axis.z,
+                focal_px: 0.5 * height * proj.y_axis.y.abs(),
+                radius_m: self.params.edl_radius_m,
+                world_space: self.params.edl_world_space as u32,
+                ortho: self.params.ortho as u32,
+                _pad: [0; 2],
+            };
             self.edl.draw(
                 device,
                 queue,
                 encoder,
-                targets.0, // Dst
-                source,    // Src
+                self.target(targets.0, "edl"), // Dst
+                source,                        // Src
                 depthlin,
-                inv_size,
-                self.params.edl_strength,
-                self.params.edl_radius_px,
+                &ubo,
             );
             source = targets.0;
             std::mem::swap(&mut targets.0, &mut targets.1);
         }
 
+        let tile_view = self.params.debug_mode == DEBUG_MODE_TILE;
+
         // Pass 2: Semantic Coloring
-        if self.params.sem_on {
+        if self.params.sem_on && !tile_view {
             self.sem.draw(
                 device,
                 queue,
                 encoder,
-                targets.0, // Dst
-                source,    // Src
+                self.target(targets.0, "sem"), // Dst
+                source,                        // Src
                 depthlin,
                 self.params.sem_amount,
             );
@@ -329,14 +454,45 @@ impl PostStack {
             std::mem::swap(&mut targets.0, &mut targets.1);
         }
 
-        // Pass 3: RGB Shift
-        if self.params.rgb_on {
+        // Pass 3: Bloom (bright pass → horizontal blur → vertical blur → composite)
+        if self.params.bloom_on && !tile_view {
+            let (bright, blurred) = (&self.bloom_pingpong.ping, &self.bloom_pingpong.pong);
+            // (dst, src, aux) per stage; only the composite reads `aux`.
+            let stages = [
+                (bright, source, source),
+                (blurred, bright, bright),
+                (bright, blurred, blurred),
+                (targets.0, source, bright),
+            ];
+            for (mode, (pass, (dst, src, aux))) in self.bloom.iter().zip(stages).enumerate() {
+                let ubo = UboBloom {
+                    inv_size,
+                    threshold: self.params.bloom_threshold,
+                    intensity: self.params.bloom_intensity,
+                    mode: mode as u32,
+                    _pad: [0; 3],
+                };
+                pass.draw(
+                    device,
+                    queue,
+                    encoder,
+                    self.target(dst, BLOOM_STAGES[mode]),
+                    [src, aux],
+                    &ubo,
+                );
+            }
+            source = targets.0;
+            std::mem::swap(&mut targets.0, &mut targets.1);
+        }
+
+        // Pass 4: RGB Shift
+        if self.params.rgb_on && !tile_view {
             self.rgb.draw(
                 device,
                 queue,
                 encoder,
-                targets.0, // Dst
-                source,    // Src
+                self.target(targets.0, "rgb"), // Dst
+                source,                        // Src
                 depthlin,
                 inv_size,
                 self.params.rgb_amount,
@@ -353,7 +509,7 @@ impl PostStack {
                 device,
                 queue,
                 encoder,
-                swapchain_dst,
+                self.target(swapchain_dst, "debug"),
                 source,
                 depthlin,
                 self.params.debug_mode,
@@ -367,7 +523,7 @@ impl PostStack {
                 device,
                 queue,
                 encoder,
-                swapchain_dst,
+                self.target(swapchain_dst, "crt"),
                 source,
                 depthlin,
                 inv_size,
@@ -376,7 +532,8 @@ impl PostStack {
                 self.params.crt_vignette,
             );
         } else {
-            self.blit.draw(device, encoder, swapchain_dst, source);
+            self.blit
+                .draw(device, encoder, self.target(swapchain_dst, "blit"), source);
         }
     }
 }
@@ -511,22 +668,29 @@ macro_rules! create_post_pass {
 
 create_post_pass!(EdlPass, UboEdl, "edl.wgsl");
 create_post_pass!(SemPost, UboSem, "sem_post.wgsl");
+create_post_pass!(BloomPass, UboBloom, "bloom.wgsl");
 create_post_pass!(RgbShiftPass, UboRgb, "rgbshift.wgsl");
 create_post_pass!(CrtPass, UboCrt, "crt.wgsl");
 create_post_pass!(DebugPass, UboDbg, "debug_vis.wgsl");
 
+/// Render target of a full-screen pass, plus optional profiler timestamps.
+pub struct PassTarget<'a> {
+    pub view: &'a wgpu::TextureView,
+    pub timestamps: Option<wgpu::RenderPassTimestampWrites<'a>>,
+}
+
 fn execute_pass(
     pipeline: &wgpu::RenderPipeline,
     encoder: &mut wgpu::CommandEncoder,
     bind_group: &wgpu::BindGroup,
     fs_vbo: &wgpu::Buffer,
-    dst: &wgpu::TextureView,
+    dst: PassTarget<'_>,
     label: &str,
 ) {
     let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
         label: Some(label),
         color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-            view: dst,
+            view: dst.view,
             resolve_target: None,
             ops: wgpu::Operations {
                 load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
@@ -534,7 +698,7 @@ fn execute_pass(
             },
         })],
         depth_stencil_attachment: None,
-        timestamp_writes: None,
+        timestamp_writes: dst.timestamps,
         occlusion_query_set: None,
     });
 
@@ -550,22 +714,12 @@ impl EdlPass {
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         encoder: &mut wgpu::CommandEncoder,
-        dst: &wgpu::TextureView,
+        dst: PassTarget<'_>,
         t_color: &wgpu::TextureView,
         t_depthlin: &wgpu::TextureView,
-        inv_size: [f32; 2],
-        strength: f32,
-        radius_px: f32,
+        ubo: &UboEdl,
     ) {
-        queue.write_buffer(
-            &self.ubo,
-            0,
-            bytemuck::bytes_of(&UboEdl {
-                inv_size,
-                strength,
-                radius_px,
-            }),
-        );
+        queue.write_buffer(&self.ubo, 0, bytemuck::bytes_of(ubo));
         let bind = device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: Some("EDL Bind"),
             layout: &self.layout,
@@ -598,7 +752,7 @@ impl SemPost {
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         encoder: &mut wgpu::CommandEncoder,
-        dst: &wgpu::TextureView,
+        dst: PassTarget<'_>,
         t_src: &wgpu::TextureView,
         t_depthlin: &wgpu::TextureView,
         amount: f32,
@@ -637,13 +791,51 @@ impl SemPost {
     }
 }
 
+impl BloomPass {
+    /// Runs the stage selected by `ubo.mode`; `textures` binds to `tSrc` and `tAux`.
+    pub fn draw(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        dst: PassTarget<'_>,
+        textures: [&wgpu::TextureView; 2],
+        ubo: &UboBloom,
+    ) {
+        queue.write_buffer(&self.ubo, 0, bytemuck::bytes_of(ubo));
+        let bind = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Bloom Bind"),
+            layout: &self.layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(textures[0]),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(textures[1]),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: self.ubo.as_entire_binding(),
+                },
+            ],
+        });
+        execute_pass(&self.pipeline, encoder, &bind, &self.fs_vbo, dst, "Bloom Pass");
+    }
+}
+
 impl RgbShiftPass {
     pub fn draw(
         &self,
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         encoder: &mut wgpu::CommandEncoder,
-        dst: &wgpu::TextureView,
+        dst: PassTarget<'_>,
         t_src: &wgpu::TextureView,
         t_depthlin: &wgpu::TextureView,
         inv_size: [f32; 2],
@@ -691,7 +883,7 @@ impl CrtPass {
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         encoder: &mut wgpu::CommandEncoder,
-        dst: &wgpu::TextureView,
+        dst: PassTarget<'_>,
         t_src: &wgpu::TextureView,
         t_depthlin: &wgpu::TextureView,
         inv_size: [f32; 2],
@@ -742,7 +934,7 @@ impl DebugPass {
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         encoder: &mut wgpu::CommandEncoder,
-        dst: &wgpu::TextureView,
+        dst: PassTarget<'_>,
         t_src: &wgpu::TextureView,
         t_depth: &wgpu::TextureView,
         mode: u32,
@@ -877,7 +1069,7 @@ impl BlitPass {
         &self,
         device: &wgpu::Device,
         encoder: &mut wgpu::CommandEncoder,
-        dst: &wgpu::TextureView,
+        dst: PassTarget<'_>,
         t_src: &wgpu::TextureView,
     ) {
         let bind = device.create_bind_group(&wgpu::BindGroupDescriptor {
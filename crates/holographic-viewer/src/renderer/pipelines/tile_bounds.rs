@@ -0,0 +1,233 @@
+//! Debug wireframe of each tile's GEOT bbox: the lat/lon rectangle on the
+//! ellipsoid (height 0), outlined in the tile's debug color.
+//!
+//! Outlines are cached per tile in ECEF (see [`TileGpu::footprint_ecef_m`])
+//! and re-based to the camera on the CPU every frame, so the f32 vertices stay
+//! precise without the hi/lo split the point shader needs.
+
+use crate::{camera::Camera, data::types::TileGpu};
+use glam::Mat4;
+
+/// One line vertex: camera-relative ECEF position and color.
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct LineVertex {
+    pos_rel_m: [f32; 3],
+    color: [f32; 3],
+}
+
+pub struct TileBoundsPipeline {
+    pipeline: wgpu::RenderPipeline,
+    bind_group: wgpu::BindGroup,
+    uniform_buffer: wgpu::Buffer,
+    vb: wgpu::Buffer,
+    /// Vertices `vb` can hold.
+    vb_capacity: u64,
+    /// Vertices written by the last `prepare`.
+    vertex_count: u32,
+}
+
+impl TileBoundsPipeline {
+    pub fn new(
+        device: &wgpu::Device,
+        color_fmt: wgpu::TextureFormat,
+        dlin_fmt: wgpu::TextureFormat,
+        depth_fmt: wgpu::TextureFormat,
+        pick_fmt: wgpu::TextureFormat,
+    ) -> Self {
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Tile Bounds Uniform Buffer"),
+            size: std::mem::size_of::<Mat4>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Tile Bounds BGL"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Tile Bounds Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Tile Bounds WGSL"),
+            source: wgpu::ShaderSource::Wgsl(TILE_BOUNDS_WGSL.into()),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Tile Bounds Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Tile Bounds Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: std::mem::size_of::<LineVertex>() as u64,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3],
+                }],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[
+                    Some(wgpu::ColorTargetState {
+                        format: color_fmt,
+                        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    }),
+                    Some(wgpu::ColorTargetState {
+                        format: dlin_fmt,
+                        blend: None, // Direct tag write
+                        write_mask: wgpu::ColorWrites::ALL,
+                    }),
+                    // Leaves the point IDs alone.
+                    Some(wgpu::ColorTargetState {
+                        format: pick_fmt,
+                        blend: None,
+                        write_mask: wgpu::ColorWrites::empty(),
+                    }),
+                ],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::LineList,
+                ..Default::default()
+            },
+            // Height 0 usually lies under the points (ellipsoidal heights are
+            // tens of meters in most places), so draw on top of them.
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: depth_fmt,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Always,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let vb_capacity = 1024;
+        let vb = Self::create_vb(device, vb_capacity);
+
+        Self {
+            pipeline,
+            bind_group,
+            uniform_buffer,
+            vb,
+            vb_capacity,
+            vertex_count: 0,
+        }
+    }
+
+    fn create_vb(device: &wgpu::Device, vertices: u64) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Tile Bounds VB"),
+            size: vertices * std::mem::size_of::<LineVertex>() as u64,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    /// Writes the outlines of the shown tiles that carry a GEOT, relative to
+    /// the camera. Call once per frame before [`TileBoundsPipeline::draw`].
+    pub fn prepare(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        tiles: &[TileGpu],
+        camera: &Camera,
+    ) {
+        let cam = camera.ecef_m();
+        let rel = |p: &[f64; 3]| -> [f32; 3] { std::array::from_fn(|k| (p[k] - cam[k]) as f32) };
+
+        let mut vertices = Vec::new();
+        for tile in tiles.iter().filter(|t| t.visible) {
+            let ring = &tile.footprint_ecef_m;
+            for (i, a) in ring.iter().enumerate() {
+                let b = &ring[(i + 1) % ring.len()];
+                for p in [a, b] {
+                    vertices.push(LineVertex {
+                        pos_rel_m: rel(p),
+                        color: tile.debug_color,
+                    });
+                }
+            }
+        }
+
+        if vertices.len() as u64 > self.vb_capacity {
+            self.vb_capacity = (vertices.len() as u64).next_power_of_two();
+            self.vb = Self::create_vb(device, self.vb_capacity);
+        }
+        queue.write_buffer(&self.vb, 0, bytemuck::cast_slice(&vertices));
+        queue.write_buffer(
+            &self.uniform_buffer,
+            0,
+            bytemuck::bytes_of(&camera.view_proj_ecef()),
+        );
+        self.vertex_count = vertices.len() as u32;
+    }
+
+    pub fn draw<'a>(&'a self, rpass: &mut wgpu::RenderPass<'a>) {
+        if self.vertex_count == 0 {
+            return;
+        }
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_bind_group(0, &self.bind_group, &[]);
+        rpass.set_vertex_buffer(0, self.vb.slice(..));
+        rpass.draw(0..self.vertex_count, 0..1);
+    }
+}
+
+pub const TILE_BOUNDS_WGSL: &str = r#"
+@group(0) @binding(0) var<uniform> view_proj: mat4x4<f32>;
+
+struct VSOut {
+    @builtin(position) clip: vec4<f32>,
+    @location(0) color: vec3<f32>,
+}
+
+@vertex
+fn vs_main(@location(0) pos_rel_m: vec3<f32>, @location(1) color: vec3<f32>) -> VSOut {
+    var out: VSOut;
+    out.clip = view_proj * vec4<f32>(pos_rel_m, 1.0);
+    out.color = color;
+    return out;
+}
+
+struct FSOut {
+    @location(0) color: vec4<f32>,
+    @location(1) dlin:  vec4<f32>,
+}
+
+@fragment
+fn fs_main(in: VSOut) -> FSOut {
+    var out: FSOut;
+    out.color = vec4<f32>(in.color, 0.9);
+    // Overlay tag (alpha=0) and background depth (r=1), as the grid writes.
+    out.dlin = vec4<f32>(1.0, 0.0, 0.0, 0.0);
+    return out;
+}
+"#;
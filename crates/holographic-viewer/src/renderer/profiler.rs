@@ -0,0 +1,219 @@
+//! GPU pass timing via timestamp queries, dumped as a Chrome trace.
+//!
+//! Each profiled render pass gets a begin/end timestamp pair. At the end of
+//! a frame the query set is resolved and read back synchronously, so
+//! profiling stalls the CPU on the GPU every frame; it's a measurement mode,
+//! not something to leave on.
+
+use anyhow::{bail, Context, Result};
+use std::{
+    cell::{Cell, RefCell},
+    fmt::Write as _,
+    path::PathBuf,
+};
+
+/// Upper bound on timed passes per frame (geometry, up to 8 post passes, egui).
+const MAX_SCOPES: u32 = 16;
+const QUERY_COUNT: u32 = MAX_SCOPES * 2;
+const QUERY_BYTES: u64 = QUERY_COUNT as u64 * std::mem::size_of::<u64>() as u64;
+
+/// One completed pass, in microseconds relative to the first profiled frame.
+struct TraceEvent {
+    name: &'static str,
+    frame: u32,
+    ts_us: f64,
+    dur_us: f64,
+}
+
+pub struct GpuProfiler {
+    query_set: wgpu::QuerySet,
+    resolve_buf: wgpu::Buffer,
+    readback_buf: wgpu::Buffer,
+    /// Nanoseconds per timestamp tick.
+    period_ns: f64,
+    /// Pass names recorded this frame, in query-pair order.
+    scopes: RefCell<Vec<&'static str>>,
+    events: RefCell<Vec<TraceEvent>>,
+    /// First timestamp seen, used as the trace origin.
+    origin: Cell<Option<u64>>,
+    frames_done: Cell<u32>,
+    frames_wanted: u32,
+    out_path: PathBuf,
+}
+
+impl GpuProfiler {
+    /// Creates a profiler that records `frames` frames and then writes the
+    /// trace to `out_path`. Fails if the device lacks `TIMESTAMP_QUERY`.
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        frames: u32,
+        out_path: PathBuf,
+    ) -> Result<Self> {
+        if !device.features().contains(wgpu::Features::TIMESTAMP_QUERY) {
+            bail!("GPU adapter does not support timestamp queries");
+        }
+
+        let query_set = device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("Profiler Query Set"),
+            ty: wgpu::QueryType::Timestamp,
+            count: QUERY_COUNT,
+        });
+        let resolve_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Profiler Resolve Buffer"),
+            size: QUERY_BYTES,
+            usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Profiler Readback Buffer"),
+            size: QUERY_BYTES,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        Ok(Self {
+            query_set,
+            resolve_buf,
+            readback_buf,
+            period_ns: queue.get_timestamp_period() as f64,
+            scopes: RefCell::new(Vec::with_capacity(MAX_SCOPES as usize)),
+            events: RefCell::new(Vec::new()),
+            origin: Cell::new(None),
+            frames_done: Cell::new(0),
+            frames_wanted: frames.max(1),
+            out_path,
+        })
+    }
+
+    /// Reserves a timestamp pair for a render pass named `name`. Returns
+    /// `None` once the per-frame budget is used up or profiling has finished.
+    pub fn pass(&self, name: &'static str) -> Option<wgpu::RenderPassTimestampWrites<'_>> {
+        if self.is_finished() {
+            return None;
+        }
+        let mut scopes = self.scopes.borrow_mut();
+        let index = scopes.len() as u32;
+        if index >= MAX_SCOPES {
+            return None;
+        }
+        scopes.push(name);
+        Some(wgpu::RenderPassTimestampWrites {
+            query_set: &self.query_set,
+            beginning_of_pass_write_index: Some(index * 2),
+            end_of_pass_write_index: Some(index * 2 + 1),
+        })
+    }
+
+    /// Resolves and reads back this frame's timestamps. Call after every
+    /// encoder that used [`GpuProfiler::pass`] has been submitted. Writes the
+    /// trace when the last requested frame completes.
+    pub fn end_frame(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> Result<()> {
+        let names = std::mem::take(&mut *self.scopes.borrow_mut());
+        if names.is_empty() || self.is_finished() {
+            return Ok(());
+        }
+        let count = names.len() as u32 * 2;
+        let bytes = count as u64 * std::mem::size_of::<u64>() as u64;
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Profiler Resolve Encoder"),
+        });
+        encoder.resolve_query_set(&self.query_set, 0..count, &self.resolve_buf, 0);
+        encoder.copy_buffer_to_buffer(&self.resolve_buf, 0, &self.readback_buf, 0, bytes);
+        queue.submit(std::iter::once(encoder.finish()));
+
+        let slice = self.readback_buf.slice(..bytes);
+        let (tx, rx) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |r| {
+            let _ = tx.send(r);
+        });
+        device.poll(wgpu::Maintain::Wait);
+        rx.recv()
+            .context("timestamp readback callback dropped")?
+            .context("failed to map timestamp readback buffer")?;
+
+        let ticks: Vec<u64> = bytemuck::cast_slice(&slice.get_mapped_range()).to_vec();
+        self.readback_buf.unmap();
+
+        let frame = self.frames_done.get();
+        let origin = self.origin.get().unwrap_or(ticks[0]);
+        self.origin.set(Some(origin));
+        let to_us = |t: u64| t.saturating_sub(origin) as f64 * self.period_ns / 1000.0;
+
+        let mut events = self.events.borrow_mut();
+        for (name, pair) in names.iter().zip(ticks.chunks_exact(2)) {
+            let (start, end) = (pair[0], pair[1].max(pair[0]));
+            events.push(TraceEvent {
+                name,
+                frame,
+                ts_us: to_us(start),
+                dur_us: to_us(end) - to_us(start),
+            });
+        }
+        drop(events);
+
+        self.frames_done.set(frame + 1);
+        if self.is_finished() {
+            self.write_trace()?;
+        }
+        Ok(())
+    }
+
+    /// True once all requested frames have been recorded and written.
+    pub fn is_finished(&self) -> bool {
+        self.frames_done.get() >= self.frames_wanted
+    }
+
+    fn write_trace(&self) -> Result<()> {
+        let events = self.events.borrow();
+        let mut json = String::from("{\"traceEvents\":[\n");
+        json.push_str(
+            "{\"name\":\"thread_name\",\"ph\":\"M\",\"pid\":0,\"tid\":0,\"args\":{\"name\":\"GPU\"}}",
+        );
+        for e in events.iter() {
+            let _ = write!(
+                json,
+                ",\n{{\"name\":\"{}\",\"cat\":\"gpu\",\"ph\":\"X\",\"pid\":0,\"tid\":0,\
+                 \"ts\":{:.3},\"dur\":{:.3},\"args\":{{\"frame\":{}}}}}",
+                e.name, e.ts_us, e.dur_us, e.frame
+            );
+        }
+        json.push_str("\n],\"displayTimeUnit\":\"ms\"}\n");
+
+        std::fs::write(&self.out_path, json)
+            .with_context(|| format!("writing {}", self.out_path.display()))?;
+
+        log::info!(
+            "Wrote {} GPU pass timings over {} frames to {}",
+            events.len(),
+            self.frames_done.get(),
+            self.out_path.display()
+        );
+        for name in unique_names(&events) {
+            let durs: Vec<f64> = events
+                .iter()
+                .filter(|e| e.name == name)
+                .map(|e| e.dur_us)
+                .collect();
+            let mean = durs.iter().sum::<f64>() / durs.len() as f64;
+            log::info!(
+                "  {:<10} mean {:>8.1} us over {} frames",
+                name,
+                mean,
+                durs.len()
+            );
+        }
+        Ok(())
+    }
+}
+
+fn unique_names(events: &[TraceEvent]) -> Vec<&'static str> {
+    let mut names: Vec<&'static str> = Vec::new();
+    for e in events {
+        if !names.contains(&e.name) {
+            names.push(e.name);
+        }
+    }
+    names
+}
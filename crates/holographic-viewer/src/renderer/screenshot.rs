@@ -0,0 +1,179 @@
+//! Frame capture to PNG.
+//!
+//! A capture copies the presented swapchain texture into a `MAP_READ` buffer
+//! in the frame's own encoder. The map is then polled without blocking on
+//! later frames, and the pixels are encoded and written on a worker thread,
+//! so neither the capture frame nor the ones after it wait on the GPU.
+
+use anyhow::{bail, Context, Result};
+use std::{path::PathBuf, sync::mpsc};
+
+/// A copy of one frame on its way back from the GPU.
+pub struct PendingScreenshot {
+    buffer: wgpu::Buffer,
+    width: u32,
+    height: u32,
+    /// Row pitch in the buffer, padded to `COPY_BYTES_PER_ROW_ALIGNMENT`.
+    padded_bytes_per_row: u32,
+    /// Whether the texture is BGRA and must be swizzled to RGBA.
+    bgra: bool,
+    /// Where the PNG is written.
+    out_path: PathBuf,
+    /// Result of `map_async`, once `map` has been called.
+    mapped: Option<mpsc::Receiver<Result<(), wgpu::BufferAsyncError>>>,
+}
+
+impl PendingScreenshot {
+    /// Records a copy of `texture` into a new readback buffer, to be saved to
+    /// `out_path`. Submit `encoder` and then call [`PendingScreenshot::map`].
+    pub fn capture(
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        texture: &wgpu::Texture,
+        out_path: PathBuf,
+    ) -> Result<Self> {
+        let bgra = match texture.format() {
+            wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => false,
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => true,
+            other => bail!("cannot capture a {:?} surface", other),
+        };
+        if !texture.usage().contains(wgpu::TextureUsages::COPY_SRC) {
+            bail!("the surface does not support copies");
+        }
+
+        let (width, height) = (texture.width(), texture.height());
+        let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let padded_bytes_per_row = (width * 4).div_ceil(align) * align;
+
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Screenshot Readback Buffer"),
+            size: padded_bytes_per_row as u64 * height as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: Some(height),
+                },
+            },
+            texture.size(),
+        );
+
+        Ok(Self {
+            buffer,
+            width,
+            height,
+            padded_bytes_per_row,
+            bgra,
+            out_path,
+            mapped: None,
+        })
+    }
+
+    /// Starts mapping the buffer. Call after the copy has been submitted.
+    pub fn map(&mut self) {
+        let (tx, rx) = mpsc::channel();
+        self.buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |r| {
+                let _ = tx.send(r);
+            });
+        self.mapped = Some(rx);
+    }
+
+    /// Checks, without blocking, whether the copy has landed. Once it has, the
+    /// pixels are handed to a thread that writes the PNG and `None` is
+    /// returned; until then the capture is given back to be polled again.
+    pub fn poll(self, device: &wgpu::Device) -> Option<Self> {
+        device.poll(wgpu::Maintain::Poll);
+        let result = match self.mapped.as_ref().map(|rx| rx.try_recv()) {
+            Some(Ok(result)) => result,
+            Some(Err(mpsc::TryRecvError::Empty)) | None => return Some(self),
+            Some(Err(mpsc::TryRecvError::Disconnected)) => {
+                log::error!("Screenshot readback was dropped");
+                return None;
+            }
+        };
+        if let Err(e) = result {
+            log::error!("Failed to map screenshot buffer: {}", e);
+            return None;
+        }
+
+        // Strip the row padding, swizzle to RGBA and force alpha opaque.
+        let row_bytes = self.width as usize * 4;
+        let mut rgba = Vec::with_capacity(row_bytes * self.height as usize);
+        {
+            let view = self.buffer.slice(..).get_mapped_range();
+            for row in view.chunks_exact(self.padded_bytes_per_row as usize) {
+                for px in row[..row_bytes].chunks_exact(4) {
+                    let [r, g, b] = if self.bgra {
+                        [px[2], px[1], px[0]]
+                    } else {
+                        [px[0], px[1], px[2]]
+                    };
+                    rgba.extend_from_slice(&[r, g, b, 255]);
+                }
+            }
+        }
+        self.buffer.unmap();
+
+        let (width, height, out_path) = (self.width, self.height, self.out_path);
+        std::thread::spawn(move || {
+            let written = encode_png(width, height, &rgba).and_then(|png| {
+                std::fs::write(&out_path, png)
+                    .with_context(|| format!("writing {}", out_path.display()))
+            });
+            match written {
+                Ok(()) => log::info!("Saved screenshot to {}", out_path.display()),
+                Err(e) => log::error!("Failed to save screenshot: {:#}", e),
+            }
+        });
+        None
+    }
+}
+
+/// A `screenshot-<local time>.png` path in the working directory.
+pub fn timestamped_path() -> PathBuf {
+    let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S%.3f");
+    PathBuf::from(format!("screenshot-{}.png", stamp))
+}
+
+/// Encodes 8-bit RGBA pixels as a PNG.
+fn encode_png(width: u32, height: u32, rgba: &[u8]) -> Result<Vec<u8>> {
+    let mut png = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(rgba)?;
+    writer.finish()?;
+    Ok(png)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encoded_png_decodes_to_the_same_pixels() {
+        let (width, height) = (3, 2);
+        let rgba: Vec<u8> = (0..width * height * 4).map(|i| (i * 7) as u8).collect();
+        let png = encode_png(width, height, &rgba).unwrap();
+
+        let mut reader = png::Decoder::new(png.as_slice()).read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+        assert_eq!((info.width, info.height), (width, height));
+        assert_eq!(
+            (info.color_type, info.bit_depth),
+            (png::ColorType::Rgba, png::BitDepth::Eight)
+        );
+        assert_eq!(&pixels[..info.buffer_size()], rgba.as_slice());
+    }
+}
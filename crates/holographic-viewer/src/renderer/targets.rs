@@ -3,18 +3,25 @@
 pub struct Targets {
     // Private textures – keep alive for the lifetime of the views.
     _color_tex: wgpu::Texture,
-    _depth_tex: wgpu::Texture,
     _dlin_tex: wgpu::Texture,
+    /// Depth texture, also read back for cursor picking.
+    pub depth_tex: wgpu::Texture,
+    /// Point ID target, read back for click picking.
+    pub pick_tex: wgpu::Texture,
 
     // Public texture views used by render passes and post‑processing.
     pub color: wgpu::TextureView,
     pub depth: wgpu::TextureView,
     pub dlin: wgpu::TextureView,
+    /// Per pixel `(tile slot + 1, instance index)` of the point drawn there;
+    /// 0 in `r` where no point was drawn.
+    pub pick: wgpu::TextureView,
 
     // Formats required by pipeline creation.
     pub color_fmt: wgpu::TextureFormat,
     pub depth_fmt: wgpu::TextureFormat,
     pub dlin_fmt: wgpu::TextureFormat,
+    pub pick_fmt: wgpu::TextureFormat,
 }
 
 impl Targets {
@@ -33,6 +40,7 @@ impl Targets {
         let color_fmt = wgpu::TextureFormat::Rgba16Float;
         let depth_fmt = wgpu::TextureFormat::Depth32Float;
         let dlin_fmt = wgpu::TextureFormat::Rgba16Float;
+        let pick_fmt = wgpu::TextureFormat::Rg32Uint;
 
         // Helper to create a texture with the given parameters.
         let create_tex = |label: &str, format, usage| {
@@ -58,7 +66,7 @@ impl Targets {
         let depth_tex = create_tex(
             "Scene Depth Target",
             depth_fmt,
-            wgpu::TextureUsages::RENDER_ATTACHMENT,
+            wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
         );
 
         let dlin_tex = create_tex(
@@ -67,17 +75,26 @@ impl Targets {
             wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
         );
 
+        let pick_tex = create_tex(
+            "Point Pick Target",
+            pick_fmt,
+            wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        );
+
         // Assemble the struct.
         Self {
             color: color_tex.create_view(&wgpu::TextureViewDescriptor::default()),
             depth: depth_tex.create_view(&wgpu::TextureViewDescriptor::default()),
             dlin: dlin_tex.create_view(&wgpu::TextureViewDescriptor::default()),
+            pick: pick_tex.create_view(&wgpu::TextureViewDescriptor::default()),
             _color_tex: color_tex,
-            _depth_tex: depth_tex,
+            depth_tex,
             _dlin_tex: dlin_tex,
+            pick_tex,
             color_fmt,
             depth_fmt,
             dlin_fmt,
+            pick_fmt,
         }
     }
 
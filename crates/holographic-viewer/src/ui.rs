@@ -1,11 +1,27 @@
 // holographic-viewer/src/ui.rs
 //! UI rendering using egui.
 
-use crate::renderer::pipelines::post_stack::PostParams;
+use crate::data::types::{TileGpu, SEM_CLASS_COLORS, SEM_UNKNOWN_COLOR};
+use crate::renderer::picking::PickedPoint;
+use crate::renderer::pipelines::post_stack::{PostParams, DEBUG_MODE_TILE};
 use egui::{Area, Frame, RichText};
 
 /// Draws the HUD overlay, including corner brackets and status text.
-pub fn draw_hud(egui_ctx: &egui::Context, altitude: i32, total_points: u32) {
+///
+/// `tiles` is the number of tiles that passed frustum culling and the number
+/// loaded; `loading` the tiles received and found while a load is running.
+/// `hover` is `None` while the cursor is outside the window, and holds the
+/// geodetic (lat°, lon°, height m) under it, if any. `frame` is the current
+/// FPS and the rolling average frame time in milliseconds.
+pub fn draw_hud(
+    egui_ctx: &egui::Context,
+    altitude: i32,
+    total_points: u32,
+    tiles: (usize, usize),
+    loading: Option<(usize, usize)>,
+    hover: Option<Option<(f64, f64, f64)>>,
+    frame: (f64, f64),
+) {
     // Draw corner brackets and central dot
     {
         let painter = egui_ctx.layer_painter(egui::LayerId::new(
@@ -102,11 +118,37 @@ pub fn draw_hud(egui_ctx: &egui::Context, altitude: i32, total_points: u32) {
                             .monospace()
                             .color(text_color),
                     );
+                    ui.label(
+                        RichText::new(format!("TILES:    {} / {} IN VIEW", tiles.0, tiles.1))
+                            .monospace()
+                            .color(text_color),
+                    );
+                    if let Some((done, total)) = loading {
+                        ui.label(
+                            RichText::new(format!("LOADING:  {} / {} TILES", done, total))
+                                .monospace()
+                                .color(text_color),
+                        );
+                    }
                     ui.label(
                         RichText::new(format!("ALTITUDE: {}M", altitude))
                             .monospace()
                             .color(text_color),
                     );
+                    ui.label(
+                        RichText::new(format!("FPS:      {:.0} ({:.2} MS AVG)", frame.0, frame.1))
+                            .monospace()
+                            .color(text_color),
+                    );
+                    if let Some(hover) = hover {
+                        let text = match hover {
+                            Some((lat, lon, h)) => {
+                                format!("CURSOR:   {:.6}, {:.6}, {:.1}M", lat, lon, h)
+                            }
+                            None => "CURSOR:   —".to_string(),
+                        };
+                        ui.label(RichText::new(text).monospace().color(text_color));
+                    }
                     ui.label(
                         RichText::new("STATUS:  SCAN  COMPLETE")
                             .monospace()
@@ -117,6 +159,121 @@ pub fn draw_hud(egui_ctx: &egui::Context, altitude: i32, total_points: u32) {
     }
 }
 
+/// Draws the result of the last click pick in the bottom-left corner, HUD
+/// style: the picked point's class, position and tile, or that the click hit
+/// no point.
+pub fn draw_pick_panel(egui_ctx: &egui::Context, pick: Option<&PickedPoint>) {
+    let text = match pick {
+        Some(p) => {
+            let class = hypc::CLASS_NAMES
+                .get(p.label as usize)
+                .map_or_else(|| format!("CLASS {}", p.label), |n| n.to_uppercase());
+            let (lat, lon, h) = p.geodetic;
+            format!(
+                "PICK:  {} ({})\nGEO:   {:.7}, {:.7}, {:.2}M\nECEF:  {:.2}, {:.2}, {:.2}\nTILE:  {}",
+                class, p.label, lat, lon, h, p.ecef_m[0], p.ecef_m[1], p.ecef_m[2], p.tile_name
+            )
+        }
+        None => "PICK:  NOTHING UNDER CURSOR".to_string(),
+    };
+
+    Area::new("pick_panel".into())
+        .anchor(egui::Align2::LEFT_BOTTOM, egui::vec2(40.0, -42.0))
+        .interactable(false)
+        .order(egui::Order::Foreground)
+        .show(egui_ctx, |ui| {
+            ui.label(
+                RichText::new(text)
+                    .monospace()
+                    .color(egui::Color32::from_rgb(45, 247, 255)),
+            );
+        });
+}
+
+/// Draws the semantic class legend in the top-right corner: one swatch per class
+/// in `classes`, colored as the semantic post pass colors it.
+pub fn draw_class_legend(egui_ctx: &egui::Context, classes: &[u8]) {
+    if classes.is_empty() {
+        return;
+    }
+
+    Area::new("class_legend".into())
+        .anchor(egui::Align2::RIGHT_TOP, egui::vec2(-40.0, 42.0))
+        .interactable(false)
+        .show(egui_ctx, |ui| {
+            Frame::dark_canvas(ui.style()).show(ui, |ui| {
+                ui.heading("Classes");
+                for &class in classes {
+                    let [r, g, b] = SEM_CLASS_COLORS
+                        .get(class as usize)
+                        .copied()
+                        .unwrap_or(SEM_UNKNOWN_COLOR);
+                    let swatch = egui::Rgba::from_rgb(r, g, b);
+                    let name = hypc::CLASS_NAMES
+                        .get(class as usize)
+                        .map_or_else(|| format!("Class {}", class), |n| n.to_string());
+
+                    ui.horizontal(|ui| {
+                        let (rect, _) =
+                            ui.allocate_exact_size(egui::vec2(14.0, 14.0), egui::Sense::hover());
+                        ui.painter().rect_filled(rect, 2.0, swatch);
+                        ui.label(name);
+                    });
+                }
+            });
+        });
+}
+
+/// Draws the tile list in the bottom-right corner: one checkbox per loaded
+/// tile, with its debug tint as swatch, toggling [`TileGpu::visible`].
+pub fn draw_tile_panel(egui_ctx: &egui::Context, tiles: &mut [TileGpu]) {
+    if tiles.is_empty() {
+        return;
+    }
+
+    Area::new("tile_panel".into())
+        .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-40.0, -40.0))
+        .show(egui_ctx, |ui| {
+            Frame::dark_canvas(ui.style()).show(ui, |ui| {
+                let shown = tiles.iter().filter(|t| t.visible).count();
+                ui.collapsing(format!("Tiles ({} / {})", shown, tiles.len()), |ui| {
+                    ui.horizontal(|ui| {
+                        if ui.button("All").clicked() {
+                            tiles.iter_mut().for_each(|t| t.visible = true);
+                        }
+                        if ui.button("None").clicked() {
+                            tiles.iter_mut().for_each(|t| t.visible = false);
+                        }
+                    });
+                    ui.separator();
+
+                    egui::ScrollArea::vertical().max_height(320.0).show(ui, |ui| {
+                        for tile in tiles.iter_mut() {
+                            let [r, g, b] = tile.debug_color;
+                            let name = tile.name();
+                            ui.horizontal(|ui| {
+                                let (rect, _) = ui.allocate_exact_size(
+                                    egui::vec2(14.0, 14.0),
+                                    egui::Sense::hover(),
+                                );
+                                ui.painter().rect_filled(rect, 2.0, egui::Rgba::from_rgb(r, g, b));
+                                let mut info = format!("{} points", tile.instances_len);
+                                if let Some(geot) = tile.geot {
+                                    let (lon_min, lon_max, lat_min, lat_max) = geot.to_deg();
+                                    info += &format!(
+                                        "\nlon {:.6}..{:.6}\nlat {:.6}..{:.6}",
+                                        lon_min, lon_max, lat_min, lat_max
+                                    );
+                                }
+                                ui.checkbox(&mut tile.visible, name).on_hover_text(info);
+                            });
+                        }
+                    });
+                });
+            });
+        });
+}
+
 pub fn draw_debug_panel(
     egui_ctx: &egui::Context,
     params: &mut PostParams,
@@ -138,8 +295,12 @@ pub fn draw_debug_panel(
                 ui.horizontal(|ui| {
                     ui.checkbox(&mut params.edl_on, "EDL");
                     ui.checkbox(&mut params.sem_on, "Semantic");
+                    ui.checkbox(&mut params.bloom_on, "Bloom");
                     ui.checkbox(&mut params.rgb_on, "RGB shift");
                     ui.checkbox(&mut params.crt_on, "CRT");
+                    ui.checkbox(&mut params.legend_on, "Legend");
+                    ui.checkbox(&mut params.tile_bounds_on, "Tile bounds")
+                        .on_hover_text("Outline each tile's GEOT bbox at height 0");
                 });
                 ui.separator();
 
@@ -152,16 +313,71 @@ pub fn draw_debug_panel(
                     ui.label(format!("Convergence (γ): {:.4}°", gamma_deg));
                 });
 
+                ui.collapsing("Background", |ui| {
+                    if ui.button("Reset").clicked() {
+                        params.bg_top = defaults.bg_top;
+                        params.bg_bottom = defaults.bg_bottom;
+                    }
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        ui.color_edit_button_rgb(&mut params.bg_top);
+                        ui.label("Top");
+                    });
+                    ui.horizontal(|ui| {
+                        ui.color_edit_button_rgb(&mut params.bg_bottom);
+                        ui.label("Bottom");
+                    });
+                });
+
+                ui.collapsing("Projection", |ui| {
+                    ui.radio_value(&mut params.ortho, false, "Perspective");
+                    ui.radio_value(&mut params.ortho, true, "Top-down (ortho)");
+                });
+
                 ui.collapsing("EDL", |ui| {
                     if ui.button("Reset").clicked() {
                         params.edl_strength = defaults.edl_strength;
                         params.edl_radius_px = defaults.edl_radius_px;
+                        params.edl_radius_m = defaults.edl_radius_m;
+                        params.edl_world_space = defaults.edl_world_space;
                     }
                     ui.separator();
                     ui.label("Strength");
                     ui.add(egui::Slider::new(&mut params.edl_strength, 0.0..=5.0));
-                    ui.label("Radius (px)");
-                    ui.add(egui::Slider::new(&mut params.edl_radius_px, 0.5..=4.0));
+                    ui.label("Radius mode");
+                    ui.radio_value(&mut params.edl_world_space, false, "Screen (px)");
+                    ui.radio_value(&mut params.edl_world_space, true, "World (m)");
+                    if params.edl_world_space {
+                        ui.label("Radius (m)");
+                        ui.add(egui::Slider::new(&mut params.edl_radius_m, 0.1..=50.0).logarithmic(true));
+                    } else {
+                        ui.label("Radius (px)");
+                        ui.add(egui::Slider::new(&mut params.edl_radius_px, 0.5..=4.0));
+                    }
+                });
+
+                ui.collapsing("Points", |ui| {
+                    if ui.button("Reset").clicked() {
+                        params.point_size_bias = defaults.point_size_bias;
+                    }
+                    ui.separator();
+                    ui.label("Size bias");
+                    ui.add(
+                        egui::Slider::new(&mut params.point_size_bias, 0.25..=4.0)
+                            .logarithmic(true),
+                    )
+                    .on_hover_text("1 = points as wide as their mean spacing on screen");
+                });
+
+                ui.collapsing("Classes", |ui| {
+                    if ui.button("Reset").clicked() {
+                        params.class_point_size = defaults.class_point_size;
+                    }
+                    ui.separator();
+                    ui.label("Point size multiplier");
+                    for (name, mult) in hypc::CLASS_NAMES.iter().zip(params.class_point_size.iter_mut()) {
+                        ui.add(egui::Slider::new(mult, 0.25..=4.0).text(*name));
+                    }
                 });
 
                 ui.collapsing("Semantic", |ui| {
@@ -173,6 +389,18 @@ pub fn draw_debug_panel(
                     ui.add(egui::Slider::new(&mut params.sem_amount, 0.0..=1.0));
                 });
 
+                ui.collapsing("Bloom", |ui| {
+                    if ui.button("Reset").clicked() {
+                        params.bloom_threshold = defaults.bloom_threshold;
+                        params.bloom_intensity = defaults.bloom_intensity;
+                    }
+                    ui.separator();
+                    ui.label("Threshold");
+                    ui.add(egui::Slider::new(&mut params.bloom_threshold, 0.0..=2.0));
+                    ui.label("Intensity");
+                    ui.add(egui::Slider::new(&mut params.bloom_intensity, 0.0..=3.0));
+                });
+
                 ui.collapsing("RGB Shift", |ui| {
                     if ui.button("Reset").clicked() {
                         params.rgb_amount = defaults.rgb_amount;
@@ -206,6 +434,7 @@ pub fn draw_debug_panel(
                 ui.radio_value(&mut params.debug_mode, 1, "Depth");
                 ui.radio_value(&mut params.debug_mode, 2, "Labels");
                 ui.radio_value(&mut params.debug_mode, 3, "Tag");
+                ui.radio_value(&mut params.debug_mode, DEBUG_MODE_TILE, "Tile");
             });
         });
 }
@@ -0,0 +1,344 @@
+//! `hypc` — command-line utilities for HYPC tiles.
+//!
+//! `hypc verify <PATH>...` structurally validates every `.hypc` file (directories are
+//! scanned recursively) without decoding points, and exits non-zero if any file fails.
+//! Tiles carrying a CRC chunk also have their checksum verified.
+//!
+//! `hypc info <FILE>...` prints header fields and, for tiles with an SMC1 mask, the
+//! per-class pixel coverage (as a table, or JSON with `--json`).
+//!
+//! `hypc split --by-class <FILE>` writes one tile per label class next to the input
+//! (`tile.buildings.hypc`, `tile.roads_major.hypc`, ...). It requires per-point labels;
+//! classes with no points are skipped.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand};
+use rayon::prelude::*;
+
+#[derive(Parser, Debug)]
+#[command(name = "hypc", version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Validate HYPC files or directories of tiles.
+    Verify {
+        /// Files or directories to scan.
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+
+        /// Only print failures and the summary.
+        #[arg(long, default_value_t = false)]
+        quiet: bool,
+    },
+
+    /// Print tile header and SMC1 class coverage.
+    Info {
+        /// Tiles to describe.
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+
+        /// Emit one JSON object per line instead of a table.
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+
+    /// Split a labelled tile into one tile per class.
+    Split {
+        /// Input tile (must carry per-point labels).
+        input: PathBuf,
+
+        /// Write one output tile per label class.
+        #[arg(long, default_value_t = false)]
+        by_class: bool,
+
+        /// Output directory (defaults to the input's directory).
+        #[arg(long)]
+        output_dir: Option<PathBuf>,
+    },
+}
+
+/// Display/file-name label for a semantic class.
+fn class_name(class: u8) -> String {
+    match hypc::CLASS_NAMES.get(class as usize) {
+        Some(name) => (*name).into(),
+        None => format!("class{}", class),
+    }
+}
+
+/// Recursively collects `.hypc` files under `path` (or `path` itself if it is a file).
+fn collect_tiles(path: &Path, out: &mut Vec<PathBuf>) {
+    if path.is_file() {
+        out.push(path.to_path_buf());
+        return;
+    }
+
+    let Ok(entries) = fs::read_dir(path) else {
+        eprintln!("skip {}: not readable", path.display());
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let p = entry.path();
+        if p.is_dir() {
+            collect_tiles(&p, out);
+        } else if p.extension().and_then(|e| e.to_str()) == Some("hypc") {
+            out.push(p);
+        }
+    }
+}
+
+/// [`hypc::validate_file`], plus a checksum pass for tiles that carry a CRC chunk.
+fn validate_with_crc(path: &Path) -> std::io::Result<hypc::HypcValidation> {
+    let v = hypc::validate_file(path)?;
+    if (v.flags & (1 << 5)) != 0 && !hypc::verify_file(path)? {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "CRC mismatch",
+        ));
+    }
+    Ok(v)
+}
+
+fn verify(paths: &[PathBuf], quiet: bool) -> ExitCode {
+    let mut files = Vec::new();
+    for p in paths {
+        collect_tiles(p, &mut files);
+    }
+    files.sort();
+
+    let results: Vec<_> = files
+        .par_iter()
+        .map(|f| (f, validate_with_crc(f)))
+        .collect();
+
+    let mut ok = 0usize;
+    let mut points = 0u64;
+    for (path, res) in &results {
+        match res {
+            Ok(v) => {
+                ok += 1;
+                points += v.points_count as u64;
+                if !quiet {
+                    println!("OK   {} ({} points)", path.display(), v.points_count);
+                }
+            }
+            Err(e) => println!("ERR  {}: {}", path.display(), e),
+        }
+    }
+
+    let failed = results.len() - ok;
+    println!(
+        "{} files checked: {} ok, {} failed, {} points total",
+        results.len(),
+        ok,
+        failed,
+        points
+    );
+
+    if failed == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+fn info(paths: &[PathBuf], json: bool) -> ExitCode {
+    let mut failed = false;
+
+    for path in paths {
+        let tile = match hypc::read_file(path) {
+            Ok(t) => t,
+            Err(e) => {
+                eprintln!("{}: {}", path.display(), e);
+                failed = true;
+                continue;
+            }
+        };
+
+        // Per-class pixel coverage of the decoded SMC1 mask, as (class, pixels).
+        let coverage = match tile.smc1.as_ref().map(|s| s.decode_grid()) {
+            Some(Ok(mask)) => {
+                let mut histogram = [0u64; 256];
+                for &c in &mask {
+                    histogram[c as usize] += 1;
+                }
+                let total = mask.len() as u64;
+                let classes: Vec<(u8, u64)> = histogram
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, &n)| n > 0)
+                    .map(|(c, &n)| (c as u8, n))
+                    .collect();
+                Some((classes, total))
+            }
+            Some(Err(e)) => {
+                eprintln!("{}: SMC1 decode failed: {}", path.display(), e);
+                failed = true;
+                None
+            }
+            None => None,
+        };
+
+        let pct = |n: u64, total: u64| {
+            if total == 0 {
+                0.0
+            } else {
+                100.0 * n as f64 / total as f64
+            }
+        };
+
+        if json {
+            let mut out = serde_json::json!({
+                "file": path.to_string_lossy(),
+                "points": tile.points_units.len(),
+                "units_per_meter": tile.units_per_meter,
+                "anchor_ecef_units": tile.anchor_ecef_units,
+                "labels": tile.labels.is_some(),
+            });
+            if let Some(g) = tile.geot {
+                let (lon_min, lon_max, lat_min, lat_max) = g.to_deg();
+                out["geot"] = serde_json::json!([lon_min, lon_max, lat_min, lat_max]);
+                if let Some((h_min, h_max)) = g.height_m() {
+                    out["height_m"] = serde_json::json!([h_min, h_max]);
+                }
+            }
+            if let Some((classes, total)) = &coverage {
+                let rows: Vec<serde_json::Value> = classes
+                    .iter()
+                    .map(|&(c, n)| {
+                        serde_json::json!({
+                            "class": c,
+                            "name": class_name(c),
+                            "pixels": n,
+                            "percent": pct(n, *total),
+                        })
+                    })
+                    .collect();
+                out["smc1_pixels"] = serde_json::json!(total);
+                out["smc1_coverage"] = serde_json::json!(rows);
+            }
+            println!("{}", out);
+            continue;
+        }
+
+        println!("{}", path.display());
+        println!("  points           {}", tile.points_units.len());
+        println!("  units_per_meter  {}", tile.units_per_meter);
+        println!(
+            "  anchor_ecef      [{}, {}, {}]",
+            tile.anchor_ecef_units[0], tile.anchor_ecef_units[1], tile.anchor_ecef_units[2]
+        );
+        println!("  labels           {}", if tile.labels.is_some() { "yes" } else { "no" });
+        if let Some(g) = tile.geot {
+            let (lon_min, lon_max, lat_min, lat_max) = g.to_deg();
+            println!(
+                "  geot             lon [{:.7}, {:.7}] lat [{:.7}, {:.7}]",
+                lon_min, lon_max, lat_min, lat_max
+            );
+            if let Some((h_min, h_max)) = g.height_m() {
+                println!("  height           [{:.3}, {:.3}] m", h_min, h_max);
+            }
+        }
+        if let Some(smc1) = tile.smc1.as_ref() {
+            println!(
+                "  smc1             {}x{} {:?} {:?}",
+                smc1.width, smc1.height, smc1.encoding, smc1.coord_space
+            );
+        }
+        if let Some((classes, total)) = &coverage {
+            println!("  {:>5}  {:<12} {:>10} {:>8}", "class", "name", "pixels", "%");
+            for &(c, n) in classes {
+                println!(
+                    "  {:>5}  {:<12} {:>10} {:>7.2}%",
+                    c,
+                    class_name(c),
+                    n,
+                    pct(n, *total)
+                );
+            }
+        }
+    }
+
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+fn split_by_class(input: &Path, output_dir: Option<&Path>) -> ExitCode {
+    let tile = match hypc::read_file(input) {
+        Ok(t) => t,
+        Err(e) => {
+            eprintln!("{}: {}", input.display(), e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let Some(labels) = tile.labels.as_ref() else {
+        eprintln!("{}: tile has no per-point labels; nothing to split", input.display());
+        return ExitCode::FAILURE;
+    };
+
+    let mut histogram = [0usize; 256];
+    for &l in labels {
+        histogram[l as usize] += 1;
+    }
+
+    let stem = input
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "tile".into());
+    let dir = output_dir
+        .map(Path::to_path_buf)
+        .or_else(|| input.parent().map(Path::to_path_buf))
+        .unwrap_or_default();
+
+    for (class, &count) in histogram.iter().enumerate() {
+        if count == 0 {
+            continue;
+        }
+
+        let class = class as u8;
+        let part = tile
+            .filter_by_label(class)
+            .expect("labels checked above");
+        let out = dir.join(format!("{}.{}.hypc", stem, class_name(class)));
+
+        if let Err(e) = hypc::write_file(&out, &part) {
+            eprintln!("{}: {}", out.display(), e);
+            return ExitCode::FAILURE;
+        }
+
+        println!("{} ({} points)", out.display(), count);
+    }
+
+    ExitCode::SUCCESS
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    match cli.command {
+        Command::Verify { paths, quiet } => verify(&paths, quiet),
+        Command::Info { paths, json } => info(&paths, json),
+        Command::Split {
+            input,
+            by_class,
+            output_dir,
+        } => {
+            if !by_class {
+                eprintln!("nothing to do: pass --by-class");
+                return ExitCode::FAILURE;
+            }
+            split_by_class(&input, output_dir.as_deref())
+        }
+    }
+}
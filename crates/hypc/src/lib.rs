@@ -3,45 +3,110 @@
 //! - Stores an i64 ECEF anchor (integer "units") and i32 offsets per point.
 //! - Default units: 1000 units/meter (millimetres).
 //! - Optional per-point labels (u8).
-//! - Optional GEOT chunk: CRS:84 bbox (deg, Q7: 1e-7 deg ticks).
+//! - Points stored raw or as delta + zigzag varints.
+//! - Optional per-point RGB colors (v3+).
+//! - Optional GEOT chunk: CRS:84 bbox (deg, Q7: 1e-7 deg ticks), or GEO2 with an
+//!   ellipsoidal height range (mm) as well.
 //! - Optional SMC1 chunk: semantic mask grid (u8), Raw or RLE encoding.
+//! - Optional XFRM chunk: rigid local-to-ECEF correction (f64 rotation + translation).
+//! - Optional CRC chunk: CRC32 over everything before it, always last.
+//!
+//! With the default `std` feature off the crate is `no_std` + `alloc`: parsing from a
+//! byte slice, the SMC1 helpers and the WGS-84 math are available, with errors reported
+//! as [`HypcError`]. File, stream and writer APIs need `std`. Such builds must enable
+//! the `libm` feature, which supplies the float math `std` otherwise provides.
+//!
+//! Deflate-compressed points blocks and SMC1 masks need the `compression` feature,
+//! which is the only one pulling in a codec; without it such tiles are rejected
+//! with [`HypcError::CompressionUnsupported`] and everything else reads as usual.
 //!
 //! File layout (little-endian):
 //!   00  : [u8;4]  magic = b"HYPC"
-//!   04  : u32     version = 2
+//!   04  : u32     version = 3 (2 is still read; no v1 layout was ever
+//!                 specified, so v1 files are rejected as unsupported)
 //!   08  : u32     flags (bitfield)
 //!                 bit 0 => tile key present (32 bytes)
 //!                 bit 1 => per-point labels present
 //!                 bit 2 => GEOT chunk present
 //!                 bit 3 => SMC1 chunk present
+//!                 bit 4 => per-point RGB present (v3+)
+//!                 bit 5 => CRC chunk present
+//!                 bit 6 => XFRM chunk present
+//!                 bit 7 => points encoding field present
 //!   0C  : u32     points_count
 //!   10  : u32     units_per_meter (default: 1000, mm)
 //!   14  : i64[3]  anchor_ecef_units
 //!   ..  : [u8;32] tile_key            (if bit0)
-//!   ..  : for each point: i32 dx, i32 dy, i32 dz, [u8 label]? (if bit1)
-//!   ..  : GEOT chunk                  (if bit2)
+//!   ..  : u8 points_encoding, u8 points_codec, [u8;2] reserved (if bit7; absent
+//!         means Raw, uncompressed)
+//!   ..  : points block, by encoding:
+//!         Raw:   for each point: i32 dx, i32 dy, i32 dz, [u8 label]? (if bit1)
+//!         Delta: u32 byte_len, byte_len bytes of varints, [u8 label; N]? (if bit1)
+//!         With points_codec Deflate the block above is stored as
+//!         u32 raw_len, u32 stored_len, stored_len bytes of zlib stream.
+//!   ..  : for each point: u8 r, u8 g, u8 b (if bit4)
+//!   ..  : GEOT or GEO2 chunk          (if bit2)
 //!   ..  : SMC1 chunk                  (if bit3)
+//!   ..  : XFRM chunk                  (if bit6)
+//!   ..  : CRC chunk                   (if bit5)
 //!
 //! GEOT chunk:
 //!   "GEOT" [i32 lon_min_q7, lon_max_q7, lat_min_q7, lat_max_q7]
+//!   "GEO2" [i32 lon_min_q7, lon_max_q7, lat_min_q7, lat_max_q7, h_min_mm, h_max_mm]
+//! Writers emit GEO2 only when the height range is known; readers take either.
 //!
 //! SMC1 chunk:
-//!   "SMC1" u16 width u16 height u8 coord_space u8 encoding u16 palette_len
+//!   "SMC1" u16 width u16 height (both > 0) u8 coord_space u8 encoding u16 palette_len
 //!          (palette_len pairs: u8 class, u8 precedence)
 //!          u32 payload_size
-//!          [payload_size bytes of pixel data] (Raw or RLE)
+//!          [payload_size bytes of pixel data] (Raw, RLE, or Deflate: a zlib
+//!          stream of the raw pixels)
+//!
+//! Delta points: for each point, dx, dy, dz minus the previous point's (the first
+//! against 0), each zigzag-mapped to u32 and written as a LEB128 varint. Points
+//! keep their order; sorting them first (`HypcTile::sort_morton`) makes the
+//! deltas small.
 //!
 //! RLE format: repeated [u16 run_len][u8 value] (little-endian)
+//! Either way the mask must cover exactly width*height pixels.
+//!
+//! XFRM chunk:
+//!   "XFRM" f64[9] rotation (row-major) f64[3] translation_m
+//!   Applied about the anchor: p' = anchor + R * (p - anchor) + t
+//!
+//! CRC chunk:
+//!   "CRC\0" u32 crc32 (IEEE, as zlib/PNG) of every byte from the magic up to the tag
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(not(any(feature = "std", feature = "libm")))]
+compile_error!("hypc needs the `std` feature or, in no_std builds, the `libm` feature");
+
+extern crate alloc;
 
+use alloc::{vec, vec::Vec};
+
+#[cfg(feature = "std")]
 use std::fs::File;
-use std::io::{self, ErrorKind, Write};
+#[cfg(feature = "std")]
+use std::io::{self, BufRead, ErrorKind, Read, Seek, SeekFrom, Write};
+#[cfg(feature = "std")]
 use std::path::Path;
 
+#[cfg(not(feature = "std"))]
+use nostd_math::F64Ext;
+
 pub const HYPC_MAGIC: [u8; 4] = *b"HYPC";
-pub const HYPC_VERSION: u32 = 2;
+pub const HYPC_VERSION: u32 = 3;
+/// Oldest version the parsers accept; v2 predates the RGB block.
+pub const HYPC_MIN_VERSION: u32 = 2;
+/// Largest `units_per_meter` the parsers accept: 0.1 µm steps, far finer than
+/// any source data. Anything above it is taken for a corrupt header.
+pub const MAX_UNITS_PER_METER: u32 = 10_000_000;
 
 /// Represents a geographic bounding box using Q7 fixed-point encoding.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct GeoExtentQ7 {
     /// Minimum longitude in Q7 format (1e-7 degrees)
     pub lon_min_q7: i32,
@@ -51,6 +116,9 @@ pub struct GeoExtentQ7 {
     pub lat_min_q7: i32,
     /// Maximum latitude in Q7 format (1e-7 degrees)
     pub lat_max_q7: i32,
+    /// Ellipsoidal height range `(h_min_mm, h_max_mm)` in millimetres, if known.
+    /// Stored as a GEO2 chunk; `None` for plain GEOT.
+    pub height_mm: Option<(i32, i32)>,
 }
 
 impl GeoExtentQ7 {
@@ -62,6 +130,38 @@ impl GeoExtentQ7 {
             lon_max_q7: (lon_max * 1e7).round() as i32,
             lat_min_q7: (lat_min * 1e7).round() as i32,
             lat_max_q7: (lat_max * 1e7).round() as i32,
+            height_mm: None,
+        }
+    }
+
+    /// Returns the extent with its height range set from metres above the ellipsoid.
+    #[inline]
+    pub fn with_height_m(self, h_min: f64, h_max: f64) -> Self {
+        Self {
+            height_mm: Some(((h_min * 1e3).round() as i32, (h_max * 1e3).round() as i32)),
+            ..self
+        }
+    }
+
+    /// Height range in metres above the ellipsoid, if present.
+    #[inline]
+    pub fn height_m(self) -> Option<(f64, f64)> {
+        self.height_mm
+            .map(|(h_min, h_max)| (h_min as f64 * 1e-3, h_max as f64 * 1e-3))
+    }
+
+    /// Smallest extent covering both; the height range is kept only if both have one.
+    #[inline]
+    pub fn union(self, other: Self) -> Self {
+        Self {
+            lon_min_q7: self.lon_min_q7.min(other.lon_min_q7),
+            lon_max_q7: self.lon_max_q7.max(other.lon_max_q7),
+            lat_min_q7: self.lat_min_q7.min(other.lat_min_q7),
+            lat_max_q7: self.lat_max_q7.max(other.lat_max_q7),
+            height_mm: match (self.height_mm, other.height_mm) {
+                (Some(a), Some(b)) => Some((a.0.min(b.0), a.1.max(b.1))),
+                _ => None,
+            },
         }
     }
 
@@ -75,16 +175,90 @@ impl GeoExtentQ7 {
             self.lat_max_q7 as f64 * 1e-7,
         )
     }
+
+    /// True if (`lon_q7`, `lat_q7`) lies inside the extent or on its edge, so a
+    /// zero-area extent contains its own corner.
+    ///
+    /// Extents don't wrap: one with `lon_min_q7 > lon_max_q7` contains nothing,
+    /// and a tile straddling the antimeridian needs two extents.
+    #[inline]
+    pub fn contains(&self, lon_q7: i32, lat_q7: i32) -> bool {
+        (self.lon_min_q7..=self.lon_max_q7).contains(&lon_q7)
+            && (self.lat_min_q7..=self.lat_max_q7).contains(&lat_q7)
+    }
+
+    /// True if the two extents overlap or touch; see [`GeoExtentQ7::contains`]
+    /// for zero-area and antimeridian extents. Heights are ignored.
+    #[inline]
+    pub fn intersects(&self, other: &GeoExtentQ7) -> bool {
+        self.lon_min_q7 <= other.lon_max_q7
+            && other.lon_min_q7 <= self.lon_max_q7
+            && self.lat_min_q7 <= other.lat_max_q7
+            && other.lat_min_q7 <= self.lat_max_q7
+    }
+
+    /// Center as (lon°, lat°).
+    #[inline]
+    pub fn center_deg(&self) -> (f64, f64) {
+        (
+            (self.lon_min_q7 as f64 + self.lon_max_q7 as f64) * 0.5e-7,
+            (self.lat_min_q7 as f64 + self.lat_max_q7 as f64) * 0.5e-7,
+        )
+    }
+
+    /// Returns the extent grown by `dlon` degrees east and west and `dlat`
+    /// degrees north and south (negative values shrink it), clamped to
+    /// ±180° / ±90°. The height range is kept.
+    #[inline]
+    pub fn expand_deg(self, dlon: f64, dlat: f64) -> Self {
+        let q7 = |deg: f64, limit: f64| (deg.clamp(-limit, limit) * 1e7).round() as i32;
+        let (lon_min, lon_max, lat_min, lat_max) = self.to_deg();
+        Self {
+            lon_min_q7: q7(lon_min - dlon, 180.0),
+            lon_max_q7: q7(lon_max + dlon, 180.0),
+            lat_min_q7: q7(lat_min - dlat, 90.0),
+            lat_max_q7: q7(lat_max + dlat, 90.0),
+            ..self
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 pub enum Smc1Encoding {
     Raw = 0,
     Rle = 1,
+    /// zlib stream of the raw pixels; needs the `compression` feature.
+    Deflate = 2,
+}
+
+/// How the points block is stored (flag bit 7 + header byte).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(u8)]
+pub enum PointsEncoding {
+    /// Packed little-endian i32 triples, 12 bytes per point.
+    #[default]
+    Raw = 0,
+    /// Per-axis deltas from the previous point as zigzag LEB128 varints.
+    Delta = 1,
+}
+
+/// General-purpose compression of the points block on disk, applied on top of
+/// its [`PointsEncoding`] (header byte after the encoding).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(u8)]
+pub enum PointsCodec {
+    #[default]
+    None = 0,
+    /// zlib stream; reading and writing need the `compression` feature.
+    Deflate = 1,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 pub enum Smc1CoordSpace {
     /// UV in "decode" space (legacy/local); not used by HYPC.
@@ -94,7 +268,23 @@ pub enum Smc1CoordSpace {
     Crs84BboxNorm = 1,
 }
 
-#[derive(Debug, Clone)]
+/// Names of the built-in semantic classes, indexed by class id: the per-point
+/// label and SMC1 pixel values obj2hypc assigns. Ids past the end have no name.
+pub const CLASS_NAMES: [&str; 10] = [
+    "unknown",
+    "buildings",
+    "roads_major",
+    "roads_minor",
+    "paths",
+    "water",
+    "parks",
+    "woodland",
+    "railways",
+    "parking",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Smc1Chunk {
     pub width: u16,
     pub height: u16,
@@ -104,100 +294,898 @@ pub struct Smc1Chunk {
     pub data: Vec<u8>,          // raw (w*h) if Raw; RLE payload if Rle
 }
 
-#[derive(Debug, Clone)]
+impl Smc1Chunk {
+    /// Returns the mask as a row-major `width * height` grid of class bytes,
+    /// decoding RLE if needed. Fails if the payload doesn't cover exactly
+    /// that many pixels.
+    #[cfg(feature = "std")]
+    pub fn decode_grid(&self) -> io::Result<Vec<u8>> {
+        Ok(self.decode_grid_inner()?)
+    }
+
+    /// Returns the mask as a row-major `width * height` grid of class bytes,
+    /// decoding RLE if needed. Fails if the payload doesn't cover exactly
+    /// that many pixels.
+    #[cfg(not(feature = "std"))]
+    pub fn decode_grid(&self) -> HypcResult<Vec<u8>> {
+        self.decode_grid_inner()
+    }
+
+    fn decode_grid_inner(&self) -> HypcResult<Vec<u8>> {
+        match self.encoding {
+            Smc1Encoding::Raw => {
+                self.check_payload()?;
+                Ok(self.data.clone())
+            }
+            Smc1Encoding::Rle => {
+                self.check_payload()?;
+                smc1_decode_rle_inner(&self.data)
+            }
+            // Inflating checks the pixel count itself.
+            Smc1Encoding::Deflate => self.inflate_grid(),
+        }
+    }
+
+    /// Inflates a Deflate payload, checking it covers exactly `width * height` pixels.
+    fn inflate_grid(&self) -> HypcResult<Vec<u8>> {
+        let expected = self.width as usize * self.height as usize;
+        // One byte of headroom, so an oversized mask is reported as such.
+        let grid = inflate(&self.data, expected + 1)?;
+        if grid.len() != expected {
+            return Err(HypcError::Smc1SizeMismatch {
+                expected,
+                actual: grid.len(),
+            });
+        }
+        Ok(grid)
+    }
+
+    /// Class of pixel (`x`, `y`) without decoding the whole grid; `None` if
+    /// the pixel is out of bounds or the payload doesn't reach it.
+    ///
+    /// RLE chunks are scanned from the start and Deflate chunks inflated whole,
+    /// so prefer [`Smc1Chunk::decode_grid`] for sampling many pixels.
+    pub fn class_at(&self, x: u16, y: u16) -> Option<u8> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let index = y as usize * self.width as usize + x as usize;
+
+        match self.encoding {
+            Smc1Encoding::Raw => self.data.get(index).copied(),
+            Smc1Encoding::Deflate => self.inflate_grid().ok()?.get(index).copied(),
+            Smc1Encoding::Rle => {
+                let mut end = 0usize;
+                for run in self.data.chunks_exact(3) {
+                    end += u16::from_le_bytes([run[0], run[1]]) as usize;
+                    if index < end {
+                        return Some(run[2]);
+                    }
+                }
+                None
+            }
+        }
+    }
+
+    /// Check that the payload covers exactly `width * height` pixels: byte
+    /// count for Raw, sum of run lengths for RLE (without decoding). Deflate
+    /// payloads have to be inflated to be checked.
+    pub fn check_payload(&self) -> Result<(), HypcError> {
+        let expected = self.width as usize * self.height as usize;
+        let actual = match self.encoding {
+            Smc1Encoding::Raw => self.data.len(),
+            Smc1Encoding::Rle => smc1_rle_expanded_len(&self.data)?,
+            Smc1Encoding::Deflate => return self.inflate_grid().map(|_| ()),
+        };
+        if actual != expected {
+            return Err(HypcError::Smc1SizeMismatch { expected, actual });
+        }
+        Ok(())
+    }
+
+    /// Returns the palette as a lookup table indexed by class id.
+    ///
+    /// Classes missing from the palette get precedence 0, so any class that is
+    /// present wins over them when masks are combined.
+    pub fn precedence_table(&self) -> [u8; 256] {
+        let mut t = [0u8; 256];
+        for &(class, precedence) in &self.palette {
+            t[class as usize] = precedence;
+        }
+        t
+    }
+
+    /// Renumbers every class in the palette and pixel data through `map`.
+    ///
+    /// Palette entries that collapse onto the same class keep the highest precedence.
+    /// A Deflate mask is stored Raw afterwards; if it can't be inflated (see
+    /// [`Smc1Chunk::check_payload`]) its pixels are left as they are.
+    pub fn remap_classes(&mut self, map: &[u8; 256]) {
+        if self.encoding == Smc1Encoding::Deflate {
+            if let Ok(grid) = self.inflate_grid() {
+                self.data = grid;
+                self.encoding = Smc1Encoding::Raw;
+            }
+        }
+        match self.encoding {
+            Smc1Encoding::Raw => {
+                for v in self.data.iter_mut() {
+                    *v = map[*v as usize];
+                }
+            }
+            Smc1Encoding::Rle => {
+                // Only the value byte of each [u16 run_len][u8 value] record changes.
+                for rec in self.data.chunks_exact_mut(3) {
+                    rec[2] = map[rec[2] as usize];
+                }
+            }
+            Smc1Encoding::Deflate => {}
+        }
+
+        let mut palette = Vec::<(u8, u8)>::with_capacity(self.palette.len());
+        for &(class, precedence) in &self.palette {
+            let class = map[class as usize];
+            match palette.iter_mut().find(|(c, _)| *c == class) {
+                Some(entry) => entry.1 = entry.1.max(precedence),
+                None => palette.push((class, precedence)),
+            }
+        }
+        self.palette = palette;
+    }
+}
+
+/// Rigid correction applied to a tile about its anchor, in metres.
+///
+/// A point at offset `ofs` from the anchor ends up at `anchor + rotation * ofs + translation_m`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RigidTransform {
+    /// Row-major 3x3 rotation.
+    pub rotation: [[f64; 3]; 3],
+    /// Translation in ECEF metres.
+    pub translation_m: [f64; 3],
+}
+
+impl RigidTransform {
+    pub const IDENTITY: Self = Self {
+        rotation: [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+        translation_m: [0.0; 3],
+    };
+
+    /// Maps an anchor-relative offset (metres) to its corrected anchor-relative offset.
+    #[inline]
+    pub fn apply_offset(&self, ofs_m: [f64; 3]) -> [f64; 3] {
+        let r = &self.rotation;
+        let t = &self.translation_m;
+        [
+            r[0][0] * ofs_m[0] + r[0][1] * ofs_m[1] + r[0][2] * ofs_m[2] + t[0],
+            r[1][0] * ofs_m[0] + r[1][1] * ofs_m[1] + r[1][2] * ofs_m[2] + t[1],
+            r[2][0] * ofs_m[0] + r[2][1] * ofs_m[1] + r[2][2] * ofs_m[2] + t[2],
+        ]
+    }
+}
+
+impl Default for RigidTransform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct HypcTile {
     pub units_per_meter: u32,
     pub anchor_ecef_units: [i64; 3],
+    #[cfg_attr(feature = "serde", serde(default, with = "tile_key_hex"))]
     pub tile_key: Option<[u8; 32]>,
     pub points_units: Vec<[i32; 3]>,
+    /// Encoding of the points block on disk; decoding is transparent.
+    pub points_encoding: PointsEncoding,
+    /// Compression of the points block on disk; decoding is transparent.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub points_codec: PointsCodec,
     pub labels: Option<Vec<u8>>,
+    /// Per-point sRGB colors, one per point (flag bit 4).
+    pub colors: Option<Vec<[u8; 3]>>,
     pub geot: Option<GeoExtentQ7>,
     pub smc1: Option<Smc1Chunk>,
+    pub transform: Option<RigidTransform>,
+    /// Write a trailing CRC chunk (flag bit 5). The parsers set this when the
+    /// file had one, and only after it matched.
+    pub crc: bool,
+}
+
+/// Serde adapter writing a tile key as 64 lowercase hex digits.
+#[cfg(feature = "serde")]
+mod tile_key_hex {
+    use alloc::string::String;
+    use serde::de::{Error, Unexpected};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(key: &Option<[u8; 32]>, s: S) -> Result<S::Ok, S::Error> {
+        const DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+        let Some(key) = key else {
+            return s.serialize_none();
+        };
+        let mut hex = [0u8; 64];
+        for (i, b) in key.iter().enumerate() {
+            hex[2 * i] = DIGITS[(b >> 4) as usize];
+            hex[2 * i + 1] = DIGITS[(b & 0x0F) as usize];
+        }
+        s.serialize_some(core::str::from_utf8(&hex).expect("hex digits are ASCII"))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Option<[u8; 32]>, D::Error> {
+        let Some(hex) = Option::<String>::deserialize(d)? else {
+            return Ok(None);
+        };
+        let invalid = || D::Error::invalid_value(Unexpected::Str(&hex), &"64 hex digits");
+        if hex.len() != 64 {
+            return Err(invalid());
+        }
+
+        let mut key = [0u8; 32];
+        for (k, pair) in key.iter_mut().zip(hex.as_bytes().chunks_exact(2)) {
+            let nibble = |c: u8| (c as char).to_digit(16).ok_or_else(invalid);
+            *k = (nibble(pair[0])? << 4 | nibble(pair[1])?) as u8;
+        }
+        Ok(Some(key))
+    }
+}
+
+impl HypcTile {
+    /// Decodes every point to absolute ECEF metres, applying `transform` if present.
+    pub fn points_ecef_m(&self) -> Vec<[f64; 3]> {
+        self.iter_points_ecef_m().collect()
+    }
+
+    /// Lazily yields each point in absolute ECEF metres, applying `transform`
+    /// if present; the iterator form of [`HypcTile::points_ecef_m`].
+    pub fn iter_points_ecef_m(&self) -> impl ExactSizeIterator<Item = [f64; 3]> + '_ {
+        let inv_upm = (self.units_per_meter as f64).recip();
+        let anchor_m = [
+            self.anchor_ecef_units[0] as f64 * inv_upm,
+            self.anchor_ecef_units[1] as f64 * inv_upm,
+            self.anchor_ecef_units[2] as f64 * inv_upm,
+        ];
+
+        self.points_units
+            .iter()
+            .map(move |p| {
+                let ofs = [
+                    p[0] as f64 * inv_upm,
+                    p[1] as f64 * inv_upm,
+                    p[2] as f64 * inv_upm,
+                ];
+                let ofs = match &self.transform {
+                    Some(xf) => xf.apply_offset(ofs),
+                    None => ofs,
+                };
+                [anchor_m[0] + ofs[0], anchor_m[1] + ofs[1], anchor_m[2] + ofs[2]]
+            })
+    }
+
+    /// Yields each point as geodetic `(lat°, lon°, height m)` on WGS-84; see
+    /// [`ecef_to_geodetic`].
+    pub fn iter_points_geodetic(&self) -> impl ExactSizeIterator<Item = (f64, f64, f64)> + '_ {
+        self.iter_points_ecef_m()
+            .map(|[x, y, z]| ecef_to_geodetic(x, y, z))
+    }
+
+    /// Samples the SMC1 mask under each point, giving one class per point in
+    /// point order; `None` unless the tile has a GEOT and a `Crs84BboxNorm`
+    /// SMC1 chunk. Fails if the mask is empty or its payload malformed.
+    ///
+    /// Each point's geodetic lon/lat is normalized into the GEOT bbox and the
+    /// nearest mask pixel taken. Points are placed from their stored offsets,
+    /// without any XFRM correction, as the mask was built against them.
+    /// Where a planar inverse of the ENU frame at the anchor stays within a
+    /// twentieth of a mask pixel of full geodesy across the GEOT extent (up to
+    /// about 0.02° for a 512-pixel mask at mid latitudes), it is used instead;
+    /// larger extents, finer masks and polar anchors get full geodesy per point.
+    #[cfg(feature = "std")]
+    pub fn smc1_point_labels(&self) -> io::Result<Option<Vec<u8>>> {
+        Ok(self.smc1_point_labels_inner()?)
+    }
+
+    /// Samples the SMC1 mask under each point, giving one class per point in
+    /// point order; `None` unless the tile has a GEOT and a `Crs84BboxNorm`
+    /// SMC1 chunk. Fails if the mask is empty or its payload malformed.
+    ///
+    /// Each point's geodetic lon/lat is normalized into the GEOT bbox and the
+    /// nearest mask pixel taken. Points are placed from their stored offsets,
+    /// without any XFRM correction, as the mask was built against them.
+    /// Where a planar inverse of the ENU frame at the anchor stays within a
+    /// twentieth of a mask pixel of full geodesy across the GEOT extent (up to
+    /// about 0.02° for a 512-pixel mask at mid latitudes), it is used instead;
+    /// larger extents, finer masks and polar anchors get full geodesy per point.
+    #[cfg(not(feature = "std"))]
+    pub fn smc1_point_labels(&self) -> HypcResult<Option<Vec<u8>>> {
+        self.smc1_point_labels_inner()
+    }
+
+    fn smc1_point_labels_inner(&self) -> HypcResult<Option<Vec<u8>>> {
+        let (Some(smc1), Some(geot)) = (&self.smc1, self.geot) else {
+            return Ok(None);
+        };
+        if smc1.coord_space != Smc1CoordSpace::Crs84BboxNorm {
+            return Ok(None);
+        }
+        if smc1.width == 0 || smc1.height == 0 {
+            return Err(HypcError::Smc1ZeroSize {
+                width: smc1.width,
+                height: smc1.height,
+            });
+        }
+        let smc = smc1.decode_grid_inner()?;
+        let (smc_w, smc_h) = (smc1.width as usize, smc1.height as usize);
+
+        let pixels = MaskPixels::new(geot, smc_w, smc_h);
+
+        let inv_upm = (self.units_per_meter as f64).recip();
+        let anchor_m = self.anchor_ecef_units.map(|u| u as f64 * inv_upm);
+        let geodesy = LocalGeodesy::new(anchor_m);
+        let planar_ok = geodesy.fits(geot, &pixels);
+
+        let label = |p: &[i32; 3]| -> u8 {
+            let ofs_m = p.map(|u| u as f64 * inv_upm);
+            let (lat_deg, lon_deg) = if planar_ok {
+                geodesy.lat_lon_deg(ofs_m)
+            } else {
+                let (lat, lon, _h) = ecef_to_geodetic(
+                    anchor_m[0] + ofs_m[0],
+                    anchor_m[1] + ofs_m[1],
+                    anchor_m[2] + ofs_m[2],
+                );
+                (lat, lon)
+            };
+
+            let (ix, iy) = pixels.index(lat_deg, lon_deg);
+            smc[iy * smc_w + ix]
+        };
+
+        #[cfg(feature = "rayon")]
+        let labels = {
+            use rayon::prelude::*;
+            self.points_units.par_iter().map(label).collect()
+        };
+        #[cfg(not(feature = "rayon"))]
+        let labels = self.points_units.iter().map(label).collect();
+
+        Ok(Some(labels))
+    }
+
+    /// Axis-aligned bounds of the decoded points in absolute ECEF metres, as
+    /// `(min, max)`; `None` for a tile with no points.
+    ///
+    /// Matches [`HypcTile::points_ecef_m`], including any XFRM correction.
+    /// Without one, the bounds are taken on the integer offsets and converted
+    /// once, so no per-point float work is needed.
+    pub fn bounding_box_ecef_m(&self) -> Option<([f64; 3], [f64; 3])> {
+        if self.points_units.is_empty() {
+            return None;
+        }
+
+        let mut min = [f64::INFINITY; 3];
+        let mut max = [f64::NEG_INFINITY; 3];
+        let mut grow = |p: [f64; 3]| {
+            for k in 0..3 {
+                min[k] = min[k].min(p[k]);
+                max[k] = max[k].max(p[k]);
+            }
+        };
+
+        if self.transform.is_some() {
+            self.iter_points_ecef_m().for_each(grow);
+        } else {
+            let mut lo = [i32::MAX; 3];
+            let mut hi = [i32::MIN; 3];
+            for p in &self.points_units {
+                for k in 0..3 {
+                    lo[k] = lo[k].min(p[k]);
+                    hi[k] = hi[k].max(p[k]);
+                }
+            }
+
+            let inv_upm = (self.units_per_meter as f64).recip();
+            let to_m = |ofs: [i32; 3]| -> [f64; 3] {
+                core::array::from_fn(|k| {
+                    self.anchor_ecef_units[k] as f64 * inv_upm + ofs[k] as f64 * inv_upm
+                })
+            };
+            grow(to_m(lo));
+            grow(to_m(hi));
+        }
+
+        Some((min, max))
+    }
+
+    /// Renumbers per-point labels and the SMC1 mask through a 256-entry lookup table.
+    ///
+    /// Used to normalize tiles from pipelines with different class numbering.
+    pub fn remap_labels(&mut self, map: &[u8; 256]) {
+        if let Some(labels) = self.labels.as_mut() {
+            for l in labels.iter_mut() {
+                *l = map[*l as usize];
+            }
+        }
+
+        if let Some(smc1) = self.smc1.as_mut() {
+            smc1.remap_classes(map);
+        }
+    }
+
+    /// Returns a copy of the tile holding only the points labelled `label`.
+    ///
+    /// Header fields, GEOT and SMC1 are carried over unchanged; colors are
+    /// filtered alongside the points. Returns `None` if the tile has no
+    /// per-point labels.
+    pub fn filter_by_label(&self, label: u8) -> Option<HypcTile> {
+        let labels = self.labels.as_ref()?;
+        let keep = |i: &usize| labels[*i] == label;
+
+        let points_units: Vec<[i32; 3]> = (0..self.points_units.len())
+            .filter(keep)
+            .map(|i| self.points_units[i])
+            .collect();
+        let colors = self
+            .colors
+            .as_ref()
+            .map(|c| (0..c.len()).filter(keep).map(|i| c[i]).collect());
+
+        Some(HypcTile {
+            units_per_meter: self.units_per_meter,
+            anchor_ecef_units: self.anchor_ecef_units,
+            tile_key: self.tile_key,
+            labels: Some(vec![label; points_units.len()]),
+            points_units,
+            points_encoding: self.points_encoding,
+            points_codec: self.points_codec,
+            colors,
+            geot: self.geot,
+            smc1: self.smc1.clone(),
+            transform: self.transform,
+            crc: self.crc,
+        })
+    }
+
+    /// Fuses `tiles` into one tile around a shared anchor, e.g. for a coarse LOD.
+    ///
+    /// The result uses the largest input `units_per_meter`, so no tile loses
+    /// precision, and is anchored at the centroid of all points. Points, labels
+    /// and colors are concatenated in input order: tiles without labels get
+    /// class 0, and colors are kept only if every tile has them. XFRM
+    /// corrections are baked into the points. GEOT is the union of the inputs'
+    /// extents and is kept only if every tile has one. SMC1 masks are not
+    /// merged, so the result has none, and it has no tile key.
+    ///
+    /// Fails on an empty slice ([`HypcError::EmptyMerge`]), and with
+    /// [`HypcError::OffsetOverflow`] if a point is too far from the merged
+    /// anchor for an i32 offset; re-quantize the inputs with a lower
+    /// `units_per_meter` and merge again. Both come back as `InvalidData`
+    /// with the [`HypcError`] inside.
+    #[cfg(feature = "std")]
+    pub fn merge(tiles: &[HypcTile]) -> io::Result<HypcTile> {
+        Ok(Self::merge_inner(tiles)?)
+    }
+
+    /// [`HypcTile::merge`] with the typed error.
+    #[cfg(feature = "std")]
+    fn merge_inner(tiles: &[HypcTile]) -> HypcResult<HypcTile> {
+        if tiles.iter().any(|t| t.units_per_meter == 0) {
+            return Err(HypcError::ZeroUnitsPerMeter);
+        }
+        let upm = tiles
+            .iter()
+            .map(|t| t.units_per_meter)
+            .max()
+            .ok_or(HypcError::EmptyMerge)?;
+
+        // Absolute positions in merged units, wide enough that nothing can overflow.
+        let mut abs: Vec<[i128; 3]> =
+            Vec::with_capacity(tiles.iter().map(|t| t.points_units.len()).sum());
+        for t in tiles {
+            if t.transform.is_some() {
+                abs.extend(
+                    t.iter_points_ecef_m()
+                        .map(|p| p.map(|m| (m * upm as f64).round() as i128)),
+                );
+            } else {
+                abs.extend(t.points_units.iter().map(|p| {
+                    core::array::from_fn(|k| {
+                        rescale_units(
+                            t.anchor_ecef_units[k] as i128 + p[k] as i128,
+                            upm,
+                            t.units_per_meter,
+                        )
+                    })
+                }));
+            }
+        }
+
+        let anchor: [i128; 3] = if abs.is_empty() {
+            tiles[0]
+                .anchor_ecef_units
+                .map(|a| rescale_units(a as i128, upm, tiles[0].units_per_meter))
+        } else {
+            let n = abs.len() as i128;
+            core::array::from_fn(|k| {
+                let sum: i128 = abs.iter().map(|p| p[k]).sum();
+                (2 * sum + n).div_euclid(2 * n)
+            })
+        };
+        let anchor_ecef_units = anchor.map(|a| a as i64);
+
+        let points_units = abs
+            .iter()
+            .map(|p| {
+                let mut ofs = [0i32; 3];
+                for k in 0..3 {
+                    ofs[k] =
+                        i32::try_from(p[k] - anchor[k]).map_err(|_| HypcError::OffsetOverflow)?;
+                }
+                Ok(ofs)
+            })
+            .collect::<HypcResult<Vec<_>>>()?;
+
+        let labels = tiles.iter().any(|t| t.labels.is_some()).then(|| {
+            tiles
+                .iter()
+                .flat_map(|t| match &t.labels {
+                    Some(l) => l.clone(),
+                    None => vec![0; t.points_units.len()],
+                })
+                .collect()
+        });
+        let colors = tiles
+            .iter()
+            .map(|t| t.colors.as_deref())
+            .collect::<Option<Vec<_>>>()
+            .map(|c| c.concat());
+        let geot = tiles
+            .iter()
+            .map(|t| t.geot)
+            .collect::<Option<Vec<_>>>()
+            .and_then(|g| g.into_iter().reduce(GeoExtentQ7::union));
+
+        Ok(HypcTile {
+            units_per_meter: upm,
+            anchor_ecef_units,
+            tile_key: None,
+            points_units,
+            points_encoding: PointsEncoding::Raw,
+            points_codec: PointsCodec::None,
+            labels,
+            colors,
+            geot,
+            smc1: None,
+            transform: None,
+            crc: tiles.iter().any(|t| t.crc),
+        })
+    }
+
+    /// Reorders points (with their labels and colors) along a 3D Morton curve.
+    ///
+    /// Neighbouring points end up next to each other, which keeps
+    /// [`PointsEncoding::Delta`] varints short. The point set is unchanged;
+    /// only its order is.
+    pub fn sort_morton(&mut self) {
+        let mut order: Vec<usize> = (0..self.points_units.len()).collect();
+        order.sort_by_cached_key(|&i| morton_key(self.points_units[i]));
+
+        self.points_units = order.iter().map(|&i| self.points_units[i]).collect();
+        if let Some(labels) = self.labels.as_mut() {
+            *labels = order.iter().map(|&i| labels[i]).collect();
+        }
+        if let Some(colors) = self.colors.as_mut() {
+            *colors = order.iter().map(|&i| colors[i]).collect();
+        }
+    }
+}
+
+/// Converts a coordinate from `from` to `to` units per metre, rounding half up.
+#[cfg(feature = "std")]
+fn rescale_units(v: i128, to: u32, from: u32) -> i128 {
+    if to == from {
+        return v;
+    }
+    let (num, den) = (v * to as i128, from as i128);
+    (2 * num + den).div_euclid(2 * den)
+}
+
+/// Interleaves the bits of an i32 offset triple (x lowest) into a 96-bit Morton code.
+/// The sign bit is flipped so negative offsets sort below positive ones.
+fn morton_key(p: [i32; 3]) -> u128 {
+    let mut key = 0u128;
+    for (axis, &v) in p.iter().enumerate() {
+        let u = (v as u32) ^ 0x8000_0000;
+        for bit in 0..32 {
+            key |= (((u >> bit) & 1) as u128) << (bit * 3 + axis);
+        }
+    }
+    key
+}
+
+/// Typed HYPC decode failure.
+///
+/// Converts into `io::Error` (kind `UnexpectedEof` for truncation, `InvalidData`
+/// otherwise) with the `HypcError` kept as the inner error, so callers on the
+/// `io::Result` APIs can still recover it via `get_ref()` + `downcast_ref`.
+/// Without `std` it is the crate's only error type.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum HypcError {
+    BadMagic,
+    /// Header version outside [`HYPC_MIN_VERSION`]..=[`HYPC_VERSION`], e.g. 1,
+    /// whose layout is not known.
+    UnsupportedVersion(u32),
+    ZeroUnitsPerMeter,
+    /// `units_per_meter` above [`MAX_UNITS_PER_METER`].
+    UnitsPerMeterTooLarge(u32),
+    PointsOverflow,
+    /// `needed` bytes were required at byte `offset` but the input ended first.
+    Truncated { offset: usize, needed: usize },
+    /// A flagged chunk did not start with its four-byte tag.
+    BadChunkTag { expected: &'static str },
+    UnknownCoordSpace(u8),
+    UnknownEncoding(u8),
+    UnknownPointsEncoding(u8),
+    UnknownPointsCodec(u8),
+    /// The tile is compressed but the crate was built without the
+    /// `compression` feature.
+    CompressionUnsupported,
+    /// A compressed block did not inflate, or not to its declared size.
+    BadCompressedBlock,
+    /// SMC1 RLE payload length is not a whole number of runs.
+    RleTruncated,
+    /// SMC1 pixel count (raw bytes, or expanded RLE runs) differs from width*height.
+    Smc1SizeMismatch { expected: usize, actual: usize },
+    /// SMC1 grid with no pixels; a mask must be at least 1x1.
+    Smc1ZeroSize { width: u16, height: u16 },
+    MisalignedPoints,
+    /// A delta-encoded points block ended mid-point or had bytes left after
+    /// the last point.
+    BadPointDeltas,
+    /// The CRC chunk does not match the bytes before it.
+    ChecksumMismatch { stored: u32, computed: u32 },
+    /// [`HypcTile::merge`] was given no tiles.
+    EmptyMerge,
+    /// A merged point's offset from the new anchor does not fit in an i32.
+    OffsetOverflow,
+    /// Underlying reader failure (streaming parser only).
+    #[cfg(feature = "std")]
+    Io(io::Error),
+}
+
+/// Result of the decoders that work without `std`.
+pub type HypcResult<T> = Result<T, HypcError>;
+
+impl core::fmt::Display for HypcError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            HypcError::BadMagic => write!(f, "bad HYPC magic"),
+            HypcError::UnsupportedVersion(v) => {
+                write!(
+                    f,
+                    "unsupported HYPC version {} (supported {}..={})",
+                    v, HYPC_MIN_VERSION, HYPC_VERSION
+                )
+            }
+            HypcError::ZeroUnitsPerMeter => write!(f, "units_per_meter must be > 0"),
+            HypcError::UnitsPerMeterTooLarge(upm) => write!(
+                f,
+                "units_per_meter {} exceeds the maximum of {}",
+                upm, MAX_UNITS_PER_METER
+            ),
+            HypcError::PointsOverflow => write!(f, "points size overflow"),
+            HypcError::Truncated { offset, needed } => {
+                write!(f, "truncated HYPC: needed {} bytes at offset {}", needed, offset)
+            }
+            HypcError::BadChunkTag { expected } => write!(f, "expected {} tag", expected),
+            HypcError::UnknownCoordSpace(x) => write!(f, "unknown SMC1 coord space {}", x),
+            HypcError::UnknownEncoding(x) => write!(f, "unknown SMC1 encoding {}", x),
+            HypcError::UnknownPointsEncoding(x) => write!(f, "unknown points encoding {}", x),
+            HypcError::UnknownPointsCodec(x) => write!(f, "unknown points codec {}", x),
+            HypcError::CompressionUnsupported => write!(
+                f,
+                "compressed HYPC data needs hypc's `compression` feature"
+            ),
+            HypcError::BadCompressedBlock => write!(f, "corrupt compressed block"),
+            HypcError::RleTruncated => write!(f, "RLE payload truncated"),
+            HypcError::Smc1SizeMismatch { expected, actual } => write!(
+                f,
+                "SMC1 mask has {} pixels, expected width*height = {}",
+                actual, expected
+            ),
+            HypcError::Smc1ZeroSize { width, height } => {
+                write!(f, "SMC1 mask is {}x{}, expected at least 1x1", width, height)
+            }
+            HypcError::MisalignedPoints => write!(f, "misaligned points block"),
+            HypcError::BadPointDeltas => {
+                write!(f, "delta points block does not match points_count")
+            }
+            HypcError::ChecksumMismatch { stored, computed } => write!(
+                f,
+                "CRC mismatch: stored {:08x}, computed {:08x}",
+                stored, computed
+            ),
+            HypcError::EmptyMerge => write!(f, "no tiles to merge"),
+            HypcError::OffsetOverflow => {
+                write!(f, "point offset overflows i32; lower units_per_meter")
+            }
+            #[cfg(feature = "std")]
+            HypcError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for HypcError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            HypcError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<HypcError> for io::Error {
+    fn from(e: HypcError) -> Self {
+        match e {
+            HypcError::Io(e) => e,
+            HypcError::Truncated { .. } => io::Error::new(ErrorKind::UnexpectedEof, e),
+            _ => io::Error::new(ErrorKind::InvalidData, e),
+        }
+    }
+}
+
+/// Read position in a HYPC buffer that remembers its absolute file offset,
+/// so truncation errors can say where the data ran out.
+struct Cursor<'a> {
+    buf: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(buf: &'a [u8]) -> Self {
+        Self::at(buf, 0)
+    }
+
+    /// A cursor over `buf`, whose first byte sits `offset` bytes into the file.
+    fn at(buf: &'a [u8], offset: usize) -> Self {
+        Self { buf, offset }
+    }
 }
 
 #[inline(always)]
-fn need(buf: &[u8], want: usize) -> io::Result<()> {
-    if buf.len() < want {
-        Err(io::Error::new(ErrorKind::UnexpectedEof, "truncated HYPC"))
+fn need(p: &Cursor, want: usize) -> Result<(), HypcError> {
+    if p.buf.len() < want {
+        Err(HypcError::Truncated {
+            offset: p.offset,
+            needed: want,
+        })
     } else {
         Ok(())
     }
 }
 
 #[inline(always)]
-fn take<'a>(buf: &mut &'a [u8], n: usize) -> io::Result<&'a [u8]> {
-    need(buf, n)?;
-    let (head, tail) = buf.split_at(n);
-    *buf = tail;
+fn take<'a>(p: &mut Cursor<'a>, n: usize) -> Result<&'a [u8], HypcError> {
+    need(p, n)?;
+    let (head, tail) = p.buf.split_at(n);
+    p.buf = tail;
+    p.offset += n;
     Ok(head)
 }
 
 #[inline(always)]
-fn le_u8(buf: &mut &[u8]) -> io::Result<u8> {
-    Ok(take(buf, 1)?[0])
+fn le_u8(p: &mut Cursor) -> Result<u8, HypcError> {
+    Ok(take(p, 1)?[0])
 }
 
 #[inline(always)]
-fn le_u16(buf: &mut &[u8]) -> io::Result<u16> {
-    let b = take(buf, 2)?;
+fn le_u16(p: &mut Cursor) -> Result<u16, HypcError> {
+    let b = take(p, 2)?;
     Ok(u16::from_le_bytes([b[0], b[1]]))
 }
 
 #[inline(always)]
-fn le_u32(buf: &mut &[u8]) -> io::Result<u32> {
-    let b = take(buf, 4)?;
+fn le_u32(p: &mut Cursor) -> Result<u32, HypcError> {
+    let b = take(p, 4)?;
     Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
 }
 
 #[inline(always)]
-fn le_i32(buf: &mut &[u8]) -> io::Result<i32> {
-    let b = take(buf, 4)?;
+fn le_i32(p: &mut Cursor) -> Result<i32, HypcError> {
+    let b = take(p, 4)?;
     Ok(i32::from_le_bytes([b[0], b[1], b[2], b[3]]))
 }
 
 #[inline(always)]
-fn le_i64(buf: &mut &[u8]) -> io::Result<i64> {
-    let b = take(buf, 8)?;
+fn le_i64(p: &mut Cursor) -> Result<i64, HypcError> {
+    let b = take(p, 8)?;
     Ok(i64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]))
 }
 
-#[cold]
-fn bad(msg: &str) -> io::Error {
-    io::Error::new(ErrorKind::InvalidData, msg)
+#[inline(always)]
+fn le_f64(p: &mut Cursor) -> Result<f64, HypcError> {
+    let b = take(p, 8)?;
+    Ok(f64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]))
 }
 
-/// Parse HYPC from a contiguous byte slice. This is the single source of truth for parsing.
-pub fn parse_hypc_bytes(mut p: &[u8]) -> io::Result<HypcTile> {
-    // Header
-    if take(&mut p, 4)? != b"HYPC" {
-        return Err(bad("bad HYPC magic"));
+/// Check a chunk's four-byte tag.
+fn expect_tag(p: &mut Cursor, tag: &'static str) -> Result<(), HypcError> {
+    if take(p, 4)? != tag.as_bytes() {
+        return Err(HypcError::BadChunkTag { expected: tag });
     }
+    Ok(())
+}
+
+/// Fixed-size prefix of a HYPC file: magic through the optional tile key.
+struct Header {
+    /// Only surfaced through [`read_header`].
+    #[cfg_attr(not(feature = "std"), allow(dead_code))]
+    version: u32,
+    flags: u32,
+    count: usize,
+    units_per_meter: u32,
+    anchor_ecef_units: [i64; 3],
+    tile_key: Option<[u8; 32]>,
+    points_encoding: PointsEncoding,
+    points_codec: PointsCodec,
+}
+
+/// Size of the fixed header without the optional tile key and encoding field.
+#[cfg(feature = "std")]
+const HEADER_LEN: usize = 4 + 4 + 4 + 4 + 4 + 3 * 8;
+
+/// Bytes that follow the fixed header for the given flags: the tile key
+/// (bit 0) and the padded points encoding field (bit 7).
+#[cfg(feature = "std")]
+fn header_extra_len(flags: u32) -> usize {
+    let key = if (flags & (1 << 0)) != 0 { 32 } else { 0 };
+    let encoding = if (flags & (1 << 7)) != 0 { 4 } else { 0 };
+    key + encoding
+}
+
+/// Flags word of a buffer holding at least [`HEADER_LEN`] bytes.
+#[cfg(feature = "std")]
+fn header_flags(head: &[u8]) -> u32 {
+    u32::from_le_bytes([head[8], head[9], head[10], head[11]])
+}
 
-    let version = le_u32(&mut p)?;
-    if version != HYPC_VERSION {
-        return Err(bad("unsupported HYPC version"));
+/// Parse the fixed header (and tile key, if flagged) from the front of `p`.
+fn parse_header(p: &mut Cursor) -> Result<Header, HypcError> {
+    if take(p, 4)? != b"HYPC" {
+        return Err(HypcError::BadMagic);
     }
 
-    let flags = le_u32(&mut p)?;
-    let has_key    = (flags & (1 << 0)) != 0;
-    let has_labels = (flags & (1 << 1)) != 0;
-    let has_geot   = (flags & (1 << 2)) != 0;
-    let has_smc1   = (flags & (1 << 3)) != 0;
+    let version = le_u32(p)?;
+    if !(HYPC_MIN_VERSION..=HYPC_VERSION).contains(&version) {
+        return Err(HypcError::UnsupportedVersion(version));
+    }
+
+    let flags = le_u32(p)?;
 
-    let count = le_u32(&mut p)? as usize;
-    let units_per_meter = le_u32(&mut p)?;
+    let count = le_u32(p)? as usize;
+    let units_per_meter = le_u32(p)?;
     if units_per_meter == 0 {
-        return Err(bad("units_per_meter must be > 0"));
+        return Err(HypcError::ZeroUnitsPerMeter);
+    }
+    if units_per_meter > MAX_UNITS_PER_METER {
+        return Err(HypcError::UnitsPerMeterTooLarge(units_per_meter));
     }
 
     let anchor_ecef_units = [
-        le_i64(&mut p)?,
-        le_i64(&mut p)?,
-        le_i64(&mut p)?,
+        le_i64(p)?,
+        le_i64(p)?,
+        le_i64(p)?,
     ];
 
-    let tile_key = if has_key {
-        let t = take(&mut p, 32)?;
+    let tile_key = if (flags & (1 << 0)) != 0 {
+        let t = take(p, 32)?;
         let mut k = [0u8; 32];
         k.copy_from_slice(t);
         Some(k)
@@ -205,128 +1193,686 @@ pub fn parse_hypc_bytes(mut p: &[u8]) -> io::Result<HypcTile> {
         None
     };
 
-    // Points (+ optional interleaved label bytes)
-    let pts_rec = 12usize + if has_labels { 1 } else { 0 };
-    let pts_bytes = count.checked_mul(pts_rec).ok_or_else(|| bad("points size overflow"))?;
-    need(p, pts_bytes)?;
+    let (points_encoding, points_codec) = if (flags & (1 << 7)) != 0 {
+        let encoding = match le_u8(p)? {
+            0 => PointsEncoding::Raw,
+            1 => PointsEncoding::Delta,
+            x => return Err(HypcError::UnknownPointsEncoding(x)),
+        };
+        let codec = match le_u8(p)? {
+            0 => PointsCodec::None,
+            1 => PointsCodec::Deflate,
+            x => return Err(HypcError::UnknownPointsCodec(x)),
+        };
+        take(p, 2)?;
+        (encoding, codec)
+    } else {
+        (PointsEncoding::Raw, PointsCodec::None)
+    };
 
-    let (points_units, labels): (Vec<[i32; 3]>, Option<Vec<u8>>) = if has_labels {
-        // Safe, simple decode of interleaved [i32; 3] and u8 records.
-        // This replaces a previous `unsafe` implementation that was a source of bugs.
-        let mut pts = Vec::<[i32; 3]>::with_capacity(count);
-        let mut ls  = Vec::<u8>::with_capacity(count);
+    Ok(Header {
+        version,
+        flags,
+        count,
+        units_per_meter,
+        anchor_ecef_units,
+        tile_key,
+        points_encoding,
+        points_codec,
+    })
+}
 
-        for _ in 0..count {
-            let dx = le_i32(&mut p)?;
-            let dy = le_i32(&mut p)?;
-            let dz = le_i32(&mut p)?;
-            let l = le_u8(&mut p)?;
-            pts.push([dx, dy, dz]);
-            ls.push(l);
-        }
+/// GEOT chunk: tag + four i32 bounds.
+#[cfg(feature = "std")]
+const GEOT_LEN: usize = 4 + 4 * 4;
+/// GEO2 chunk: GEOT plus the i32 height range.
+#[cfg(feature = "std")]
+const GEO2_LEN: usize = GEOT_LEN + 2 * 4;
+/// Fixed part of SMC1: tag, width, height, coord space, encoding, palette length.
+#[cfg(feature = "std")]
+const SMC1_HEAD_LEN: usize = 4 + 2 + 2 + 1 + 1 + 2;
+/// XFRM chunk: tag + 3x3 rotation + translation, all f64.
+#[cfg(feature = "std")]
+const XFRM_LEN: usize = 4 + 12 * 8;
+/// CRC chunk: tag + u32 checksum.
+#[cfg(feature = "std")]
+const CRC_LEN: usize = 4 + 4;
+
+/// Total length of a GEOT or GEO2 chunk, from its tag.
+#[cfg(feature = "std")]
+fn geot_len(tag: &[u8]) -> Result<usize, HypcError> {
+    match tag {
+        b"GEOT" => Ok(GEOT_LEN),
+        b"GEO2" => Ok(GEO2_LEN),
+        _ => Err(HypcError::BadChunkTag { expected: "GEOT" }),
+    }
+}
 
-        (pts, Some(ls))
-    } else {
-        // Fast path: points block is tightly packed 12N bytes; zero‑copy reinterpret + to_vec().
-        let raw = take(&mut p, count * 12)?;
+fn parse_geot(p: &mut Cursor) -> Result<GeoExtentQ7, HypcError> {
+    let has_height = match take(p, 4)? {
+        b"GEOT" => false,
+        b"GEO2" => true,
+        _ => return Err(HypcError::BadChunkTag { expected: "GEOT" }),
+    };
 
-        #[cfg(target_endian = "little")]
-        {
-            // Safety:
-            // - alignment: header is 44 or 76 bytes (both %4 == 0), so this slice is 4‑aligned.
-            // - repr: [i32;3] has no padding beyond 12 bytes.
-            // - endianness: little.
-            let as_i32x3: &[[i32; 3]] = bytemuck::try_cast_slice(raw)
-                .map_err(|_| bad("misaligned points block"))?;
+    let mut geot = GeoExtentQ7 {
+        lon_min_q7: le_i32(p)?,
+        lon_max_q7: le_i32(p)?,
+        lat_min_q7: le_i32(p)?,
+        lat_max_q7: le_i32(p)?,
+        height_mm: None,
+    };
+    if has_height {
+        geot.height_mm = Some((le_i32(p)?, le_i32(p)?));
+    }
+    Ok(geot)
+}
 
-            (as_i32x3.to_vec(), None)
-        }
+/// Parse the fixed SMC1 prefix into a chunk with no palette or data yet,
+/// plus the declared palette length.
+fn parse_smc1_head(p: &mut Cursor) -> Result<(Smc1Chunk, usize), HypcError> {
+    expect_tag(p, "SMC1")?;
 
-        #[cfg(not(target_endian = "little"))]
-        {
-            // Fallback: portable decode (still a single pass).
-            let mut pts = Vec::<[i32; 3]>::with_capacity(count);
-
-            for chunk in raw.chunks_exact(12) {
-                let dx = i32::from_le_bytes(chunk[0..4].try_into().unwrap());
-                let dy = i32::from_le_bytes(chunk[4..8].try_into().unwrap());
-                let dz = i32::from_le_bytes(chunk[8..12].try_into().unwrap());
-                pts.push([dx, dy, dz]);
-            }
+    let width  = le_u16(p)?;
+    let height = le_u16(p)?;
+    if width == 0 || height == 0 {
+        return Err(HypcError::Smc1ZeroSize { width, height });
+    }
 
-            (pts, None)
-        }
+    let coord_space = match le_u8(p)? {
+        0 => Smc1CoordSpace::DecodeXY,
+        1 => Smc1CoordSpace::Crs84BboxNorm,
+        x => return Err(HypcError::UnknownCoordSpace(x)),
     };
 
-    // GEOT
-    let geot = if has_geot {
-        if take(&mut p, 4)? != b"GEOT" {
-            return Err(bad("expected GEOT tag"));
-        }
+    let encoding = match le_u8(p)? {
+        0 => Smc1Encoding::Raw,
+        1 => Smc1Encoding::Rle,
+        2 => Smc1Encoding::Deflate,
+        x => return Err(HypcError::UnknownEncoding(x)),
+    };
 
-        Some(GeoExtentQ7 {
-            lon_min_q7: le_i32(&mut p)?,
-            lon_max_q7: le_i32(&mut p)?,
-            lat_min_q7: le_i32(&mut p)?,
-            lat_max_q7: le_i32(&mut p)?,
-        })
-    } else {
-        None
+    let palette_len = le_u16(p)? as usize;
+
+    let chunk = Smc1Chunk {
+        width,
+        height,
+        coord_space,
+        encoding,
+        palette: Vec::with_capacity(palette_len),
+        data: Vec::new(),
     };
+    Ok((chunk, palette_len))
+}
 
-    // SMC1
-    let smc1 = if has_smc1 {
-        if take(&mut p, 4)? != b"SMC1" {
-            return Err(bad("expected SMC1 tag"));
+fn parse_xfrm(p: &mut Cursor) -> Result<RigidTransform, HypcError> {
+    expect_tag(p, "XFRM")?;
+
+    let mut v = [0f64; 12];
+    for x in v.iter_mut() {
+        *x = le_f64(p)?;
+    }
+
+    Ok(RigidTransform {
+        rotation: [[v[0], v[1], v[2]], [v[3], v[4], v[5]], [v[6], v[7], v[8]]],
+        translation_m: [v[9], v[10], v[11]],
+    })
+}
+
+/// Read the CRC chunk and compare it against `computed`, the CRC of every
+/// byte before the chunk.
+fn check_crc(p: &mut Cursor, computed: u32) -> Result<(), HypcError> {
+    expect_tag(p, "CRC\0")?;
+
+    let stored = le_u32(p)?;
+    if stored != computed {
+        return Err(HypcError::ChecksumMismatch { stored, computed });
+    }
+    Ok(())
+}
+
+/// Encode points as per-axis deltas, zigzag-mapped and LEB128-varint packed.
+#[cfg(feature = "std")]
+fn encode_point_deltas(points: &[[i32; 3]]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(points.len() * 4);
+    let mut prev = [0i32; 3];
+    for p in points {
+        for k in 0..3 {
+            let d = p[k].wrapping_sub(prev[k]);
+            let mut z = ((d << 1) ^ (d >> 31)) as u32;
+            while z >= 0x80 {
+                out.push(z as u8 | 0x80);
+                z >>= 7;
+            }
+            out.push(z as u8);
         }
+        prev = *p;
+    }
+    out
+}
 
-        let width  = le_u16(&mut p)?;
-        let height = le_u16(&mut p)?;
+/// Inverse of [`encode_point_deltas`]; `buf` must hold exactly `count` points.
+fn decode_point_deltas(buf: &[u8], count: usize) -> Result<Vec<[i32; 3]>, HypcError> {
+    // Every point takes at least three bytes, which bounds the allocation.
+    let mut points = Vec::with_capacity(count.min(buf.len() / 3));
+    let mut bytes = buf.iter();
+    let mut prev = [0i32; 3];
+
+    for _ in 0..count {
+        let mut p = [0i32; 3];
+        for k in 0..3 {
+            let mut z = 0u32;
+            let mut shift = 0;
+            loop {
+                let &b = bytes.next().ok_or(HypcError::BadPointDeltas)?;
+                if shift == 28 && b > 0x0F {
+                    return Err(HypcError::BadPointDeltas);
+                }
+                z |= ((b & 0x7F) as u32) << shift;
+                if b & 0x80 == 0 {
+                    break;
+                }
+                shift += 7;
+            }
+            let d = ((z >> 1) as i32) ^ -((z & 1) as i32);
+            p[k] = prev[k].wrapping_add(d);
+        }
+        points.push(p);
+        prev = p;
+    }
 
-        let coord_space = match le_u8(&mut p)? {
-            0 => Smc1CoordSpace::DecodeXY,
-            1 => Smc1CoordSpace::Crs84BboxNorm,
-            x => return Err(bad(&format!("unknown SMC1 coord space {}", x))),
-        };
+    if bytes.next().is_some() {
+        return Err(HypcError::BadPointDeltas);
+    }
+    Ok(points)
+}
+
+/// Decoded points block: points plus labels, if flagged.
+type PointsAndLabels = (Vec<[i32; 3]>, Option<Vec<u8>>);
 
-        let encoding = match le_u8(&mut p)? {
-            0 => Smc1Encoding::Raw,
-            1 => Smc1Encoding::Rle,
-            x => return Err(bad(&format!("unknown SMC1 encoding {}", x))),
+/// Decode a points block (and its labels, if flagged) from the front of `p`.
+fn parse_points_block(
+    p: &mut Cursor,
+    count: usize,
+    has_labels: bool,
+    points_encoding: PointsEncoding,
+) -> Result<PointsAndLabels, HypcError> {
+    let pts_rec = 12usize + if has_labels { 1 } else { 0 };
+    let pts_bytes = count.checked_mul(pts_rec).ok_or(HypcError::PointsOverflow)?;
+
+    // Reject a count the input can't hold before anything is sized by it. A
+    // delta-encoded point takes at least three bytes, plus its label byte.
+    let min_pts_bytes = if points_encoding == PointsEncoding::Delta {
+        let per_point = 3 + if has_labels { 1 } else { 0 };
+        count.checked_mul(per_point).ok_or(HypcError::PointsOverflow)?
+    } else {
+        pts_bytes
+    };
+    need(p, min_pts_bytes)?;
+
+    let decoded = if points_encoding == PointsEncoding::Delta {
+        // Varint stream, then the labels as their own block.
+        let len = le_u32(p)? as usize;
+        let points = decode_point_deltas(take(p, len)?, count)?;
+        let labels = if has_labels {
+            Some(take(p, count)?.to_vec())
+        } else {
+            None
         };
 
-        let palette_len = le_u16(&mut p)? as usize;
-        let mut palette = Vec::<(u8, u8)>::with_capacity(palette_len);
+        (points, labels)
+    } else if has_labels {
+        // Safe, simple decode of interleaved [i32; 3] and u8 records.
+        // This replaces a previous `unsafe` implementation that was a source of bugs.
+        let mut pts = Vec::<[i32; 3]>::with_capacity(count);
+        let mut ls  = Vec::<u8>::with_capacity(count);
+
+        for _ in 0..count {
+            let dx = le_i32(p)?;
+            let dy = le_i32(p)?;
+            let dz = le_i32(p)?;
+            let l = le_u8(p)?;
+            pts.push([dx, dy, dz]);
+            ls.push(l);
+        }
+
+        (pts, Some(ls))
+    } else {
+        // Fast path: points block is tightly packed 12N bytes; zero‑copy reinterpret + to_vec().
+        let raw = take(p, pts_bytes)?;
+
+        // Safety:
+        // - alignment: header is 44, 48, 76 or 80 bytes (all %4 == 0), so in a
+        //   whole file this slice is 4‑aligned; an inflated block may not be.
+        // - repr: [i32;3] has no padding beyond 12 bytes.
+        // - endianness: little.
+        #[cfg(target_endian = "little")]
+        if let Ok(as_i32x3) = bytemuck::try_cast_slice::<u8, [i32; 3]>(raw) {
+            return Ok((as_i32x3.to_vec(), None));
+        }
+
+        // Fallback: portable decode (still a single pass).
+        let mut pts = Vec::<[i32; 3]>::with_capacity(count);
+
+        for chunk in raw.chunks_exact(12) {
+            let dx = i32::from_le_bytes(chunk[0..4].try_into().unwrap());
+            let dy = i32::from_le_bytes(chunk[4..8].try_into().unwrap());
+            let dz = i32::from_le_bytes(chunk[8..12].try_into().unwrap());
+            pts.push([dx, dy, dz]);
+        }
+
+        (pts, None)
+    };
+    Ok(decoded)
+}
+
+/// Inflate a Deflate-compressed points block, which must expand to exactly
+/// `raw_len` bytes, and decode it as [`parse_points_block`] does.
+///
+/// `raw_len` comes from the file, so it is checked against the size `count`
+/// points take in `points_encoding` before anything is inflated: exactly
+/// `count` records when Raw, three to fifteen varint bytes per point plus the
+/// length prefix when Delta.
+fn parse_deflated_points_block(
+    stored: &[u8],
+    raw_len: usize,
+    count: usize,
+    has_labels: bool,
+    points_encoding: PointsEncoding,
+) -> Result<PointsAndLabels, HypcError> {
+    let n = count as u64;
+    let labels_len = if has_labels { n } else { 0 };
+    let (min_len, max_len) = match points_encoding {
+        PointsEncoding::Raw => {
+            let len = n * (12 + u64::from(has_labels));
+            (len, len)
+        }
+        PointsEncoding::Delta => (4 + 3 * n + labels_len, 4 + 15 * n + labels_len),
+    };
+    if !(min_len..=max_len).contains(&(raw_len as u64)) {
+        return Err(HypcError::BadCompressedBlock);
+    }
+    let block = inflate(stored, raw_len)?;
+    if block.len() != raw_len {
+        return Err(HypcError::BadCompressedBlock);
+    }
+    let mut p = Cursor::new(&block);
+    let decoded = parse_points_block(&mut p, count, has_labels, points_encoding)?;
+    if !p.buf.is_empty() {
+        return Err(HypcError::BadCompressedBlock);
+    }
+    Ok(decoded)
+}
+
+/// Inflate a zlib stream, failing if it would exceed `limit` bytes.
+#[cfg(feature = "compression")]
+fn inflate(data: &[u8], limit: usize) -> HypcResult<Vec<u8>> {
+    miniz_oxide::inflate::decompress_to_vec_zlib_with_limit(data, limit)
+        .map_err(|_| HypcError::BadCompressedBlock)
+}
+
+#[cfg(not(feature = "compression"))]
+fn inflate(_data: &[u8], _limit: usize) -> HypcResult<Vec<u8>> {
+    Err(HypcError::CompressionUnsupported)
+}
+
+/// zlib level of written Deflate blocks: miniz's default, a good size/speed balance.
+#[cfg(feature = "compression")]
+const DEFLATE_LEVEL: u8 = 6;
+
+/// zlib-compress `raw` at [`DEFLATE_LEVEL`].
+#[cfg(all(feature = "std", feature = "compression"))]
+fn deflate(raw: &[u8]) -> io::Result<Vec<u8>> {
+    Ok(miniz_oxide::deflate::compress_to_vec_zlib(raw, DEFLATE_LEVEL))
+}
+
+#[cfg(all(feature = "std", not(feature = "compression")))]
+fn deflate(_raw: &[u8]) -> io::Result<Vec<u8>> {
+    Err(HypcError::CompressionUnsupported.into())
+}
+
+/// Version of a tile older than [`HYPC_MIN_VERSION`], from the start of its bytes.
+fn legacy_version(head: &[u8]) -> Option<u32> {
+    let version = u32::from_le_bytes(head.get(4..8)?.try_into().ok()?);
+    (head.starts_with(&HYPC_MAGIC) && version < HYPC_MIN_VERSION).then_some(version)
+}
+
+/// Conversion hook for tiles older than [`HYPC_MIN_VERSION`]: turns a whole
+/// legacy file into the current [`HypcTile`], fields it lacks left at their
+/// defaults. Both parsers (and so `read_file`) route such tiles here.
+///
+/// No pre-v2 layout was ever specified, so every legacy version is rejected
+/// with [`HypcError::UnsupportedVersion`] until one is recovered.
+fn parse_legacy(version: u32, _bytes: &[u8]) -> Result<HypcTile, HypcError> {
+    Err(HypcError::UnsupportedVersion(version))
+}
+
+/// Parse HYPC from a contiguous byte slice. This is the single source of truth for parsing.
+pub fn parse_hypc_bytes(bytes: &[u8]) -> Result<HypcTile, HypcError> {
+    if let Some(version) = legacy_version(bytes) {
+        return parse_legacy(version, bytes);
+    }
+    let mut p = Cursor::new(bytes);
+
+    // Header
+    let Header {
+        flags,
+        count,
+        units_per_meter,
+        anchor_ecef_units,
+        tile_key,
+        points_encoding,
+        points_codec,
+        ..
+    } = parse_header(&mut p)?;
+
+    let has_labels = (flags & (1 << 1)) != 0;
+    let has_rgb    = (flags & (1 << 4)) != 0;
+    let has_geot   = (flags & (1 << 2)) != 0;
+    let has_smc1   = (flags & (1 << 3)) != 0;
+    let has_xfrm   = (flags & (1 << 6)) != 0;
+    let has_crc    = (flags & (1 << 5)) != 0;
+
+    // Points (+ labels)
+    let (points_units, labels) = match points_codec {
+        PointsCodec::None => parse_points_block(&mut p, count, has_labels, points_encoding)?,
+        PointsCodec::Deflate => {
+            let raw_len = le_u32(&mut p)? as usize;
+            let stored_len = le_u32(&mut p)? as usize;
+            let stored = take(&mut p, stored_len)?;
+            parse_deflated_points_block(stored, raw_len, count, has_labels, points_encoding)?
+        }
+    };
+
+    // RGB
+    let colors = if has_rgb {
+        let rgb_bytes = count.checked_mul(3).ok_or(HypcError::PointsOverflow)?;
+        let raw = take(&mut p, rgb_bytes)?;
+        Some(raw.chunks_exact(3).map(|c| [c[0], c[1], c[2]]).collect())
+    } else {
+        None
+    };
+
+    // GEOT
+    let geot = if has_geot {
+        Some(parse_geot(&mut p)?)
+    } else {
+        None
+    };
+
+    // SMC1
+    let smc1 = if has_smc1 {
+        let (mut chunk, palette_len) = parse_smc1_head(&mut p)?;
 
         for _ in 0..palette_len {
             let class = le_u8(&mut p)?;
             let precedence = le_u8(&mut p)?;
-            palette.push((class, precedence));
+            chunk.palette.push((class, precedence));
         }
 
         let payload_size = le_u32(&mut p)? as usize;
-        let data = take(&mut p, payload_size)?.to_vec();
-
-        Some(Smc1Chunk {
-            width,
-            height,
-            coord_space,
-            encoding,
-            palette,
-            data,
-        })
+        chunk.data = take(&mut p, payload_size)?.to_vec();
+        chunk.check_payload()?;
+
+        Some(chunk)
+    } else {
+        None
+    };
+
+    // XFRM
+    let transform = if has_xfrm {
+        Some(parse_xfrm(&mut p)?)
+    } else {
+        None
+    };
+
+    // CRC
+    if has_crc {
+        let computed = crc32(&bytes[..p.offset]);
+        check_crc(&mut p, computed)?;
+    }
+
+    Ok(HypcTile {
+        units_per_meter,
+        anchor_ecef_units,
+        tile_key,
+        points_units,
+        labels,
+        colors,
+        geot,
+        smc1,
+        transform,
+        crc: has_crc,
+        points_encoding,
+        points_codec,
+    })
+}
+
+/// Points decoded per `read_exact` in [`parse_hypc_reader`].
+#[cfg(feature = "std")]
+const READER_CHUNK_POINTS: usize = 4096;
+
+/// Fill `buf` from `r`, advancing `offset` and reporting a short stream the
+/// same way the slice parser does.
+#[cfg(feature = "std")]
+fn read_exact_at<R: Read>(r: &mut R, offset: &mut usize, buf: &mut [u8]) -> Result<(), HypcError> {
+    r.read_exact(buf).map_err(|e| {
+        if e.kind() == ErrorKind::UnexpectedEof {
+            HypcError::Truncated {
+                offset: *offset,
+                needed: buf.len(),
+            }
+        } else {
+            HypcError::Io(e)
+        }
+    })?;
+    *offset += buf.len();
+    Ok(())
+}
+
+/// Read exactly `n` bytes without trusting `n` for the up-front allocation.
+#[cfg(feature = "std")]
+fn read_vec_at<R: BufRead>(r: &mut R, offset: &mut usize, n: usize) -> Result<Vec<u8>, HypcError> {
+    let mut out = Vec::new();
+    r.take(n as u64).read_to_end(&mut out).map_err(HypcError::Io)?;
+    if out.len() != n {
+        return Err(HypcError::Truncated {
+            offset: *offset,
+            needed: n,
+        });
+    }
+    *offset += n;
+    Ok(out)
+}
+
+/// Raw points block (+ optional interleaved label bytes), decoded in
+/// fixed-size chunks. Capacity grows with what actually arrives, so a bogus
+/// count can't force a huge allocation.
+#[cfg(feature = "std")]
+fn read_raw_points<R: BufRead>(
+    r: &mut R,
+    offset: &mut usize,
+    count: usize,
+    has_labels: bool,
+) -> Result<PointsAndLabels, HypcError> {
+    let pts_rec = 12usize + if has_labels { 1 } else { 0 };
+    count.checked_mul(pts_rec).ok_or(HypcError::PointsOverflow)?;
+
+    let mut points_units = Vec::<[i32; 3]>::with_capacity(count.min(READER_CHUNK_POINTS));
+    let mut labels = has_labels.then(|| Vec::<u8>::with_capacity(count.min(READER_CHUNK_POINTS)));
+    let mut buf = vec![0u8; READER_CHUNK_POINTS.min(count) * pts_rec];
+
+    let mut left = count;
+    while left > 0 {
+        let n = left.min(READER_CHUNK_POINTS);
+        let chunk = &mut buf[..n * pts_rec];
+        read_exact_at(r, offset, chunk)?;
+
+        for rec in chunk.chunks_exact(pts_rec) {
+            points_units.push([
+                i32::from_le_bytes([rec[0], rec[1], rec[2], rec[3]]),
+                i32::from_le_bytes([rec[4], rec[5], rec[6], rec[7]]),
+                i32::from_le_bytes([rec[8], rec[9], rec[10], rec[11]]),
+            ]);
+        }
+        if let Some(ls) = labels.as_mut() {
+            ls.extend(chunk.iter().skip(12).step_by(pts_rec));
+        }
+
+        left -= n;
+    }
+
+    Ok((points_units, labels))
+}
+
+/// Parse HYPC incrementally from a stream (socket, decompressor, ...).
+///
+/// Produces the same [`HypcTile`] as [`parse_hypc_bytes`] without holding the
+/// whole file: the header and chunks are read piecewise and a raw points block
+/// is decoded [`READER_CHUNK_POINTS`] records at a time (a delta or compressed
+/// block is read whole, being already compact). Bytes after the last chunk are left unread.
+///
+/// Reader failures come back unchanged; malformed input as `InvalidData` (or
+/// `UnexpectedEof` when truncated) with the [`HypcError`] inside.
+#[cfg(feature = "std")]
+pub fn parse_hypc_reader<R: BufRead>(r: &mut R) -> io::Result<HypcTile> {
+    Ok(parse_hypc_stream(r)?)
+}
+
+/// [`parse_hypc_reader`] with the typed error.
+#[cfg(feature = "std")]
+fn parse_hypc_stream<R: BufRead>(r: &mut R) -> HypcResult<HypcTile> {
+    let r = &mut Crc32Reader::new(r);
+    let mut offset = 0usize;
+
+    // Header (+ key and encoding if flagged)
+    let mut head = [0u8; HEADER_LEN + 32 + 4];
+    read_exact_at(r, &mut offset, &mut head[..HEADER_LEN])?;
+    if let Some(version) = legacy_version(&head) {
+        let mut bytes = head[..HEADER_LEN].to_vec();
+        r.read_to_end(&mut bytes).map_err(HypcError::Io)?;
+        return parse_legacy(version, &bytes);
+    }
+    let extra = header_extra_len(header_flags(&head));
+    read_exact_at(r, &mut offset, &mut head[HEADER_LEN..HEADER_LEN + extra])?;
+    let Header {
+        flags,
+        count,
+        units_per_meter,
+        anchor_ecef_units,
+        tile_key,
+        points_encoding,
+        points_codec,
+        ..
+    } = parse_header(&mut Cursor::new(&head[..offset]))?;
+
+    let has_labels = (flags & (1 << 1)) != 0;
+    let has_rgb    = (flags & (1 << 4)) != 0;
+    let has_geot   = (flags & (1 << 2)) != 0;
+    let has_smc1   = (flags & (1 << 3)) != 0;
+    let has_xfrm   = (flags & (1 << 6)) != 0;
+    let has_crc    = (flags & (1 << 5)) != 0;
+
+    // Only pay for hashing when there is a checksum to compare against.
+    r.active = has_crc;
+
+    // Points (+ labels)
+    let (points_units, labels) = if points_codec == PointsCodec::Deflate {
+        let mut lens = [0u8; 8];
+        read_exact_at(r, &mut offset, &mut lens)?;
+        let raw_len = u32::from_le_bytes([lens[0], lens[1], lens[2], lens[3]]) as usize;
+        let stored_len = u32::from_le_bytes([lens[4], lens[5], lens[6], lens[7]]) as usize;
+        let stored = read_vec_at(r, &mut offset, stored_len)?;
+        parse_deflated_points_block(&stored, raw_len, count, has_labels, points_encoding)?
+    } else if points_encoding == PointsEncoding::Delta {
+        let mut len = [0u8; 4];
+        read_exact_at(r, &mut offset, &mut len)?;
+        let deltas = read_vec_at(r, &mut offset, u32::from_le_bytes(len) as usize)?;
+        let points = decode_point_deltas(&deltas, count)?;
+        let labels = if has_labels {
+            Some(read_vec_at(r, &mut offset, count)?)
+        } else {
+            None
+        };
+
+        (points, labels)
+    } else {
+        read_raw_points(r, &mut offset, count, has_labels)?
+    };
+
+    // RGB
+    let colors = if has_rgb {
+        let rgb_bytes = count.checked_mul(3).ok_or(HypcError::PointsOverflow)?;
+        let raw = read_vec_at(r, &mut offset, rgb_bytes)?;
+        Some(raw.chunks_exact(3).map(|c| [c[0], c[1], c[2]]).collect())
     } else {
         None
     };
 
+    // GEOT
+    let geot = if has_geot {
+        let start = offset;
+        let mut b = [0u8; GEO2_LEN];
+        read_exact_at(r, &mut offset, &mut b[..4])?;
+        let len = geot_len(&b[..4])?;
+        read_exact_at(r, &mut offset, &mut b[4..len])?;
+        Some(parse_geot(&mut Cursor::at(&b[..len], start))?)
+    } else {
+        None
+    };
+
+    // SMC1
+    let smc1 = if has_smc1 {
+        let start = offset;
+        let mut b = [0u8; SMC1_HEAD_LEN];
+        read_exact_at(r, &mut offset, &mut b)?;
+        let (mut chunk, palette_len) = parse_smc1_head(&mut Cursor::at(&b, start))?;
+
+        let pal = read_vec_at(r, &mut offset, palette_len * 2)?;
+        chunk.palette = pal.chunks_exact(2).map(|c| (c[0], c[1])).collect();
+
+        let mut size = [0u8; 4];
+        read_exact_at(r, &mut offset, &mut size)?;
+        chunk.data = read_vec_at(r, &mut offset, u32::from_le_bytes(size) as usize)?;
+        chunk.check_payload()?;
+
+        Some(chunk)
+    } else {
+        None
+    };
+
+    // XFRM
+    let transform = if has_xfrm {
+        let start = offset;
+        let mut b = [0u8; XFRM_LEN];
+        read_exact_at(r, &mut offset, &mut b)?;
+        Some(parse_xfrm(&mut Cursor::at(&b, start))?)
+    } else {
+        None
+    };
+
+    // CRC
+    if has_crc {
+        let computed = r.crc.finish();
+        let start = offset;
+        let mut b = [0u8; CRC_LEN];
+        read_exact_at(r, &mut offset, &mut b)?;
+        check_crc(&mut Cursor::at(&b, start), computed)?;
+    }
+
     Ok(HypcTile {
         units_per_meter,
         anchor_ecef_units,
         tile_key,
         points_units,
         labels,
+        colors,
         geot,
         smc1,
+        transform,
+        crc: has_crc,
+        points_encoding,
+        points_codec,
     })
 }
 
@@ -335,16 +1881,287 @@ pub fn parse_hypc_bytes(mut p: &[u8]) -> io::Result<HypcTile> {
 pub fn read_file<P: AsRef<Path>>(path: P) -> io::Result<HypcTile> {
     let file = File::open(path)?;
     let map = unsafe { memmap2::MmapOptions::new().map(&file)? };
-    parse_hypc_bytes(&map)
+    Ok(parse_hypc_bytes(&map)?)
 }
 
-#[cfg(not(feature = "mmap"))]
+#[cfg(all(feature = "std", not(feature = "mmap")))]
 pub fn read_file<P: AsRef<Path>>(path: P) -> io::Result<HypcTile> {
     let bytes = std::fs::read(path)?;
-    parse_hypc_bytes(&bytes)
+    Ok(parse_hypc_bytes(&bytes)?)
 }
 
-pub fn write_file<P: AsRef<Path>>(path: P, tile: &HypcTile) -> io::Result<()> {
+/// The fixed header of a HYPC tile, as returned by [`read_header`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HypcHeader {
+    pub version: u32,
+    pub flags: u32,
+    pub points_count: u32,
+    pub units_per_meter: u32,
+    pub anchor_ecef_units: [i64; 3],
+    pub tile_key: Option<[u8; 32]>,
+}
+
+/// Read just the header of a HYPC file: the fixed prefix plus the tile key and
+/// points encoding when flagged, at most 80 bytes.
+///
+/// Nothing after the header is read or checked, so a tile whose points or
+/// chunks are truncated or corrupt still yields its header; use
+/// [`validate_file`] for that.
+#[cfg(feature = "std")]
+pub fn read_header<P: AsRef<Path>>(path: P) -> io::Result<HypcHeader> {
+    let mut file = File::open(path)?;
+    let mut offset = 0usize;
+    let mut head = [0u8; HEADER_LEN + 32 + 4];
+    read_exact_at(&mut file, &mut offset, &mut head[..HEADER_LEN])?;
+    let extra = header_extra_len(header_flags(&head));
+    read_exact_at(
+        &mut file,
+        &mut offset,
+        &mut head[HEADER_LEN..HEADER_LEN + extra],
+    )?;
+    Ok(parse_header_bytes(&head[..offset])?)
+}
+
+/// Parse just the header from the front of `bytes`, as [`read_header`] does
+/// for a file. Anything after the header is ignored.
+pub fn parse_header_bytes(bytes: &[u8]) -> Result<HypcHeader, HypcError> {
+    let header = parse_header(&mut Cursor::new(bytes))?;
+
+    Ok(HypcHeader {
+        version: header.version,
+        flags: header.flags,
+        points_count: header.count as u32,
+        units_per_meter: header.units_per_meter,
+        anchor_ecef_units: header.anchor_ecef_units,
+        tile_key: header.tile_key,
+    })
+}
+
+/// Summary of a tile that passed [`validate_file`] / [`validate_hypc_bytes`].
+#[derive(Debug, Clone, Copy)]
+pub struct HypcValidation {
+    pub flags: u32,
+    pub points_count: u32,
+    pub units_per_meter: u32,
+    /// Total bytes covered by the header and all chunks.
+    pub byte_len: u64,
+}
+
+/// Structurally validate a HYPC stream without materializing points or mask data.
+///
+/// Parses the header, then seeks over the points block and each chunk,
+/// checking tags, enum values and that every declared length is present.
+#[cfg(feature = "std")]
+pub fn validate_reader<R: Read + Seek>(r: &mut R) -> io::Result<HypcValidation> {
+    let start = r.stream_position()?;
+    let end = r.seek(SeekFrom::End(0))?;
+    r.seek(SeekFrom::Start(start))?;
+
+    let avail = end.saturating_sub(start);
+    let mut pos = 0u64;
+
+    // Reads exactly `n` bytes, reporting truncation the same way the parser does.
+    let truncated = |pos: u64, n: u64| -> io::Error {
+        HypcError::Truncated {
+            offset: pos as usize,
+            needed: n as usize,
+        }
+        .into()
+    };
+
+    let read = |r: &mut R, pos: &mut u64, n: usize| -> io::Result<Vec<u8>> {
+        if *pos + n as u64 > avail {
+            return Err(truncated(*pos, n as u64));
+        }
+        let mut buf = vec![0u8; n];
+        r.read_exact(&mut buf)?;
+        *pos += n as u64;
+        Ok(buf)
+    };
+
+    let skip = |r: &mut R, pos: &mut u64, n: u64| -> io::Result<()> {
+        if *pos + n > avail {
+            return Err(truncated(*pos, n));
+        }
+        r.seek(SeekFrom::Current(n as i64))?;
+        *pos += n;
+        Ok(())
+    };
+
+    // Header (+ key and encoding if flagged)
+    let mut head = read(r, &mut pos, HEADER_LEN)?;
+    let extra = header_extra_len(header_flags(&head));
+    head.extend_from_slice(&read(r, &mut pos, extra)?);
+    let header = parse_header(&mut Cursor::new(&head))?;
+
+    let has_labels = (header.flags & (1 << 1)) != 0;
+    let has_rgb    = (header.flags & (1 << 4)) != 0;
+    let has_geot   = (header.flags & (1 << 2)) != 0;
+    let has_smc1   = (header.flags & (1 << 3)) != 0;
+    let has_xfrm   = (header.flags & (1 << 6)) != 0;
+    let has_crc    = (header.flags & (1 << 5)) != 0;
+
+    // Points (a delta or compressed block's point count is only checkable by
+    // decoding it)
+    if header.points_codec == PointsCodec::Deflate {
+        let lens = read(r, &mut pos, 8)?;
+        skip(r, &mut pos, u32::from_le_bytes([lens[4], lens[5], lens[6], lens[7]]) as u64)?;
+    } else if header.points_encoding == PointsEncoding::Delta {
+        let len = read(r, &mut pos, 4)?;
+        skip(r, &mut pos, u32::from_le_bytes([len[0], len[1], len[2], len[3]]) as u64)?;
+        if has_labels {
+            skip(r, &mut pos, header.count as u64)?;
+        }
+    } else {
+        let pts_rec = 12u64 + if has_labels { 1 } else { 0 };
+        let pts_bytes = (header.count as u64)
+            .checked_mul(pts_rec)
+            .ok_or(HypcError::PointsOverflow)?;
+        skip(r, &mut pos, pts_bytes)?;
+    }
+
+    // RGB
+    if has_rgb {
+        skip(r, &mut pos, header.count as u64 * 3)?;
+    }
+
+    // GEOT
+    if has_geot {
+        let tag = read(r, &mut pos, 4)?;
+        skip(r, &mut pos, (geot_len(&tag)? - 4) as u64)?;
+    }
+
+    // SMC1
+    if has_smc1 {
+        let start = pos as usize;
+        let fixed = read(r, &mut pos, SMC1_HEAD_LEN)?;
+        let (head, palette_len) = parse_smc1_head(&mut Cursor::at(&fixed, start))?;
+        let encoding = head.encoding;
+
+        skip(r, &mut pos, palette_len as u64 * 2)?;
+
+        let size = read(r, &mut pos, 4)?;
+        let payload_size = u32::from_le_bytes([size[0], size[1], size[2], size[3]]) as u64;
+
+        if encoding == Smc1Encoding::Rle && !payload_size.is_multiple_of(3) {
+            return Err(HypcError::RleTruncated.into());
+        }
+
+        // Raw sizes are checkable from the header alone; RLE run totals and
+        // Deflate sizes would need the payload, which validation deliberately
+        // doesn't read.
+        let pixels = head.width as u64 * head.height as u64;
+        if encoding == Smc1Encoding::Raw && payload_size != pixels {
+            return Err(HypcError::Smc1SizeMismatch {
+                expected: pixels as usize,
+                actual: payload_size as usize,
+            }
+            .into());
+        }
+
+        skip(r, &mut pos, payload_size)?;
+    }
+
+    // XFRM
+    if has_xfrm {
+        let start = pos as usize;
+        let xfrm = read(r, &mut pos, XFRM_LEN)?;
+        expect_tag(&mut Cursor::at(&xfrm, start), "XFRM")?;
+    }
+
+    // CRC (tag only; see verify_file for the checksum itself)
+    if has_crc {
+        let start = pos as usize;
+        let crc = read(r, &mut pos, CRC_LEN)?;
+        expect_tag(&mut Cursor::at(&crc, start), "CRC\0")?;
+    }
+
+    Ok(HypcValidation {
+        flags: header.flags,
+        points_count: header.count as u32,
+        units_per_meter: header.units_per_meter,
+        byte_len: pos,
+    })
+}
+
+/// Validate an in-memory HYPC buffer; see [`validate_reader`].
+#[cfg(feature = "std")]
+pub fn validate_hypc_bytes(bytes: &[u8]) -> io::Result<HypcValidation> {
+    validate_reader(&mut io::Cursor::new(bytes))
+}
+
+/// Validate a HYPC file on disk without loading it; see [`validate_reader`].
+#[cfg(feature = "std")]
+pub fn validate_file<P: AsRef<Path>>(path: P) -> io::Result<HypcValidation> {
+    let mut file = io::BufReader::new(File::open(path)?);
+    validate_reader(&mut file)
+}
+
+/// Check a tile's CRC chunk against its contents without decoding it.
+///
+/// The file is validated structurally (errors are returned as from
+/// [`validate_file`]) and then hashed in a single streaming pass. Returns
+/// `Ok(true)` only if the stored CRC matches; a tile written without a CRC
+/// chunk cannot be checked and yields `Ok(false)`.
+#[cfg(feature = "std")]
+pub fn verify_file<P: AsRef<Path>>(path: P) -> io::Result<bool> {
+    let mut file = io::BufReader::new(File::open(path)?);
+    let v = validate_reader(&mut file)?;
+    if (v.flags & (1 << 5)) == 0 {
+        return Ok(false);
+    }
+
+    file.rewind()?;
+    let mut crc = Crc32::new();
+    let mut body = (&mut file).take(v.byte_len - CRC_LEN as u64);
+    loop {
+        let buf = body.fill_buf()?;
+        if buf.is_empty() {
+            break;
+        }
+        crc.update(buf);
+        let n = buf.len();
+        body.consume(n);
+    }
+
+    let mut chunk = [0u8; CRC_LEN];
+    file.read_exact(&mut chunk)?;
+    let stored = u32::from_le_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]);
+    Ok(stored == crc.finish())
+}
+
+/// Serialize `tile` in HYPC layout to any writer.
+///
+/// This is the single encoder behind [`write_file`] and [`write_hypc_bytes`].
+/// The writer sees many small writes, so wrap unbuffered sinks in a
+/// `BufWriter`. With `tile.crc` set, the bytes are hashed on the way through
+/// and the CRC chunk is appended last.
+#[cfg(feature = "std")]
+pub fn write_to<W: Write>(w: &mut W, tile: &HypcTile) -> io::Result<()> {
+    if !tile.crc {
+        return write_body(w, tile);
+    }
+
+    let mut hashed = Crc32Writer::new(&mut *w);
+    write_body(&mut hashed, tile)?;
+    let crc = hashed.crc.finish();
+
+    w.write_all(b"CRC\0")?;
+    write_u32(w, crc)
+}
+
+/// Everything [`write_to`] emits before the CRC chunk.
+#[cfg(feature = "std")]
+fn write_body<W: Write>(w: &mut W, tile: &HypcTile) -> io::Result<()> {
+    if let Some(colors) = tile.colors.as_ref() {
+        if colors.len() != tile.points_units.len() {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                "colors length != points length",
+            ));
+        }
+    }
+
     let mut flags = 0u32;
 
     if tile.tile_key.is_some() {
@@ -363,22 +2180,40 @@ pub fn write_file<P: AsRef<Path>>(path: P, tile: &HypcTile) -> io::Result<()> {
         flags |= 1 << 3;
     }
 
-    let mut file = File::create(path)?;
+    if tile.colors.is_some() {
+        flags |= 1 << 4;
+    }
+
+    if tile.crc {
+        flags |= 1 << 5;
+    }
+
+    if tile.transform.is_some() {
+        flags |= 1 << 6;
+    }
+
+    if tile.points_encoding != PointsEncoding::Raw || tile.points_codec != PointsCodec::None {
+        flags |= 1 << 7;
+    }
 
-    file.write_all(&HYPC_MAGIC)?;
+    w.write_all(&HYPC_MAGIC)?;
 
-    write_u32(&mut file, HYPC_VERSION)?;
-    write_u32(&mut file, flags)?;
+    write_u32(w, HYPC_VERSION)?;
+    write_u32(w, flags)?;
 
-    write_u32(&mut file, tile.points_units.len() as u32)?;
-    write_u32(&mut file, tile.units_per_meter)?;
+    write_u32(w, tile.points_units.len() as u32)?;
+    write_u32(w, tile.units_per_meter)?;
 
-    write_i64(&mut file, tile.anchor_ecef_units[0])?;
-    write_i64(&mut file, tile.anchor_ecef_units[1])?;
-    write_i64(&mut file, tile.anchor_ecef_units[2])?;
+    write_i64(w, tile.anchor_ecef_units[0])?;
+    write_i64(w, tile.anchor_ecef_units[1])?;
+    write_i64(w, tile.anchor_ecef_units[2])?;
 
     if let Some(key) = tile.tile_key {
-        file.write_all(&key)?;
+        w.write_all(&key)?;
+    }
+
+    if (flags & (1 << 7)) != 0 {
+        w.write_all(&[tile.points_encoding as u8, tile.points_codec as u8, 0, 0])?;
     }
 
     if let Some(labels) = tile.labels.as_ref() {
@@ -388,56 +2223,193 @@ pub fn write_file<P: AsRef<Path>>(path: P, tile: &HypcTile) -> io::Result<()> {
                 "labels length != points length",
             ));
         }
+    }
+
+    match tile.points_codec {
+        PointsCodec::None => write_points_block(w, tile)?,
+        PointsCodec::Deflate => {
+            let mut raw = Vec::new();
+            write_points_block(&mut raw, tile)?;
+            let stored = deflate(&raw)?;
+            let len = |n: usize| {
+                u32::try_from(n).map_err(|_| {
+                    io::Error::new(ErrorKind::InvalidData, "points block exceeds 4 GiB")
+                })
+            };
+            write_u32(w, len(raw.len())?)?;
+            write_u32(w, len(stored.len())?)?;
+            w.write_all(&stored)?;
+        }
+    }
+
+    if let Some(colors) = tile.colors.as_ref() {
+        w.write_all(colors.as_flattened())?;
+    }
+
+    if let Some(geot) = tile.geot.as_ref() {
+        let tag = if geot.height_mm.is_some() {
+            b"GEO2"
+        } else {
+            b"GEOT"
+        };
+        w.write_all(tag)?;
+
+        write_i32(w, geot.lon_min_q7)?;
+        write_i32(w, geot.lon_max_q7)?;
+        write_i32(w, geot.lat_min_q7)?;
+        write_i32(w, geot.lat_max_q7)?;
+
+        if let Some((h_min_mm, h_max_mm)) = geot.height_mm {
+            write_i32(w, h_min_mm)?;
+            write_i32(w, h_max_mm)?;
+        }
+    }
+
+    if let Some(smc1) = tile.smc1.as_ref() {
+        w.write_all(b"SMC1")?;
+
+        write_u16(w, smc1.width)?;
+        write_u16(w, smc1.height)?;
+
+        w.write_all(&[smc1.coord_space as u8])?;
+        w.write_all(&[smc1.encoding as u8])?;
+
+        write_u16(w, smc1.palette.len() as u16)?;
+
+        for &(class, precedence) in &smc1.palette {
+            w.write_all(&[class, precedence])?;
+        }
+
+        write_u32(w, smc1.data.len() as u32)?;
+
+        w.write_all(&smc1.data)?;
+    }
+
+    if let Some(xf) = tile.transform.as_ref() {
+        w.write_all(b"XFRM")?;
+
+        for row in &xf.rotation {
+            for &v in row {
+                write_f64(w, v)?;
+            }
+        }
+
+        for &v in &xf.translation_m {
+            write_f64(w, v)?;
+        }
+    }
+
+    Ok(())
+}
 
+/// The points block of `tile` (with its labels), uncompressed.
+#[cfg(feature = "std")]
+fn write_points_block<W: Write>(w: &mut W, tile: &HypcTile) -> io::Result<()> {
+    if tile.points_encoding == PointsEncoding::Delta {
+        let deltas = encode_point_deltas(&tile.points_units);
+        let len = u32::try_from(deltas.len()).map_err(|_| {
+            io::Error::new(ErrorKind::InvalidData, "delta points block exceeds 4 GiB")
+        })?;
+        write_u32(w, len)?;
+        w.write_all(&deltas)?;
+
+        if let Some(labels) = tile.labels.as_ref() {
+            w.write_all(labels)?;
+        }
+    } else if let Some(labels) = tile.labels.as_ref() {
         for (index, point) in tile.points_units.iter().enumerate() {
-            write_i32(&mut file, point[0])?;
-            write_i32(&mut file, point[1])?;
-            write_i32(&mut file, point[2])?;
+            write_i32(w, point[0])?;
+            write_i32(w, point[1])?;
+            write_i32(w, point[2])?;
 
-            file.write_all(&[labels[index]])?;
+            w.write_all(&[labels[index]])?;
         }
     } else {
         for point in tile.points_units.iter() {
-            write_i32(&mut file, point[0])?;
-            write_i32(&mut file, point[1])?;
-            write_i32(&mut file, point[2])?;
+            write_i32(w, point[0])?;
+            write_i32(w, point[1])?;
+            write_i32(w, point[2])?;
         }
     }
 
-    if let Some(geot) = tile.geot.as_ref() {
-        file.write_all(b"GEOT")?;
-
-        write_i32(&mut file, geot.lon_min_q7)?;
-        write_i32(&mut file, geot.lon_max_q7)?;
-        write_i32(&mut file, geot.lat_min_q7)?;
-        write_i32(&mut file, geot.lat_max_q7)?;
-    }
-
-    if let Some(smc1) = tile.smc1.as_ref() {
-        file.write_all(b"SMC1")?;
-
-        write_u16(&mut file, smc1.width)?;
-        write_u16(&mut file, smc1.height)?;
-
-        file.write_all(&[smc1.coord_space as u8])?;
-        file.write_all(&[smc1.encoding as u8])?;
+    Ok(())
+}
 
-        write_u16(&mut file, smc1.palette.len() as u16)?;
+/// Serialize `tile` into a fresh buffer; the bytes are exactly what
+/// [`write_file`] puts on disk and what [`parse_hypc_bytes`] reads back.
+#[cfg(feature = "std")]
+pub fn write_hypc_bytes(tile: &HypcTile) -> io::Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    write_to(&mut buf, tile)?;
+    Ok(buf)
+}
 
-        for &(class, precedence) in &smc1.palette {
-            file.write_all(&[class, precedence])?;
-        }
+#[cfg(feature = "std")]
+pub fn write_file<P: AsRef<Path>>(path: P, tile: &HypcTile) -> io::Result<()> {
+    let mut file = File::create(path)?;
+    write_to(&mut file, tile)?;
+    file.flush()?;
 
-        write_u32(&mut file, smc1.data.len() as u32)?;
+    Ok(())
+}
 
-        file.write_all(&smc1.data)?;
+/// Like [`write_file`], but never leaves a partial tile at `path`.
+///
+/// The tile is written to `path` with `.tmp` appended, synced to disk, then
+/// renamed over `path`, so an interrupted write leaves either the old file or
+/// the new one, plus at worst a stray `.tmp`. The rename is only atomic
+/// because the temp file sits in the same directory, and so on the same
+/// filesystem; a cross-filesystem rename would fail or copy.
+#[cfg(feature = "std")]
+pub fn write_file_atomic<P: AsRef<Path>>(path: P, tile: &HypcTile) -> io::Result<()> {
+    let path = path.as_ref();
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = std::path::PathBuf::from(tmp);
+
+    let result = (|| {
+        let mut file = File::create(&tmp)?;
+        write_to(&mut file, tile)?;
+        file.flush()?;
+        file.sync_all()?;
+        std::fs::rename(&tmp, path)
+    })();
+    if result.is_err() {
+        let _ = std::fs::remove_file(&tmp);
     }
+    result
+}
 
-    file.flush()?;
+/// Async variant of [`read_file`].
+///
+/// The file read yields to the runtime (`tokio::fs` runs it on the blocking pool);
+/// parsing, which is CPU-bound, runs via `spawn_blocking`.
+#[cfg(feature = "tokio")]
+pub async fn read_file_async<P: AsRef<Path>>(path: P) -> io::Result<HypcTile> {
+    let bytes = tokio::fs::read(path).await?;
+    tokio::task::spawn_blocking(move || parse_hypc_bytes(&bytes))
+        .await
+        .map_err(io::Error::other)?
+        .map_err(io::Error::from)
+}
 
-    Ok(())
+/// Async variant of [`write_file`].
+///
+/// Serialization into an in-memory buffer happens on the calling task (a plain
+/// memory copy, no syscalls); only the file write yields to the runtime.
+#[cfg(feature = "tokio")]
+pub async fn write_file_async<P: AsRef<Path>>(path: P, tile: &HypcTile) -> io::Result<()> {
+    let buf = write_hypc_bytes(tile)?;
+    tokio::fs::write(path, buf).await
 }
 
+/// Longest run a single RLE record can hold; longer runs are split.
+pub const SMC1_RLE_MAX_RUN: usize = u16::MAX as usize;
+
+/// Encode a raw mask as `[u16 run_len][u8 value]` records.
+///
+/// Runs longer than [`SMC1_RLE_MAX_RUN`] are split into several records of
+/// the same value, so no record has a zero or overflowing length.
 pub fn smc1_encode_rle(raw: &[u8]) -> Vec<u8> {
     let mut out = Vec::<u8>::with_capacity(raw.len() / 2);
     if raw.is_empty() {
@@ -451,7 +2423,7 @@ pub fn smc1_encode_rle(raw: &[u8]) -> Vec<u8> {
 
         while i + run_length < raw.len()
             && raw[i + run_length] == value
-            && run_length < u16::MAX as usize
+            && run_length < SMC1_RLE_MAX_RUN
         {
             run_length += 1;
         }
@@ -464,27 +2436,76 @@ pub fn smc1_encode_rle(raw: &[u8]) -> Vec<u8> {
     out
 }
 
-pub fn smc1_decode_rle(rle: &[u8]) -> io::Result<Vec<u8>> {
-    let mut out = Vec::<u8>::new();
-    let mut i = 0usize;
+/// Compress a raw mask into an [`Smc1Encoding::Deflate`] payload.
+#[cfg(feature = "compression")]
+pub fn smc1_encode_deflate(raw: &[u8]) -> Vec<u8> {
+    miniz_oxide::deflate::compress_to_vec_zlib(raw, DEFLATE_LEVEL)
+}
 
-    while i + 3 <= rle.len() {
-        let run = u16::from_le_bytes([rle[i], rle[i + 1]]) as usize;
-        let v = rle[i + 2];
-        out.resize(out.len() + run, v);
-        i += 3;
+/// Total pixel count encoded by an RLE payload.
+fn smc1_rle_expanded_len(rle: &[u8]) -> Result<usize, HypcError> {
+    if !rle.len().is_multiple_of(3) {
+        return Err(HypcError::RleTruncated);
     }
+    Ok(rle
+        .chunks_exact(3)
+        .map(|run| u16::from_le_bytes([run[0], run[1]]) as usize)
+        .sum())
+}
 
-    if i != rle.len() {
-        return Err(io::Error::new(
-            ErrorKind::InvalidData,
-            "RLE payload truncated",
-        ));
+/// Expand `[u16 run_len][u8 value]` records back into raw mask bytes.
+#[cfg(feature = "std")]
+pub fn smc1_decode_rle(rle: &[u8]) -> io::Result<Vec<u8>> {
+    Ok(smc1_decode_rle_inner(rle)?)
+}
+
+/// Expand `[u16 run_len][u8 value]` records back into raw mask bytes.
+#[cfg(not(feature = "std"))]
+pub fn smc1_decode_rle(rle: &[u8]) -> HypcResult<Vec<u8>> {
+    smc1_decode_rle_inner(rle)
+}
+
+fn smc1_decode_rle_inner(rle: &[u8]) -> HypcResult<Vec<u8>> {
+    let mut out = Vec::<u8>::with_capacity(smc1_rle_expanded_len(rle)?);
+
+    for run in rle.chunks_exact(3) {
+        let len = u16::from_le_bytes([run[0], run[1]]) as usize;
+        out.resize(out.len() + len, run[2]);
     }
 
     Ok(out)
 }
 
+/// The `f64` methods the geodesy helpers use that live in `std`, backed by
+/// `libm` when building without it.
+#[cfg(not(feature = "std"))]
+mod nostd_math {
+    pub trait F64Ext {
+        fn round(self) -> f64;
+        fn sqrt(self) -> f64;
+        fn sin_cos(self) -> (f64, f64);
+        fn atan2(self, other: f64) -> f64;
+    }
+
+    impl F64Ext for f64 {
+        fn round(self) -> f64 {
+            libm::round(self)
+        }
+
+        fn sqrt(self) -> f64 {
+            libm::sqrt(self)
+        }
+
+        fn sin_cos(self) -> (f64, f64) {
+            libm::sincos(self)
+        }
+
+        fn atan2(self, other: f64) -> f64 {
+            libm::atan2(self, other)
+        }
+    }
+}
+
 pub mod wgs84 {
     /// Semi-major axis (equatorial radius) in meters.
     pub const A: f64 = 6_378_137.0;
@@ -523,6 +2544,134 @@ pub fn geodetic_to_ecef(lat_deg: f64, lon_deg: f64, h_m: f64) -> [f64; 3] {
     [x, y, z]
 }
 
+/// Batch [`geodetic_to_ecef`] over parallel slices of latitudes, longitudes
+/// (degrees) and heights (metres), writing one ECEF point per input.
+///
+/// Bit-identical to the scalar function. Points are converted in fixed-size
+/// blocks: the per-latitude terms (sin, cos and the prime-vertical
+/// radius) and per-longitude sin/cos are first gathered through small caches
+/// keyed on the angle, so every grid row or column shared anywhere in the
+/// input costs one `sin_cos` while it stays cached, not one per point. A
+/// branch-free pass over the block then does the remaining arithmetic.
+///
+/// # Panics
+/// If the four slices differ in length.
+pub fn geodetic_to_ecef_many(lats: &[f64], lons: &[f64], hs: &[f64], out: &mut [[f64; 3]]) {
+    assert!(
+        lats.len() == out.len() && lons.len() == out.len() && hs.len() == out.len(),
+        "geodetic_to_ecef_many: slice lengths differ"
+    );
+
+    // Per latitude: [sin φ, cos φ, N, N (1 - e²)]; per longitude: [sin λ, cos λ].
+    let mut lat_cache = AngleCache::new(|lat: f64| {
+        let (sin_lat, cos_lat) = lat.to_radians().sin_cos();
+        let n = wgs84::A / (1.0 - wgs84::E2 * sin_lat * sin_lat).sqrt();
+        [sin_lat, cos_lat, n, n * (1.0 - wgs84::E2)]
+    });
+    let mut lon_cache = AngleCache::new(|lon: f64| {
+        let (sin_lon, cos_lon) = lon.to_radians().sin_cos();
+        [sin_lon, cos_lon]
+    });
+    let mut lat_terms = [[0.0; 4]; GEODETIC_BLOCK];
+    let mut lon_terms = [[0.0; 2]; GEODETIC_BLOCK];
+
+    for (start, dst) in (0..out.len())
+        .step_by(GEODETIC_BLOCK)
+        .zip(out.chunks_mut(GEODETIC_BLOCK))
+    {
+        let end = start + dst.len();
+        for (terms, &lat) in lat_terms.iter_mut().zip(&lats[start..end]) {
+            *terms = lat_cache.get(lat);
+        }
+        for (terms, &lon) in lon_terms.iter_mut().zip(&lons[start..end]) {
+            *terms = lon_cache.get(lon);
+        }
+
+        // Same expression order as the scalar path.
+        let terms = lat_terms.iter().zip(&lon_terms);
+        for ((p, (lat, lon)), &h) in dst.iter_mut().zip(terms).zip(&hs[start..end]) {
+            let [sin_lat, cos_lat, n, n_polar] = *lat;
+            let [sin_lon, cos_lon] = *lon;
+            *p = [
+                (n + h) * cos_lat * cos_lon,
+                (n + h) * cos_lat * sin_lon,
+                (n_polar + h) * sin_lat,
+            ];
+        }
+    }
+}
+
+/// Points per block in [`geodetic_to_ecef_many`].
+const GEODETIC_BLOCK: usize = 256;
+
+/// log2 of the slot count of each [`AngleCache`].
+const ANGLE_CACHE_BITS: u32 = 10;
+
+/// Direct-mapped cache of the `K` terms `f` derives from an angle, keyed on
+/// its bits, so repeats anywhere in the input hit as long as no other angle
+/// has taken the slot since.
+struct AngleCache<const K: usize, F> {
+    keys: Vec<u64>,
+    terms: Vec<[f64; K]>,
+    f: F,
+}
+
+impl<const K: usize, F: Fn(f64) -> [f64; K]> AngleCache<K, F> {
+    fn new(f: F) -> Self {
+        // Every slot starts out holding NaN, which is then a hit like any
+        // other angle.
+        let slots = 1 << ANGLE_CACHE_BITS;
+        Self {
+            keys: vec![f64::NAN.to_bits(); slots],
+            terms: vec![f(f64::NAN); slots],
+            f,
+        }
+    }
+
+    #[inline]
+    fn get(&mut self, deg: f64) -> [f64; K] {
+        let bits = deg.to_bits();
+        // Fibonacci hashing, so angles differing only in low mantissa bits
+        // still spread over the slots.
+        let slot = (bits.wrapping_mul(0x9E37_79B9_7F4A_7C15) >> (64 - ANGLE_CACHE_BITS)) as usize;
+        if self.keys[slot] != bits {
+            self.keys[slot] = bits;
+            self.terms[slot] = (self.f)(deg);
+        }
+        self.terms[slot]
+    }
+}
+
+/// [`geodetic_to_ecef_many`] split across the rayon pool in fixed-size chunks.
+///
+/// # Panics
+/// If the four slices differ in length.
+#[cfg(feature = "rayon")]
+pub fn geodetic_to_ecef_many_par(lats: &[f64], lons: &[f64], hs: &[f64], out: &mut [[f64; 3]]) {
+    use rayon::prelude::*;
+
+    const CHUNK: usize = 64 * 1024;
+    assert!(
+        lats.len() == out.len() && lons.len() == out.len() && hs.len() == out.len(),
+        "geodetic_to_ecef_many_par: slice lengths differ"
+    );
+
+    out.par_chunks_mut(CHUNK).enumerate().for_each(|(i, dst)| {
+        let range = i * CHUNK..i * CHUNK + dst.len();
+        geodetic_to_ecef_many(&lats[range.clone()], &lons[range.clone()], &hs[range], dst);
+    });
+}
+
+/// Upper bound on Bowring refinement steps in [`ecef_to_geodetic`]; two
+/// already reach sub-micrometre latitude for terrestrial heights.
+const BOWRING_MAX_ITERATIONS: usize = 4;
+
+/// ECEF metres to geodetic (lat°, lon°, h m) on WGS-84.
+///
+/// Bowring's formula iterated on the reduced latitude until it stops moving,
+/// so the result stays sub-millimetre at high latitudes and altitudes where
+/// the single-pass form drifts. Height uses the form that is stable at the
+/// poles (no division by cos φ).
 #[inline]
 pub fn ecef_to_geodetic(x: f64, y: f64, z: f64) -> (f64, f64, f64) {
     // Compute the distance from the Z-axis
@@ -531,25 +2680,205 @@ pub fn ecef_to_geodetic(x: f64, y: f64, z: f64) -> (f64, f64, f64) {
     // Compute longitude (λ)
     let lon = y.atan2(x);
 
-    // Initial latitude estimate (θ)
-    let theta = (z * wgs84::A).atan2(p * wgs84::B);
-    let (sin_theta, cos_theta) = theta.sin_cos();
+    // Initial reduced-latitude estimate (β)
+    let mut beta = (z * wgs84::A).atan2(p * wgs84::B);
+    let mut lat = beta;
 
-    // Compute latitude (φ)
-    let lat_numerator = z + wgs84::E2P * wgs84::B * sin_theta * sin_theta * sin_theta;
-    let lat_denominator = p - wgs84::E2 * wgs84::A * cos_theta * cos_theta * cos_theta;
-    let lat = lat_numerator.atan2(lat_denominator);
+    for _ in 0..BOWRING_MAX_ITERATIONS {
+        let (sin_beta, cos_beta) = beta.sin_cos();
 
-    // Compute the radius of curvature in the prime vertical (N)
-    let sin_lat = lat.sin();
-    let n = wgs84::A / (1.0 - wgs84::E2 * sin_lat * sin_lat).sqrt();
+        // Latitude (φ) from the current β
+        let lat_numerator = z + wgs84::E2P * wgs84::B * sin_beta * sin_beta * sin_beta;
+        let lat_denominator = p - wgs84::E2 * wgs84::A * cos_beta * cos_beta * cos_beta;
+        lat = lat_numerator.atan2(lat_denominator);
+
+        // β consistent with that φ: tan β = (1 - f) tan φ
+        let (sin_lat, cos_lat) = lat.sin_cos();
+        let next_beta = ((1.0 - wgs84::F) * sin_lat).atan2(cos_lat);
+        if (next_beta - beta).abs() < 1e-15 {
+            break;
+        }
+        beta = next_beta;
+    }
 
-    // Compute ellipsoidal height (h)
-    let h = p / lat.cos() - n;
+    // Ellipsoidal height (h), valid at every latitude
+    let (sin_lat, cos_lat) = lat.sin_cos();
+    let n = wgs84::A / (1.0 - wgs84::E2 * sin_lat * sin_lat).sqrt();
+    let h = p * cos_lat + z * sin_lat - n * (1.0 - wgs84::E2 * sin_lat * sin_lat);
 
     (lat.to_degrees(), lon.to_degrees(), h)
 }
 
+/// Rotation from ECEF to the local East-North-Up frame at geodetic (lat°, lon°).
+///
+/// Row 0 is East, row 1 North and row 2 Up, each a unit vector in ECEF
+/// components, so `m * d` turns an ECEF offset `d` into `[east, north, up]`
+/// metres. The matrix is orthonormal; its transpose maps ENU back to ECEF, with
+/// the columns then being East, North, Up. Up is the ellipsoid normal.
+#[inline]
+pub fn ecef_to_enu_matrix(lat_deg: f64, lon_deg: f64) -> [[f64; 3]; 3] {
+    let (sin_lat, cos_lat) = lat_deg.to_radians().sin_cos();
+    let (sin_lon, cos_lon) = lon_deg.to_radians().sin_cos();
+    [
+        [-sin_lon, cos_lon, 0.0],
+        [-sin_lat * cos_lon, -sin_lat * sin_lon, cos_lat],
+        [cos_lat * cos_lon, cos_lat * sin_lon, sin_lat],
+    ]
+}
+
+/// ECEF point to `[east, north, up]` metres relative to `ref_ecef`, in the ENU
+/// frame at the reference's geodetic position (see [`ecef_to_enu_matrix`]).
+///
+/// Each call converts the reference to geodetic; for many points about one
+/// reference, build the matrix once instead.
+#[inline]
+pub fn ecef_to_enu(point_ecef: [f64; 3], ref_ecef: [f64; 3]) -> [f64; 3] {
+    let (lat, lon, _) = ecef_to_geodetic(ref_ecef[0], ref_ecef[1], ref_ecef[2]);
+    let m = ecef_to_enu_matrix(lat, lon);
+    let d = [
+        point_ecef[0] - ref_ecef[0],
+        point_ecef[1] - ref_ecef[1],
+        point_ecef[2] - ref_ecef[2],
+    ];
+    m.map(|row| row[0] * d[0] + row[1] * d[1] + row[2] * d[2])
+}
+
+/// Inverse of [`ecef_to_enu`]: `[east, north, up]` metres in the ENU frame at
+/// `ref_ecef` back to an ECEF point.
+#[inline]
+pub fn enu_to_ecef(enu: [f64; 3], ref_ecef: [f64; 3]) -> [f64; 3] {
+    let (lat, lon, _) = ecef_to_geodetic(ref_ecef[0], ref_ecef[1], ref_ecef[2]);
+    let m = ecef_to_enu_matrix(lat, lon);
+    core::array::from_fn(|k| {
+        ref_ecef[k] + m[0][k] * enu[0] + m[1][k] * enu[1] + m[2][k] * enu[2]
+    })
+}
+
+/// Nearest-pixel lookup of geodetic lat/lon in a `Crs84BboxNorm` SMC1 mask
+/// spanning a GEOT extent.
+struct MaskPixels {
+    lon_min: f64,
+    lat_min: f64,
+    inv_dlon: f64,
+    inv_dlat: f64,
+    /// Largest pixel column and row, as the scales from normalized coordinates.
+    max_x: f64,
+    max_y: f64,
+}
+
+impl MaskPixels {
+    fn new(geot: GeoExtentQ7, width: usize, height: usize) -> Self {
+        let (lon_min, lon_max, lat_min, lat_max) = geot.to_deg();
+        Self {
+            lon_min,
+            lat_min,
+            inv_dlon: 1.0 / (lon_max - lon_min + 1e-12),
+            inv_dlat: 1.0 / (lat_max - lat_min + 1e-12),
+            max_x: width.saturating_sub(1) as f64,
+            max_y: height.saturating_sub(1) as f64,
+        }
+    }
+
+    /// `(x, y)` of the pixel nearest to a point, clamped into the mask.
+    #[inline(always)]
+    fn index(&self, lat_deg: f64, lon_deg: f64) -> (usize, usize) {
+        let u = ((lon_deg - self.lon_min) * self.inv_dlon).clamp(0.0, 1.0);
+        let v = ((lat_deg - self.lat_min) * self.inv_dlat).clamp(0.0, 1.0);
+        let (x, y) = ((u * self.max_x).round(), (v * self.max_y).round());
+        (x as usize, y as usize)
+    }
+
+    /// Pixel pitch in degrees of latitude and longitude; infinite along an
+    /// axis with a single pixel.
+    fn pitch_deg(&self) -> (f64, f64) {
+        (
+            (self.inv_dlat * self.max_y).recip(),
+            (self.inv_dlon * self.max_x).recip(),
+        )
+    }
+}
+
+/// Largest error, in mask pixels, at which [`LocalGeodesy`] may stand in for
+/// full geodesy when sampling a mask.
+const PLANAR_MAX_ERROR_PX: f64 = 0.05;
+
+/// Local planar inverse of the ENU mapping at a tile anchor.
+///
+/// Maps an anchor-relative ECEF offset to geodetic lat/lon with one 3x3 multiply
+/// and two scales, instead of running full geodesy per point. The error grows with
+/// the square of the distance from the anchor: about 1 cm at 300 m, but already
+/// tens of metres a quarter degree out at mid latitudes, so check
+/// [`LocalGeodesy::fits`] before using it over an extent.
+struct LocalGeodesy {
+    anchor_m: [f64; 3],
+    lat0_deg: f64,
+    lon0_deg: f64,
+    h0_m: f64,
+    ecef_to_enu: [[f64; 3]; 3],
+    /// Degrees of latitude per metre north.
+    deg_per_m_north: f64,
+    /// Degrees of longitude per metre east.
+    deg_per_m_east: f64,
+}
+
+impl LocalGeodesy {
+    fn new(anchor_m: [f64; 3]) -> Self {
+        let (lat0_deg, lon0_deg, h0) = ecef_to_geodetic(anchor_m[0], anchor_m[1], anchor_m[2]);
+        let (sin_lat, cos_lat) = lat0_deg.to_radians().sin_cos();
+
+        // Meridional (M) and prime-vertical (N) radii of curvature at the anchor.
+        let w = (1.0 - wgs84::E2 * sin_lat * sin_lat).sqrt();
+        let n = wgs84::A / w;
+        let m = wgs84::A * (1.0 - wgs84::E2) / (w * w * w);
+
+        Self {
+            anchor_m,
+            lat0_deg,
+            lon0_deg,
+            h0_m: h0,
+            ecef_to_enu: ecef_to_enu_matrix(lat0_deg, lon0_deg),
+            deg_per_m_north: (1.0 / (m + h0)).to_degrees(),
+            deg_per_m_east: (1.0 / ((n + h0) * cos_lat)).to_degrees(),
+        }
+    }
+
+    /// Approximate (lat, lon) in degrees for an anchor-relative offset in metres.
+    #[inline(always)]
+    fn lat_lon_deg(&self, ofs_m: [f64; 3]) -> (f64, f64) {
+        let [east, north, _] = self
+            .ecef_to_enu
+            .map(|row| row[0] * ofs_m[0] + row[1] * ofs_m[1] + row[2] * ofs_m[2]);
+        (
+            self.lat0_deg + north * self.deg_per_m_north,
+            self.lon0_deg + east * self.deg_per_m_east,
+        )
+    }
+
+    /// Whether [`LocalGeodesy::lat_lon_deg`] stays within
+    /// [`PLANAR_MAX_ERROR_PX`] of full geodesy over `extent`, in units of the
+    /// mask's pixels. The error is checked at the corners, edge midpoints and
+    /// centre of the extent, at the anchor's height; polar anchors never fit.
+    fn fits(&self, extent: GeoExtentQ7, pixels: &MaskPixels) -> bool {
+        if self.lat0_deg.abs() >= 85.0 {
+            return false;
+        }
+        let (lon_min, lon_max, lat_min, lat_max) = extent.to_deg();
+        let (pitch_lat, pitch_lon) = pixels.pitch_deg();
+        let lats = [lat_min, (lat_min + lat_max) / 2.0, lat_max];
+        let lons = [lon_min, (lon_min + lon_max) / 2.0, lon_max];
+
+        lats.iter().all(|&lat| {
+            lons.iter().all(|&lon| {
+                let p = geodetic_to_ecef(lat, lon, self.h0_m);
+                let ofs_m = core::array::from_fn(|k| p[k] - self.anchor_m[k]);
+                let (planar_lat, planar_lon) = self.lat_lon_deg(ofs_m);
+                (planar_lat - lat).abs() <= PLANAR_MAX_ERROR_PX * pitch_lat
+                    && (planar_lon - lon).abs() <= PLANAR_MAX_ERROR_PX * pitch_lon
+            })
+        })
+    }
+}
+
 #[inline]
 pub fn quantize_units(meters: f64, units_per_meter: u32) -> i64 {
     (meters * (units_per_meter as f64)).round() as i64
@@ -562,22 +2891,808 @@ pub fn split_f64_to_f32_pair(v: f64) -> (f32, f32) {
     (hi, lo)
 }
 
+/// CRC32 lookup table for the reflected IEEE polynomial, built at compile time.
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut c = i as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 { 0xEDB8_8320 ^ (c >> 1) } else { c >> 1 };
+            k += 1;
+        }
+        table[i] = c;
+        i += 1;
+    }
+    table
+};
+
+/// Incremental CRC32 (IEEE 802.3, the zlib/PNG variant).
+#[derive(Clone, Copy)]
+struct Crc32(u32);
+
+impl Crc32 {
+    fn new() -> Self {
+        Self(!0)
+    }
+
+    fn update(&mut self, bytes: &[u8]) {
+        let mut c = self.0;
+        for &b in bytes {
+            c = CRC32_TABLE[((c ^ b as u32) & 0xFF) as usize] ^ (c >> 8);
+        }
+        self.0 = c;
+    }
+
+    fn finish(self) -> u32 {
+        !self.0
+    }
+}
+
+/// CRC32 of `bytes` in one call.
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = Crc32::new();
+    crc.update(bytes);
+    crc.finish()
+}
+
+/// Passes writes through while hashing them.
+#[cfg(feature = "std")]
+struct Crc32Writer<W> {
+    inner: W,
+    crc: Crc32,
+}
+
+#[cfg(feature = "std")]
+impl<W: Write> Crc32Writer<W> {
+    fn new(inner: W) -> Self {
+        Self {
+            inner,
+            crc: Crc32::new(),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: Write> Write for Crc32Writer<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.crc.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Hashes every byte consumed from `inner` while `active`.
+#[cfg(feature = "std")]
+struct Crc32Reader<R> {
+    inner: R,
+    crc: Crc32,
+    active: bool,
+}
+
+#[cfg(feature = "std")]
+impl<R> Crc32Reader<R> {
+    fn new(inner: R) -> Self {
+        Self {
+            inner,
+            crc: Crc32::new(),
+            active: true,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: Read> Read for Crc32Reader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        if self.active {
+            self.crc.update(&buf[..n]);
+        }
+        Ok(n)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: BufRead> BufRead for Crc32Reader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        if self.active {
+            if let Ok(buf) = self.inner.fill_buf() {
+                self.crc.update(&buf[..amt]);
+            }
+        }
+        self.inner.consume(amt)
+    }
+}
+
+#[cfg(feature = "std")]
 #[inline]
 fn write_u16<W: Write>(w: &mut W, v: u16) -> io::Result<()> {
     w.write_all(&v.to_le_bytes())
 }
 
+#[cfg(feature = "std")]
 #[inline]
 fn write_u32<W: Write>(w: &mut W, v: u32) -> io::Result<()> {
     w.write_all(&v.to_le_bytes())
 }
 
+#[cfg(feature = "std")]
 #[inline]
 fn write_i32<W: Write>(w: &mut W, v: i32) -> io::Result<()> {
     w.write_all(&v.to_le_bytes())
 }
 
+#[cfg(feature = "std")]
 #[inline]
 fn write_i64<W: Write>(w: &mut W, v: i64) -> io::Result<()> {
     w.write_all(&v.to_le_bytes())
 }
+
+#[cfg(feature = "std")]
+#[inline]
+fn write_f64<W: Write>(w: &mut W, v: f64) -> io::Result<()> {
+    w.write_all(&v.to_le_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A small tile with a GEOT, points a few metres around a Munich anchor.
+    fn sample_tile() -> HypcTile {
+        HypcTile {
+            units_per_meter: 1000,
+            anchor_ecef_units: [4_177_000_123, 855_000_456, 4_727_000_789],
+            tile_key: None,
+            points_units: (0..12)
+                .map(|i| {
+                    [
+                        i * 1_000 - 6_000,
+                        (i % 4) * 2_500 - 3_750,
+                        (i % 3) * -700 + 350,
+                    ]
+                })
+                .collect(),
+            points_encoding: PointsEncoding::Raw,
+            points_codec: PointsCodec::None,
+            labels: None,
+            colors: None,
+            geot: Some(GeoExtentQ7::from_deg(11.50, 11.51, 48.13, 48.14)),
+            smc1: None,
+            transform: None,
+            crc: false,
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn v1_tiles_are_unsupported() {
+        let mut bytes = write_hypc_bytes(&sample_tile()).unwrap();
+        bytes[4..8].copy_from_slice(&1u32.to_le_bytes());
+
+        assert!(matches!(
+            parse_hypc_bytes(&bytes),
+            Err(HypcError::UnsupportedVersion(1))
+        ));
+        assert!(matches!(
+            parse_hypc_stream(&mut bytes.as_slice()),
+            Err(HypcError::UnsupportedVersion(1))
+        ));
+        // Even a bare v1 header is routed to the legacy path, not read as v2+.
+        assert!(matches!(
+            parse_hypc_bytes(&bytes[..8]),
+            Err(HypcError::UnsupportedVersion(1))
+        ));
+    }
+
+    #[test]
+    #[cfg(all(feature = "std", feature = "compression"))]
+    fn deflate_raw_len_must_match_the_point_count() {
+        for encoding in [PointsEncoding::Raw, PointsEncoding::Delta] {
+            let mut tile = sample_tile();
+            tile.points_encoding = encoding;
+            tile.points_codec = PointsCodec::Deflate;
+            let bytes = write_hypc_bytes(&tile).unwrap();
+            let at = HEADER_LEN + header_extra_len(header_flags(&bytes));
+            let raw_len = u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap());
+
+            // Refused before inflating, whether the block would fit or not.
+            let too_long = match encoding {
+                PointsEncoding::Raw => raw_len + 1,
+                PointsEncoding::Delta => 4 + 15 * 12 + 1,
+            };
+            for bad in [0, too_long, u32::MAX] {
+                let mut corrupt = bytes.clone();
+                corrupt[at..at + 4].copy_from_slice(&bad.to_le_bytes());
+                assert!(
+                    matches!(
+                        parse_hypc_bytes(&corrupt),
+                        Err(HypcError::BadCompressedBlock)
+                    ),
+                    "{:?} raw_len {}",
+                    encoding,
+                    bad
+                );
+                assert!(matches!(
+                    parse_hypc_stream(&mut corrupt.as_slice()),
+                    Err(HypcError::BadCompressedBlock)
+                ));
+            }
+        }
+    }
+
+    #[test]
+    fn geodetic_round_trips_below_a_millimetre() {
+        // Metres per degree of latitude, near enough for an error bound.
+        const M_PER_DEG: f64 = 111_320.0;
+
+        for lat in (-89..=89).map(f64::from) {
+            for lon in (-180..180).step_by(15).map(f64::from) {
+                for h in [-500.0, 0.0, 123.456, 9_000.0, 400_000.0] {
+                    let [x, y, z] = geodetic_to_ecef(lat, lon, h);
+                    let (lat2, lon2, h2) = ecef_to_geodetic(x, y, z);
+
+                    let north_m = (lat2 - lat) * M_PER_DEG;
+                    let east_m = (lon2 - lon) * M_PER_DEG * lat.to_radians().cos();
+                    assert!(
+                        north_m.abs() < 1e-3 && east_m.abs() < 1e-3 && (h2 - h).abs() < 1e-3,
+                        "({}, {}, {}) came back as ({}, {}, {})",
+                        lat,
+                        lon,
+                        h,
+                        lat2,
+                        lon2,
+                        h2
+                    );
+                }
+            }
+        }
+    }
+
+    /// Component-wise min and max of `points_ecef_m`, the reference for the
+    /// bounding box.
+    fn fold_bounds(tile: &HypcTile) -> ([f64; 3], [f64; 3]) {
+        tile.points_ecef_m().iter().fold(
+            ([f64::INFINITY; 3], [f64::NEG_INFINITY; 3]),
+            |(lo, hi), p| {
+                (
+                    core::array::from_fn(|k| lo[k].min(p[k])),
+                    core::array::from_fn(|k| hi[k].max(p[k])),
+                )
+            },
+        )
+    }
+
+    #[test]
+    fn bounding_box_of_empty_tile_is_none() {
+        let mut tile = sample_tile();
+        tile.points_units.clear();
+        assert_eq!(tile.bounding_box_ecef_m(), None);
+    }
+
+    #[test]
+    fn bounding_box_of_single_point_is_that_point() {
+        let mut tile = sample_tile();
+        tile.points_units.truncate(1);
+        let p = tile.points_ecef_m()[0];
+        assert_eq!(tile.bounding_box_ecef_m(), Some((p, p)));
+    }
+
+    #[test]
+    fn bounding_box_matches_decoded_points() {
+        let mut tile = sample_tile();
+        assert_eq!(tile.bounding_box_ecef_m(), Some(fold_bounds(&tile)));
+
+        // The XFRM path bounds the corrected points instead.
+        tile.transform = Some(RigidTransform {
+            rotation: [[0.0, -1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]],
+            translation_m: [0.25, -1.5, 3.0],
+        });
+        assert_eq!(tile.bounding_box_ecef_m(), Some(fold_bounds(&tile)));
+    }
+
+    #[test]
+    fn point_iterators_yield_first_and_last_points() {
+        let tile = sample_tile();
+        let first = [
+            4_177_000.123 - 6.0,
+            855_000.456 - 3.75,
+            4_727_000.789 + 0.35,
+        ];
+        let last = [
+            4_177_000.123 + 5.0,
+            855_000.456 + 3.75,
+            4_727_000.789 - 1.05,
+        ];
+        let close = |a: [f64; 3], b: [f64; 3]| (0..3).all(|k| (a[k] - b[k]).abs() < 1e-6);
+
+        let ecef = tile.iter_points_ecef_m();
+        assert_eq!(ecef.len(), 12);
+        let ecef: Vec<[f64; 3]> = ecef.collect();
+        assert!(close(ecef[0], first), "first point {:?}", ecef[0]);
+        assert!(close(ecef[11], last), "last point {:?}", ecef[11]);
+
+        let geo: Vec<(f64, f64, f64)> = tile.iter_points_geodetic().collect();
+        assert_eq!(geo.len(), 12);
+        for (got, p) in [(geo[0], first), (geo[11], last)] {
+            let (lat, lon, h) = ecef_to_geodetic(p[0], p[1], p[2]);
+            assert!((got.0 - lat).abs() < 1e-9 && (got.1 - lon).abs() < 1e-9);
+            assert!((got.2 - h).abs() < 1e-6);
+            // The anchor is over Munich.
+            assert!((48.0..48.3).contains(&got.0) && (11.3..11.7).contains(&got.1));
+        }
+    }
+
+    #[test]
+    fn enu_round_trips() {
+        let offsets = [
+            [0.0, 0.0, 0.0],
+            [12.5, -3.0, 0.75],
+            [-850.0, 1_200.0, -40.0],
+        ];
+        for (lat, lon, h) in [
+            (48.135, 11.582, 520.0),
+            (-33.9, 151.2, 0.0),
+            (0.0, -179.9, 10.0),
+            (89.5, 45.0, 2_000.0),
+        ] {
+            // The rotation is orthonormal: m * m^T = I.
+            let m = ecef_to_enu_matrix(lat, lon);
+            for i in 0..3 {
+                for j in 0..3 {
+                    let dot: f64 = (0..3).map(|k| m[i][k] * m[j][k]).sum();
+                    let want = if i == j { 1.0 } else { 0.0 };
+                    assert!(
+                        (dot - want).abs() < 1e-12,
+                        "m*m^T[{}][{}] = {} at ({}, {})",
+                        i,
+                        j,
+                        dot,
+                        lat,
+                        lon
+                    );
+                }
+            }
+
+            let ref_ecef = geodetic_to_ecef(lat, lon, h);
+            for enu in offsets {
+                let p = enu_to_ecef(enu, ref_ecef);
+                let back = ecef_to_enu(p, ref_ecef);
+                assert!(
+                    (0..3).all(|k| (back[k] - enu[k]).abs() < 1e-6),
+                    "{:?} came back as {:?} at ({}, {})",
+                    enu,
+                    back,
+                    lat,
+                    lon
+                );
+            }
+
+            // Straight up from the reference is pure Up.
+            let up = ecef_to_enu(geodetic_to_ecef(lat, lon, h + 100.0), ref_ecef);
+            assert!(
+                up[0].abs() < 1e-6 && up[1].abs() < 1e-6 && (up[2] - 100.0).abs() < 1e-6,
+                "{:?}",
+                up
+            );
+        }
+    }
+
+    /// xorshift64*, enough to drive the fuzz loop reproducibly without a dependency.
+    struct XorShift(u64);
+
+    impl XorShift {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 >> 12;
+            self.0 ^= self.0 << 25;
+            self.0 ^= self.0 >> 27;
+            self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+        }
+
+        fn below(&mut self, n: usize) -> usize {
+            (self.next() % n.max(1) as u64) as usize
+        }
+    }
+
+    #[test]
+    fn parser_survives_truncated_and_garbled_headers() {
+        let seeds: [&[u8]; 6] = [
+            include_bytes!("../fixtures/minimal.hypc"),
+            include_bytes!("../fixtures/delta_labels.hypc"),
+            include_bytes!("../fixtures/rgb_labels.hypc"),
+            include_bytes!("../fixtures/smc1_rle_key_labels.hypc"),
+            include_bytes!("../fixtures/crc_geot_labels.hypc"),
+            include_bytes!("../fixtures/deflate_delta_labels.hypc"),
+        ];
+
+        // A count no input can hold is refused up front, not allocated for.
+        let mut huge = seeds[0].to_vec();
+        huge[0x0C..0x10].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(matches!(
+            parse_hypc_bytes(&huge),
+            Err(HypcError::Truncated { .. })
+        ));
+
+        // Magic through anchor.
+        const FIXED: usize = 44;
+        let mut rng = XorShift(0x5eed_1234_abcd_0001);
+        for _ in 0..20_000 {
+            let seed = seeds[rng.below(seeds.len())];
+            let mut bytes = seed.to_vec();
+            match rng.below(4) {
+                // Cut anywhere.
+                0 => bytes.truncate(rng.below(seed.len())),
+                // Flip a few bytes in and just past the fixed header.
+                1 => {
+                    for _ in 0..1 + rng.below(4) {
+                        let i = rng.below(FIXED.min(bytes.len()) + 16).min(bytes.len() - 1);
+                        bytes[i] ^= 1 << rng.below(8);
+                    }
+                }
+                // Any points count, then cut.
+                2 => {
+                    bytes[0x0C..0x10].copy_from_slice(&(rng.next() as u32).to_le_bytes());
+                    bytes.truncate(FIXED + rng.below(seed.len()));
+                }
+                // Valid magic and version, garbage after.
+                _ => {
+                    bytes.truncate(8);
+                    let n = rng.below(160);
+                    bytes.extend((0..n).map(|_| rng.next() as u8));
+                }
+            }
+            // Errors are expected; panics and runaway allocations are not.
+            let _ = parse_hypc_bytes(&bytes);
+        }
+    }
+
+    #[test]
+    fn extents_at_the_antimeridian_do_not_wrap() {
+        let east = GeoExtentQ7::from_deg(179.9, 180.0, -17.0, -16.9);
+        let west = GeoExtentQ7::from_deg(-180.0, -179.9, -17.0, -16.9);
+        let lat = -169_500_000;
+
+        assert!(east.contains(1_800_000_000, lat));
+        assert!(west.contains(-1_800_000_000, lat));
+        assert!(!east.contains(-1_800_000_000, lat));
+        assert!(!west.contains(1_800_000_000, lat));
+
+        // The two halves of a straddling tile are 359.8° apart, not adjacent.
+        assert!(!east.intersects(&west) && !west.intersects(&east));
+        assert!(east.intersects(&east.union(west)));
+
+        // A wrapped extent (min > max) contains and meets nothing.
+        let wrapped = GeoExtentQ7 {
+            lon_min_q7: east.lon_min_q7,
+            lon_max_q7: west.lon_max_q7,
+            ..east
+        };
+        assert!(!wrapped.contains(1_800_000_000, lat));
+        assert!(!wrapped.contains(-1_800_000_000, lat));
+        assert!(!wrapped.intersects(&east) && !wrapped.intersects(&west));
+    }
+
+    #[test]
+    fn zero_area_extents_contain_and_meet_their_corner() {
+        let point = GeoExtentQ7::from_deg(11.5, 11.5, 48.1, 48.1);
+        let (lon, lat) = (point.lon_min_q7, point.lat_min_q7);
+        assert!(point.contains(lon, lat));
+        assert!(!point.contains(lon + 1, lat) && !point.contains(lon, lat - 1));
+        assert!(point.intersects(&point));
+
+        // Touching an edge counts; one tick off does not.
+        let box_ne = GeoExtentQ7::from_deg(11.5, 11.6, 48.1, 48.2);
+        assert!(point.intersects(&box_ne) && box_ne.intersects(&point));
+        let box_off = GeoExtentQ7 {
+            lon_min_q7: lon + 1,
+            ..box_ne
+        };
+        assert!(!point.intersects(&box_off) && !box_off.intersects(&point));
+
+        // A zero-width meridian line crossing a box meets it.
+        let line = GeoExtentQ7::from_deg(11.55, 11.55, 47.0, 49.0);
+        assert!(line.intersects(&box_ne));
+    }
+
+    /// A `rows` x `cols` lat/lon grid in row order, as the batch path expects
+    /// from gridded input, with heights varying per point.
+    fn geodetic_grid(rows: usize, cols: usize) -> (Vec<f64>, Vec<f64>, Vec<f64>) {
+        let n = rows * cols;
+        let lats = (0..n)
+            .map(|i| -80.0 + 160.0 * (i / cols) as f64 / rows as f64)
+            .collect();
+        let lons = (0..n)
+            .map(|i| -179.5 + 359.0 * (i % cols) as f64 / cols as f64)
+            .collect();
+        let hs = (0..n).map(|i| (i % 7) as f64 * 250.0 - 300.0).collect();
+        (lats, lons, hs)
+    }
+
+    /// `geodetic_grid` visited column by column, so no two consecutive points
+    /// share a longitude.
+    fn transposed(v: &[f64], rows: usize, cols: usize) -> Vec<f64> {
+        (0..rows * cols)
+            .map(|i| v[(i % rows) * cols + i / rows])
+            .collect()
+    }
+
+    #[test]
+    fn batch_geodetic_to_ecef_matches_scalar() {
+        let (lats, lons, hs) = geodetic_grid(40, 50);
+        let mut lats = [lats.clone(), transposed(&lats, 40, 50)].concat();
+        let mut lons = [lons.clone(), transposed(&lons, 40, 50)].concat();
+        let hs = [hs.clone(), hs].concat();
+        // And a stretch with no repeated angles, so the caches keep missing,
+        // plus poles, the antimeridian and a NaN.
+        for i in 0..200 {
+            lats[1_000 + i] = -60.0 + i as f64 * 0.61;
+            lons[1_000 + i] = 170.0 - i as f64 * 1.7;
+        }
+        lats[..4].copy_from_slice(&[90.0, -90.0, 0.0, f64::NAN]);
+        lons[..4].copy_from_slice(&[180.0, -180.0, 0.0, 12.5]);
+        let scalar: Vec<[f64; 3]> = (0..lats.len())
+            .map(|i| geodetic_to_ecef(lats[i], lons[i], hs[i]))
+            .collect();
+
+        // Compared as bits, so the NaN point must match too.
+        let bits = |points: &[[f64; 3]]| -> Vec<[u64; 3]> {
+            points.iter().map(|p| p.map(f64::to_bits)).collect()
+        };
+        let mut out = vec![[0.0; 3]; lats.len()];
+        geodetic_to_ecef_many(&lats, &lons, &hs, &mut out);
+        assert_eq!(bits(&out), bits(&scalar));
+
+        #[cfg(feature = "rayon")]
+        {
+            let mut out = vec![[0.0; 3]; lats.len()];
+            geodetic_to_ecef_many_par(&lats, &lons, &hs, &mut out);
+            assert_eq!(bits(&out), bits(&scalar));
+        }
+    }
+
+    /// 1M points of a 1000x1000 grid, row by row and column by column, must
+    /// convert faster in a batch than through the scalar function. Timing is
+    /// only meaningful optimized: run with
+    /// `cargo test --release -p hypc -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    #[cfg(feature = "std")]
+    fn batch_geodetic_to_ecef_throughput() {
+        use std::time::{Duration, Instant};
+
+        // Best of a few runs, to keep scheduler noise out of the comparison.
+        fn best(mut run: impl FnMut()) -> Duration {
+            (0..5)
+                .map(|_| {
+                    let t = Instant::now();
+                    run();
+                    t.elapsed()
+                })
+                .min()
+                .unwrap()
+        }
+
+        let (lats, lons, hs) = geodetic_grid(1_000, 1_000);
+        let orders = [
+            ("rows", lats.clone(), lons.clone()),
+            (
+                "columns",
+                transposed(&lats, 1_000, 1_000),
+                transposed(&lons, 1_000, 1_000),
+            ),
+        ];
+        for (order, lats, lons) in orders {
+            let mut scalar_out = vec![[0.0; 3]; lats.len()];
+            let scalar = best(|| {
+                for (i, dst) in scalar_out.iter_mut().enumerate() {
+                    *dst = geodetic_to_ecef(lats[i], lons[i], hs[i]);
+                }
+                std::hint::black_box(&mut scalar_out);
+            });
+            let mut out = vec![[0.0; 3]; lats.len()];
+            let batch = best(|| {
+                geodetic_to_ecef_many(&lats, &lons, &hs, &mut out);
+                std::hint::black_box(&mut out);
+            });
+            std::println!("by {}: scalar {:?}, batch {:?}", order, scalar, batch);
+            assert_eq!(out, scalar_out);
+            assert!(batch < scalar, "batch is no faster by {}", order);
+        }
+    }
+
+    /// A tile with a `px`-square mask over a `size_deg` square at 45°N, 10°E,
+    /// anchored at its centre, whose pixel (x, y) holds class `(x + 3y) % 251`.
+    /// The points sit on the mask's edge pixels, a little above and below the
+    /// anchor's height.
+    fn edge_tile(size_deg: f64, px: u16) -> HypcTile {
+        let half = size_deg / 2.0;
+        let geot = GeoExtentQ7::from_deg(10.0 - half, 10.0 + half, 45.0 - half, 45.0 + half);
+        let (lon_min, lon_max, lat_min, lat_max) = geot.to_deg();
+        let anchor = geodetic_to_ecef(45.0, 10.0, 200.0).map(|m| quantize_units(m, 1000));
+
+        let max = px as usize - 1;
+        let edge = (0..=max)
+            .flat_map(|i| [(i, 0), (i, max), (0, i), (max, i)])
+            .flat_map(|xy| [150.0, 200.0, 260.0].map(|h| (xy, h)));
+        let points_units = edge
+            .map(|((x, y), h)| {
+                let lon = lon_min + (lon_max - lon_min) * x as f64 / max as f64;
+                let lat = lat_min + (lat_max - lat_min) * y as f64 / max as f64;
+                let p = geodetic_to_ecef(lat, lon, h);
+                core::array::from_fn(|k| (quantize_units(p[k], 1000) - anchor[k]) as i32)
+            })
+            .collect();
+
+        let grid = (0..px as usize * px as usize)
+            .map(|i| ((i % px as usize + 3 * (i / px as usize)) % 251) as u8)
+            .collect();
+        HypcTile {
+            anchor_ecef_units: anchor,
+            points_units,
+            geot: Some(geot),
+            smc1: Some(Smc1Chunk {
+                width: px,
+                height: px,
+                coord_space: Smc1CoordSpace::Crs84BboxNorm,
+                encoding: Smc1Encoding::Raw,
+                palette: Vec::new(),
+                data: grid,
+            }),
+            ..sample_tile()
+        }
+    }
+
+    /// The anchor geodesy and mask pixels `smc1_point_labels` works from.
+    fn planar_setup(tile: &HypcTile) -> (LocalGeodesy, MaskPixels, GeoExtentQ7) {
+        let smc1 = tile.smc1.as_ref().unwrap();
+        let geot = tile.geot.unwrap();
+        let anchor_m = tile.anchor_ecef_units.map(|u| u as f64 / 1000.0);
+        let pixels = MaskPixels::new(geot, smc1.width as usize, smc1.height as usize);
+        (LocalGeodesy::new(anchor_m), pixels, geot)
+    }
+
+    #[test]
+    fn planar_geodesy_is_gated_on_mask_pixel_error() {
+        for (size_deg, px, fits) in [
+            (0.005, 512, true),
+            (0.02, 512, true),
+            (0.03, 512, false),
+            (0.03, 256, true),
+            // ~80 m off in longitude at the corners: about half a 150 m pixel,
+            // but well inside a 2.5 km one.
+            (0.48, 256, false),
+            (0.48, 16, true),
+        ] {
+            let (geodesy, pixels, geot) = planar_setup(&edge_tile(size_deg, px));
+            assert_eq!(geodesy.fits(geot, &pixels), fits, "{:?}", (size_deg, px));
+        }
+    }
+
+    #[test]
+    fn planar_labels_match_full_geodesy_at_the_extent_edge() {
+        for (size_deg, px) in [(0.02, 512), (0.48, 256)] {
+            let tile = edge_tile(size_deg, px);
+            let (_, pixels, _) = planar_setup(&tile);
+            let grid = &tile.smc1.as_ref().unwrap().data;
+            let expected: Vec<u8> = tile
+                .iter_points_geodetic()
+                .map(|(lat, lon, _)| {
+                    let (x, y) = pixels.index(lat, lon);
+                    grid[y * px as usize + x]
+                })
+                .collect();
+            let labels = tile.smc1_point_labels().unwrap().unwrap();
+            assert_eq!(labels, expected, "{}° at {} px", size_deg, px);
+        }
+    }
+
+    /// Per-point lat/lon for 1M points over a 0.02° tile, through full geodesy
+    /// and through the planar inverse, and `smc1_point_labels` on them. Timing
+    /// is only meaningful optimized: run with
+    /// `cargo test --release -p hypc -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    #[cfg(feature = "std")]
+    fn planar_geodesy_throughput() {
+        use std::time::Instant;
+
+        let mut tile = edge_tile(0.02, 512);
+        let edge = tile.points_units.clone();
+        tile.points_units = (0..1_000_000)
+            .map(|i| {
+                let [x, y, z] = edge[i % edge.len()];
+                let t = (i % 997) as i32;
+                [x - t * 7, y + t * 3, z - t]
+            })
+            .collect();
+        let (geodesy, _, _) = planar_setup(&tile);
+        let anchor_m = geodesy.anchor_m;
+        let offsets: Vec<[f64; 3]> = tile
+            .points_units
+            .iter()
+            .map(|p| p.map(|u| u as f64 / 1000.0))
+            .collect();
+
+        let t = Instant::now();
+        let full: Vec<(f64, f64)> = offsets
+            .iter()
+            .map(|o| {
+                let (lat, lon, _) =
+                    ecef_to_geodetic(anchor_m[0] + o[0], anchor_m[1] + o[1], anchor_m[2] + o[2]);
+                (lat, lon)
+            })
+            .collect();
+        let full_time = t.elapsed();
+
+        let t = Instant::now();
+        let planar: Vec<(f64, f64)> = offsets.iter().map(|&o| geodesy.lat_lon_deg(o)).collect();
+        let planar_time = t.elapsed();
+
+        let t = Instant::now();
+        let labels = tile.smc1_point_labels().unwrap().unwrap();
+        std::println!(
+            "1M points: ecef_to_geodetic {:?}, planar {:?}, smc1_point_labels {:?}",
+            full_time,
+            planar_time,
+            t.elapsed()
+        );
+        assert_eq!(labels.len(), 1_000_000);
+        assert_eq!((full.len(), planar.len()), (labels.len(), labels.len()));
+        assert!(planar_time < full_time);
+    }
+
+    /// `sample_tile` with labels and an RLE mask in the class numbering
+    /// `[unlabelled, building, road]`.
+    fn labelled_tile(classes: [u8; 3]) -> HypcTile {
+        let grid = [1, 1, 2, 2, 0, 0, 1, 2].map(|i| classes[i]);
+        HypcTile {
+            labels: Some((0..12).map(|i| classes[i % 3]).collect()),
+            smc1: Some(Smc1Chunk {
+                width: 4,
+                height: 2,
+                coord_space: Smc1CoordSpace::Crs84BboxNorm,
+                encoding: Smc1Encoding::Rle,
+                palette: vec![(classes[1], 10), (classes[2], 5)],
+                data: smc1_encode_rle(&grid),
+            }),
+            ..sample_tile()
+        }
+    }
+
+    #[test]
+    fn remap_labels_identity_is_a_no_op() {
+        let identity: [u8; 256] = core::array::from_fn(|i| i as u8);
+        let tile = labelled_tile([0, 1, 2]);
+        let mut remapped = tile.clone();
+        remapped.remap_labels(&identity);
+        assert_eq!(remapped, tile);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn remapped_tiles_merge_with_shared_numbering() {
+        let ours = labelled_tile([0, 1, 2]);
+        let mut theirs = labelled_tile([0, 5, 7]);
+
+        let mut map: [u8; 256] = core::array::from_fn(|i| i as u8);
+        map[5] = 1;
+        map[7] = 2;
+        theirs.remap_labels(&map);
+        assert_eq!(theirs.labels, ours.labels);
+        assert_eq!(theirs.smc1, ours.smc1);
+
+        let merged = HypcTile::merge(&[ours.clone(), theirs]).unwrap();
+        let mut expected = ours.labels.clone().unwrap();
+        expected.extend_from_within(..);
+        assert_eq!(merged.labels, Some(expected));
+    }
+}
@@ -0,0 +1,619 @@
+//! Golden fixture tiles in `fixtures/`, which lock the on-disk layout.
+//!
+//! Each committed file must parse, with both parsers, to the tile defined here
+//! and be byte-identical to what the writer produces today. Goldens from the v2
+//! encoder are kept under `fixtures/v2` and only checked to parse, and the
+//! `reject_*` files must fail in both parsers with a specific error.
+//!
+//! After an intentional format change, regenerate the files with
+//! `HYPC_WRITE_FIXTURES=1 cargo test -p hypc --test fixtures`.
+
+#![cfg(feature = "std")]
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use hypc::{
+    GeoExtentQ7, HypcError, HypcTile, PointsCodec, PointsEncoding, RigidTransform, Smc1Chunk,
+    Smc1CoordSpace, Smc1Encoding,
+};
+
+fn fixtures_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures")
+}
+
+/// Whether to rewrite the committed fixtures instead of checking them.
+fn regenerate() -> bool {
+    std::env::var_os("HYPC_WRITE_FIXTURES").is_some()
+}
+
+/// The [`HypcError`] inside an error from one of the `io::Result` APIs.
+fn hypc_error(e: &io::Error) -> Option<&HypcError> {
+    e.get_ref()?.downcast_ref()
+}
+
+/// Fails with every problem found, so one run reports all broken fixtures.
+fn assert_no_problems(problems: &[String]) {
+    assert!(problems.is_empty(), "{}", problems.join("\n"));
+}
+
+/// Golden tiles covering each optional section of the format, built from fixed data.
+///
+/// Changing any of these (or the writer) changes the committed bytes; regenerate
+/// them (see the module docs) only for an intentional format change.
+fn golden_tiles() -> Vec<(&'static str, HypcTile)> {
+    // A small, non-degenerate point set around a Munich-area anchor (mm lattice).
+    let points: Vec<[i32; 3]> = (0..12)
+        .map(|i| [i * 1_000 - 6_000, (i % 4) * 2_500 - 3_750, (i % 3) * -700 + 350])
+        .collect();
+    let labels: Vec<u8> = (0..points.len()).map(|i| (i % 10) as u8).collect();
+    let colors: Vec<[u8; 3]> = (0..points.len() as u8)
+        .map(|i| [i * 21, 255 - i * 13, (i * 7) ^ 0x5a])
+        .collect();
+    let key: [u8; 32] = std::array::from_fn(|i| (i as u8).wrapping_mul(37).wrapping_add(11));
+    let geot = GeoExtentQ7::from_deg(11.5000000, 11.5100000, 48.1300000, 48.1400000);
+
+    // 8x8 mask: buildings block, a road row, water corner, unknown elsewhere.
+    let mask: Vec<u8> = (0..64)
+        .map(|i| {
+            let (x, y) = (i % 8, i / 8);
+            match (x, y) {
+                (_, 4) => 2,
+                (0..=2, 0..=2) => 1,
+                (6..=7, 6..=7) => 5,
+                _ => 0,
+            }
+        })
+        .collect();
+    let palette: Vec<(u8, u8)> = vec![(0, 0), (1, 200), (2, 150), (5, 200)];
+
+    let base = HypcTile {
+        units_per_meter: 1000,
+        anchor_ecef_units: [4_177_000_123, 855_000_456, 4_727_000_789],
+        tile_key: None,
+        points_units: points.clone(),
+        points_encoding: PointsEncoding::Raw,
+        points_codec: PointsCodec::None,
+        labels: None,
+        colors: None,
+        geot: None,
+        smc1: None,
+        transform: None,
+        crc: false,
+    };
+
+    vec![
+        ("minimal.hypc", base.clone()),
+        (
+            "key_labels.hypc",
+            HypcTile {
+                tile_key: Some(key),
+                labels: Some(labels.clone()),
+                ..base.clone()
+            },
+        ),
+        (
+            "geot.hypc",
+            HypcTile {
+                geot: Some(geot),
+                ..base.clone()
+            },
+        ),
+        (
+            "geo2.hypc",
+            HypcTile {
+                geot: Some(geot.with_height_m(-0.5, 512.25)),
+                ..base.clone()
+            },
+        ),
+        (
+            "smc1_raw.hypc",
+            HypcTile {
+                geot: Some(geot),
+                smc1: Some(Smc1Chunk {
+                    width: 8,
+                    height: 8,
+                    coord_space: Smc1CoordSpace::Crs84BboxNorm,
+                    encoding: Smc1Encoding::Raw,
+                    palette: palette.clone(),
+                    data: mask.clone(),
+                }),
+                ..base.clone()
+            },
+        ),
+        (
+            "smc1_rle_key_labels.hypc",
+            HypcTile {
+                tile_key: Some(key),
+                labels: Some(labels.clone()),
+                geot: Some(geot),
+                smc1: Some(Smc1Chunk {
+                    width: 8,
+                    height: 8,
+                    coord_space: Smc1CoordSpace::Crs84BboxNorm,
+                    encoding: Smc1Encoding::Rle,
+                    palette: palette.clone(),
+                    data: hypc::smc1_encode_rle(&mask),
+                }),
+                ..base.clone()
+            },
+        ),
+        (
+            "rgb_labels.hypc",
+            HypcTile {
+                labels: Some(labels.clone()),
+                colors: Some(colors),
+                ..base.clone()
+            },
+        ),
+        (
+            "crc_geot_labels.hypc",
+            HypcTile {
+                labels: Some(labels.clone()),
+                geot: Some(geot),
+                crc: true,
+                ..base.clone()
+            },
+        ),
+        (
+            // Unsorted, with extremes, so deltas wrap: decoding must restore the exact order.
+            "delta_labels.hypc",
+            HypcTile {
+                points_units: (0..points.len())
+                    .map(|i| points[(i * 5) % points.len()])
+                    .chain([[i32::MIN, i32::MAX, 0], [i32::MAX, i32::MIN, -1]])
+                    .collect(),
+                points_encoding: PointsEncoding::Delta,
+                labels: Some((0..points.len() as u8 + 2).collect()),
+                ..base.clone()
+            },
+        ),
+        (
+            "deflate_raw.hypc",
+            HypcTile {
+                points_codec: PointsCodec::Deflate,
+                ..base.clone()
+            },
+        ),
+        (
+            "deflate_delta_labels.hypc",
+            HypcTile {
+                points_units: (0..points.len())
+                    .map(|i| points[(i * 3) % points.len()])
+                    .collect(),
+                points_encoding: PointsEncoding::Delta,
+                points_codec: PointsCodec::Deflate,
+                labels: Some(labels),
+                ..base.clone()
+            },
+        ),
+        (
+            "smc1_deflate.hypc",
+            HypcTile {
+                geot: Some(geot),
+                smc1: Some(Smc1Chunk {
+                    width: 8,
+                    height: 8,
+                    coord_space: Smc1CoordSpace::Crs84BboxNorm,
+                    encoding: Smc1Encoding::Deflate,
+                    palette,
+                    data: hypc::smc1_encode_deflate(&mask),
+                }),
+                ..base.clone()
+            },
+        ),
+        (
+            "xfrm.hypc",
+            HypcTile {
+                transform: Some(RigidTransform {
+                    rotation: [[0.0, -1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]],
+                    translation_m: [0.25, -0.5, 1.125],
+                }),
+                ..base
+            },
+        ),
+    ]
+}
+
+/// Predicate a rejected fixture's parse error must satisfy.
+type ErrorCheck = fn(&HypcError) -> bool;
+
+/// Malformed tiles both parsers must reject, with the error each must produce.
+fn rejected_tiles() -> Vec<(&'static str, Vec<u8>, ErrorCheck)> {
+    let encode = |tile: &HypcTile| hypc::write_hypc_bytes(tile).expect("encodable tile");
+
+    let smc1_raw = golden("smc1_raw.hypc");
+    let mask = smc1_raw.smc1.as_ref().map(|c| c.data.clone()).unwrap_or_default();
+    let long_mask = [mask.as_slice(), &[0u8; 4]].concat();
+
+    let with_payload = |encoding: Smc1Encoding, data: Vec<u8>| {
+        let mut tile = smc1_raw.clone();
+        if let Some(chunk) = tile.smc1.as_mut() {
+            chunk.encoding = encoding;
+            chunk.data = data;
+        }
+        encode(&tile)
+    };
+    let size_mismatch: ErrorCheck = |e| matches!(e, HypcError::Smc1SizeMismatch { .. });
+
+    // One flipped bit in the first point's dx, past the header and tile key.
+    let mut bit_flip = encode(&golden("crc_geot_labels.hypc"));
+    bit_flip[44] ^= 0x10;
+
+    // Varint stream one byte short (length field after the 44-byte header and
+    // 4-byte encoding field), so the last point is incomplete.
+    let mut short_deltas = encode(&golden("delta_labels.hypc"));
+    let len = u32::from_le_bytes(short_deltas[48..52].try_into().unwrap());
+    short_deltas[48..52].copy_from_slice(&(len - 1).to_le_bytes());
+    short_deltas.remove(52 + len as usize - 1);
+
+    // Last byte of the zlib Adler-32 trailer flipped, after the header, the
+    // encoding field and the two block lengths.
+    let mut bad_adler = encode(&golden("deflate_raw.hypc"));
+    let stored = u32::from_le_bytes(bad_adler[52..56].try_into().unwrap());
+    bad_adler[56 + stored as usize - 1] ^= 0x01;
+
+    vec![
+        (
+            "reject_smc1_raw_short.hypc",
+            with_payload(Smc1Encoding::Raw, mask[..mask.len() - 4].to_vec()),
+            size_mismatch,
+        ),
+        (
+            "reject_smc1_raw_long.hypc",
+            with_payload(Smc1Encoding::Raw, long_mask.clone()),
+            size_mismatch,
+        ),
+        (
+            "reject_smc1_rle_short.hypc",
+            with_payload(
+                Smc1Encoding::Rle,
+                hypc::smc1_encode_rle(&mask[..mask.len() - 4]),
+            ),
+            size_mismatch,
+        ),
+        (
+            "reject_smc1_rle_long.hypc",
+            with_payload(Smc1Encoding::Rle, hypc::smc1_encode_rle(&long_mask)),
+            size_mismatch,
+        ),
+        ("reject_crc_bit_flip.hypc", bit_flip, |e| {
+            matches!(e, HypcError::ChecksumMismatch { .. })
+        }),
+        ("reject_delta_short.hypc", short_deltas, |e| {
+            matches!(e, HypcError::BadPointDeltas)
+        }),
+        ("reject_deflate_checksum.hypc", bad_adler, |e| {
+            matches!(e, HypcError::BadCompressedBlock)
+        }),
+        (
+            // A 0x8 grid with an empty payload covers its 0 pixels exactly.
+            "reject_smc1_zero_dim.hypc",
+            {
+                let mut tile = smc1_raw.clone();
+                if let Some(chunk) = tile.smc1.as_mut() {
+                    chunk.width = 0;
+                    chunk.data.clear();
+                }
+                encode(&tile)
+            },
+            |e| matches!(e, HypcError::Smc1ZeroSize { width: 0, height: 8 }),
+        ),
+    ]
+}
+
+/// Masks the SMC1 RLE codec must round-trip exactly, built from a fixed seed.
+fn rle_patterns() -> Vec<(String, Vec<u8>)> {
+    // xorshift64, so the patterns are identical on every run.
+    let mut state = 0x9E37_79B9_7F4A_7C15u64;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+
+    let max = hypc::SMC1_RLE_MAX_RUN;
+    let mut patterns: Vec<(String, Vec<u8>)> = vec![
+        ("empty".into(), Vec::new()),
+        ("single pixel".into(), vec![7]),
+        ("uniform 300000".into(), vec![3; 300_000]),
+        (
+            "alternating 200000".into(),
+            (0..200_000).map(|i| (i % 2) as u8).collect(),
+        ),
+        (
+            "random 100000".into(),
+            (0..100_000).map(|_| next() as u8).collect(),
+        ),
+    ];
+
+    // A run of exactly n pixels between single different pixels, for n at and
+    // around each multiple of the record limit.
+    for n in [max - 1, max, max + 1, 2 * max, 2 * max + 1, 5 * max + 3] {
+        let mut mask = vec![1u8];
+        mask.resize(1 + n, 2);
+        mask.push(1);
+        patterns.push((format!("run of {}", n), mask));
+    }
+
+    // Random runs, mostly short with one in ten up to three records long, and
+    // random values (repeats merge into even longer runs).
+    let mut mask = Vec::new();
+    while mask.len() < 600_000 {
+        let limit = if next() % 10 != 0 { 300 } else { 3 * max as u64 };
+        let len = (next() % limit) as usize + 1;
+        mask.resize(mask.len() + len, (next() % 4) as u8);
+    }
+    patterns.push((format!("random runs {}", mask.len()), mask));
+
+    patterns
+}
+
+
+/// Subdirectory of the fixtures dir holding goldens written by the v2 encoder.
+const LEGACY_V2_DIR: &str = "v2";
+/// Golden tiles that existed when v2 was current, i.e. the ones in [`LEGACY_V2_DIR`].
+const LEGACY_V2_TILES: [&str; 6] = [
+    "minimal.hypc",
+    "key_labels.hypc",
+    "geot.hypc",
+    "smc1_raw.hypc",
+    "smc1_rle_key_labels.hypc",
+    "xfrm.hypc",
+];
+
+fn golden(name: &str) -> HypcTile {
+    golden_tiles()
+        .into_iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, tile)| tile)
+        .expect("golden tile")
+}
+
+#[test]
+fn golden_tiles_match_the_writer() {
+    let dir = fixtures_dir();
+    let tiles = golden_tiles();
+    if regenerate() {
+        for (name, tile) in &tiles {
+            hypc::write_file(dir.join(name), tile).expect("writing fixture");
+        }
+    }
+
+    // The file writer goes through a scratch directory, so its bytes are compared too.
+    let scratch = std::env::temp_dir().join(format!("hypc-fixtures-{}", std::process::id()));
+    fs::create_dir_all(&scratch).expect("scratch dir");
+
+    let mut problems = Vec::new();
+    for (name, expected) in &tiles {
+        let path = dir.join(name);
+        let problem = match fs::read(&path) {
+            Err(e) => Some(format!("unreadable: {}", e)),
+            Ok(golden) => match hypc::parse_hypc_bytes(&golden) {
+                Err(e) => Some(format!("parse failed: {}", e)),
+                Ok(parsed) if parsed != *expected => {
+                    Some("parsed tile differs from the fixture definition".into())
+                }
+                Ok(_) if !matches!(
+                    hypc::parse_hypc_reader(&mut golden.as_slice()),
+                    Ok(ref streamed) if streamed == expected
+                ) =>
+                {
+                    Some("streaming parser disagrees with the slice parser".into())
+                }
+                Ok(_) => {
+                    let fresh = scratch.join(name);
+                    match hypc::write_file(&fresh, expected).and_then(|_| fs::read(&fresh)) {
+                        Err(e) => Some(format!("re-encoding failed: {}", e)),
+                        Ok(bytes) if bytes != golden => Some(format!(
+                            "writer output differs from golden ({} vs {} bytes)",
+                            bytes.len(),
+                            golden.len()
+                        )),
+                        Ok(_) => match hypc::write_hypc_bytes(expected) {
+                            Err(e) => Some(format!("in-memory encoding failed: {}", e)),
+                            Ok(bytes) if bytes != golden => {
+                                Some("in-memory writer output differs from golden".into())
+                            }
+                            Ok(_) => None,
+                        },
+                    }
+                }
+            },
+        };
+        let problem = problem.or_else(|| {
+            (expected.crc && !matches!(hypc::verify_file(&path), Ok(true)))
+                .then(|| "verify_file rejected a valid CRC".to_string())
+        });
+        problems.extend(problem.map(|msg| format!("{}: {}", path.display(), msg)));
+    }
+    let _ = fs::remove_dir_all(&scratch);
+
+    assert_no_problems(&problems);
+}
+
+/// Frozen v2 goldens: the reader must still accept them. They are never rewritten.
+#[test]
+fn legacy_v2_tiles_still_parse() {
+    let dir = fixtures_dir().join(LEGACY_V2_DIR);
+    let mut problems = Vec::new();
+    for (name, expected) in golden_tiles()
+        .iter()
+        .filter(|(name, _)| LEGACY_V2_TILES.contains(name))
+    {
+        let path = dir.join(name);
+        let problem = match fs::read(&path) {
+            Err(e) => Some(format!("unreadable: {}", e)),
+            Ok(bytes) => match (
+                hypc::parse_hypc_bytes(&bytes).map_err(io::Error::from),
+                hypc::parse_hypc_reader(&mut bytes.as_slice()),
+            ) {
+                (Ok(a), Ok(b)) if a == *expected && b == *expected => None,
+                (Err(e), _) | (_, Err(e)) => Some(format!("parse failed: {}", e)),
+                _ => Some("parsed tile differs from the fixture definition".into()),
+            },
+        };
+        problems.extend(problem.map(|msg| format!("{}: {}", path.display(), msg)));
+    }
+    assert_no_problems(&problems);
+}
+
+/// Malformed goldens: both parsers must fail with the expected error.
+#[test]
+fn malformed_tiles_are_rejected() {
+    let dir = fixtures_dir();
+    let rejected = rejected_tiles();
+    if regenerate() {
+        for (name, bytes, _) in &rejected {
+            fs::write(dir.join(name), bytes).expect("writing fixture");
+        }
+    }
+
+    let mut problems = Vec::new();
+    for (name, _, expected) in &rejected {
+        let path = dir.join(name);
+        let problem = match fs::read(&path) {
+            Err(e) => Some(format!("unreadable: {}", e)),
+            Ok(bytes) => match (
+                hypc::parse_hypc_bytes(&bytes),
+                hypc::parse_hypc_reader(&mut bytes.as_slice()),
+            ) {
+                _ if matches!(hypc::verify_file(&path), Ok(true)) => {
+                    Some("verify_file accepted a malformed tile".into())
+                }
+                (Err(a), Err(b)) if expected(&a) && hypc_error(&b).is_some_and(expected) => None,
+                (Ok(_), _) | (_, Ok(_)) => Some("malformed tile was accepted".into()),
+                (Err(a), Err(b)) => Some(format!("unexpected errors: {} / {}", a, b)),
+            },
+        };
+        problems.extend(problem.map(|msg| format!("{}: {}", path.display(), msg)));
+    }
+    assert_no_problems(&problems);
+}
+
+/// Sampling every pixel with `class_at` must reproduce `decode_grid`.
+#[test]
+fn class_at_matches_decode_grid() {
+    for (name, tile) in golden_tiles() {
+        let Some(smc1) = tile.smc1.as_ref() else {
+            continue;
+        };
+        let grid = smc1.decode_grid().expect(name);
+        let sampled: Vec<Option<u8>> = (0..smc1.height)
+            .flat_map(|y| (0..smc1.width).map(move |x| (x, y)))
+            .map(|(x, y)| smc1.class_at(x, y))
+            .collect();
+
+        assert!(
+            sampled.iter().copied().eq(grid.iter().map(|&c| Some(c))),
+            "{}: class_at disagrees with decode_grid",
+            name
+        );
+        assert_eq!(smc1.class_at(smc1.width, 0), None, "{}", name);
+        assert_eq!(smc1.class_at(0, smc1.height), None, "{}", name);
+    }
+}
+
+/// Every golden tile survives a JSON round trip, with its key as a hex string.
+#[test]
+fn golden_tiles_round_trip_through_json() {
+    for (name, tile) in golden_tiles() {
+        let json = serde_json::to_value(&tile).expect(name);
+        match tile.tile_key {
+            Some(_) => assert!(
+                json["tile_key"].as_str().is_some_and(|hex| hex.len() == 64),
+                "{}: tile_key is not a hex string: {}",
+                name,
+                json["tile_key"]
+            ),
+            None => assert!(json["tile_key"].is_null(), "{}", name),
+        }
+
+        let back: HypcTile = serde_json::from_value(json).expect(name);
+        assert_eq!(back, tile, "{}: JSON round trip changed the tile", name);
+    }
+}
+
+/// Each of [`rle_patterns`] round-trips exactly, and every record is in range.
+#[test]
+fn rle_round_trips() {
+    for (name, raw) in rle_patterns() {
+        let rle = hypc::smc1_encode_rle(&raw);
+        assert!(
+            rle.chunks_exact(3)
+                .all(|run| u16::from_le_bytes([run[0], run[1]]) != 0),
+            "RLE {}: encoder emitted a zero-length run",
+            name
+        );
+        let decoded = hypc::smc1_decode_rle(&rle).expect(&name);
+        assert!(decoded == raw, "RLE {}: decoded pixels differ", name);
+    }
+}
+
+/// Merges tiles with different resolutions: points stay put, labels are
+/// filled with class 0, and the GEOTs are unioned.
+#[test]
+fn merge_mixed_resolutions() {
+    let fine = golden("key_labels.hypc");
+    let geot = golden("geo2.hypc").geot.unwrap();
+
+    // Same shape at a quarter of the resolution, 20 m away, with a shifted GEOT.
+    let coarse = HypcTile {
+        units_per_meter: 250,
+        anchor_ecef_units: fine.anchor_ecef_units.map(|a| a / 4 + 5_000),
+        points_units: fine
+            .points_units
+            .iter()
+            .map(|p| p.map(|v| v / 4 + 1))
+            .collect(),
+        labels: None,
+        geot: Some(GeoExtentQ7 {
+            lon_min_q7: geot.lon_min_q7 + 50_000,
+            lon_max_q7: geot.lon_max_q7 + 50_000,
+            height_mm: Some((-2_000, 100_000)),
+            ..geot
+        }),
+        ..fine.clone()
+    };
+    let fine = HypcTile {
+        geot: Some(geot),
+        ..fine
+    };
+
+    let inputs = [fine.clone(), coarse.clone()];
+    let merged = HypcTile::merge(&inputs).expect("merge");
+    let want: Vec<[f64; 3]> = inputs.iter().flat_map(|t| t.points_ecef_m()).collect();
+    let got = merged.points_ecef_m();
+    let mut labels = fine.labels.clone().unwrap();
+    labels.resize(want.len(), 0);
+
+    assert_eq!(merged.units_per_meter, 1000);
+    assert_eq!(got.len(), want.len());
+    for (a, b) in got.iter().zip(&want) {
+        assert!((0..3).all(|k| (a[k] - b[k]).abs() <= 1e-9), "merged points moved");
+    }
+    assert_eq!(merged.labels, Some(labels));
+    assert_eq!(merged.geot, Some(geot.union(coarse.geot.unwrap())));
+
+    let bytes = hypc::write_hypc_bytes(&merged).expect("merged tile encodes");
+    hypc::parse_hypc_bytes(&bytes).expect("merged tile round-trips");
+}
+
+#[test]
+fn merge_refuses_offsets_beyond_i32() {
+    let fine = golden("key_labels.hypc");
+    let far = HypcTile {
+        anchor_ecef_units: fine.anchor_ecef_units.map(|a| -a),
+        ..fine.clone()
+    };
+    let err = HypcTile::merge(&[fine, far]).unwrap_err();
+    assert!(matches!(hypc_error(&err), Some(HypcError::OffsetOverflow)));
+}
+
+#[test]
+fn merge_refuses_no_tiles() {
+    let err = HypcTile::merge(&[]).unwrap_err();
+    assert!(matches!(hypc_error(&err), Some(HypcError::EmptyMerge)));
+}
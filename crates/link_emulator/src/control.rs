@@ -0,0 +1,203 @@
+//! `/config` control endpoint: read or change the impairments of a running
+//! emulator. Changes reach existing connections at their next read.
+
+use crate::{Config, LinkParams};
+use axum::{
+    extract::{rejection::JsonRejection, State},
+    http::StatusCode,
+    routing::get,
+    Json, Router,
+};
+use std::sync::{Arc, RwLock};
+
+pub type SharedConfig = Arc<RwLock<Config>>;
+
+/// The hot-reloadable part of [`Config`].
+#[derive(serde::Serialize)]
+struct LinkConfig {
+    up: LinkParams,
+    down: LinkParams,
+}
+
+/// A partial update; unset fields keep their current value.
+#[derive(serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigPatch {
+    #[serde(default)]
+    up: LinkParamsPatch,
+    #[serde(default)]
+    down: LinkParamsPatch,
+}
+
+#[derive(serde::Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct LinkParamsPatch {
+    latency_ms: Option<u64>,
+    jitter_ms: Option<u64>,
+    rate_bps: Option<u64>,
+    bucket_bytes: Option<usize>,
+    stall_period_ms: Option<u64>,
+    stall_duration_ms: Option<u64>,
+    reset_chance_percent: Option<u8>,
+    dup_chance_percent: Option<u8>,
+    reorder_chance_percent: Option<u8>,
+    drop_chance_percent: Option<u8>,
+}
+
+impl LinkParamsPatch {
+    fn apply(self, p: &mut LinkParams) {
+        macro_rules! set {
+            ($($field:ident),*) => {
+                $(if let Some(v) = self.$field { p.$field = v; })*
+            };
+        }
+        set!(
+            latency_ms,
+            jitter_ms,
+            rate_bps,
+            bucket_bytes,
+            stall_period_ms,
+            stall_duration_ms,
+            reset_chance_percent,
+            dup_chance_percent,
+            reorder_chance_percent,
+            drop_chance_percent
+        );
+    }
+}
+
+pub fn router(shared: SharedConfig) -> Router {
+    Router::new()
+        .route("/config", get(get_config).post(post_config))
+        .with_state(shared)
+}
+
+fn snapshot(cfg: &Config) -> Json<LinkConfig> {
+    Json(LinkConfig {
+        up: cfg.up.clone(),
+        down: cfg.down.clone(),
+    })
+}
+
+async fn get_config(State(shared): State<SharedConfig>) -> Json<LinkConfig> {
+    snapshot(&shared.read().unwrap())
+}
+
+/// Applies a [`ConfigPatch`] and returns the resulting config, or 400 without
+/// changing anything if the body is malformed or a value is out of range.
+async fn post_config(
+    State(shared): State<SharedConfig>,
+    patch: Result<Json<ConfigPatch>, JsonRejection>,
+) -> Result<Json<LinkConfig>, (StatusCode, String)> {
+    let Json(patch) = patch.map_err(|e| (StatusCode::BAD_REQUEST, e.body_text()))?;
+
+    let mut cfg = shared.write().unwrap();
+    let (mut up, mut down) = (cfg.up.clone(), cfg.down.clone());
+    patch.up.apply(&mut up);
+    patch.down.apply(&mut down);
+    for (name, params) in [("up", &up), ("down", &down)] {
+        params
+            .validate()
+            .map_err(|e| (StatusCode::BAD_REQUEST, format!("{name}: {e}")))?;
+    }
+
+    cfg.up = up;
+    cfg.down = down;
+    tracing::info!(up = ?cfg.up, down = ?cfg.down, "Impairments updated");
+    Ok(snapshot(&cfg))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Proto;
+    use axum::{body::Body, http::Request};
+    use tower::ServiceExt;
+
+    fn params() -> LinkParams {
+        LinkParams {
+            latency_ms: 10,
+            jitter_ms: 0,
+            rate_bps: 0,
+            bucket_bytes: 0,
+            stall_period_ms: 0,
+            stall_duration_ms: 0,
+            reset_chance_percent: 0,
+            dup_chance_percent: 0,
+            reorder_chance_percent: 0,
+            drop_chance_percent: 0,
+        }
+    }
+
+    fn shared() -> SharedConfig {
+        Arc::new(RwLock::new(Config {
+            proto: Proto::Udp,
+            listen: "127.0.0.1:0".into(),
+            target: "127.0.0.1:0".into(),
+            metrics_listen_addr: "127.0.0.1:0".into(),
+            rng_seed: Some(1),
+            up: params(),
+            down: params(),
+        }))
+    }
+
+    async fn post(shared: &SharedConfig, body: &str) -> StatusCode {
+        let request = Request::post("/config")
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_owned()))
+            .unwrap();
+        router(shared.clone())
+            .oneshot(request)
+            .await
+            .unwrap()
+            .status()
+    }
+
+    #[tokio::test]
+    async fn valid_patch_is_applied() {
+        let shared = shared();
+        let status = post(
+            &shared,
+            r#"{"up": {"latency_ms": 50}, "down": {"drop_chance_percent": 10}}"#,
+        )
+        .await;
+
+        assert_eq!(status, StatusCode::OK);
+        let cfg = shared.read().unwrap();
+        assert_eq!(
+            cfg.up,
+            LinkParams {
+                latency_ms: 50,
+                ..params()
+            }
+        );
+        assert_eq!(
+            cfg.down,
+            LinkParams {
+                drop_chance_percent: 10,
+                ..params()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn out_of_range_patch_is_rejected_and_changes_nothing() {
+        let shared = shared();
+        for body in [
+            // A chance above 100%, alongside a valid change that must not land.
+            r#"{"up": {"latency_ms": 50}, "down": {"drop_chance_percent": 101}}"#,
+            r#"{"up": {"latency_ms": -5}}"#,
+            r#"{"up": {"dup_chance_percent": 300}}"#,
+            r#"{"up": {"loss": 0.5}}"#,
+        ] {
+            assert_eq!(
+                post(&shared, body).await,
+                StatusCode::BAD_REQUEST,
+                "{}",
+                body
+            );
+            let cfg = shared.read().unwrap();
+            assert_eq!((&cfg.up, &cfg.down), (&params(), &params()), "{}", body);
+        }
+    }
+}
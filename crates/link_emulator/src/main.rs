@@ -1,8 +1,15 @@
+mod control;
 mod metrics;
+mod udp;
 
-use crate::metrics::EmulatorMetrics;
+use crate::{control::SharedConfig, metrics::EmulatorMetrics};
 use anyhow::{anyhow, bail};
-use std::{sync::Arc, time::SystemTime};
+use prometheus::IntGauge;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::{
+    sync::{Arc, RwLock},
+    time::SystemTime,
+};
 use tokio::{
     io::{AsyncReadExt, AsyncWriteExt},
     net::{TcpListener, TcpStream},
@@ -10,10 +17,44 @@ use tokio::{
 };
 use tracing_subscriber::{fmt, EnvFilter};
 
+/// Transport the emulator proxies.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Proto {
+    /// Byte streams, one upstream connection per accepted client.
+    Tcp,
+    /// Datagrams, one upstream socket per client source address.
+    Udp,
+}
+
+impl std::str::FromStr for Proto {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "tcp" => Ok(Proto::Tcp),
+            "udp" => Ok(Proto::Udp),
+            _ => bail!("expected tcp or udp, got {s:?}"),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 struct Config {
+    proto: Proto,
     listen: String,
     target: String,
+    metrics_listen_addr: String,
+    /// Seed for the per-connection impairment RNGs; entropy when unset.
+    rng_seed: Option<u64>,
+    /// Impairments on client → server traffic.
+    up: LinkParams,
+    /// Impairments on server → client traffic.
+    down: LinkParams,
+}
+
+/// Impairments applied to one direction of a connection.
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+struct LinkParams {
     latency_ms: u64,
     jitter_ms: u64,
     rate_bps: u64,
@@ -21,44 +62,92 @@ struct Config {
     stall_period_ms: u64,
     stall_duration_ms: u64,
     reset_chance_percent: u8,
-    metrics_listen_addr: String,
+    dup_chance_percent: u8,
+    reorder_chance_percent: u8,
+    /// UDP only: a stream can't lose bytes without corrupting it.
+    drop_chance_percent: u8,
 }
 
-impl Config {
-    fn from_env() -> anyhow::Result<Self> {
-        let reset_chance_percent: u8 = std::env::var("EMULATOR_RESET_CHANCE_PERCENT")
-            .unwrap_or_else(|_| "0".into())
-            .parse()?;
-        if reset_chance_percent > 100 {
-            bail!("EMULATOR_RESET_CHANCE_PERCENT must be between 0 and 100");
+impl LinkParams {
+    fn validate(&self) -> Result<(), String> {
+        for (name, v) in [
+            ("reset_chance_percent", self.reset_chance_percent),
+            ("dup_chance_percent", self.dup_chance_percent),
+            ("reorder_chance_percent", self.reorder_chance_percent),
+            ("drop_chance_percent", self.drop_chance_percent),
+        ] {
+            if v > 100 {
+                return Err(format!("{name} must be between 0 and 100, got {v}"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads each `EMULATOR_<NAME>` setting for one direction: the directional
+    /// `EMULATOR_<NAME>_<suffix>` (`UP` = client → server, `DOWN` = server →
+    /// client) takes precedence over the shared `EMULATOR_<NAME>`, which takes
+    /// precedence over the default.
+    fn from_env(suffix: &str) -> anyhow::Result<Self> {
+        fn var<T: std::str::FromStr>(name: &str, suffix: &str, default: T) -> anyhow::Result<T>
+        where
+            T::Err: std::error::Error + Send + Sync + 'static,
+        {
+            let directional = format!("{name}_{suffix}");
+            for key in [directional.as_str(), name] {
+                if let Ok(v) = std::env::var(key) {
+                    return v
+                        .parse()
+                        .map_err(|e| anyhow!("{key}: invalid value {v:?}: {e}"));
+                }
+            }
+            Ok(default)
         }
 
+        let percent = |name: &str| -> anyhow::Result<u8> {
+            let v: u8 = var(name, suffix, 0)?;
+            if v > 100 {
+                bail!("{name}(_{suffix}) must be between 0 and 100");
+            }
+            Ok(v)
+        };
+
         Ok(Self {
+            latency_ms: var("EMULATOR_LATENCY_MS", suffix, 0)?,
+            jitter_ms: var("EMULATOR_JITTER_MS", suffix, 0)?,
+            rate_bps: var("EMULATOR_RATE_BPS", suffix, 0)?,
+            bucket_bytes: var("EMULATOR_BUCKET_BYTES", suffix, 0)?,
+            stall_period_ms: var("EMULATOR_STALL_PERIOD_MS", suffix, 0)?,
+            stall_duration_ms: var("EMULATOR_STALL_DURATION_MS", suffix, 0)?,
+            reset_chance_percent: percent("EMULATOR_RESET_CHANCE_PERCENT")?,
+            dup_chance_percent: percent("EMULATOR_DUP_CHANCE_PERCENT")?,
+            reorder_chance_percent: percent("EMULATOR_REORDER_CHANCE_PERCENT")?,
+            drop_chance_percent: percent("EMULATOR_DROP_CHANCE_PERCENT")?,
+        })
+    }
+}
+
+impl Config {
+    fn from_env() -> anyhow::Result<Self> {
+        Ok(Self {
+            proto: match std::env::var("EMULATOR_PROTO") {
+                Ok(v) => v.parse().map_err(|e| anyhow!("EMULATOR_PROTO: {e}"))?,
+                Err(_) => Proto::Tcp,
+            },
             listen: std::env::var("EMULATOR_LISTEN_ADDR")
                 .map_err(|_| anyhow!("EMULATOR_LISTEN_ADDR required"))?,
             target: std::env::var("EMULATOR_TARGET_ADDR")
                 .map_err(|_| anyhow!("EMULATOR_TARGET_ADDR required"))?,
             metrics_listen_addr: std::env::var("EMULATOR_METRICS_LISTEN_ADDR")
                 .map_err(|_| anyhow!("EMULATOR_METRICS_LISTEN_ADDR required"))?,
-            latency_ms: std::env::var("EMULATOR_LATENCY_MS")
-                .unwrap_or_else(|_| "0".into())
-                .parse()?,
-            jitter_ms: std::env::var("EMULATOR_JITTER_MS")
-                .unwrap_or_else(|_| "0".into())
-                .parse()?,
-            rate_bps: std::env::var("EMULATOR_RATE_BPS")
-                .unwrap_or_else(|_| "0".into())
-                .parse()?,
-            bucket_bytes: std::env::var("EMULATOR_BUCKET_BYTES")
-                .unwrap_or_else(|_| "0".into())
-                .parse()?,
-            stall_period_ms: std::env::var("EMULATOR_STALL_PERIOD_MS")
-                .unwrap_or_else(|_| "0".into())
-                .parse()?,
-            stall_duration_ms: std::env::var("EMULATOR_STALL_DURATION_MS")
-                .unwrap_or_else(|_| "0".into())
-                .parse()?,
-            reset_chance_percent,
+            rng_seed: match std::env::var("EMULATOR_RNG_SEED") {
+                Ok(v) => Some(
+                    v.parse()
+                        .map_err(|e| anyhow!("EMULATOR_RNG_SEED: invalid value {v:?}: {e}"))?,
+                ),
+                Err(_) => None,
+            },
+            up: LinkParams::from_env("UP")?,
+            down: LinkParams::from_env("DOWN")?,
         })
     }
 }
@@ -75,9 +164,14 @@ async fn main() -> anyhow::Result<()> {
 
     let metrics = Arc::new(EmulatorMetrics::new());
 
-    // Start metrics server
-    let router = metrics.router();
-    let metrics_addr: std::net::SocketAddr = cfg.metrics_listen_addr.parse()?;
+    let proto = cfg.proto;
+    let listen = cfg.listen.clone();
+    let target = cfg.target.clone();
+    let shared: SharedConfig = Arc::new(RwLock::new(cfg));
+
+    // Start metrics server, which also serves the /config control endpoint
+    let router = metrics.router().merge(control::router(shared.clone()));
+    let metrics_addr: std::net::SocketAddr = shared.read().unwrap().metrics_listen_addr.parse()?;
     tokio::spawn(async move {
         let listener = tokio::net::TcpListener::bind(metrics_addr).await.unwrap();
         tracing::info!(addr = %metrics_addr, "Metrics server started");
@@ -86,19 +180,29 @@ async fn main() -> anyhow::Result<()> {
             .unwrap();
     });
 
-    let listener = TcpListener::bind(&cfg.listen).await?;
-    tracing::info!(addr = cfg.listen, target = cfg.target, "Link emulator listening");
+    if proto == Proto::Udp {
+        return udp::run(&listen, &target, shared, metrics).await;
+    }
+
+    let listener = TcpListener::bind(&listen).await?;
+    tracing::info!(addr = listen, target = target, "Link emulator listening");
 
+    let mut next_conn_id = 0u64;
     loop {
         let (inbound, client_addr) = listener.accept().await?;
-        let cfg_clone = cfg.clone();
+        let conn_id = next_conn_id;
+        next_conn_id += 1;
+        let target = target.clone();
+        let shared = shared.clone();
         let metrics_clone = metrics.clone();
 
         tokio::spawn(async move {
             metrics_clone.connections_total.inc();
             metrics_clone.active_connections.inc();
 
-            if let Err(e) = handle_connection(inbound, cfg_clone, metrics_clone.clone()).await {
+            if let Err(e) =
+                handle_connection(inbound, &target, &shared, metrics_clone.clone(), conn_id).await
+            {
                 tracing::warn!(error = %e, client = %client_addr, "Connection ended with error");
             }
 
@@ -107,146 +211,318 @@ async fn main() -> anyhow::Result<()> {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Direction {
+    /// Client → server.
+    Up,
+    /// Server → client.
+    Down,
+}
+
+impl Direction {
+    const ALL: [Direction; 2] = [Direction::Up, Direction::Down];
+
+    /// Metrics label.
+    fn label(self) -> &'static str {
+        match self {
+            Direction::Up => "client_to_server",
+            Direction::Down => "server_to_client",
+        }
+    }
+
+    fn params(self, cfg: &Config) -> &LinkParams {
+        match self {
+            Direction::Up => &cfg.up,
+            Direction::Down => &cfg.down,
+        }
+    }
+
+    /// The RNG for this direction of connection `conn_id` (counted from 0 in
+    /// accept order, or in order of first datagram for UDP peers): with a
+    /// seed, uplink `n` uses `seed + 2n` and downlink `seed + 2n + 1`.
+    fn rng(self, seed: Option<u64>, conn_id: u64) -> StdRng {
+        let downlink = matches!(self, Direction::Down) as u64;
+        match seed {
+            Some(seed) => {
+                StdRng::seed_from_u64(seed.wrapping_add(conn_id.wrapping_mul(2) + downlink))
+            }
+            None => StdRng::from_entropy(),
+        }
+    }
+}
+
 async fn handle_connection(
     mut inbound: TcpStream,
-    cfg: Config,
+    target: &str,
+    shared: &SharedConfig,
     metrics: Arc<EmulatorMetrics>,
+    conn_id: u64,
 ) -> anyhow::Result<()> {
-    let mut outbound = TcpStream::connect(&cfg.target).await?;
+    let mut outbound = TcpStream::connect(target).await?;
     let (mut ri, mut wi) = inbound.split();
     let (mut ro, mut wo) = outbound.split();
 
     let c1 = impair_copy(
         &mut ri,
         &mut wo,
-        &cfg,
+        shared,
+        Direction::Up,
+        conn_id,
         metrics.clone(),
-        "client_to_server",
     );
     let c2 = impair_copy(
         &mut ro,
         &mut wi,
-        &cfg,
+        shared,
+        Direction::Down,
+        conn_id,
         metrics.clone(),
-        "server_to_client",
     );
 
     tokio::try_join!(c1, c2)?;
     Ok(())
 }
 
+/// How long a chunk held back for reordering waits for a successor before it is
+/// sent in order, so a request/response exchange can't deadlock on it.
+const REORDER_HOLD: Duration = Duration::from_millis(50);
+
+/// True with probability `percent`/100. Draws nothing when `percent` is 0.
+fn roll(rng: &mut StdRng, percent: u8) -> bool {
+    percent > 0 && rng.gen::<u8>() % 100 < percent
+}
+
+/// Latency plus a uniform draw from `0..=jitter_ms`. Draws nothing without jitter.
+fn sample_delay(cfg: &LinkParams, rng: &mut StdRng) -> Duration {
+    let jitter = if cfg.jitter_ms > 0 {
+        rng.gen::<u64>() % (cfg.jitter_ms + 1)
+    } else {
+        0
+    };
+    Duration::from_millis(cfg.latency_ms + jitter)
+}
+
+/// Token bucket pacing writes to `rate_bps`, refilled every 10 ms.
+struct TokenBucket {
+    rate_limited: bool,
+    tokens: usize,
+    capacity: usize,
+    per_interval: usize,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    const REFILL_INTERVAL: Duration = Duration::from_millis(10);
+
+    fn new(cfg: &LinkParams) -> Self {
+        let per_interval = if cfg.rate_bps == 0 {
+            usize::MAX
+        } else {
+            std::cmp::max(1, cfg.rate_bps as usize / 100) // 100 intervals per second
+        };
+        Self {
+            rate_limited: cfg.rate_bps > 0,
+            tokens: cfg.bucket_bytes,
+            capacity: cfg.bucket_bytes.max(per_interval),
+            per_interval,
+            last_refill: Instant::now(),
+        }
+    }
+
+    fn add_tokens(&mut self) {
+        self.tokens = std::cmp::min(self.tokens.saturating_add(self.per_interval), self.capacity);
+        self.last_refill = Instant::now();
+    }
+
+    fn refill(&mut self) {
+        if self.last_refill.elapsed() >= Self::REFILL_INTERVAL {
+            self.add_tokens();
+        }
+    }
+
+    /// Writes `data`, waiting for tokens as needed.
+    async fn write<W: AsyncWriteExt + Unpin>(
+        &mut self,
+        w: &mut W,
+        data: &[u8],
+        metrics: &EmulatorMetrics,
+        direction: Direction,
+    ) -> anyhow::Result<()> {
+        let mut sent = 0;
+        while sent < data.len() {
+            // Wait for tokens if rate limiting is enabled
+            if self.rate_limited && self.tokens == 0 {
+                sleep(Self::REFILL_INTERVAL).await;
+                self.add_tokens();
+                continue;
+            }
+
+            let chunk_size = if self.rate_limited {
+                std::cmp::min(data.len() - sent, self.tokens)
+            } else {
+                data.len() - sent
+            };
+
+            w.write_all(&data[sent..sent + chunk_size]).await?;
+            sent += chunk_size;
+
+            // Deduct from token bucket
+            if self.rate_limited {
+                self.tokens = self.tokens.saturating_sub(chunk_size);
+            }
+
+            // Update metrics
+            metrics.record_sent(direction, chunk_size);
+        }
+        Ok(())
+    }
+}
+
+/// Bytes one direction has read but not yet written, mirrored into the
+/// `buffered_bytes` gauge. Whatever is still counted when the copy ends (an
+/// error mid-write) is released on drop.
+struct Buffered(IntGauge, i64);
+
+impl Buffered {
+    fn add(&mut self, bytes: usize) {
+        self.1 += bytes as i64;
+        self.0.add(bytes as i64);
+    }
+
+    fn sub(&mut self, bytes: usize) {
+        self.1 -= bytes as i64;
+        self.0.sub(bytes as i64);
+    }
+}
+
+impl Drop for Buffered {
+    fn drop(&mut self) {
+        self.0.sub(self.1);
+    }
+}
+
+fn next_stall_after(now: Instant, cfg: &LinkParams) -> Instant {
+    if cfg.stall_period_ms > 0 {
+        now + Duration::from_millis(cfg.stall_period_ms)
+    } else {
+        now + Duration::from_secs(3600 * 24) // Far future
+    }
+}
+
 async fn impair_copy<R: AsyncReadExt + Unpin, W: AsyncWriteExt + Unpin>(
     r: &mut R,
     w: &mut W,
-    cfg: &Config,
+    shared: &SharedConfig,
+    dir: Direction,
+    conn_id: u64,
     metrics: Arc<EmulatorMetrics>,
-    direction: &str,
 ) -> anyhow::Result<()> {
-    let mut buf = vec![0u8; 16 * 1024];
-    let mut bucket = cfg.bucket_bytes;
-    let mut last_refill = Instant::now();
-    let refill_interval = Duration::from_millis(10);
-    let bytes_per_interval = if cfg.rate_bps == 0 {
-        usize::MAX
-    } else {
-        std::cmp::max(1, cfg.rate_bps as usize / 100) // 100 intervals per second
-    };
-
-    let mut next_stall = if cfg.stall_period_ms > 0 {
-        Instant::now() + Duration::from_millis(cfg.stall_period_ms)
-    } else {
-        Instant::now() + Duration::from_secs(3600 * 24) // Far future
+    let (mut cfg, mut rng) = {
+        let shared = shared.read().unwrap();
+        (
+            dir.params(&shared).clone(),
+            dir.rng(shared.rng_seed, conn_id),
+        )
     };
+    let mut buffered = Buffered(metrics.buffered_bytes.with_label_values(&[dir.label()]), 0);
+    let mut buf = vec![0u8; 16 * 1024];
+    let mut bucket = TokenBucket::new(&cfg);
+    // A chunk held back to be sent after the next one.
+    let mut held: Option<Vec<u8>> = None;
+    let mut next_stall = next_stall_after(Instant::now(), &cfg);
 
     loop {
-        // Token bucket refill
-        if last_refill.elapsed() >= refill_interval {
-            bucket = std::cmp::min(
-                bucket + bytes_per_interval,
-                cfg.bucket_bytes.max(bytes_per_interval),
-            );
-            last_refill = Instant::now();
-        }
+        bucket.refill();
 
         // Scheduled stall window
         if Instant::now() >= next_stall && cfg.stall_period_ms > 0 {
             if cfg.stall_duration_ms > 0 {
-                tracing::debug!(duration_ms = cfg.stall_duration_ms, "Applying network stall");
+                tracing::debug!(
+                    duration_ms = cfg.stall_duration_ms,
+                    "Applying network stall"
+                );
                 metrics.stall_windows_total.inc();
                 sleep(Duration::from_millis(cfg.stall_duration_ms)).await;
             }
             next_stall += Duration::from_millis(cfg.stall_period_ms);
         }
 
-        let n = r.read(&mut buf).await?;
+        // While a chunk is held, only wait REORDER_HOLD for its successor.
+        let n = match &held {
+            Some(chunk) => match tokio::time::timeout(REORDER_HOLD, r.read(&mut buf)).await {
+                Ok(read) => read?,
+                Err(_) => {
+                    bucket.write(w, chunk, &metrics, dir).await?;
+                    buffered.sub(chunk.len());
+                    held = None;
+                    continue;
+                }
+            },
+            None => r.read(&mut buf).await?,
+        };
+        buffered.add(n);
 
-        // Inject connection reset based on probability
-        if n > 0 && cfg.reset_chance_percent > 0 {
-            let roll = rand::random::<u8>() % 100;
-            if roll < cfg.reset_chance_percent {
-                metrics.resets_injected_total.inc();
-                tracing::warn!(
-                    chance = cfg.reset_chance_percent,
-                    "Injecting connection reset"
-                );
-                return Err(anyhow!("injected connection reset"));
+        // Pick up changes made through the control endpoint, so they apply from
+        // the chunk just read.
+        let latest = dir.params(&shared.read().unwrap()).clone();
+        if latest != cfg {
+            if latest.rate_bps != cfg.rate_bps || latest.bucket_bytes != cfg.bucket_bytes {
+                bucket = TokenBucket::new(&latest);
             }
+            if latest.stall_period_ms != cfg.stall_period_ms {
+                next_stall = next_stall_after(Instant::now(), &latest);
+            }
+            cfg = latest;
+        }
+
+        // Inject connection reset based on probability
+        if n > 0 && roll(&mut rng, cfg.reset_chance_percent) {
+            metrics.resets_injected_total.inc();
+            tracing::warn!(
+                chance = cfg.reset_chance_percent,
+                "Injecting connection reset"
+            );
+            return Err(anyhow!("injected connection reset"));
         }
 
         if n == 0 {
+            if let Some(chunk) = held.take() {
+                bucket.write(w, &chunk, &metrics, dir).await?;
+                buffered.sub(chunk.len());
+            }
             let _ = w.shutdown().await;
             return Ok(());
         }
 
         // Apply latency + jitter
-        let jitter = if cfg.jitter_ms > 0 {
-            rand::random::<u64>() % (cfg.jitter_ms + 1)
-        } else {
-            0
-        };
-        let total_delay = cfg.latency_ms + jitter;
+        let total_delay = sample_delay(&cfg, &mut rng);
 
-        if total_delay > 0 {
+        if !total_delay.is_zero() {
             let delay_start = SystemTime::now();
-            sleep(Duration::from_millis(total_delay)).await;
+            sleep(total_delay).await;
             let actual_delay = delay_start.elapsed().unwrap_or_default().as_secs_f64();
             metrics.latency_histogram.observe(actual_delay);
         }
 
-        // Rate limiting via token bucket
-        let mut sent = 0;
-        while sent < n {
-            // Wait for tokens if rate limiting is enabled
-            if cfg.rate_bps > 0 && bucket == 0 {
-                sleep(refill_interval).await;
-                // Refill happens at the top of the loop, so we must continue here.
-                bucket = std::cmp::min(
-                    bucket + bytes_per_interval,
-                    cfg.bucket_bytes.max(bytes_per_interval),
-                );
-                last_refill = Instant::now();
-                continue;
-            }
-
-            let chunk_size = if cfg.rate_bps == 0 {
-                n - sent
-            } else {
-                std::cmp::min(n - sent, bucket)
-            };
-
-            w.write_all(&buf[sent..sent + chunk_size]).await?;
-            sent += chunk_size;
-
-            // Deduct from token bucket
-            if cfg.rate_bps > 0 {
-                bucket = bucket.saturating_sub(chunk_size);
-            }
+        // Hold this chunk back so the next one overtakes it. The held chunk has
+        // already had its delay; it goes out right after its successor's.
+        let chunk = &buf[..n];
+        if held.is_none() && roll(&mut rng, cfg.reorder_chance_percent) {
+            held = Some(chunk.to_vec());
+            continue;
+        }
 
-            // Update metrics
-            metrics
-                .bytes_transferred_total
-                .with_label_values(&[direction])
-                .inc_by(chunk_size as u64);
+        // Rate limiting via token bucket
+        bucket.write(w, chunk, &metrics, dir).await?;
+        buffered.sub(n);
+        if roll(&mut rng, cfg.dup_chance_percent) {
+            metrics.dups_injected_total.inc();
+            bucket.write(w, chunk, &metrics, dir).await?;
+        }
+        if let Some(earlier) = held.take() {
+            metrics.reorders_injected_total.inc();
+            bucket.write(w, &earlier, &metrics, dir).await?;
+            buffered.sub(earlier.len());
         }
     }
 }
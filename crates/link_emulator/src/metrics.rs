@@ -1,6 +1,47 @@
 // symtex/crates/link_emulator/src/metrics.rs
+use crate::Direction;
 use axum::{response::IntoResponse, routing::get, Router};
-use prometheus::{Encoder, Gauge, Histogram, IntCounter, IntCounterVec, Registry, TextEncoder};
+use prometheus::{
+    Encoder, Gauge, GaugeVec, Histogram, IntCounter, IntCounterVec, IntGaugeVec, Registry,
+    TextEncoder,
+};
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Span `throughput_bps` averages over.
+const THROUGHPUT_WINDOW: Duration = Duration::from_secs(5);
+/// Granularity of the throughput window.
+const THROUGHPUT_SLOT: Duration = Duration::from_millis(100);
+
+/// Bytes sent per [`THROUGHPUT_SLOT`] over the last [`THROUGHPUT_WINDOW`].
+#[derive(Default)]
+struct RateWindow {
+    slots: VecDeque<(Instant, u64)>,
+}
+
+impl RateWindow {
+    fn add(&mut self, now: Instant, bytes: u64) {
+        match self.slots.back_mut() {
+            Some((start, sum)) if now.duration_since(*start) < THROUGHPUT_SLOT => *sum += bytes,
+            _ => self.slots.push_back((now, bytes)),
+        }
+    }
+
+    /// Bytes per second over the window ending at `now`.
+    fn rate(&mut self, now: Instant) -> f64 {
+        while let Some(&(start, _)) = self.slots.front() {
+            if now.duration_since(start) < THROUGHPUT_WINDOW {
+                break;
+            }
+            self.slots.pop_front();
+        }
+        let total: u64 = self.slots.iter().map(|&(_, bytes)| bytes).sum();
+        total as f64 / THROUGHPUT_WINDOW.as_secs_f64()
+    }
+}
 
 pub struct EmulatorMetrics {
     pub registry: Registry,
@@ -10,6 +51,17 @@ pub struct EmulatorMetrics {
     pub latency_histogram: Histogram,
     pub active_connections: Gauge,
     pub stall_windows_total: IntCounter,
+    pub dups_injected_total: IntCounter,
+    pub reorders_injected_total: IntCounter,
+    pub drops_injected_total: IntCounter,
+    /// Bytes per second sent, per direction, averaged over [`THROUGHPUT_WINDOW`].
+    pub throughput_bps: GaugeVec,
+    /// Bytes read but not yet written (delayed, rate-limited or held for
+    /// reordering), per direction.
+    pub buffered_bytes: IntGaugeVec,
+    /// Throughput window per direction, indexed by [`Direction`], each behind
+    /// its own lock so the two directions never wait on each other.
+    windows: Arc<[Mutex<RateWindow>; 2]>,
 }
 
 impl EmulatorMetrics {
@@ -18,8 +70,9 @@ impl EmulatorMetrics {
 
         macro_rules! reg {
             ($m:expr) => {{
-                registry.register(Box::new($m.clone())).unwrap();
-                $m
+                let m = $m;
+                registry.register(Box::new(m.clone())).unwrap();
+                m
             }};
         }
 
@@ -57,17 +110,74 @@ impl EmulatorMetrics {
                 "Total number of injected stall windows"
             )
             .unwrap()),
+            dups_injected_total: reg!(IntCounter::new(
+                "proxy_dups_injected_total",
+                "Total number of chunks sent twice"
+            )
+            .unwrap()),
+            reorders_injected_total: reg!(IntCounter::new(
+                "proxy_reorders_injected_total",
+                "Total number of chunks sent after their successor"
+            )
+            .unwrap()),
+            drops_injected_total: reg!(IntCounter::new(
+                "proxy_drops_injected_total",
+                "Total number of datagrams dropped"
+            )
+            .unwrap()),
+            throughput_bps: reg!(GaugeVec::new(
+                prometheus::Opts::new(
+                    "proxy_throughput_bps",
+                    "Bytes per second sent, averaged over the last 5 seconds"
+                ),
+                &["direction"]
+            )
+            .unwrap()),
+            buffered_bytes: reg!(IntGaugeVec::new(
+                prometheus::Opts::new(
+                    "proxy_buffered_bytes",
+                    "Bytes read but not yet written (delay, rate limit, reorder hold)"
+                ),
+                &["direction"]
+            )
+            .unwrap()),
+            windows: Arc::default(),
             registry,
         }
     }
 
+    /// Counts `bytes` written in `direction`.
+    pub fn record_sent(&self, direction: Direction, bytes: usize) {
+        self.bytes_transferred_total
+            .with_label_values(&[direction.label()])
+            .inc_by(bytes as u64);
+
+        let now = Instant::now();
+        let mut window = self.windows[direction as usize].lock().unwrap();
+        window.add(now, bytes as u64);
+        self.throughput_bps
+            .with_label_values(&[direction.label()])
+            .set(window.rate(now));
+    }
+
     pub fn router(&self) -> Router {
         let reg = self.registry.clone();
+        let windows = self.windows.clone();
+        let throughput = self.throughput_bps.clone();
         Router::new().route(
             "/metrics",
             get(move || {
                 let reg = reg.clone();
+                let windows = windows.clone();
+                let throughput = throughput.clone();
                 async move {
+                    // Let idle directions decay rather than report their last rate.
+                    let now = Instant::now();
+                    for direction in Direction::ALL {
+                        let rate = windows[direction as usize].lock().unwrap().rate(now);
+                        throughput.with_label_values(&[direction.label()]).set(rate);
+                    }
+
                     let mf = reg.gather();
                     let mut buf = Vec::new();
                     TextEncoder::new().encode(&mf, &mut buf).unwrap();
@@ -0,0 +1,446 @@
+//! UDP mode (`EMULATOR_PROTO=udp`): datagram proxying with per-datagram
+//! latency, jitter, drop, duplication and reordering.
+//!
+//! UDP has no connections, so each client source address is a peer with its
+//! own upstream socket, the source of the target's replies. A peer lasts until
+//! [`PEER_IDLE_TIMEOUT`] passes without a datagram either way.
+//!
+//! Delayed datagrams go through a per-direction delay line that sends them in
+//! order, as a serial link would, so jitter alone doesn't reorder them; only
+//! `reorder_chance_percent` does. Rate limits, stalls and resets are TCP-only.
+
+use crate::{
+    control::SharedConfig, metrics::EmulatorMetrics, roll, sample_delay, Direction, LinkParams,
+    REORDER_HOLD,
+};
+use anyhow::{anyhow, Context};
+use prometheus::IntGauge;
+use rand::rngs::StdRng;
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+use tokio::{
+    net::UdpSocket,
+    sync::mpsc,
+    time::{interval, sleep_until, Duration, Instant},
+};
+
+/// How long a peer may stay silent, in both directions, before it is dropped.
+const PEER_IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Datagrams queued for a peer's task; more are dropped, as a full socket would.
+const PEER_QUEUE: usize = 1024;
+
+/// Largest UDP payload.
+const MAX_DATAGRAM: usize = 65_535;
+
+/// A client source address seen on the listening socket.
+struct Peer {
+    tx: mpsc::Sender<Vec<u8>>,
+    last_seen: Arc<Mutex<Instant>>,
+}
+
+pub async fn run(
+    listen: &str,
+    target: &str,
+    shared: SharedConfig,
+    metrics: Arc<EmulatorMetrics>,
+) -> anyhow::Result<()> {
+    let target_addr = tokio::net::lookup_host(target)
+        .await?
+        .next()
+        .ok_or_else(|| anyhow!("{target}: no address"))?;
+    let listener = Arc::new(UdpSocket::bind(listen).await?);
+    tracing::info!(addr = listen, target = target, "Link emulator listening (UDP)");
+
+    let mut peers: HashMap<SocketAddr, Peer> = HashMap::new();
+    let mut next_peer_id = 0u64;
+    let mut sweep = interval(PEER_IDLE_TIMEOUT / 4);
+    let mut buf = vec![0u8; MAX_DATAGRAM];
+
+    loop {
+        let (n, client_addr) = tokio::select! {
+            recv = listener.recv_from(&mut buf) => recv?,
+            _ = sweep.tick() => {
+                peers.retain(|addr, peer| {
+                    let live = !peer.tx.is_closed()
+                        && peer.last_seen.lock().unwrap().elapsed() < PEER_IDLE_TIMEOUT;
+                    if !live {
+                        tracing::debug!(client = %addr, "UDP peer idle, dropping");
+                    }
+                    live
+                });
+                continue;
+            }
+        };
+
+        let peer = match peers.entry(client_addr) {
+            Entry::Occupied(e) => e.into_mut(),
+            Entry::Vacant(e) => {
+                let peer_id = next_peer_id;
+                next_peer_id += 1;
+                let (tx, rx) = mpsc::channel(PEER_QUEUE);
+                let last_seen = Arc::new(Mutex::new(Instant::now()));
+                let task = PeerTask {
+                    client_addr,
+                    target_addr,
+                    listener: listener.clone(),
+                    shared: shared.clone(),
+                    metrics: metrics.clone(),
+                    peer_id,
+                    last_seen: last_seen.clone(),
+                };
+                tokio::spawn(async move {
+                    task.metrics.connections_total.inc();
+                    task.metrics.active_connections.inc();
+                    let metrics = task.metrics.clone();
+                    if let Err(e) = task.run(rx).await {
+                        tracing::warn!(error = %e, client = %client_addr, "UDP peer ended with error");
+                    }
+                    metrics.active_connections.dec();
+                });
+                e.insert(Peer { tx, last_seen })
+            }
+        };
+        *peer.last_seen.lock().unwrap() = Instant::now();
+        match peer.tx.try_send(buf[..n].to_vec()) {
+            Ok(()) => {}
+            Err(mpsc::error::TrySendError::Full(_)) => {
+                tracing::debug!(client = %client_addr, "UDP peer queue full, dropping datagram");
+            }
+            // The peer's task failed; start over with its next datagram.
+            Err(mpsc::error::TrySendError::Closed(_)) => {
+                peers.remove(&client_addr);
+            }
+        }
+    }
+}
+
+/// Everything one peer's task needs.
+struct PeerTask {
+    client_addr: SocketAddr,
+    target_addr: SocketAddr,
+    listener: Arc<UdpSocket>,
+    shared: SharedConfig,
+    metrics: Arc<EmulatorMetrics>,
+    peer_id: u64,
+    last_seen: Arc<Mutex<Instant>>,
+}
+
+impl PeerTask {
+    /// Relays the datagrams from `rx` to the target and the target's replies to
+    /// the client, until the peer is dropped.
+    async fn run(self, mut rx: mpsc::Receiver<Vec<u8>>) -> anyhow::Result<()> {
+        let bind: SocketAddr = if self.target_addr.is_ipv4() {
+            ([0, 0, 0, 0], 0).into()
+        } else {
+            ([0u16; 8], 0).into()
+        };
+        let upstream = Arc::new(UdpSocket::bind(bind).await?);
+        upstream
+            .connect(self.target_addr)
+            .await
+            .with_context(|| format!("connecting to {}", self.target_addr))?;
+
+        let mut up = DatagramLink::new(
+            Direction::Up,
+            &self,
+            Destination::Upstream(upstream.clone()),
+        );
+        let mut down = DatagramLink::new(
+            Direction::Down,
+            &self,
+            Destination::Client(self.listener.clone(), self.client_addr),
+        );
+        let mut buf = vec![0u8; MAX_DATAGRAM];
+
+        loop {
+            tokio::select! {
+                datagram = rx.recv() => match datagram {
+                    Some(d) => up.push(d, &self.shared, &self.metrics),
+                    None => {
+                        up.release_held();
+                        down.release_held();
+                        return Ok(());
+                    }
+                },
+                recv = upstream.recv(&mut buf) => match recv {
+                    Ok(n) => {
+                        *self.last_seen.lock().unwrap() = Instant::now();
+                        down.push(buf[..n].to_vec(), &self.shared, &self.metrics);
+                    }
+                    // An ICMP port unreachable for an earlier datagram; the
+                    // target may not be up yet.
+                    Err(e) if e.kind() == std::io::ErrorKind::ConnectionRefused => {
+                        tracing::debug!(error = %e, "UDP target unreachable");
+                    }
+                    Err(e) => return Err(e.into()),
+                },
+                _ = up.hold_expired() => up.release_held(),
+                _ = down.hold_expired() => down.release_held(),
+            }
+        }
+    }
+}
+
+/// Where a direction's datagrams are sent.
+enum Destination {
+    /// To the target, from the peer's own socket.
+    Upstream(Arc<UdpSocket>),
+    /// Back to the client, from the listening socket.
+    Client(Arc<UdpSocket>, SocketAddr),
+}
+
+impl Destination {
+    async fn send(&self, datagram: &[u8]) -> std::io::Result<usize> {
+        match self {
+            Destination::Upstream(socket) => socket.send(datagram).await,
+            Destination::Client(socket, addr) => socket.send_to(datagram, addr).await,
+        }
+    }
+}
+
+/// A datagram on the delay line: when it arrived and when it is due.
+struct Scheduled {
+    arrived: Instant,
+    due: Instant,
+    datagram: Vec<u8>,
+}
+
+/// A datagram held back for reordering.
+struct Held {
+    scheduled: Scheduled,
+    /// When it goes out in order if no successor has overtaken it.
+    release_at: Instant,
+}
+
+/// What the impairments do to one datagram.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Fate {
+    Dropped,
+    /// Due `delay` after arrival. A `held` datagram waits for its successor to
+    /// overtake it; a `duplicated` one is followed by a copy.
+    Delivered {
+        delay: Duration,
+        held: bool,
+        duplicated: bool,
+    },
+}
+
+impl Fate {
+    /// Draws the fate of the next datagram from `rng`: drop, then delay, then
+    /// reorder, then duplicate. Reordering is only drawn while nothing is
+    /// `holding`, and a held datagram is never duplicated.
+    fn draw(cfg: &LinkParams, rng: &mut StdRng, holding: bool) -> Self {
+        if roll(rng, cfg.drop_chance_percent) {
+            return Fate::Dropped;
+        }
+        let delay = sample_delay(cfg, rng);
+        let held = !holding && roll(rng, cfg.reorder_chance_percent);
+        let duplicated = !held && roll(rng, cfg.dup_chance_percent);
+        Fate::Delivered {
+            delay,
+            held,
+            duplicated,
+        }
+    }
+}
+
+/// The impairments of one direction of a peer.
+struct DatagramLink {
+    dir: Direction,
+    rng: StdRng,
+    buffered: IntGauge,
+    held: Option<Held>,
+    line: mpsc::UnboundedSender<Scheduled>,
+}
+
+impl DatagramLink {
+    /// Starts the delay line sending to `dest`. It stops once the link is
+    /// dropped and the line has drained.
+    fn new(dir: Direction, peer: &PeerTask, dest: Destination) -> Self {
+        let rng = dir.rng(peer.shared.read().unwrap().rng_seed, peer.peer_id);
+        let buffered = peer
+            .metrics
+            .buffered_bytes
+            .with_label_values(&[dir.label()]);
+        let (line, mut rx) = mpsc::unbounded_channel::<Scheduled>();
+
+        let metrics = peer.metrics.clone();
+        let gauge = buffered.clone();
+        tokio::spawn(async move {
+            while let Some(s) = rx.recv().await {
+                sleep_until(s.due).await;
+                gauge.sub(s.datagram.len() as i64);
+                match dest.send(&s.datagram).await {
+                    Ok(n) => metrics.record_sent(dir, n),
+                    Err(e) => tracing::debug!(error = %e, "UDP send failed"),
+                }
+                metrics
+                    .latency_histogram
+                    .observe(s.arrived.elapsed().as_secs_f64());
+            }
+        });
+
+        Self {
+            dir,
+            rng,
+            buffered,
+            held: None,
+            line,
+        }
+    }
+
+    /// Applies this direction's current impairments to one datagram.
+    fn push(&mut self, datagram: Vec<u8>, shared: &SharedConfig, metrics: &EmulatorMetrics) {
+        let cfg: LinkParams = self.dir.params(&shared.read().unwrap()).clone();
+
+        let fate = Fate::draw(&cfg, &mut self.rng, self.held.is_some());
+        let (delay, held, duplicated) = match fate {
+            Fate::Dropped => {
+                metrics.drops_injected_total.inc();
+                return;
+            }
+            Fate::Delivered {
+                delay,
+                held,
+                duplicated,
+            } => (delay, held, duplicated),
+        };
+
+        let arrived = Instant::now();
+        let due = arrived + delay;
+
+        let scheduled = Scheduled {
+            arrived,
+            due,
+            datagram,
+        };
+        self.buffered.add(scheduled.datagram.len() as i64);
+
+        // Hold this datagram back so the next one overtakes it.
+        if held {
+            self.held = Some(Held {
+                scheduled,
+                release_at: arrived + REORDER_HOLD,
+            });
+            return;
+        }
+
+        if duplicated {
+            metrics.dups_injected_total.inc();
+            self.buffered.add(scheduled.datagram.len() as i64);
+            let _ = self.line.send(Scheduled {
+                datagram: scheduled.datagram.clone(),
+                ..scheduled
+            });
+        }
+        let _ = self.line.send(scheduled);
+        // The held datagram goes out right after its successor.
+        if let Some(mut earlier) = self.held.take() {
+            metrics.reorders_injected_total.inc();
+            earlier.scheduled.due = earlier.scheduled.due.max(due);
+            let _ = self.line.send(earlier.scheduled);
+        }
+    }
+
+    /// Resolves when the held datagram's wait for a successor is over; never
+    /// while nothing is held.
+    async fn hold_expired(&self) {
+        match &self.held {
+            Some(held) => sleep_until(held.release_at).await,
+            None => std::future::pending().await,
+        }
+    }
+
+    /// Sends the held datagram in order.
+    fn release_held(&mut self) {
+        if let Some(held) = self.held.take() {
+            let _ = self.line.send(held.scheduled);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lossy() -> LinkParams {
+        LinkParams {
+            latency_ms: 20,
+            jitter_ms: 15,
+            rate_bps: 0,
+            bucket_bytes: 0,
+            stall_period_ms: 0,
+            stall_duration_ms: 0,
+            reset_chance_percent: 0,
+            dup_chance_percent: 20,
+            reorder_chance_percent: 25,
+            drop_chance_percent: 30,
+        }
+    }
+
+    /// The fates of `n` datagrams on a link whose held datagrams are all
+    /// overtaken by their successors, as in a steady stream.
+    fn fates(dir: Direction, seed: u64, conn_id: u64, n: usize) -> Vec<Fate> {
+        let cfg = lossy();
+        let mut rng = dir.rng(Some(seed), conn_id);
+        let mut holding = false;
+        (0..n)
+            .map(|_| {
+                let fate = Fate::draw(&cfg, &mut rng, holding);
+                if let Fate::Delivered { held, .. } = fate {
+                    holding = held;
+                }
+                fate
+            })
+            .collect()
+    }
+
+    #[test]
+    fn same_seed_impairs_the_same_datagrams() {
+        let run = fates(Direction::Up, 42, 3, 1_000);
+        assert_eq!(run, fates(Direction::Up, 42, 3, 1_000));
+
+        // Every kind of impairment actually happened, and no datagram was
+        // held while another already was.
+        assert!(run.contains(&Fate::Dropped));
+        let delivered: Vec<(bool, bool)> = run
+            .iter()
+            .filter_map(|fate| match *fate {
+                Fate::Delivered {
+                    held, duplicated, ..
+                } => Some((held, duplicated)),
+                Fate::Dropped => None,
+            })
+            .collect();
+        assert!(delivered.iter().any(|&(held, _)| held));
+        assert!(delivered.iter().any(|&(_, duplicated)| duplicated));
+        assert!(delivered.windows(2).all(|w| !(w[0].0 && w[1].0)));
+
+        // The other direction, another peer and another seed each get their
+        // own stream.
+        assert_ne!(run, fates(Direction::Down, 42, 3, 1_000));
+        assert_ne!(run, fates(Direction::Up, 42, 4, 1_000));
+        assert_ne!(run, fates(Direction::Up, 43, 3, 1_000));
+    }
+
+    #[test]
+    fn holding_skips_the_reorder_draw() {
+        let cfg = LinkParams {
+            drop_chance_percent: 0,
+            dup_chance_percent: 0,
+            reorder_chance_percent: 100,
+            ..lossy()
+        };
+        let mut rng = Direction::Up.rng(Some(7), 0);
+        let draw = |rng: &mut StdRng, holding| match Fate::draw(&cfg, rng, holding) {
+            Fate::Delivered { held, .. } => held,
+            Fate::Dropped => panic!("nothing is dropped"),
+        };
+        assert!(draw(&mut rng, false));
+        assert!(!draw(&mut rng, true));
+    }
+}
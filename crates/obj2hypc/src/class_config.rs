@@ -0,0 +1,150 @@
+//! `--class-config`: SMC1 classes defined in a JSON file instead of the built-in
+//! rules in `classify_tags`.
+//!
+//! ```json
+//! {
+//!   "fallback_to_builtin": true,
+//!   "classes": [
+//!     { "id": 10, "name": "bridge", "precedence": 170, "width_m": 8.0,
+//!       "tags": { "bridge": "yes", "highway": "*" } },
+//!     { "id": 11, "name": "coastline", "precedence": 210, "width_m": 6.0,
+//!       "tags": { "natural": "coastline" } },
+//!     { "id": 5, "name": "water", "precedence": 200, "area": true,
+//!       "tags": { "natural": "water|bay" } }
+//!   ]
+//! }
+//! ```
+//!
+//! Classes are tried in file order and the first whose `tags` all match wins; a
+//! value of `*` matches any value and `a|b` matches either. Unmatched elements
+//! fall back to the built-in rules only if `fallback_to_builtin` is set.
+
+use crate::{classify_tags, default_class_precedence, parse_width_m, ClassPrecedence};
+use anyhow::{bail, Context, Result};
+use std::{collections::BTreeMap, fs::File, io::BufReader, path::Path};
+
+#[derive(Debug, serde::Deserialize)]
+pub(crate) struct ClassConfig {
+    classes: Vec<ClassRule>,
+    /// Classify elements no rule matches with the built-in rules.
+    #[serde(default)]
+    fallback_to_builtin: bool,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct ClassRule {
+    /// SMC1 class id; 0 is reserved for "unknown".
+    id: u8,
+    /// For logs only; SMC1 palettes carry ids, not names.
+    #[serde(default)]
+    name: Option<String>,
+    precedence: u8,
+    /// Line width in metres when the element has no usable `width` tag. Required
+    /// for line classes, ignored for areas.
+    #[serde(default)]
+    width_m: f32,
+    /// Fill closed ways and multipolygons instead of drawing lines.
+    #[serde(default)]
+    area: bool,
+    /// Tag predicates that must all hold.
+    tags: BTreeMap<String, String>,
+}
+
+impl ClassRule {
+    fn matches(&self, tags: &[(&str, &str)]) -> bool {
+        self.tags.iter().all(|(key, want)| {
+            tags.iter()
+                .find(|(k, _)| k == key)
+                .is_some_and(|(_, v)| want == "*" || want.split('|').any(|w| w == *v))
+        })
+    }
+}
+
+impl ClassConfig {
+    pub(crate) fn load(path: &Path) -> Result<Self> {
+        let file = File::open(path).with_context(|| format!("opening {}", path.display()))?;
+        let config: Self = serde_json::from_reader(BufReader::new(file))
+            .with_context(|| format!("parsing {}", path.display()))?;
+
+        for (i, rule) in config.classes.iter().enumerate() {
+            let label = rule.name.as_deref().unwrap_or("unnamed");
+            if rule.id == 0 {
+                bail!("class {} ({}): id 0 is reserved for unknown", i, label);
+            }
+            if rule.tags.is_empty() {
+                bail!("class {} ({}): no tag predicates", i, label);
+            }
+            let has_width = rule.width_m.is_finite() && rule.width_m > 0.0;
+            if !rule.area && !has_width {
+                bail!(
+                    "class {} ({}): line classes need a positive width_m",
+                    i,
+                    label
+                );
+            }
+        }
+        Ok(config)
+    }
+
+    /// Class id, line width and whether it is an area, like `classify_tags`.
+    pub(crate) fn classify(&self, tags: &[(&str, &str)]) -> Option<(u8, f32, bool)> {
+        let rule = self.classes.iter().find(|rule| rule.matches(tags));
+        match rule {
+            Some(rule) if rule.area => Some((rule.id, 0.0, true)),
+            Some(rule) => {
+                let width_m = tags
+                    .iter()
+                    .find(|(k, _)| *k == "width")
+                    .and_then(|(_, v)| parse_width_m(v))
+                    .filter(|w| w.is_finite() && *w > 0.0)
+                    .unwrap_or(rule.width_m);
+                Some((rule.id, width_m, false))
+            }
+            None if self.fallback_to_builtin => classify_tags(tags),
+            None => None,
+        }
+    }
+
+    /// Paint precedence: the configured classes over the built-in table when
+    /// falling back to it, else over an empty one.
+    pub(crate) fn precedence_table(&self) -> ClassPrecedence {
+        let mut t = if self.fallback_to_builtin {
+            default_class_precedence()
+        } else {
+            [0u8; 256]
+        };
+        for rule in &self.classes {
+            t[rule.id as usize] = rule.precedence;
+        }
+        t
+    }
+
+    /// Ids this config can assign, plus the built-in ones when falling back.
+    pub(crate) fn class_ids(&self) -> Vec<u8> {
+        let mut ids: Vec<u8> = self.classes.iter().map(|rule| rule.id).collect();
+        if self.fallback_to_builtin {
+            ids.extend(0..=9);
+        }
+        ids.sort_unstable();
+        ids.dedup();
+        ids
+    }
+
+    /// Whether the built-in `osmium tags-filter` expressions are still needed.
+    pub(crate) fn uses_builtin(&self) -> bool {
+        self.fallback_to_builtin
+    }
+
+    /// `osmium tags-filter` expressions keeping every element a rule could match.
+    /// Rules with several predicates are keyed on each, which over-selects a little.
+    pub(crate) fn osmium_filters(&self) -> Vec<String> {
+        self.classes
+            .iter()
+            .flat_map(|rule| &rule.tags)
+            .map(|(key, value)| match value.as_str() {
+                "*" => format!("nwr/{}", key),
+                values => format!("nwr/{}={}", key, values.replace('|', ",")),
+            })
+            .collect()
+    }
+}
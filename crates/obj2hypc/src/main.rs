@@ -5,22 +5,30 @@ use rayon::prelude::*;
 use std::{
     collections::{BTreeMap, HashMap},
     fs::{self, File},
-    io::{BufRead, BufReader, Read},
+    io::{BufRead, BufReader, BufWriter, Read, Write},
     path::{Path, PathBuf},
-    sync::Arc,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
     time::Instant,
 };
 use walkdir::WalkDir;
 
+mod class_config;
+mod ply;
+
+use class_config::ClassConfig;
+
 // OSM / geometry utilities
-use osmpbf::{Element, ElementReader, Way};
+use osmpbf::{Element, ElementReader, RelMemberType};
 use rstar::{RTree, RTreeObject, AABB};
 use smallvec::SmallVec;
 
 // HYPC writer + math
 use hypc::{
-    geodetic_to_ecef, quantize_units, smc1_encode_rle, GeoExtentQ7, HypcTile, Smc1Chunk,
-    Smc1CoordSpace, Smc1Encoding,
+    geodetic_to_ecef, quantize_units, smc1_encode_rle, GeoExtentQ7, HypcTile, PointsCodec,
+    PointsEncoding, Smc1Chunk, Smc1CoordSpace, Smc1Encoding,
 };
 
 /// How to interpret incoming OBJ vertex triples.
@@ -59,13 +67,33 @@ struct Args {
     output_dir: String,
 
     /// Units per meter for HYPC integer lattice (1000 = millimetres)
-    #[arg(long, default_value_t = 1000)]
+    #[arg(
+        long,
+        default_value_t = 1000,
+        value_parser = clap::value_parser!(u32).range(1..=hypc::MAX_UNITS_PER_METER as i64)
+    )]
     units_per_meter: u32,
 
     /// Either auto-detect or force the input coordinate system of OBJ vertices.
     #[arg(long, value_enum, default_value_t = InputCs::Auto)]
     input_cs: InputCs,
 
+    /// Seed for the vertex sample used to auto-detect the input coordinate system.
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+
+    /// Process tiles one at a time in sorted prefix order, so logs and outputs are
+    /// reproducible run to run. Implies `--jobs 1`.
+    #[arg(long, default_value_t = false)]
+    single_thread: bool,
+
+    /// Worker threads for the per-tile loops; 0 uses every core. Each worker holds
+    /// one mesh at a time, so this also bounds peak memory. OSM overlay building
+    /// (--osm-pbf) is sequential and finishes before the tile loop starts, so it
+    /// is unaffected.
+    #[arg(long, default_value_t = 0)]
+    jobs: usize,
+
     #[arg(long, default_value_t = false)]
     overwrite: bool,
 
@@ -73,14 +101,51 @@ struct Args {
     #[arg(long)]
     feature_index: Option<String>,
 
-    /// If multiple matches exist, prefer .zip over .obj
-    #[arg(long, default_value_t = true)]
+    /// Only process tiles intersecting `lon_min,lat_min,lon_max,lat_max` (degrees).
+    /// With --feature-index, tiles are skipped by their feature bbox before any file
+    /// is read. Otherwise only geodetic input can be filtered, and only after the
+    /// mesh is parsed for its bounds, which is much slower.
+    #[arg(long, value_parser = parse_bbox_filter)]
+    bbox_filter: Option<GeoBboxDeg>,
+
+    /// If multiple matches exist, prefer .zip over .obj (`--prefer-zip false` to
+    /// prefer the loose .obj/.ply instead)
+    #[arg(
+        long,
+        default_value_t = true,
+        action = clap::ArgAction::Set,
+        num_args = 0..=1,
+        default_missing_value = "true"
+    )]
     prefer_zip: bool,
 
     /// Write the optional GEOT footer with bbox in CRS:84 (deg, 1e-7 ticks)
     #[arg(long, default_value_t = true)]
     write_geot: bool,
 
+    /// Append a CRC32 chunk so truncated or corrupted tiles are rejected on load
+    #[arg(long, default_value_t = false)]
+    write_crc: bool,
+
+    /// Store points Morton-sorted as delta+zigzag varints instead of raw i32 triples.
+    /// Much smaller, but reorders the points within each tile.
+    #[arg(long, default_value_t = false)]
+    delta_points: bool,
+
+    /// Deflate-compress the points block (after any delta encoding).
+    #[arg(long, default_value_t = false)]
+    deflate_points: bool,
+
+    /// Downsample to one point (the centroid) per occupied voxel of this size in ECEF
+    /// metres, before quantization. 0 disables.
+    #[arg(long, default_value_t = 0.0)]
+    voxel_size_m: f64,
+
+    /// Also read normals (OBJ `vn` matched to vertices by index, PLY `nx/ny/nz`).
+    /// HYPC has no normal channel yet, so they are checked and counted but not written.
+    #[arg(long, default_value_t = false)]
+    read_normals: bool,
+
     // === SMC1 additions ===
     /// Optional OSM .pbf path for semantic overlays (roads/buildings/water/parks/etc.)
     #[arg(long)]
@@ -94,10 +159,35 @@ struct Args {
     #[arg(long, default_value_t = true)]
     write_smc1: bool,
 
+    /// Sample the SMC1 mask under every point and store the classes as per-point
+    /// labels, for readers that can't sample SMC1 themselves (the sim). Uses the
+    /// viewer's lookup (`HypcTile::smc1_point_labels`), so the labels match what it
+    /// shows. Tiles without a mask get no labels.
+    #[arg(long, default_value_t = false)]
+    bake_labels: bool,
+
+    /// Leave the SMC1 chunk out of tiles whose labels were baked.
+    #[arg(long, default_value_t = false, requires = "bake_labels")]
+    drop_smc1: bool,
+
     /// Compress SMC1 with internal RLE (no external deps); if false -> raw bytes
     #[arg(long, default_value_t = true)]
     smc1_compress: bool,
 
+    /// Rasterize SMC1 areas by coverage (4×4 samples per pixel, painted at ≥50%)
+    /// instead of one sample at the pixel centre: smoother edges, and areas smaller
+    /// than a pixel still mark their best-covered pixel. Slower.
+    #[arg(long, default_value_t = false)]
+    smc1_aa: bool,
+
+    /// Also write `<tile>.bheight` next to each tile with an SMC1 mask: a raw
+    /// `sem_grid`×`sem_grid` u8 grid, row-major with the same pixel mapping as SMC1,
+    /// holding building heights in whole metres (saturating at 255, 0 = no building or
+    /// unknown). Heights come from OSM `height` (metres, or `ft`), else
+    /// `building:levels` × 3 m.
+    #[arg(long, default_value_t = false)]
+    write_building_heights: bool,
+
     /// Expand each tile bbox by this margin when retaining nodes (meters).
     #[arg(long, default_value_t = 50.0)]
     osm_margin_m: f64,
@@ -109,6 +199,48 @@ struct Args {
     /// Try to run 'osmium extract' + 'osmium tags-filter' to shrink the PBF first.
     #[arg(long, default_value_t = false)]
     osm_prefilter: bool,
+
+    /// Quantize every tile against this ECEF anchor `x,y,z` (metres) instead of its own
+    /// centroid, so a batch shares one reference frame. UPM is still reduced per tile
+    /// when offsets from the anchor would overflow i32.
+    #[arg(long, value_parser = parse_anchor_ecef)]
+    anchor_ecef: Option<[f64; 3]>,
+
+    /// Use one units_per_meter for the whole batch (two-pass: size every tile first,
+    /// then pick the largest UPM that fits all of them). Recorded in catalog.json.
+    #[arg(long, default_value_t = false)]
+    fixed_upm: bool,
+
+    /// Override SMC1 paint precedence per class, e.g. `paths=85,parking=90` (higher
+    /// wins; defaults run from buildings/water 200 down to parking 80). Stored in the
+    /// SMC1 palette.
+    #[arg(long, value_delimiter = ',', value_parser = parse_precedence_override)]
+    class_precedence: Vec<(u8, u8)>,
+
+    /// JSON file of SMC1 classes (id, precedence, line width, OSM tag predicates) that
+    /// replaces the built-in OSM classification; see `class_config.rs` for the format.
+    /// Its ids and precedences are written to the SMC1 palette. `--class-precedence`
+    /// still applies on top.
+    #[arg(long)]
+    class_config: Option<PathBuf>,
+
+    /// Also write `agent_points.bin`, a legacy flat export (u64 LE point count, then
+    /// f32 LE ECEF xyz per point) for tools built on the old agent format; `sim_agent`
+    /// itself now loads the HYPC tiles. Point IDs are the run's tiles concatenated in
+    /// file-name order, as `sim_agent` numbers them, with each tile's first ID
+    /// recorded in catalog.json.
+    #[arg(long, default_value_t = false)]
+    agent_format: bool,
+
+    /// Where to write the per-tile manifest (prefix, file, point count, UPM, anchor,
+    /// GEOT bbox, SMC1, labels) of the tiles written by this run. Default:
+    /// `<output-dir>/manifest.json`.
+    #[arg(long)]
+    manifest: Option<String>,
+
+    /// Don't write the tile manifest.
+    #[arg(long, default_value_t = false, conflicts_with = "manifest")]
+    no_manifest: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -124,13 +256,48 @@ struct GeoJsonRoot {
 
 #[derive(Debug, serde::Deserialize)]
 struct Feature {
-    geometry: Geometry,
+    geometry: Option<Geometry>,
     properties: Properties,
 }
 
+/// A GeoJSON position: `[lon, lat]` with an optional altitude, which is ignored.
+type Position = Vec<f64>;
+
 #[derive(Debug, serde::Deserialize)]
-struct Geometry {
-    coordinates: Vec<Vec<[f64; 2]>>,
+#[serde(tag = "type")]
+enum Geometry {
+    Point {
+        coordinates: Position,
+    },
+    LineString {
+        coordinates: Vec<Position>,
+    },
+    Polygon {
+        coordinates: Vec<Vec<Position>>,
+    },
+    MultiPolygon {
+        coordinates: Vec<Vec<Vec<Position>>>,
+    },
+    /// Any other geometry type; such features get no bbox.
+    #[serde(other)]
+    Unsupported,
+}
+
+impl Geometry {
+    /// The positions that bound the geometry: all of a Point's or LineString's,
+    /// and the outer ring of a Polygon or of each MultiPolygon part.
+    fn bounding_positions(&self) -> Vec<&Position> {
+        match self {
+            Geometry::Point { coordinates } => vec![coordinates],
+            Geometry::LineString { coordinates } => coordinates.iter().collect(),
+            Geometry::Polygon { coordinates } => coordinates.iter().take(1).flatten().collect(),
+            Geometry::MultiPolygon { coordinates } => coordinates
+                .iter()
+                .flat_map(|poly| poly.iter().take(1).flatten())
+                .collect(),
+            Geometry::Unsupported => Vec::new(),
+        }
+    }
 }
 
 #[derive(Debug, serde::Deserialize)]
@@ -146,16 +313,34 @@ struct GeoBboxDeg {
     lat_max: f64,
 }
 
-fn bbox_from_polygon_deg(poly: &Geometry) -> GeoBboxDeg {
-    // The first ring is the outer boundary of the polygon.
-    let ring = &poly.coordinates[0];
+impl GeoBboxDeg {
+    /// True if the two boxes overlap or touch, compared at GEOT precision.
+    fn intersects(&self, other: &GeoBboxDeg) -> bool {
+        self.to_q7().intersects(&other.to_q7())
+    }
+
+    fn to_q7(self) -> GeoExtentQ7 {
+        GeoExtentQ7::from_deg(self.lon_min, self.lon_max, self.lat_min, self.lat_max)
+    }
+}
+
+/// The lon/lat bbox of a feature geometry. Fails for unsupported types, and for
+/// geometries without finite coordinates or with a zero-area extent (a Point, a
+/// straight north-south line), which can't anchor a semantic mask.
+fn bbox_from_geometry_deg(geom: &Geometry) -> Result<GeoBboxDeg> {
+    if matches!(geom, Geometry::Unsupported) {
+        anyhow::bail!("unsupported geometry type");
+    }
 
     // Initialise the extents.
     let (mut min_lon, mut min_lat) = (f64::INFINITY, f64::INFINITY);
     let (mut max_lon, mut max_lat) = (f64::NEG_INFINITY, f64::NEG_INFINITY);
 
     // Walk all vertices, updating the bounds for finite coordinates.
-    for &[lon, lat] in ring {
+    for pos in geom.bounding_positions() {
+        let &[lon, lat, ..] = pos.as_slice() else {
+            continue;
+        };
         if lon.is_finite() && lat.is_finite() {
             if lon < min_lon {
                 min_lon = lon;
@@ -172,12 +357,19 @@ fn bbox_from_polygon_deg(poly: &Geometry) -> GeoBboxDeg {
         }
     }
 
-    GeoBboxDeg {
+    if min_lon > max_lon || min_lat > max_lat {
+        anyhow::bail!("no finite coordinates");
+    }
+    if min_lon == max_lon || min_lat == max_lat {
+        anyhow::bail!("zero-area extent");
+    }
+
+    Ok(GeoBboxDeg {
         lon_min: min_lon,
         lat_min: min_lat,
         lon_max: max_lon,
         lat_max: max_lat,
-    }
+    })
 }
 
 fn load_feature_index(path: &str) -> anyhow::Result<Vec<WorkItem>> {
@@ -198,8 +390,19 @@ fn load_feature_index(path: &str) -> anyhow::Result<Vec<WorkItem>> {
                 .to_string_lossy()
                 .into_owned();
 
-            // Compute the bounding box from the geometry.
-            let bbox = Some(bbox_from_polygon_deg(&feature.geometry));
+            // Compute the bounding box from the geometry; a feature without a usable
+            // one is still converted, just without GEOT-from-bbox or SMC1.
+            let bbox = match feature.geometry.as_ref().map(bbox_from_geometry_deg) {
+                Some(Ok(bbox)) => Some(bbox),
+                Some(Err(err)) => {
+                    warn!("Feature {}: {:#}; converting without a bbox", prefix, err);
+                    None
+                }
+                None => {
+                    warn!("Feature {}: no geometry; converting without a bbox", prefix);
+                    None
+                }
+            };
 
             WorkItem { prefix, bbox }
         })
@@ -214,12 +417,13 @@ struct LocalIndex {
     names: BTreeMap<String, Vec<PathBuf>>,
 }
 
-fn build_local_index(input_dir: &str) -> LocalIndex {
+fn build_local_index(input_dir: &str, prefer_zip: bool) -> LocalIndex {
     let mut index = LocalIndex::default();
 
     // Walk the directory tree, following symlinks, and collect only regular files.
     for entry in WalkDir::new(input_dir)
         .follow_links(true)
+        .sort_by_file_name()
         .into_iter()
         .filter_map(Result::ok)
     {
@@ -237,8 +441,8 @@ fn build_local_index(input_dir: &str) -> LocalIndex {
             .map(|s| s.to_ascii_lowercase())
             .unwrap_or_default();
 
-        // We're only interested in OBJ and ZIP files.
-        if ext != "obj" && ext != "zip" {
+        // We're only interested in OBJ, PLY and ZIP files.
+        if ext != "obj" && ext != "ply" && ext != "zip" {
             continue;
         }
 
@@ -256,17 +460,14 @@ fn build_local_index(input_dir: &str) -> LocalIndex {
             .or_default()
             .push(path.clone());
 
-        // Maintain an exact‑match map, preferring `.zip` over `.obj`.
+        // Maintain an exact‑match map, ranked by `ext_rank` and then the smaller
+        // path, so the winner doesn't depend on walk order.
         index
             .exact
             .entry(stem.clone())
             .and_modify(|existing| {
-                let existing_ext = existing
-                    .extension()
-                    .and_then(|s| s.to_str())
-                    .unwrap_or("");
-                // Replace a stored `.obj` with a `.zip` if we encounter one.
-                if existing_ext.eq_ignore_ascii_case("obj") && ext == "zip" {
+                let rank = |p| ext_rank(p, prefer_zip);
+                if (rank(&path), &path) < (rank(existing), &*existing) {
                     *existing = path.clone();
                 }
             })
@@ -276,6 +477,22 @@ fn build_local_index(input_dir: &str) -> LocalIndex {
     index
 }
 
+/// Exact-match preference of an input file by extension (lower wins): `.zip`,
+/// `.obj`, `.ply` with `prefer_zip`, otherwise `.obj`, `.ply`, `.zip`.
+fn ext_rank(path: &Path, prefer_zip: bool) -> u8 {
+    let ext = path
+        .extension()
+        .and_then(|s| s.to_str())
+        .unwrap_or("")
+        .to_ascii_lowercase();
+    match (ext.as_str(), prefer_zip) {
+        ("zip", true) => 0,
+        ("zip", false) => 3,
+        ("obj", _) => 1,
+        _ => 2,
+    }
+}
+
 fn resolve_by_prefix(idx: &LocalIndex, prefix: &str, prefer_zip: bool) -> Option<PathBuf> {
     // 1. Exact match – the fast path.
     if let Some(path) = idx.exact.get(prefix) {
@@ -328,14 +545,77 @@ fn tilekey_from_prefix(prefix: &str) -> [u8; 32] {
     key
 }
 
-/// Read the raw vertex triples from an OBJ file (or any `Read` source).
-fn parse_obj_vertices<R: Read>(reader: R) -> Result<Vec<[f64; 3]>> {
-    let mut vertices = Vec::new();
+/// Vertex records read from an OBJ or PLY file, in file order.
+struct MeshVertices {
+    xyz: Vec<[f64; 3]>,
+    /// Per-vertex color, present if any vertex carried one. Vertices without
+    /// a color are white.
+    colors: Option<Vec<[u8; 3]>>,
+    /// Per-vertex normals, if requested and one was given per vertex.
+    normals: Option<Vec<[f32; 3]>>,
+}
+
+impl MeshVertices {
+    /// Keeps only the vertices with a finite position, along with their color
+    /// and normal.
+    fn finite(
+        xyz: Vec<[f64; 3]>,
+        colors: Option<Vec<[u8; 3]>>,
+        normals: Option<Vec<[f32; 3]>>,
+    ) -> Self {
+        let finite: Vec<bool> = xyz
+            .iter()
+            .map(|p| p.iter().all(|c| c.is_finite()))
+            .collect();
+        fn keep<T>(v: Vec<T>, finite: &[bool]) -> Vec<T> {
+            v.into_iter()
+                .zip(finite)
+                .filter_map(|(v, &ok)| ok.then_some(v))
+                .collect()
+        }
+
+        Self {
+            colors: colors.map(|c| keep(c, &finite)),
+            normals: normals.map(|n| keep(n, &finite)),
+            xyz: keep(xyz, &finite),
+        }
+    }
+}
+
+/// One OBJ record of interest.
+enum ObjRecord {
+    /// A `v` record: position and the optional color suffix, as written.
+    Vertex([f64; 3], Option<[f64; 3]>),
+    /// A `vn` record.
+    Normal([f32; 3]),
+}
 
+/// Calls `f` for each `v` record of an OBJ source, in file order, and for
+/// each `vn` record if `read_normals` is set.
+fn for_each_obj_record<R: Read>(
+    reader: R,
+    read_normals: bool,
+    mut f: impl FnMut(ObjRecord),
+) -> Result<()> {
     for line_result in BufReader::new(reader).lines() {
         let line = line_result?;
         let trimmed = line.trim();
 
+        // Normals are "vn x y z", matched to the i-th "v" record.
+        if read_normals && trimmed.starts_with("vn ") {
+            let n: Vec<f32> = trimmed
+                .split_whitespace()
+                .skip(1)
+                .take(3)
+                .map(str::parse)
+                .collect::<Result<_, _>>()?;
+            if n.len() != 3 {
+                anyhow::bail!("Normal with {} components: {}", n.len(), trimmed);
+            }
+            f(ObjRecord::Normal([n[0], n[1], n[2]]));
+            continue;
+        }
+
         // OBJ vertex records begin with "v ".
         if !trimmed.starts_with("v ") {
             continue;
@@ -362,13 +642,123 @@ fn parse_obj_vertices<R: Read>(reader: R) -> Result<Vec<[f64; 3]>> {
             .context("Missing z coordinate")?
             .parse()?;
 
-        // Store only finite triples.
-        if x.is_finite() && y.is_finite() && z.is_finite() {
-            vertices.push([x, y, z]);
-        }
+        // Three more values are a color; a lone fourth is the homogeneous w.
+        let rest: Vec<&str> = parts.collect();
+        let color = if rest.len() >= 3 {
+            Some([rest[0].parse()?, rest[1].parse()?, rest[2].parse()?])
+        } else {
+            None
+        };
+
+        f(ObjRecord::Vertex([x, y, z], color));
+    }
+    Ok(())
+}
+
+/// Converts one OBJ vertex color to 8 bits, reading it as 0–255 if `scale` is
+/// 1 or as 0–1 if it is 255. A missing color is white.
+fn obj_color_to_u8(color: Option<[f64; 3]>, scale: f64) -> [u8; 3] {
+    match color {
+        Some(c) => c.map(|v| (v * scale).round().clamp(0.0, 255.0) as u8),
+        None => [255; 3],
     }
+}
+
+/// Read the vertex triples from an OBJ file (or any `Read` source), with the
+/// optional color suffix and, if `read_normals` is set, the `vn` records.
+///
+/// Colors are taken as 0–1 floats unless some component exceeds 1, in which
+/// case the whole file is read as 0–255. Vertices with a non-finite position
+/// are dropped together with their color and normal.
+fn parse_obj_vertices<R: Read>(reader: R, read_normals: bool) -> Result<MeshVertices> {
+    let mut xyz = Vec::new();
+    let mut rgb: Vec<Option<[f64; 3]>> = Vec::new();
+    let mut normals = Vec::new();
+
+    for_each_obj_record(reader, read_normals, |record| match record {
+        ObjRecord::Vertex(p, color) => {
+            xyz.push(p);
+            rgb.push(color);
+        }
+        ObjRecord::Normal(n) => normals.push(n),
+    })?;
+
+    let normals = if !read_normals || normals.is_empty() {
+        None
+    } else if normals.len() != xyz.len() {
+        warn!(
+            "{} normals for {} vertices; ignoring normals",
+            normals.len(),
+            xyz.len()
+        );
+        None
+    } else {
+        Some(normals)
+    };
+
+    let colors = if rgb.iter().any(Option::is_some) {
+        let scale = if rgb.iter().flatten().flatten().any(|&c| c > 1.0) {
+            1.0
+        } else {
+            255.0
+        };
+        Some(rgb.iter().map(|&c| obj_color_to_u8(c, scale)).collect::<Vec<_>>())
+    } else {
+        None
+    };
+
+    Ok(MeshVertices::finite(xyz, colors, normals))
+}
+
+/// Vertex colors under the 0–1 and the 0–255 reading.
+type DualColors = (Vec<[u8; 3]>, Vec<[u8; 3]>);
+
+/// Reads an OBJ source whose coordinate system is known up front (`Geodetic`
+/// or `Ecef`) straight into ECEF metres, without collecting the raw vertices
+/// first. Only the converted points are kept, plus the colors once any vertex
+/// has one, read as in [`parse_obj_vertices`]. Normals are counted, as HYPC
+/// does not store them.
+///
+/// Vertices with a non-finite position are dropped with their color.
+fn stream_obj_ecef<R: Read>(reader: R, cs: InputCs, read_normals: bool) -> Result<MeshEcef> {
+    use log::debug;
+
+    let mut mesh = MeshEcef::new(cs);
+    // Colors under both readings, 0–1 and 0–255, since which one applies is
+    // only known at the end; `None` until the first colored vertex.
+    let mut colors: Option<DualColors> = None;
+    let mut above_one = false;
+    let mut normals = 0usize;
+
+    for_each_obj_record(reader, read_normals, |record| match record {
+        ObjRecord::Vertex(p, color) => {
+            if !p.iter().all(|c| c.is_finite()) {
+                return;
+            }
+            if color.is_some() && colors.is_none() {
+                let white = vec![[255; 3]; mesh.points_m.len()];
+                colors = Some((white.clone(), white));
+            }
+            if let Some((unit, byte)) = colors.as_mut() {
+                above_one |= color.is_some_and(|c| c.iter().any(|&v| v > 1.0));
+                unit.push(obj_color_to_u8(color, 255.0));
+                byte.push(obj_color_to_u8(color, 1.0));
+            }
+            mesh.push(p);
+        }
+        ObjRecord::Normal(_) => normals += 1,
+    })?;
 
-    Ok(vertices)
+    mesh.colors = colors.map(|(unit, byte)| if above_one { byte } else { unit });
+    if normals > 0 {
+        debug!(
+            "Read {} vertex normals for {} vertices (not stored in HYPC)",
+            normals,
+            mesh.points_m.len()
+        );
+    }
+    mesh.log_bounds();
+    Ok(mesh)
 }
 
 // ==============================
@@ -390,19 +780,116 @@ enum SemClass {
     Parking = 9,
 }
 
-#[inline(always)]
-fn class_precedence(c: u8) -> u8 {
-    match c {
-        5 | 1 => 200, // Water, Building
-        8 => 160,     // Railway
-        2 => 150,     // RoadMajor
-        3 => 140,     // RoadMinor
-        4 => 130,     // Path
-        6 => 100,     // Park
-        7 => 90,      // Woodland
-        9 => 80,      // Parking
-        _ => 0,       // Unknown or unhandled
+/// Paint precedence per class id; a pixel only takes a class whose precedence is
+/// at least that of the class already there.
+type ClassPrecedence = [u8; 256];
+
+/// Paint precedence plus the `(class, precedence)` palette stored in each SMC1 chunk.
+struct SemPalette {
+    precedence: ClassPrecedence,
+    entries: Vec<(u8, u8)>,
+}
+
+impl SemPalette {
+    /// The palette lists every class `classes` can assign (the built-in ones without
+    /// a config) plus any other class given a precedence.
+    fn new(classes: Option<&ClassConfig>, overrides: &[(u8, u8)]) -> Self {
+        let (base, ids) = match classes {
+            Some(config) => (config.precedence_table(), config.class_ids()),
+            None => (default_class_precedence(), (0..=9).collect()),
+        };
+        let precedence = class_precedence_table(base, overrides);
+        let entries = (0u8..=255u8)
+            .filter(|i| ids.contains(i) || precedence[*i as usize] != 0)
+            .map(|i| (i, precedence[i as usize]))
+            .collect();
+        Self {
+            precedence,
+            entries,
+        }
+    }
+}
+
+/// Built-in precedence table, used unless overridden with `--class-precedence`.
+const fn default_class_precedence() -> ClassPrecedence {
+    let mut t = [0u8; 256]; // Unknown or unhandled
+    t[SemClass::Water as usize] = 200;
+    t[SemClass::Building as usize] = 200;
+    t[SemClass::Railway as usize] = 160;
+    t[SemClass::RoadMajor as usize] = 150;
+    t[SemClass::RoadMinor as usize] = 140;
+    t[SemClass::Path as usize] = 130;
+    t[SemClass::Park as usize] = 100;
+    t[SemClass::Woodland as usize] = 90;
+    t[SemClass::Parking as usize] = 80;
+    t
+}
+
+/// `base` with the `(class, precedence)` overrides applied in order.
+fn class_precedence_table(base: ClassPrecedence, overrides: &[(u8, u8)]) -> ClassPrecedence {
+    let mut t = base;
+    for &(class, precedence) in overrides {
+        t[class as usize] = precedence;
+    }
+    t
+}
+
+/// Parses one `--class-precedence` entry: `<class>=<precedence>`, where `<class>` is a
+/// name from [`hypc::CLASS_NAMES`] (e.g. `paths`) or a numeric id.
+fn parse_precedence_override(s: &str) -> Result<(u8, u8), String> {
+    let (class, precedence) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected <class>=<precedence>, got '{}'", s))?;
+    let class = class.trim();
+    let class = match hypc::CLASS_NAMES.iter().position(|n| n.eq_ignore_ascii_case(class)) {
+        Some(i) => i as u8,
+        None => class.parse::<u8>().map_err(|_| {
+            format!(
+                "unknown class '{}' (expected one of {} or 0-255)",
+                class,
+                hypc::CLASS_NAMES.join(", ")
+            )
+        })?,
+    };
+    let precedence = precedence
+        .trim()
+        .parse::<u8>()
+        .map_err(|e| format!("invalid precedence '{}': {}", precedence, e))?;
+    Ok((class, precedence))
+}
+
+fn parse_anchor_ecef(s: &str) -> Result<[f64; 3], String> {
+    let parts: Vec<&str> = s.split(',').map(str::trim).collect();
+    let [x, y, z] = parts.as_slice() else {
+        return Err(format!("expected x,y,z in metres, got '{}'", s));
+    };
+    let parse = |v: &str| match v.parse::<f64>() {
+        Ok(v) if v.is_finite() => Ok(v),
+        _ => Err(format!("invalid coordinate '{}'", v)),
+    };
+    Ok([parse(x)?, parse(y)?, parse(z)?])
+}
+
+/// Parses `--bbox-filter lon_min,lat_min,lon_max,lat_max` (degrees).
+fn parse_bbox_filter(s: &str) -> Result<GeoBboxDeg, String> {
+    let parts: Vec<&str> = s.split(',').map(str::trim).collect();
+    let [lon_min, lat_min, lon_max, lat_max] = parts.as_slice() else {
+        return Err(format!("expected lon_min,lat_min,lon_max,lat_max in degrees, got '{}'", s));
+    };
+    let parse = |v: &str| match v.parse::<f64>() {
+        Ok(v) if v.is_finite() => Ok(v),
+        _ => Err(format!("invalid coordinate '{}'", v)),
+    };
+    let bbox = GeoBboxDeg {
+        lon_min: parse(lon_min)?,
+        lat_min: parse(lat_min)?,
+        lon_max: parse(lon_max)?,
+        lat_max: parse(lat_max)?,
+    };
+    if bbox.lon_min > bbox.lon_max || bbox.lat_min > bbox.lat_max {
+        return Err(format!("bbox '{}' has min > max", s));
     }
+    Ok(bbox)
 }
 
 #[derive(Clone)]
@@ -412,10 +899,14 @@ struct Polyline {
     pts: Arc<Vec<(f64, f64)>>,
 }
 
+/// An area: one ring for a closed way, or every ring of a multipolygon
+/// relation, filled with the even-odd rule so inner rings become holes.
 #[derive(Clone)]
 struct Polygon {
     class: u8,
-    ring: Arc<Vec<(f64, f64)>>,
+    rings: Arc<Vec<Vec<(f64, f64)>>>,
+    /// Building height in metres, see [`building_height_m`].
+    height_m: Option<f32>,
 }
 
 #[derive(Default, Clone)]
@@ -426,6 +917,8 @@ struct SemOverlayPerTile {
 
 type OverlayMap = HashMap<String, SemOverlayPerTile>;
 
+type NodeMap = hashbrown::HashMap<i64, NodeRec, nohash_hasher::BuildNoHashHasher<i64>>;
+
 #[derive(Clone)]
 struct NodeRec {
     lon: f64,
@@ -550,10 +1043,34 @@ fn parse_width_m(s: &str) -> Option<f32> {
     s.parse::<f32>().ok()
 }
 
-fn classify_way(w: &Way) -> Option<(u8, f32, bool)> {
-    // Collect all tags for repeated lookup.
-    let tags: Vec<(&str, &str)> = w.tags().collect();
+/// Metres per storey when a building only has `building:levels`.
+const METRES_PER_LEVEL: f32 = 3.0;
+
+/// A building's height in metres: the `height` tag (metres, or feet with an
+/// `ft` suffix), else `building:levels` × [`METRES_PER_LEVEL`], else unknown.
+fn building_height_m(tags: &[(&str, &str)]) -> Option<f32> {
+    let get = |key: &str| tags.iter().find_map(|(k, v)| (*k == key).then_some(*v));
+
+    get("height")
+        .and_then(parse_width_m)
+        .or_else(|| {
+            get("building:levels")
+                .and_then(|v| v.trim().parse::<f32>().ok())
+                .map(|levels| levels * METRES_PER_LEVEL)
+        })
+        .filter(|h| h.is_finite() && *h > 0.0)
+}
+
+/// Classifies with `--class-config` if one was given, else the built-in rules.
+fn classify(classes: Option<&ClassConfig>, tags: &[(&str, &str)]) -> Option<(u8, f32, bool)> {
+    match classes {
+        Some(config) => config.classify(tags),
+        None => classify_tags(tags),
+    }
+}
 
+/// Class, line width and whether it is an area, for a way's or relation's tags.
+fn classify_tags(tags: &[(&str, &str)]) -> Option<(u8, f32, bool)> {
     // Helper that returns the first value associated with a given key.
     let get = |key: &str| tags.iter().find_map(|(k, v)| if *k == key { Some(*v) } else { None });
 
@@ -643,6 +1160,7 @@ fn build_osm_overlays(
     margin_m: f64,
     log_every: usize,
     prefilter: bool,
+    classes: Option<&ClassConfig>,
 ) -> Result<OverlayMap> {
     // --------------------------------------------------------------------
     // Ensure every tile provides a bounding box – required for the OSM overlay.
@@ -681,7 +1199,8 @@ fn build_osm_overlays(
     // Possibly pre‑filter the PBF with osmium, falling back to the original.
     // --------------------------------------------------------------------
     let pbf_source = if prefilter {
-        prefilter_with_osmium(pbf_path, tiles, margin_m).unwrap_or_else(|| pbf_path.to_string())
+        prefilter_with_osmium(pbf_path, tiles, margin_m, classes)
+            .unwrap_or_else(|| pbf_path.to_string())
     } else {
         pbf_path.to_string()
     };
@@ -689,8 +1208,7 @@ fn build_osm_overlays(
     // --------------------------------------------------------------------
     // First pass: read all nodes, keep those that intersect any tile.
     // --------------------------------------------------------------------
-    let mut node_map: hashbrown::HashMap<i64, NodeRec, nohash_hasher::BuildNoHashHasher<i64>> =
-        hashbrown::HashMap::with_hasher(nohash_hasher::BuildNoHashHasher::default());
+    let mut node_map: NodeMap = NodeMap::with_hasher(Default::default());
 
     let mut seen_nodes = 0usize;
     let mut tick = Tick::new(log_every);
@@ -747,7 +1265,8 @@ fn build_osm_overlays(
             seen_ways += 1;
 
             // Classify the way and obtain its rendering parameters.
-            if let Some((class_id, width_m, is_area)) = classify_way(&way) {
+            let tags: Vec<(&str, &str)> = way.tags().collect();
+            if let Some((class_id, width_m, is_area)) = classify(classes, &tags) {
                 // Gather coordinates for all referenced nodes that are present in
                 // our node_map, and collect the set of tiles the way touches.
                 let mut coords = Vec::with_capacity(way.refs().len());
@@ -767,15 +1286,27 @@ fn build_osm_overlays(
                 // We need at least two points for a line or three for a polygon.
                 let enough_coords = if is_area { coords.len() >= 3 } else { coords.len() >= 2 };
                 if enough_coords && !touched_tiles.is_empty() {
-                    let coords_arc = Arc::new(coords);
-                    for tile_idx in touched_tiles {
-                        let tile = &tiles[tile_idx as usize];
-                        let entry = overlays.entry(tile.prefix.clone()).or_default();
-                        if is_area {
-                            entry
-                                .areas
-                                .push(Polygon { class: class_id, ring: coords_arc.clone() });
+                    if is_area {
+                        let rings = Arc::new(vec![coords]);
+                        let height_m = if class_id == SemClass::Building as u8 {
+                            building_height_m(&tags)
                         } else {
+                            None
+                        };
+                        for tile_idx in touched_tiles {
+                            let tile = &tiles[tile_idx as usize];
+                            let entry = overlays.entry(tile.prefix.clone()).or_default();
+                            entry.areas.push(Polygon {
+                                class: class_id,
+                                rings: rings.clone(),
+                                height_m,
+                            });
+                        }
+                    } else {
+                        let coords_arc = Arc::new(coords);
+                        for tile_idx in touched_tiles {
+                            let tile = &tiles[tile_idx as usize];
+                            let entry = overlays.entry(tile.prefix.clone()).or_default();
                             entry.roads.push(Polyline {
                                 class: class_id,
                                 width_m,
@@ -798,10 +1329,197 @@ fn build_osm_overlays(
         }
     })?;
 
+    add_multipolygon_overlays(
+        &pbf_source,
+        &node_map,
+        &tile_tree,
+        tiles,
+        log_every,
+        classes,
+        &mut overlays,
+    )?;
+
     Ok(overlays)
 }
 
-fn prefilter_with_osmium(pbf_in: &str, tiles: &[WorkItem], margin_m: f64) -> Option<String> {
+/// Area class and member ways of a `type=multipolygon` relation.
+struct MultipolygonRel {
+    class: u8,
+    height_m: Option<f32>,
+    ways: Vec<i64>,
+}
+
+/// Passes C–E: adds `type=multipolygon` relations (lakes, large buildings) to
+/// the overlays of every tile their bbox touches.
+///
+/// Member ways are often untagged and their nodes may lie outside every tile
+/// (a lake around a tile), so the member ways and any nodes missing from
+/// `node_map` are read in passes of their own.
+fn add_multipolygon_overlays(
+    pbf_source: &str,
+    node_map: &NodeMap,
+    tile_tree: &RTree<TileBox>,
+    tiles: &[WorkItem],
+    log_every: usize,
+    classes: Option<&ClassConfig>,
+    overlays: &mut OverlayMap,
+) -> Result<()> {
+    // Pass C: relations with an area classification.
+    let mut rels = Vec::new();
+    let mut seen_rels = 0usize;
+    let mut tick = Tick::new(log_every);
+    ElementReader::from_path(pbf_source)?.for_each(|elem| {
+        if let Element::Relation(rel) = elem {
+            seen_rels += 1;
+            let tags: Vec<(&str, &str)> = rel.tags().collect();
+            if tags.contains(&("type", "multipolygon")) {
+                if let Some((class, _, true)) = classify(classes, &tags) {
+                    let ways = rel
+                        .members()
+                        .filter(|m| m.member_type == RelMemberType::Way)
+                        .filter(|m| matches!(m.role(), Ok("outer" | "inner" | "")))
+                        .map(|m| m.member_id)
+                        .collect();
+                    let height_m = if class == SemClass::Building as u8 {
+                        building_height_m(&tags)
+                    } else {
+                        None
+                    };
+                    rels.push(MultipolygonRel {
+                        class,
+                        height_m,
+                        ways,
+                    });
+                }
+            }
+
+            if tick.should(seen_rels) {
+                info!(
+                    "Pass C: relations seen {:>11}, multipolygons {:>9}, rate {:5.2} M/s",
+                    seen_rels,
+                    rels.len(),
+                    tick.rate_mps(seen_rels)
+                );
+                tick.bump();
+            }
+        }
+    })?;
+    if rels.is_empty() {
+        return Ok(());
+    }
+
+    // Pass D: node lists of the member ways.
+    let mut way_refs: HashMap<i64, Vec<i64>> = rels
+        .iter()
+        .flat_map(|r| r.ways.iter().map(|&w| (w, Vec::new())))
+        .collect();
+    ElementReader::from_path(pbf_source)?.for_each(|elem| {
+        if let Element::Way(way) = elem {
+            if let Some(refs) = way_refs.get_mut(&way.id()) {
+                *refs = way.refs().collect();
+            }
+        }
+    })?;
+
+    // Pass E: member nodes outside every tile.
+    let mut extra_nodes: HashMap<i64, (f64, f64)> = way_refs
+        .values()
+        .flatten()
+        .filter(|id| !node_map.contains_key(*id))
+        .map(|&id| (id, (f64::NAN, f64::NAN)))
+        .collect();
+    if !extra_nodes.is_empty() {
+        ElementReader::from_path(pbf_source)?.for_each(|elem| {
+            let (id, lon, lat) = match elem {
+                Element::Node(node) => (node.id(), node.lon(), node.lat()),
+                Element::DenseNode(dn) => (dn.id(), dn.lon(), dn.lat()),
+                _ => return,
+            };
+            if let Some(coord) = extra_nodes.get_mut(&id) {
+                *coord = (lon, lat);
+            }
+        })?;
+    }
+    let coord = |id: &i64| match node_map.get(id) {
+        Some(node) => Some((node.lon, node.lat)),
+        None => extra_nodes.get(id).copied().filter(|c| c.0.is_finite()),
+    };
+
+    // Stitch rings and hand each relation to every tile its bbox touches.
+    let mut added = 0usize;
+    for rel in &rels {
+        let members = rel.ways.iter().filter_map(|w| way_refs.get(w)).cloned().collect();
+        let rings: Vec<Vec<(f64, f64)>> = assemble_rings(members)
+            .iter()
+            .map(|ring| ring.iter().filter_map(coord).collect::<Vec<_>>())
+            .filter(|ring| ring.len() >= 3)
+            .collect();
+        if rings.is_empty() {
+            continue;
+        }
+
+        let (mut lo, mut hi) = ([f64::INFINITY; 2], [f64::NEG_INFINITY; 2]);
+        for &(lon, lat) in rings.iter().flatten() {
+            lo = [lo[0].min(lon), lo[1].min(lat)];
+            hi = [hi[0].max(lon), hi[1].max(lat)];
+        }
+
+        let rings = Arc::new(rings);
+        for tb in tile_tree.locate_in_envelope_intersecting(&AABB::from_corners(lo, hi)) {
+            let tile = &tiles[tb.idx as usize];
+            overlays
+                .entry(tile.prefix.clone())
+                .or_default()
+                .areas
+                .push(Polygon {
+                    class: rel.class,
+                    rings: rings.clone(),
+                    height_m: rel.height_m,
+                });
+        }
+        added += 1;
+    }
+    info!(
+        "Pass C-E: {} of {} multipolygon relations added to tile overlays",
+        added,
+        rels.len()
+    );
+
+    Ok(())
+}
+
+/// Joins member ways (node ID lists) end to end into rings, reversing ways as
+/// needed. A chain that cannot be closed is kept open; the rasterizer closes it.
+fn assemble_rings(mut ways: Vec<Vec<i64>>) -> Vec<Vec<i64>> {
+    ways.retain(|w| w.len() >= 2);
+
+    let mut rings = Vec::new();
+    while let Some(mut ring) = ways.pop() {
+        while ring.first() != ring.last() {
+            let end = *ring.last().unwrap();
+            let Some(i) = ways
+                .iter()
+                .position(|w| w.first() == Some(&end) || w.last() == Some(&end))
+            else {
+                break;
+            };
+            let mut next = ways.swap_remove(i);
+            if next.first() != Some(&end) {
+                next.reverse();
+            }
+            ring.extend_from_slice(&next[1..]);
+        }
+        rings.push(ring);
+    }
+    rings
+}
+
+fn prefilter_with_osmium(
+    pbf_in: &str,
+    tiles: &[WorkItem],
+    margin_m: f64,
+    classes: Option<&ClassConfig>,
+) -> Option<String> {
     use std::process::Command;
 
     if Command::new("osmium").arg("--version").output().is_err() {
@@ -850,7 +1568,14 @@ fn prefilter_with_osmium(pbf_in: &str, tiles: &[WorkItem], margin_m: f64) -> Opt
         return None;
     }
 
-    let filter = "nwr/building nwr/highway nwr/landuse=forest,grass,meadow,reservoir nwr/leisure=park,pitch nwr/natural=water nwr/waterway=riverbank nwr/railway nwr/amenity=parking";
+    let builtin = "nwr/building nwr/highway nwr/landuse=forest,grass,meadow,reservoir nwr/leisure=park,pitch nwr/natural=water nwr/waterway=riverbank nwr/railway nwr/amenity=parking";
+    let mut filter: Vec<String> = match classes {
+        Some(config) => config.osmium_filters(),
+        None => Vec::new(),
+    };
+    if classes.is_none_or(ClassConfig::uses_builtin) {
+        filter.extend(builtin.split_whitespace().map(str::to_owned));
+    }
 
     let filter_status = Command::new("osmium")
         .args([
@@ -860,7 +1585,7 @@ fn prefilter_with_osmium(pbf_in: &str, tiles: &[WorkItem], margin_m: f64) -> Opt
             &tmp_filtered,
             &tmp_extract,
         ])
-        .args(filter.split_whitespace())
+        .args(&filter)
         .status()
         .ok()?;
 
@@ -877,6 +1602,9 @@ struct SemMask {
     w: u16,
     h: u16,
     data: Vec<u8>,
+    /// Building height per pixel in whole metres (saturating at 255), laid out
+    /// like `data`; 0 where the pixel is not a building or has no height.
+    heights: Vec<u8>,
 }
 
 #[inline]
@@ -896,28 +1624,144 @@ fn uv_to_pixel(u: f32, v: f32, w: u16, h: u16) -> (i32, i32) {
     (x, y)
 }
 
-fn paint_pixel(mask: &mut SemMask, x: i32, y: i32, class: u8) {
+/// Like [`uv_to_pixel`], without snapping to the pixel grid.
+#[inline]
+fn uv_to_pixel_f(u: f32, v: f32, w: u16, h: u16) -> (f32, f32) {
+    (
+        u.clamp(0.0, 1.0) * (w as f32 - 1.0),
+        v.clamp(0.0, 1.0) * (h as f32 - 1.0),
+    )
+}
+
+fn paint_pixel(
+    mask: &mut SemMask,
+    x: i32,
+    y: i32,
+    class: u8,
+    height: u8,
+    precedence: &ClassPrecedence,
+) {
     // Check bounds
     if x < 0 || y < 0 || x >= mask.w as i32 || y >= mask.h as i32 {
         return;
     }
 
-    // Compute index and update if new class has higher precedence
+    // Compute index and update if new class has higher precedence. Where
+    // buildings overlap, the taller height wins.
     let idx = y as usize * mask.w as usize + x as usize;
-    if class_precedence(class) >= class_precedence(mask.data[idx]) {
+    if precedence[class as usize] >= precedence[mask.data[idx] as usize] {
+        let prev = if mask.data[idx] == class {
+            mask.heights[idx]
+        } else {
+            0
+        };
         mask.data[idx] = class;
+        mask.heights[idx] = prev.max(height);
+    }
+}
+
+/// Samples per pixel axis for [`rasterize_polygon_aa`].
+const AA_SAMPLES: i32 = 4;
+
+/// Coverage-based variant of [`rasterize_polygon`]: each pixel is sampled on an
+/// [`AA_SAMPLES`]² grid with the even-odd rule and painted when at least half
+/// the samples are inside. If that paints nothing, the best-covered pixel is
+/// painted so sub-pixel areas still register. `rings` are in unsnapped pixel
+/// coordinates (pixel centres at integers).
+fn rasterize_polygon_aa(
+    mask: &mut SemMask,
+    rings: &[Vec<(f32, f32)>],
+    class: u8,
+    height: u8,
+    precedence: &ClassPrecedence,
+) {
+    if rings.iter().all(|ring| ring.len() < 3) {
+        return;
+    }
+
+    let (mut xmin, mut ymin, mut xmax, mut ymax) = (f32::MAX, f32::MAX, f32::MIN, f32::MIN);
+    for &(x, y) in rings.iter().flatten() {
+        xmin = xmin.min(x);
+        xmax = xmax.max(x);
+        ymin = ymin.min(y);
+        ymax = ymax.max(y);
+    }
+    let xmin = clamp_i(xmin.round() as i32, 0, mask.w as i32 - 1);
+    let xmax = clamp_i(xmax.round() as i32, 0, mask.w as i32 - 1);
+    let ymin = clamp_i(ymin.round() as i32, 0, mask.h as i32 - 1);
+    let ymax = clamp_i(ymax.round() as i32, 0, mask.h as i32 - 1);
+
+    // Sample `k` of pixel `p` sits at p - 0.5 + (k + 0.5) / AA_SAMPLES; `sample`
+    // numbers them globally as p * AA_SAMPLES + k.
+    let s = AA_SAMPLES as f32;
+    let sample_pos = |sample: i32| (sample as f32 + 0.5) / s - 0.5;
+    let first_sample_at_or_after = |x: f32| ((x + 0.5) * s - 0.5).ceil() as i32;
+
+    let full = AA_SAMPLES * AA_SAMPLES;
+    let mut painted = false;
+    let mut best: Option<(i32, i32, i32)> = None; // (coverage, x, y)
+    let mut coverage = vec![0i32; (xmax - xmin + 1) as usize];
+    let mut crossings: Vec<f32> = Vec::new();
+    for y in ymin..=ymax {
+        coverage.fill(0);
+
+        for sy in y * AA_SAMPLES..(y + 1) * AA_SAMPLES {
+            let yf = sample_pos(sy);
+            crossings.clear();
+            for ring in rings.iter().filter(|ring| ring.len() >= 3) {
+                let mut j = ring.len() - 1;
+                for i in 0..ring.len() {
+                    let (xi, yi) = ring[i];
+                    let (xj, yj) = ring[j];
+                    if (yi > yf) != (yj > yf) {
+                        crossings.push(xi + (xj - xi) * (yf - yi) / (yj - yi));
+                    }
+                    j = i;
+                }
+            }
+
+            crossings.sort_by(f32::total_cmp);
+            for span in crossings.chunks_exact(2) {
+                let s0 = first_sample_at_or_after(span[0]).max(xmin * AA_SAMPLES);
+                let s1 = first_sample_at_or_after(span[1]).min((xmax + 1) * AA_SAMPLES);
+                for sx in s0..s1 {
+                    coverage[(sx / AA_SAMPLES - xmin) as usize] += 1;
+                }
+            }
+        }
+
+        for (x, &c) in (xmin..).zip(&coverage) {
+            if 2 * c >= full {
+                paint_pixel(mask, x, y, class, height, precedence);
+                painted = true;
+            } else if c > 0 && best.is_none_or(|(b, _, _)| c > b) {
+                best = Some((c, x, y));
+            }
+        }
+    }
+
+    if let (false, Some((_, x, y))) = (painted, best) {
+        paint_pixel(mask, x, y, class, height, precedence);
     }
 }
 
-fn rasterize_polygon(mask: &mut SemMask, poly: &[(i32, i32)], class: u8) {
+/// Fills the area enclosed by `rings` with the even-odd rule across all of
+/// them, so rings inside another ring cut holes.
+fn rasterize_polygon(
+    mask: &mut SemMask,
+    rings: &[Vec<(i32, i32)>],
+    class: u8,
+    height: u8,
+    precedence: &ClassPrecedence,
+) {
     // A polygon needs at least three vertices.
-    if poly.len() < 3 {
+    if rings.iter().all(|ring| ring.len() < 3) {
         return;
     }
 
     // ------- Compute the axis‑aligned bounding box of the polygon ------------
     let (mut xmin, mut ymin, mut xmax, mut ymax) = (i32::MAX, i32::MAX, i32::MIN, i32::MIN);
-    for &(x, y) in poly {
+    for &(x, y) in rings.iter().flatten() {
         xmin = xmin.min(x);
         xmax = xmax.max(x);
         ymin = ymin.min(y);
@@ -930,11 +1774,15 @@ fn rasterize_polygon(mask: &mut SemMask, poly: &[(i32, i32)], class: u8) {
     ymin = clamp_i(ymin, 0, mask.h as i32 - 1);
     ymax = clamp_i(ymax, 0, mask.h as i32 - 1);
 
-    // ------- Scan the bounding rectangle and apply the even‑odd rule ---------
-    let n = poly.len();
+    // ------- Scan each row and apply the even‑odd rule ------------------------
+    // A pixel is inside when an odd number of edge crossings lie to its right,
+    // i.e. between the 2k-th and (2k+1)-th sorted crossing of its row.
+    let mut crossings: Vec<f32> = Vec::new();
     for y in ymin..=ymax {
-        for x in xmin..=xmax {
-            let mut inside = false;
+        crossings.clear();
+
+        for poly in rings.iter().filter(|ring| ring.len() >= 3) {
+            let n = poly.len();
             let mut j = n - 1; // Index of the previous vertex
 
             for i in 0..n {
@@ -947,17 +1795,19 @@ fn rasterize_polygon(mask: &mut SemMask, poly: &[(i32, i32)], class: u8) {
                     let x_inter = (xj - xi) as f32
                         * ((y - yi) as f32 / ((yj - yi) as f32 + 1e-20))
                         + xi as f32;
-
-                    if (x as f32) < x_inter {
-                        inside = !inside;
-                    }
+                    crossings.push(x_inter);
                 }
 
                 j = i;
             }
+        }
 
-            if inside {
-                paint_pixel(mask, x, y, class);
+        crossings.sort_by(f32::total_cmp);
+        for span in crossings.chunks_exact(2) {
+            let x0 = (span[0].ceil() as i32).max(xmin);
+            let x1 = (span[1].ceil() as i32 - 1).min(xmax);
+            for x in x0..=x1 {
+                paint_pixel(mask, x, y, class, height, precedence);
             }
         }
     }
@@ -976,6 +1826,7 @@ fn rasterize_polyline(
     line: &[(i32, i32)],
     radius_px: f32,
     class: u8,
+    precedence: &ClassPrecedence,
 ) {
     // Need at least a start and end point to form a segment.
     if line.len() < 2 {
@@ -1022,7 +1873,7 @@ fn rasterize_polyline(
 
                 // If the pixel centre lies within the radius, paint it.
                 if sqr(px - cx) + sqr(py - cy) <= radius_sq {
-                    paint_pixel(mask, x, y, class);
+                    paint_pixel(mask, x, y, class, 0, precedence);
                 }
             }
         }
@@ -1033,6 +1884,8 @@ fn build_smc1_mask(
     overlay: &SemOverlayPerTile,
     tile_bbox_deg: GeoBboxDeg,
     grid: u16,
+    precedence: &ClassPrecedence,
+    antialias: bool,
 ) -> SemMask {
     // --------------------------------------------------------------------
     // Initialise an empty mask – one-byte per pixel, initially all zero.
@@ -1041,6 +1894,7 @@ fn build_smc1_mask(
         w: grid,
         h: grid,
         data: vec![0u8; (grid as usize).pow(2)],
+        heights: vec![0u8; (grid as usize).pow(2)],
     };
 
     // --------------------------------------------------------------------
@@ -1058,13 +1912,33 @@ fn build_smc1_mask(
     // Rasterise polygonal areas (e.g. buildings, water, parks).
     // --------------------------------------------------------------------
     for area in &overlay.areas {
-        let ring_px: Vec<(i32, i32)> = area
-            .ring
+        // Whole metres, rounded; `as` saturates at 255.
+        let height = area.height_m.map_or(0, |h| h.round() as u8);
+
+        if antialias {
+            let rings_px: Vec<Vec<(f32, f32)>> = area
+                .rings
+                .iter()
+                .map(|ring| {
+                    ring.iter()
+                        .map(|&(lon, lat)| uv_to_pixel_f(lon_to_u(lon), lat_to_v(lat), grid, grid))
+                        .collect()
+                })
+                .collect();
+            rasterize_polygon_aa(&mut mask, &rings_px, area.class, height, precedence);
+            continue;
+        }
+
+        let rings_px: Vec<Vec<(i32, i32)>> = area
+            .rings
             .iter()
-            .map(|&(lon, lat)| uv_to_pixel(lon_to_u(lon), lat_to_v(lat), grid, grid))
+            .map(|ring| {
+                ring.iter()
+                    .map(|&(lon, lat)| uv_to_pixel(lon_to_u(lon), lat_to_v(lat), grid, grid))
+                    .collect()
+            })
             .collect();
-
-        rasterize_polygon(&mut mask, &ring_px, area.class);
+        rasterize_polygon(&mut mask, &rings_px, area.class, height, precedence);
     }
 
     // --------------------------------------------------------------------
@@ -1091,7 +1965,7 @@ fn build_smc1_mask(
             .map(|&(lon, lat)| uv_to_pixel(lon_to_u(lon), lat_to_v(lat), grid, grid))
             .collect();
 
-        rasterize_polyline(&mut mask, &line_px, radius_px, road.class);
+        rasterize_polyline(&mut mask, &line_px, radius_px, road.class, precedence);
     }
 
     mask
@@ -1099,6 +1973,31 @@ fn build_smc1_mask(
 
 // ---------- Input CS detection and safe quantization ----------
 
+/// Vertices sampled for input CS auto-detection.
+const CS_SAMPLE_LEN: usize = 4096;
+
+/// Up to `n` vertices drawn uniformly (with replacement) from `xyz`, seeded by
+/// `seed` so reruns see the same sample. Meshes with at most `n` vertices are
+/// returned whole.
+fn sample_vertices(xyz: &[[f64; 3]], n: usize, seed: u64) -> Vec<[f64; 3]> {
+    if xyz.len() <= n {
+        return xyz.to_vec();
+    }
+
+    // splitmix64
+    let mut state = seed;
+    let mut next = || {
+        state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    };
+    (0..n)
+        .map(|_| xyz[(next() % xyz.len() as u64) as usize])
+        .collect()
+}
+
 /// Heuristic to decide how OBJ vertex coordinates should be interpreted.
 fn detect_input_cs(sample: &[[f64; 3]]) -> InputCs {
     // --------------------------------------------------------------------
@@ -1155,25 +2054,71 @@ struct Quantized {
     used_upm: u32,
 }
 
-/// Quantize to integer lattice with an anchor, automatically down‑scaling
-/// `units_per_meter` (UPM) to fit into an `i32` if necessary.
-fn quantize_with_anchor(points_m: &[[f64; 3]], requested_upm: u32) -> Quantized {
-    debug_assert!(!points_m.is_empty());
+/// Snaps points to a `voxel_m` grid and keeps each occupied voxel's centroid, in
+/// order of first occurrence. Colors are averaged per voxel. Labels need no vote:
+/// `--bake-labels` samples them from the SMC1 mask after downsampling.
+fn voxel_downsample(
+    points_m: &[[f64; 3]],
+    colors: Option<&[[u8; 3]]>,
+    voxel_m: f64,
+) -> (Vec<[f64; 3]>, Option<Vec<[u8; 3]>>) {
+    /// Running sums for one voxel; positions relative to its min corner so the
+    /// sums keep precision far from the ECEF origin.
+    struct Voxel {
+        corner: [f64; 3],
+        sum: [f64; 3],
+        rgb: [u64; 3],
+        n: u32,
+    }
 
-    // ------------------------------------------------------------------------
-    // 1  Compute the centroid (anchor) in metres.
-    // ------------------------------------------------------------------------
+    let mut index: HashMap<[i64; 3], usize> = HashMap::new();
+    let mut voxels: Vec<Voxel> = Vec::new();
+    for (i, p) in points_m.iter().enumerate() {
+        let cell = p.map(|c| (c / voxel_m).floor() as i64);
+        let v = *index.entry(cell).or_insert_with(|| {
+            voxels.push(Voxel {
+                corner: cell.map(|c| c as f64 * voxel_m),
+                sum: [0.0; 3],
+                rgb: [0; 3],
+                n: 0,
+            });
+            voxels.len() - 1
+        });
+        let v = &mut voxels[v];
+        v.sum = std::array::from_fn(|k| v.sum[k] + (p[k] - v.corner[k]));
+        if let Some(colors) = colors {
+            v.rgb = std::array::from_fn(|k| v.rgb[k] + colors[i][k] as u64);
+        }
+        v.n += 1;
+    }
+
+    let points = voxels
+        .iter()
+        .map(|v| std::array::from_fn(|k| v.corner[k] + v.sum[k] / v.n as f64))
+        .collect();
+    let colors = colors.map(|_| {
+        voxels
+            .iter()
+            .map(|v| v.rgb.map(|c| ((c + v.n as u64 / 2) / v.n as u64) as u8))
+            .collect()
+    });
+    (points, colors)
+}
+
+/// Centroid of `points_m` and the largest per-axis absolute offset from it (metres).
+fn centroid_and_max_offset(points_m: &[[f64; 3]]) -> ([f64; 3], f64) {
     let (sum_x, sum_y, sum_z) = points_m.iter().fold((0.0_f64, 0.0_f64, 0.0_f64), |(ax, ay, az), p| {
         (ax + p[0], ay + p[1], az + p[2])
     });
     let inv_n = 1.0_f64 / points_m.len() as f64;
     let anchor_m = [sum_x * inv_n, sum_y * inv_n, sum_z * inv_n];
 
-    // ------------------------------------------------------------------------
-    // 2  Determine the maximum absolute offset from the anchor (in metres).
-    // ------------------------------------------------------------------------
-    const EPS: f64 = 1e-12;
-    let max_off_m = points_m
+    (anchor_m, max_offset_from(points_m, anchor_m))
+}
+
+/// Largest per-axis absolute offset of `points_m` from `anchor_m` (metres).
+fn max_offset_from(points_m: &[[f64; 3]], anchor_m: [f64; 3]) -> f64 {
+    points_m
         .iter()
         .map(|p| {
             (p[0] - anchor_m[0])
@@ -1181,32 +2126,79 @@ fn quantize_with_anchor(points_m: &[[f64; 3]], requested_upm: u32) -> Quantized
                 .max((p[1] - anchor_m[1]).abs())
                 .max((p[2] - anchor_m[2]).abs())
         })
-        .fold(0.0_f64, f64::max);
+        .fold(0.0_f64, f64::max)
+}
+
+/// The anchor a tile is quantized against (`fixed_anchor_m`, else the centroid)
+/// and the largest per-axis offset from it (metres).
+fn anchor_and_max_offset(
+    points_m: &[[f64; 3]],
+    fixed_anchor_m: Option<[f64; 3]>,
+) -> ([f64; 3], f64) {
+    match fixed_anchor_m {
+        Some(anchor_m) => (anchor_m, max_offset_from(points_m, anchor_m)),
+        None => centroid_and_max_offset(points_m),
+    }
+}
+
+/// Share of the i32 range past which offsets from a fixed anchor get a warning.
+const FIXED_ANCHOR_WARN_FRACTION: f64 = 0.5;
+
+/// Largest UPM (capped at `requested_upm`) that keeps `max_off_m` inside an `i32`.
+fn max_upm_for_offset(max_off_m: f64, requested_upm: u32) -> u32 {
+    const EPS: f64 = 1e-12;
+
+    // If the geometry collapses to a point we can keep the caller's request.
+    if max_off_m <= EPS {
+        return requested_upm;
+    }
+
+    // Aim to keep a 5% head‑room before hitting i32::MAX.
+    ((i32::MAX as f64) / (max_off_m * 1.05))
+        .floor()
+        .clamp(1.0, requested_upm as f64) as u32
+}
+
+/// Quantize to integer lattice with an anchor, automatically down‑scaling
+/// `units_per_meter` (UPM) to fit into an `i32` if necessary. The anchor is
+/// `fixed_anchor_m` if given, else the centroid of `points_m`.
+fn quantize_with_anchor(
+    points_m: &[[f64; 3]],
+    requested_upm: u32,
+    fixed_anchor_m: Option<[f64; 3]>,
+) -> Quantized {
+    debug_assert!(!points_m.is_empty());
 
     // ------------------------------------------------------------------------
-    // 3  Choose a usable UPM that fits all offsets into a signed 32‑bit int.
+    // 1  Compute the anchor and maximum absolute offset (metres).
     // ------------------------------------------------------------------------
-    // If the geometry collapses to a point we can keep the caller's request.
-    let mut upm = if max_off_m <= EPS {
-        requested_upm
-    } else {
-        // Aim to keep a 5% head‑room before hitting i32::MAX.
-        let max_upm_fit = ((i32::MAX as f64) / (max_off_m * 1.05))
-            .floor()
-            .clamp(1.0, requested_upm as f64) as u32;
+    let (anchor_m, max_off_m) = anchor_and_max_offset(points_m, fixed_anchor_m);
 
-        if max_upm_fit < requested_upm {
-            warn!(
-                "units_per_meter={} too high for this tile span (~{:.3} m max offset). \
-                 Using {} u/m instead.",
-                requested_upm, max_off_m, max_upm_fit
-            );
-        }
-        max_upm_fit.max(1)
-    };
+    // ------------------------------------------------------------------------
+    // 2  Choose a usable UPM that fits all offsets into a signed 32‑bit int.
+    // ------------------------------------------------------------------------
+    let mut upm = max_upm_for_offset(max_off_m, requested_upm);
+    if upm < requested_upm {
+        warn!(
+            "units_per_meter={} too high for this tile span (~{:.3} m max offset). \
+             Using {} u/m instead.",
+            requested_upm, max_off_m, upm
+        );
+    }
+    if fixed_anchor_m.is_some()
+        && max_off_m * upm as f64 >= FIXED_ANCHOR_WARN_FRACTION * i32::MAX as f64
+    {
+        warn!(
+            "Tile lies up to {:.3} m from the fixed anchor: offsets reach {:.0}% of the \
+             i32 range at {} u/m.",
+            max_off_m,
+            max_off_m * upm as f64 / i32::MAX as f64 * 100.0,
+            upm
+        );
+    }
 
     // ------------------------------------------------------------------------
-    // 4  Helper: try to quantise all points with the current UPM.
+    // 3  Helper: try to quantise all points with the current UPM.
     // ------------------------------------------------------------------------
     fn try_quantize(
         points_m: &[[f64; 3]],
@@ -1238,7 +2230,7 @@ fn quantize_with_anchor(points_m: &[[f64; 3]], requested_upm: u32) -> Quantized
     }
 
     // ------------------------------------------------------------------------
-    // 5  Compute the anchor in integer units for the current UPM.
+    // 4  Compute the anchor in integer units for the current UPM.
     // ------------------------------------------------------------------------
     let mut anchor_units = [
         quantize_units(anchor_m[0], upm),
@@ -1247,7 +2239,7 @@ fn quantize_with_anchor(points_m: &[[f64; 3]], requested_upm: u32) -> Quantized
     ];
 
     // ------------------------------------------------------------------------
-    // 6  Attempt quantisation; on failure, keep halving UPM until it succeeds.
+    // 5  Attempt quantisation; on failure, keep halving UPM until it succeeds.
     // ------------------------------------------------------------------------
     let points_units = loop {
         match try_quantize(points_m, upm, anchor_units) {
@@ -1267,7 +2259,7 @@ fn quantize_with_anchor(points_m: &[[f64; 3]], requested_upm: u32) -> Quantized
     };
 
     // ------------------------------------------------------------------------
-    // 7  Assemble the result.
+    // 6  Assemble the result.
     // ------------------------------------------------------------------------
     Quantized {
         anchor_units,
@@ -1276,38 +2268,179 @@ fn quantize_with_anchor(points_m: &[[f64; 3]], requested_upm: u32) -> Quantized
     }
 }
 
-fn process_one_mesh(
-    path: &Path,
-    args: &Args,
-    prefix: &str,
-    bbox: Option<GeoBboxDeg>,
-    overlays: Option<&SemOverlayPerTile>,
-) -> Result<()> {
-    use log::debug;
+/// Per-tile quantization outcome, gathered for the end-of-run summary.
+#[derive(Debug, Clone, Copy)]
+struct QuantStats {
+    /// Largest distance between an input point and its quantized reconstruction (metres).
+    max_err_m: f64,
+    used_upm: u32,
+}
 
-    // ---------------------------------------------------------------------
-    // Output path handling
-    // ---------------------------------------------------------------------
-    let out_path = Path::new(&args.output_dir).join(format!(
-        "{}.hypc",
-        Path::new(prefix)
-            .file_stem()
-            .expect("prefix must have a stem")
-            .to_string_lossy()
-    ));
+/// A tile in the output directory: its manifest entry and, if this run wrote
+/// it, its quantization stats.
+struct TileOutcome {
+    stats: Option<QuantStats>,
+    manifest: serde_json::Value,
+}
 
-    if out_path.exists() && !args.overwrite {
-        debug!("Skipping existing file: {}", out_path.display());
-        return Ok(());
+/// Result of `process_one_mesh` for one work item.
+enum MeshOutcome {
+    Written(TileOutcome),
+    /// Existing output kept (no `--overwrite`), with its manifest entry.
+    Existing(serde_json::Value),
+    /// No usable vertices.
+    Skipped,
+    /// Parsed, but its bounds miss `--bbox-filter`.
+    OutsideBboxFilter,
+}
+
+/// Largest reconstruction error of `q` against the original points, in metres.
+fn max_reconstruction_error_m(points_m: &[[f64; 3]], q: &Quantized) -> f64 {
+    let inv_upm = 1.0 / q.used_upm as f64;
+    points_m
+        .iter()
+        .zip(&q.points_units)
+        .map(|(p, u)| {
+            let d = |i: usize| (q.anchor_units[i] + u[i] as i64) as f64 * inv_upm - p[i];
+            (d(0) * d(0) + d(1) * d(1) + d(2) * d(2)).sqrt()
+        })
+        .fold(0.0_f64, f64::max)
+}
+
+/// Nearest-rank percentile of an ascending-sorted slice (`q` in `0..=1`).
+fn percentile_sorted(sorted: &[f64], q: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
     }
+    let rank = (q * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
 
-    info!("Processing {} -> {}", path.display(), out_path.display());
+/// Batch-wide quantization summary: error percentiles over the per-tile maxima and
+/// how many tiles ended up at each `used_upm`.
+fn summarize_quantization(stats: &[QuantStats], requested_upm: u32) -> serde_json::Value {
+    let mut errs: Vec<f64> = stats.iter().map(|s| s.max_err_m).collect();
+    errs.sort_by(f64::total_cmp);
 
-    // ---------------------------------------------------------------------
-    // Load raw OBJ vertices (supports plain .obj or .zip containing a single .obj)
-    // ---------------------------------------------------------------------
-    debug!("Loading vertices from {}", path.display());
-    let raw_xyz: Vec<[f64; 3]> = if path.extension().and_then(|s| s.to_str()) == Some("zip") {
+    let mut upm_hist = BTreeMap::<u32, usize>::new();
+    for s in stats {
+        *upm_hist.entry(s.used_upm).or_default() += 1;
+    }
+    let degraded = stats.iter().filter(|s| s.used_upm < requested_upm).count();
+
+    let (median, p95, max) = (
+        percentile_sorted(&errs, 0.5),
+        percentile_sorted(&errs, 0.95),
+        errs.last().copied().unwrap_or(0.0),
+    );
+    info!(
+        "Quantization summary over {} tiles: max error median {:.3} mm, p95 {:.3} mm, max {:.3} mm; \
+         {} tile(s) below the requested {} u/m; used_upm distribution {:?}",
+        stats.len(),
+        median * 1e3,
+        p95 * 1e3,
+        max * 1e3,
+        degraded,
+        requested_upm,
+        upm_hist
+    );
+
+    serde_json::json!({
+        "tiles": stats.len(),
+        "max_error_m": { "median": median, "p95": p95, "max": max },
+        "degraded_tiles": degraded,
+        "used_upm": upm_hist
+            .iter()
+            .map(|(upm, n)| (upm.to_string(), serde_json::json!(n)))
+            .collect::<serde_json::Map<_, _>>(),
+    })
+}
+
+/// A mesh's vertices converted to ECEF metres, plus the lon/lat bounds seen for
+/// geodetic input and the ellipsoidal height range for geodetic or local input
+/// (infinite otherwise).
+struct MeshEcef {
+    cs: InputCs,
+    points_m: Vec<[f64; 3]>,
+    /// Per-point colors, aligned with `points_m`.
+    colors: Option<Vec<[u8; 3]>>,
+    lon_min: f64,
+    lon_max: f64,
+    lat_min: f64,
+    lat_max: f64,
+    h_min: f64,
+    h_max: f64,
+}
+
+impl MeshEcef {
+    /// An empty mesh with inverted bounds.
+    fn new(cs: InputCs) -> Self {
+        Self {
+            cs,
+            points_m: Vec::new(),
+            colors: None,
+            lon_min: f64::INFINITY,
+            lon_max: f64::NEG_INFINITY,
+            lat_min: f64::INFINITY,
+            lat_max: f64::NEG_INFINITY,
+            h_min: f64::INFINITY,
+            h_max: f64::NEG_INFINITY,
+        }
+    }
+
+    /// Converts one `Geodetic` (`[lon, lat, h_m]`) or `Ecef` vertex and
+    /// appends it, widening the bounds.
+    fn push(&mut self, p: [f64; 3]) {
+        match self.cs {
+            InputCs::Geodetic => {
+                let [lon, lat, h_m] = p;
+                self.lon_min = self.lon_min.min(lon);
+                self.lon_max = self.lon_max.max(lon);
+                self.lat_min = self.lat_min.min(lat);
+                self.lat_max = self.lat_max.max(lat);
+                self.h_min = self.h_min.min(h_m);
+                self.h_max = self.h_max.max(h_m);
+                self.points_m.push(geodetic_to_ecef(lat, lon, h_m));
+            }
+            InputCs::Ecef => self.points_m.push(p),
+            InputCs::LocalM | InputCs::Auto => {
+                unreachable!("{} vertices need the whole mesh to convert", self.cs)
+            }
+        }
+    }
+
+    /// Logs the bounds gathered by [`MeshEcef::push`].
+    fn log_bounds(&self) {
+        use log::debug;
+
+        match self.cs {
+            InputCs::Geodetic => {
+                debug!("Geodetic bounds: lon=[{:.6}, {:.6}], lat=[{:.6}, {:.6}]", self.lon_min, self.lon_max, self.lat_min, self.lat_max);
+                debug!("Height range: [{:.3}, {:.3}]m", self.h_min, self.h_max);
+            }
+            InputCs::Ecef => {
+                let (mut min_x, mut max_x) = (f64::INFINITY, f64::NEG_INFINITY);
+                let (mut min_y, mut max_y) = (f64::INFINITY, f64::NEG_INFINITY);
+                let (mut min_z, mut max_z) = (f64::INFINITY, f64::NEG_INFINITY);
+                for &[x, y, z] in &self.points_m {
+                    min_x = min_x.min(x); max_x = max_x.max(x);
+                    min_y = min_y.min(y); max_y = max_y.max(y);
+                    min_z = min_z.min(z); max_z = max_z.max(z);
+                }
+                debug!("ECEF bounds: X=[{:.1}, {:.1}], Y=[{:.1}, {:.1}], Z=[{:.1}, {:.1}]m",
+                       min_x, max_x, min_y, max_y, min_z, max_z);
+            }
+            InputCs::LocalM | InputCs::Auto => {}
+        }
+    }
+}
+
+/// Opens the OBJ at `path`, or the `.obj` inside it if it is a `.zip`, and
+/// hands the reader to `f`.
+fn with_obj_reader<T>(path: &Path, f: impl FnOnce(&mut dyn Read) -> Result<T>) -> Result<T> {
+    use log::debug;
+
+    if path.extension().and_then(|s| s.to_str()) == Some("zip") {
         debug!("Opening ZIP archive");
         let file = File::open(path)?;
 
@@ -1320,28 +2453,117 @@ fn process_one_mesh(
 
         debug!("Found OBJ file in ZIP: {}", obj_name);
         let mut obj_file = archive.by_name(&obj_name)?;
-
-        parse_obj_vertices(&mut obj_file)?
+        f(&mut obj_file)
     } else {
         debug!("Opening OBJ file directly");
-        parse_obj_vertices(File::open(path)?)?
+        f(&mut File::open(path)?)
+    }
+}
+
+/// Loads a mesh's vertices and converts them to finite ECEF metres.
+///
+/// An OBJ with a forced `Geodetic` or `Ecef` `--input-cs` is streamed: each
+/// vertex is converted as it is read, so the raw vertices are never held in
+/// memory. Auto-detection samples the whole file, and `LocalM` needs the planar
+/// extent of every vertex for its origin, so both read all raw vertices first;
+/// so does PLY input.
+///
+/// Returns `None` if the mesh has no usable vertices.
+fn load_mesh_ecef(path: &Path, args: &Args, bbox: Option<GeoBboxDeg>) -> Result<Option<MeshEcef>> {
+    use log::debug;
+
+    let is_ply = path.extension().is_some_and(|e| e.eq_ignore_ascii_case("ply"));
+    let mut mesh = match args.input_cs {
+        cs @ (InputCs::Geodetic | InputCs::Ecef) if !is_ply => {
+            debug!("Streaming vertices from {} as {}", path.display(), cs);
+            info!("Input CS (forced): {cs}");
+            let mesh = with_obj_reader(path, |r| stream_obj_ecef(r, cs, args.read_normals))?;
+            if mesh.points_m.is_empty() {
+                warn!("{}: no vertices", path.display());
+                return Ok(None);
+            }
+            debug!("Converted {} vertices to ECEF", mesh.points_m.len());
+            mesh
+        }
+        _ => match load_mesh_ecef_buffered(path, args, bbox, is_ply)? {
+            Some(mesh) => mesh,
+            None => return Ok(None),
+        },
     };
 
+    // ---------------------------------------------------------------------
+    // Drop non-finite ECEF points (poles, degenerate bboxes) so they can't
+    // poison the anchor or hit the NaN `as i64` cast during quantization.
+    // ---------------------------------------------------------------------
+    let converted = mesh.points_m.len();
+    if let Some(colors) = mesh.colors.as_mut() {
+        let mut points = mesh.points_m.iter();
+        colors.retain(|_| points.next().is_some_and(|p| p.iter().all(|c| c.is_finite())));
+    }
+    mesh.points_m.retain(|p: &[f64; 3]| p.iter().all(|c| c.is_finite()));
+    let dropped = converted - mesh.points_m.len();
+
+    if dropped > 0 {
+        warn!(
+            "{}: dropped {} of {} non-finite points after {} -> ECEF conversion",
+            path.display(),
+            dropped,
+            converted,
+            mesh.cs
+        );
+    }
+
+    if mesh.points_m.is_empty() {
+        warn!("{}: no finite points after conversion", path.display());
+        return Ok(None);
+    }
+
+    Ok(Some(mesh))
+}
+
+/// The non-streaming half of [`load_mesh_ecef`]: reads every raw vertex, picks
+/// the coordinate system, then converts. Returns `None` if there are no vertices.
+fn load_mesh_ecef_buffered(
+    path: &Path,
+    args: &Args,
+    bbox: Option<GeoBboxDeg>,
+    is_ply: bool,
+) -> Result<Option<MeshEcef>> {
+    use log::debug;
+
+    // ---------------------------------------------------------------------
+    // Load raw vertices (plain .obj or .ply, or a .zip containing a single .obj)
+    // ---------------------------------------------------------------------
+    debug!("Loading vertices from {}", path.display());
+    let obj = if is_ply {
+        debug!("Opening PLY file");
+        ply::parse_ply_vertices(BufReader::new(File::open(path)?), args.read_normals)?
+    } else {
+        with_obj_reader(path, |r| parse_obj_vertices(r, args.read_normals))?
+    };
+    let raw_xyz = obj.xyz;
+
     if raw_xyz.is_empty() {
         warn!("{}: no vertices", path.display());
-        return Ok(());
+        return Ok(None);
     }
 
     debug!("Loaded {} raw vertices", raw_xyz.len());
+    if obj.colors.is_some() {
+        debug!("Vertices carry colors");
+    }
+    if let Some(normals) = &obj.normals {
+        debug!("Read {} vertex normals (not stored in HYPC)", normals.len());
+    }
 
     // ---------------------------------------------------------------------
     // Determine coordinate system (auto‑detect if requested)
     // ---------------------------------------------------------------------
     let cs = match args.input_cs {
         InputCs::Auto => {
-            debug!("Auto-detecting coordinate system from {} sample vertices", raw_xyz.len().min(4096));
-            let sample_len = raw_xyz.len().min(4096);
-            let guess = detect_input_cs(&raw_xyz[..sample_len]);
+            let sample = sample_vertices(&raw_xyz, CS_SAMPLE_LEN, args.seed);
+            debug!("Auto-detecting coordinate system from {} sample vertices", sample.len());
+            let guess = detect_input_cs(&sample);
 
             info!("Input CS (auto‑detected): {guess}");
             guess
@@ -1357,42 +2579,22 @@ fn process_one_mesh(
     // Convert vertices to ECEF metres and optionally track lon/lat bounds
     // ---------------------------------------------------------------------
     debug!("Converting {} vertices from {:?} to ECEF", raw_xyz.len(), cs);
-    let mut points_m = Vec::with_capacity(raw_xyz.len());
-    let mut lon_min = f64::INFINITY;
-    let mut lon_max = f64::NEG_INFINITY;
-    let mut lat_min = f64::INFINITY;
-    let mut lat_max = f64::NEG_INFINITY;
+    let mut mesh = MeshEcef::new(cs);
+    mesh.colors = obj.colors;
 
     match cs {
         InputCs::Geodetic => {
             debug!("Processing {} geodetic coordinates (lon, lat, height)", raw_xyz.len());
-            for &[lon, lat, h_m] in &raw_xyz {
-                lon_min = lon_min.min(lon);
-                lon_max = lon_max.max(lon);
-                lat_min = lat_min.min(lat);
-                lat_max = lat_max.max(lat);
-                points_m.push(geodetic_to_ecef(lat, lon, h_m));
+            mesh.points_m.reserve_exact(raw_xyz.len());
+            for &p in &raw_xyz {
+                mesh.push(p);
             }
-            debug!("Geodetic bounds: lon=[{:.6}, {:.6}], lat=[{:.6}, {:.6}]", lon_min, lon_max, lat_min, lat_max);
-            debug!("Height range: [{:.3}, {:.3}]m",
-                   raw_xyz.iter().map(|p| p[2]).fold(f64::INFINITY, f64::min),
-                   raw_xyz.iter().map(|p| p[2]).fold(f64::NEG_INFINITY, f64::max));
+            mesh.log_bounds();
         }
         InputCs::Ecef => {
             debug!("Using {} ECEF coordinates directly", raw_xyz.len());
-            points_m.extend(raw_xyz.iter().copied());
-
-            // Calculate some basic statistics for debugging
-            let (mut min_x, mut max_x) = (f64::INFINITY, f64::NEG_INFINITY);
-            let (mut min_y, mut max_y) = (f64::INFINITY, f64::NEG_INFINITY);
-            let (mut min_z, mut max_z) = (f64::INFINITY, f64::NEG_INFINITY);
-            for &[x, y, z] in &raw_xyz {
-                min_x = min_x.min(x); max_x = max_x.max(x);
-                min_y = min_y.min(y); max_y = max_y.max(y);
-                min_z = min_z.min(z); max_z = max_z.max(z);
-            }
-            debug!("ECEF bounds: X=[{:.1}, {:.1}], Y=[{:.1}, {:.1}], Z=[{:.1}, {:.1}]m",
-                   min_x, max_x, min_y, max_y, min_z, max_z);
+            mesh.points_m = raw_xyz;
+            mesh.log_bounds();
         }
         InputCs::LocalM => {
             debug!("Converting {} local ENU coordinates to ECEF", raw_xyz.len());
@@ -1417,7 +2619,8 @@ fn process_one_mesh(
                 min_y = min_y.min(y); max_y = max_y.max(y);
             }
 
-            // Derive a planar origin so large UTM-like values become small offsets
+            // Derive a planar origin so large UTM-like values become small offsets.
+            // It depends on every vertex, which is why LocalM input cannot stream.
             let e0 = 0.5 * (min_x + max_x);
             let n0 = 0.5 * (min_y + max_y);
 
@@ -1447,6 +2650,8 @@ fn process_one_mesh(
             let meters_to_deg_lat = (1.0 / m).to_degrees();
             let meters_to_deg_lon = (1.0 / (n * cos_lat_c)).to_degrees();
 
+            mesh.points_m.reserve_exact(raw_xyz.len());
+
             // Transform each point by calculating its precise geodetic coordinate
             // and then converting to ECEF. This replaces the flawed tangent
             // plane approximation.
@@ -1463,9 +2668,11 @@ fn process_one_mesh(
                 let point_lat = lat_c + d_lat;
                 let point_lon = lon_c + d_lon;
                 let point_h = z_u; // Assume z_u is height above ellipsoid
+                mesh.h_min = mesh.h_min.min(point_h);
+                mesh.h_max = mesh.h_max.max(point_h);
 
                 // Convert this precise geodetic coordinate to ECEF
-                points_m.push(geodetic_to_ecef(point_lat, point_lon, point_h));
+                mesh.points_m.push(geodetic_to_ecef(point_lat, point_lon, point_h));
             }
 
             debug!("Successfully transformed {} ENU coordinates to ECEF with curvature correction", raw_xyz.len());
@@ -1473,26 +2680,181 @@ fn process_one_mesh(
         InputCs::Auto => unreachable!(),
     }
 
+    Ok(Some(mesh))
+}
+
+/// Output HYPC path for a work item prefix.
+fn tile_out_path(output_dir: &str, prefix: &str) -> PathBuf {
+    Path::new(output_dir).join(format!(
+        "{}.hypc",
+        Path::new(prefix)
+            .file_stem()
+            .expect("prefix must have a stem")
+            .to_string_lossy()
+    ))
+}
+
+/// Writes the legacy flat point file (see `--agent-format`) from already-written
+/// HYPC tiles.
+///
+/// Layout: u64 LE point count, then f32 LE ECEF xyz (metres) per point. Tiles are
+/// concatenated in the given order, so global point ID = the tile's first ID + its
+/// index within the HYPC tile. Returns one catalog entry per tile with that offset.
+fn write_agent_points(out_path: &Path, tiles: &[PathBuf]) -> Result<Vec<serde_json::Value>> {
+    // Header pass: the count comes first, so size every tile before writing points.
+    let counts = tiles
+        .iter()
+        .map(|t| Ok(hypc::validate_file(t)?.points_count as u64))
+        .collect::<std::io::Result<Vec<u64>>>()
+        .context("sizing tiles for the agent point file")?;
+    let total: u64 = counts.iter().sum();
+
+    let mut w = BufWriter::new(
+        File::create(out_path).with_context(|| format!("creating {}", out_path.display()))?,
+    );
+    w.write_all(&total.to_le_bytes())?;
+
+    let mut entries = Vec::with_capacity(tiles.len());
+    let mut first_id = 0u64;
+    for (path, &count) in tiles.iter().zip(&counts) {
+        let tile = hypc::read_file(path).with_context(|| format!("reading {}", path.display()))?;
+        for p in tile.iter_points_ecef_m() {
+            for c in p {
+                w.write_all(&(c as f32).to_le_bytes())?;
+            }
+        }
+        entries.push(serde_json::json!({
+            "tile": path.file_name().map(|n| n.to_string_lossy()),
+            "first_point_id": first_id,
+            "points": count,
+        }));
+        first_id += count;
+    }
+    w.flush()?;
+
+    info!(
+        "Wrote agent point file {} ({} points from {} tiles)",
+        out_path.display(),
+        total,
+        tiles.len()
+    );
+    Ok(entries)
+}
+
+/// True if the mesh's own lon/lat bounds miss `filter`. Only geodetic input
+/// has such bounds; anything else is never filtered out here.
+fn mesh_outside_filter(mesh: &MeshEcef, filter: &GeoBboxDeg) -> bool {
+    let bounds = GeoBboxDeg {
+        lon_min: mesh.lon_min,
+        lat_min: mesh.lat_min,
+        lon_max: mesh.lon_max,
+        lat_max: mesh.lat_max,
+    };
+    matches!(mesh.cs, InputCs::Geodetic)
+        && [bounds.lon_min, bounds.lat_min, bounds.lon_max, bounds.lat_max]
+            .iter()
+            .all(|v| v.is_finite())
+        && !bounds.intersects(filter)
+}
+
+fn process_one_mesh(
+    path: &Path,
+    args: &Args,
+    prefix: &str,
+    bbox: Option<GeoBboxDeg>,
+    overlays: Option<&SemOverlayPerTile>,
+    units_per_meter: u32,
+    palette: &SemPalette,
+) -> Result<MeshOutcome> {
+    use log::debug;
+
+    // ---------------------------------------------------------------------
+    // Output path handling
+    // ---------------------------------------------------------------------
+    let out_path = tile_out_path(&args.output_dir, prefix);
+
+    if out_path.exists() && !args.overwrite {
+        debug!("Skipping existing file: {}", out_path.display());
+        let tile = hypc::read_file(&out_path)
+            .with_context(|| format!("reading existing {}", out_path.display()))?;
+        let heights_path = Some(out_path.with_extension("bheight")).filter(|p| p.exists());
+        return Ok(MeshOutcome::Existing(tile_manifest(
+            prefix,
+            &out_path,
+            &tile,
+            heights_path.as_deref(),
+        )));
+    }
+
+    info!("Processing {} -> {}", path.display(), out_path.display());
+
+    let Some(mesh) = load_mesh_ecef(path, args, bbox)? else {
+        return Ok(MeshOutcome::Skipped);
+    };
+    // Tiles with a feature bbox were already filtered before loading.
+    if let (None, Some(filter)) = (bbox, &args.bbox_filter) {
+        if mesh_outside_filter(&mesh, filter) {
+            debug!("{}: outside --bbox-filter", path.display());
+            return Ok(MeshOutcome::OutsideBboxFilter);
+        }
+    }
+    let MeshEcef {
+        cs,
+        mut points_m,
+        mut colors,
+        lon_min,
+        lon_max,
+        lat_min,
+        lat_max,
+        h_min,
+        h_max,
+    } = mesh;
+
+    // ---------------------------------------------------------------------
+    // Optional voxel-grid downsampling
+    // ---------------------------------------------------------------------
+    if args.voxel_size_m > 0.0 {
+        let before = points_m.len();
+        (points_m, colors) = voxel_downsample(&points_m, colors.as_deref(), args.voxel_size_m);
+        info!(
+            "{}: voxel grid {} m kept {} of {} points",
+            path.display(),
+            args.voxel_size_m,
+            points_m.len(),
+            before
+        );
+    }
+
     // ---------------------------------------------------------------------
     // Quantize coordinates with a safe units‑per‑meter value.
     // ---------------------------------------------------------------------
-    debug!("Quantizing with requested units_per_meter: {}", args.units_per_meter);
-    let q = quantize_with_anchor(&points_m, args.units_per_meter);
+    debug!("Quantizing with requested units_per_meter: {}", units_per_meter);
+    let q = quantize_with_anchor(&points_m, units_per_meter, args.anchor_ecef);
 
-    if q.used_upm != args.units_per_meter {
-        debug!("Quantization used reduced units_per_meter: {} -> {}", args.units_per_meter, q.used_upm);
+    if q.used_upm != units_per_meter {
+        debug!("Quantization used reduced units_per_meter: {} -> {}", units_per_meter, q.used_upm);
     }
     debug!("Quantized {} points with anchor: [{}, {}, {}]",
            q.points_units.len(), q.anchor_units[0], q.anchor_units[1], q.anchor_units[2]);
 
+    let stats = QuantStats {
+        max_err_m: max_reconstruction_error_m(&points_m, &q),
+        used_upm: q.used_upm,
+    };
+    debug!("Max reconstruction error: {:.4} mm", stats.max_err_m * 1e3);
+
     // ---------------------------------------------------------------------
     // Optional SMC1 semantic mask
     // ---------------------------------------------------------------------
+    let mut building_heights = None;
     let smc1_opt = if args.write_smc1 {
         if let (Some(bb), Some(ov)) = (bbox, overlays) {
             debug!("Building SMC1 semantic mask {}x{} with {} roads, {} areas",
                    args.sem_grid, args.sem_grid, ov.roads.len(), ov.areas.len());
-            let mask = build_smc1_mask(ov, bb, args.sem_grid);
+            let mask = build_smc1_mask(ov, bb, args.sem_grid, &palette.precedence, args.smc1_aa);
+            if args.write_building_heights {
+                building_heights = Some(mask.heights.clone());
+            }
             let (encoding, data) = if args.smc1_compress {
                 let compressed = smc1_encode_rle(&mask.data);
                 debug!("SMC1 RLE compression: {} -> {} bytes ({:.1}%)",
@@ -1510,9 +2872,7 @@ fn process_one_mesh(
                 coord_space: Smc1CoordSpace::Crs84BboxNorm,
                 encoding,
                 data,
-                palette: (0u8..=9u8)
-                    .map(|i| (i, class_precedence(i)))
-                    .collect(),
+                palette: palette.entries.clone(),
             })
         } else {
             debug!("SMC1 requested but no bbox or overlays available");
@@ -1530,12 +2890,7 @@ fn process_one_mesh(
         if let Some(bb) = bbox {
             debug!("Using GEOT from bbox: lon=[{:.6}, {:.6}], lat=[{:.6}, {:.6}]",
                    bb.lon_min, bb.lon_max, bb.lat_min, bb.lat_max);
-            Some(GeoExtentQ7::from_deg(
-                bb.lon_min,
-                bb.lon_max,
-                bb.lat_min,
-                bb.lat_max,
-            ))
+            Some(bb.to_q7())
         } else if matches!(cs, InputCs::Geodetic)
             && lon_min.is_finite()
             && lon_max.is_finite()
@@ -1553,22 +2908,65 @@ fn process_one_mesh(
         debug!("GEOT generation disabled");
         None
     };
+    // Heights are known for geodetic and local input; ECEF input keeps plain GEOT.
+    let geot = geot.map(|g| {
+        if h_min.is_finite() && h_max.is_finite() {
+            debug!("GEOT height range: [{:.3}, {:.3}]m", h_min, h_max);
+            g.with_height_m(h_min, h_max)
+        } else {
+            g
+        }
+    });
 
     // ---------------------------------------------------------------------
     // Assemble the HYPC tile and write it to disk
     // ---------------------------------------------------------------------
-    let tile = HypcTile {
+    let mut tile = HypcTile {
         units_per_meter: q.used_upm,
         anchor_ecef_units: q.anchor_units,
         tile_key: Some(tilekey_from_prefix(prefix)),
         points_units: q.points_units,
+        points_encoding: PointsEncoding::Raw,
+        points_codec: PointsCodec::None,
         labels: None,
+        colors,
         geot,
         smc1: smc1_opt,
+        transform: None,
+        crc: args.write_crc,
     };
+    if args.bake_labels {
+        tile.labels = tile.smc1_point_labels()?;
+        match &tile.labels {
+            Some(_) if args.drop_smc1 => {
+                debug!("Baked SMC1 labels, dropping the mask");
+                tile.smc1 = None;
+            }
+            Some(_) => debug!("Baked SMC1 labels"),
+            None => debug!("No SMC1 mask to bake labels from"),
+        }
+    }
+    if args.delta_points {
+        tile.sort_morton();
+        tile.points_encoding = PointsEncoding::Delta;
+    }
+    if args.deflate_points {
+        tile.points_codec = PointsCodec::Deflate;
+    }
 
     debug!("Writing HYPC tile to {}", out_path.display());
-    hypc::write_file(&out_path, &tile)?;
+    hypc::write_file_atomic(&out_path, &tile)?;
+
+    let heights_path = match building_heights {
+        Some(heights) => {
+            let heights_path = out_path.with_extension("bheight");
+            debug!("Writing building heights to {}", heights_path.display());
+            fs::write(&heights_path, heights)
+                .with_context(|| format!("writing {}", heights_path.display()))?;
+            Some(heights_path)
+        }
+        None => None,
+    };
 
     info!(
         "OK {} -> {} ({} pts, {} u/m)",
@@ -1578,7 +2976,34 @@ fn process_one_mesh(
         tile.units_per_meter
     );
 
-    Ok(())
+    let manifest = tile_manifest(prefix, &out_path, &tile, heights_path.as_deref());
+    Ok(MeshOutcome::Written(TileOutcome {
+        stats: Some(stats),
+        manifest,
+    }))
+}
+
+/// The manifest entry of the tile for `prefix`, written to `out_path` along
+/// with its building heights at `heights_path`, if any.
+fn tile_manifest(
+    prefix: &str,
+    out_path: &Path,
+    tile: &HypcTile,
+    heights_path: Option<&Path>,
+) -> serde_json::Value {
+    serde_json::json!({
+        "prefix": prefix,
+        "file": out_path.file_name().map(|f| f.to_string_lossy()),
+        "points": tile.points_units.len(),
+        "units_per_meter": tile.units_per_meter,
+        "anchor_ecef_units": tile.anchor_ecef_units,
+        "geot": tile.geot.map(|g| <[f64; 4]>::from(g.to_deg())),
+        "smc1": tile.smc1.is_some(),
+        "labels": tile.labels.is_some(),
+        "building_heights": heights_path
+            .and_then(|p| p.file_name())
+            .map(|f| f.to_string_lossy()),
+    })
 }
 
 fn main() -> Result<()> {
@@ -1588,11 +3013,18 @@ fn main() -> Result<()> {
     let args = Args::parse();
     fs::create_dir_all(&args.output_dir)?;
 
-    // Index all OBJ/ZIP files in the input directory.
-    let local_index = build_local_index(&args.input_dir);
+    // Pool for the per-tile loops. With one worker, rayon runs them in order.
+    let jobs = if args.single_thread { 1 } else { args.jobs };
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(jobs)
+        .build()
+        .context("building the worker pool")?;
+
+    // Index all OBJ/PLY/ZIP files in the input directory.
+    let local_index = build_local_index(&args.input_dir, args.prefer_zip);
 
     // Determine work items, optionally filtering with a feature index.
-    let work_items: Vec<WorkItem> = match &args.feature_index {
+    let mut work_items: Vec<WorkItem> = match &args.feature_index {
         Some(feature_path) => {
             let mut items = load_feature_index(feature_path)?;
             items.retain(|item| {
@@ -1610,6 +3042,21 @@ fn main() -> Result<()> {
             .collect(),
     };
 
+    // Tiles skipped by --bbox-filter: here by feature bbox, later by mesh bounds.
+    let mut outside_filter = 0usize;
+    if let Some(filter) = &args.bbox_filter {
+        let before = work_items.len();
+        work_items.retain(|item| item.bbox.is_none_or(|b| b.intersects(filter)));
+        outside_filter = before - work_items.len();
+
+        if work_items.iter().any(|item| item.bbox.is_none()) {
+            info!(
+                "--bbox-filter: tiles without a feature bbox are parsed before filtering, \
+                 which is slower; only geodetic input can be filtered that way"
+            );
+        }
+    }
+
     // Helper struct that couples a work item with its resolved file path.
     #[derive(Clone)]
     struct ResolvedWorkItem {
@@ -1618,7 +3065,7 @@ fn main() -> Result<()> {
     }
 
     // Resolve each work item to an actual file on disk.
-    let resolved_items: Vec<ResolvedWorkItem> = work_items
+    let mut resolved_items: Vec<ResolvedWorkItem> = work_items
         .iter()
         .filter_map(|work_item| {
             resolve_by_prefix(&local_index, &work_item.prefix, args.prefer_zip).map(|path| {
@@ -1629,6 +3076,22 @@ fn main() -> Result<()> {
             })
         })
         .collect();
+    if args.single_thread {
+        resolved_items.sort_by(|a, b| a.item.prefix.cmp(&b.item.prefix));
+    }
+
+    let class_config = match &args.class_config {
+        Some(path) => {
+            let config = ClassConfig::load(path)?;
+            info!(
+                "Loaded SMC1 classes {:?} from {}",
+                config.class_ids(),
+                path.display()
+            );
+            Some(config)
+        }
+        None => None,
+    };
 
     // Build semantic overlays once if an OSM PBF file was supplied.
     let overlays_map = if let Some(pbf_path) = &args.osm_pbf {
@@ -1643,33 +3106,252 @@ fn main() -> Result<()> {
             args.osm_margin_m,
             args.osm_log_every,
             args.osm_prefilter,
+            class_config.as_ref(),
         )?))
     } else {
         None
     };
 
+    // Effective SMC1 paint precedence (built-in or --class-config ordering plus
+    // --class-precedence) and the palette written with each mask.
+    let palette = SemPalette::new(class_config.as_ref(), &args.class_precedence);
+    for &(class, p) in &args.class_precedence {
+        info!("SMC1 precedence override: class {} -> {}", class, p);
+    }
+
+    if let Some([x, y, z]) = args.anchor_ecef {
+        info!(
+            "Fixed ECEF anchor for this batch: [{:.3}, {:.3}, {:.3}] m",
+            x, y, z
+        );
+    }
+
+    // With --fixed-upm, size every tile first so the whole batch shares one scale.
+    let units_per_meter = if args.fixed_upm {
+        // Largest UPM that fits one tile; None if it is unusable or filtered out.
+        let tile_upm = |ri: &ResolvedWorkItem| match load_mesh_ecef(&ri.path, &args, ri.item.bbox) {
+            Ok(Some(mesh))
+                if ri.item.bbox.is_none()
+                    && args
+                        .bbox_filter
+                        .is_some_and(|f| mesh_outside_filter(&mesh, &f)) =>
+            {
+                None
+            }
+            Ok(Some(mesh)) => {
+                let (_, max_off_m) = anchor_and_max_offset(&mesh.points_m, args.anchor_ecef);
+                Some(max_upm_for_offset(max_off_m, args.units_per_meter))
+            }
+            Ok(None) => None,
+            Err(err) => {
+                warn!("Error sizing {}: {:#}", ri.path.display(), err);
+                None
+            }
+        };
+        let upm = pool
+            .install(|| resolved_items.par_iter().filter_map(tile_upm).min())
+            .unwrap_or(args.units_per_meter);
+
+        if upm < args.units_per_meter {
+            warn!(
+                "--fixed-upm: largest tile forces a global units_per_meter of {} (requested {}); \
+                 all tiles lose resolution",
+                upm, args.units_per_meter
+            );
+        }
+        info!("Fixed units_per_meter for this batch: {}", upm);
+
+        upm
+    } else {
+        args.units_per_meter
+    };
+
     info!("Processing {} items...", resolved_items.len());
 
     // Process meshes in parallel, reporting any errors.
-    resolved_items.par_iter().for_each(|resolved_item| {
+    let outside_filter_parsed = AtomicUsize::new(0);
+    let outcomes: Vec<TileOutcome> = pool.install(|| resolved_items.par_iter().filter_map(|resolved_item| {
         let overlay = overlays_map
             .as_ref()
             .and_then(|map| map.get(&resolved_item.item.prefix));
 
-        if let Err(err) = process_one_mesh(
+        match process_one_mesh(
             &resolved_item.path,
             &args,
             &resolved_item.item.prefix,
             resolved_item.item.bbox,
             overlay,
+            units_per_meter,
+            &palette,
         ) {
-            warn!(
-                "Error processing {}: {:#}",
-                resolved_item.path.display(),
-                err
-            );
+            Ok(MeshOutcome::Written(outcome)) => Some(outcome),
+            Ok(MeshOutcome::Existing(manifest)) => Some(TileOutcome {
+                stats: None,
+                manifest,
+            }),
+            Ok(MeshOutcome::Skipped) => None,
+            Ok(MeshOutcome::OutsideBboxFilter) => {
+                outside_filter_parsed.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+            Err(err) => {
+                warn!(
+                    "Error processing {}: {:#}",
+                    resolved_item.path.display(),
+                    err
+                );
+                None
+            }
         }
+    }).collect());
+    let stats: Vec<QuantStats> = outcomes.iter().filter_map(|o| o.stats).collect();
+
+    if args.bbox_filter.is_some() {
+        let parsed = outside_filter_parsed.into_inner();
+        info!(
+            "--bbox-filter skipped {} tiles ({} by feature bbox, {} after parsing)",
+            outside_filter + parsed,
+            outside_filter,
+            parsed
+        );
+    }
+
+    // Optional legacy point file: every tile of this run (new or pre-existing), in name order.
+    let agent_points = if args.agent_format {
+        let mut tiles: Vec<PathBuf> = resolved_items
+            .iter()
+            .map(|ri| tile_out_path(&args.output_dir, &ri.item.prefix))
+            .filter(|p| p.exists())
+            .collect();
+        tiles.sort();
+        tiles.dedup();
+
+        let path = Path::new(&args.output_dir).join("agent_points.bin");
+        let entries = write_agent_points(&path, &tiles)?;
+        serde_json::json!({ "path": "agent_points.bin", "tiles": entries })
+    } else {
+        serde_json::Value::Null
+    };
+
+    // Batch catalog: the UPM policy for the run plus the quantization summary.
+    let catalog = serde_json::json!({
+        "units_per_meter": units_per_meter,
+        "fixed_upm": args.fixed_upm,
+        "anchor_ecef_m": args.anchor_ecef,
+        "tiles": resolved_items.len(),
+        "quantization": summarize_quantization(&stats, units_per_meter),
+        "agent_points": agent_points,
     });
+    let catalog_path = Path::new(&args.output_dir).join("catalog.json");
+    fs::write(&catalog_path, serde_json::to_string_pretty(&catalog)?)
+        .with_context(|| format!("writing {}", catalog_path.display()))?;
+
+    // Per-tile manifest of every tile this run wrote or kept, in work item order.
+    if !args.no_manifest {
+        let manifest_path = match &args.manifest {
+            Some(path) => PathBuf::from(path),
+            None => Path::new(&args.output_dir).join("manifest.json"),
+        };
+        let tiles: Vec<&serde_json::Value> = outcomes.iter().map(|o| &o.manifest).collect();
+        let manifest = serde_json::json!({ "tiles": tiles });
+        fs::write(&manifest_path, serde_json::to_string_pretty(&manifest)?)
+            .with_context(|| format!("writing {}", manifest_path.display()))?;
+        info!("Wrote manifest for {} tiles to {}", tiles.len(), manifest_path.display());
+    }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn empty_mask(n: u16) -> SemMask {
+        let len = n as usize * n as usize;
+        SemMask {
+            w: n,
+            h: n,
+            data: vec![0; len],
+            heights: vec![0; len],
+        }
+    }
+
+    /// Distance from `p` to the nearest edge of `poly`.
+    fn edge_distance(poly: &[(f32, f32)], p: (f32, f32)) -> f32 {
+        (0..poly.len())
+            .map(|i| {
+                let (a, b) = (poly[i], poly[(i + 1) % poly.len()]);
+                let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+                let t =
+                    (((p.0 - a.0) * dx + (p.1 - a.1) * dy) / (dx * dx + dy * dy)).clamp(0.0, 1.0);
+                (p.0 - a.0 - t * dx).hypot(p.1 - a.1 - t * dy)
+            })
+            .fold(f32::INFINITY, f32::min)
+    }
+
+    /// `poly` through the plain rasterizer, snapped as `uv_to_pixel` snaps.
+    fn plain(mask: &mut SemMask, poly: &[(f32, f32)]) {
+        let ring = poly
+            .iter()
+            .map(|&(x, y)| (x.round() as i32, y.round() as i32))
+            .collect();
+        rasterize_polygon(mask, &[ring], 1, 0, &[0; 256]);
+    }
+
+    #[test]
+    fn aa_rasterization_differs_from_plain_near_edges() {
+        let tri = [(2.3, 2.2), (13.6, 3.1), (5.4, 12.8)];
+        let mut hard = empty_mask(16);
+        plain(&mut hard, &tri);
+        let mut aa = empty_mask(16);
+        rasterize_polygon_aa(&mut aa, &[tri.to_vec()], 1, 0, &[0; 256]);
+
+        // Both fill the interior and leave the far corner alone.
+        for mask in [&hard, &aa] {
+            assert_eq!(mask.data[6 * 16 + 6], 1);
+            assert_eq!(mask.data[15 * 16 + 15], 0);
+        }
+
+        // They disagree somewhere, and only on pixels along the edges.
+        let differing: Vec<(f32, f32)> = (0..16 * 16)
+            .filter(|&i| hard.data[i] != aa.data[i])
+            .map(|i| ((i % 16) as f32, (i / 16) as f32))
+            .collect();
+        assert!(!differing.is_empty());
+        for p in differing {
+            assert!(edge_distance(&tri, p) < 1.0, "pixel {:?} differs", p);
+        }
+    }
+
+    #[test]
+    fn aa_rasterization_keeps_sub_pixel_polygons() {
+        let sliver = [(7.1, 7.1), (7.4, 7.05), (7.2, 7.35)];
+        let mut hard = empty_mask(16);
+        plain(&mut hard, &sliver);
+        let mut aa = empty_mask(16);
+        rasterize_polygon_aa(&mut aa, &[sliver.to_vec()], 1, 0, &[0; 256]);
+
+        assert!(hard.data.iter().all(|&c| c == 0));
+        let painted: Vec<usize> = (0..aa.data.len()).filter(|&i| aa.data[i] == 1).collect();
+        assert_eq!(painted, [7 * 16 + 7]);
+    }
+
+    #[test]
+    fn prefer_zip_decides_between_exact_matches() {
+        let dir = std::env::temp_dir().join(format!("obj2hypc-index-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for name in ["tile_7.obj", "tile_7.zip"] {
+            fs::write(dir.join(name), b"").unwrap();
+        }
+        let input_dir = dir.to_str().unwrap();
+
+        for (prefer_zip, expected) in [(true, "tile_7.zip"), (false, "tile_7.obj")] {
+            let index = build_local_index(input_dir, prefer_zip);
+            let found = resolve_by_prefix(&index, "tile_7", prefer_zip).unwrap();
+            assert_eq!(found, dir.join(expected), "prefer_zip = {}", prefer_zip);
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,365 @@
+//! PLY vertex reader: `ascii` and `binary_little_endian` bodies, positions plus
+//! optional `red/green/blue` colors and `nx/ny/nz` normals. Elements other than
+//! `vertex` (faces, edges, ...) are skipped.
+
+use crate::MeshVertices;
+use anyhow::{bail, Context, Result};
+use std::io::{BufRead, Read};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Format {
+    Ascii,
+    BinaryLe,
+}
+
+/// A PLY scalar property type.
+#[derive(Clone, Copy, Debug)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn from_name(name: &str) -> Result<Self> {
+        Ok(match name {
+            "char" | "int8" => Self::I8,
+            "uchar" | "uint8" => Self::U8,
+            "short" | "int16" => Self::I16,
+            "ushort" | "uint16" => Self::U16,
+            "int" | "int32" => Self::I32,
+            "uint" | "uint32" => Self::U32,
+            "float" | "float32" => Self::F32,
+            "double" | "float64" => Self::F64,
+            other => bail!("Unknown PLY property type {:?}", other),
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Self::I8 | Self::U8 => 1,
+            Self::I16 | Self::U16 => 2,
+            Self::I32 | Self::U32 | Self::F32 => 4,
+            Self::F64 => 8,
+        }
+    }
+
+    fn is_float(self) -> bool {
+        matches!(self, Self::F32 | Self::F64)
+    }
+
+    /// Decodes one little-endian value of this type from `b[..self.size()]`.
+    fn decode_le(self, b: &[u8]) -> f64 {
+        match self {
+            Self::I8 => b[0] as i8 as f64,
+            Self::U8 => b[0] as f64,
+            Self::I16 => i16::from_le_bytes([b[0], b[1]]) as f64,
+            Self::U16 => u16::from_le_bytes([b[0], b[1]]) as f64,
+            Self::I32 => i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+            Self::U32 => u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+            Self::F32 => f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+            Self::F64 => f64::from_le_bytes(b[..8].try_into().unwrap()),
+        }
+    }
+}
+
+enum Property {
+    Scalar {
+        name: String,
+        ty: Scalar,
+    },
+    /// A list property (e.g. face indices); read past, never used.
+    List {
+        count: Scalar,
+        item: Scalar,
+    },
+}
+
+struct Element {
+    name: String,
+    count: usize,
+    props: Vec<Property>,
+}
+
+struct Header {
+    format: Format,
+    elements: Vec<Element>,
+}
+
+fn parse_header<R: BufRead>(r: &mut R) -> Result<Header> {
+    let mut line = String::new();
+    let mut next_line = |line: &mut String| -> Result<()> {
+        line.clear();
+        if r.read_line(line)? == 0 {
+            bail!("PLY header ended before end_header");
+        }
+        Ok(())
+    };
+
+    next_line(&mut line)?;
+    if line.trim() != "ply" {
+        bail!("Not a PLY file (missing 'ply' magic)");
+    }
+
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+    loop {
+        next_line(&mut line)?;
+        let mut parts = line.split_whitespace();
+        match parts.next() {
+            Some("format") => {
+                format = Some(match parts.next() {
+                    Some("ascii") => Format::Ascii,
+                    Some("binary_little_endian") => Format::BinaryLe,
+                    other => bail!("Unsupported PLY format {:?}", other.unwrap_or("")),
+                });
+            }
+            Some("element") => {
+                let name = parts.next().context("PLY element without a name")?;
+                let count = parts
+                    .next()
+                    .context("PLY element without a count")?
+                    .parse()
+                    .with_context(|| format!("Bad count for PLY element {}", name))?;
+                elements.push(Element {
+                    name: name.to_owned(),
+                    count,
+                    props: Vec::new(),
+                });
+            }
+            Some("property") => {
+                let element = elements
+                    .last_mut()
+                    .context("PLY property before any element")?;
+                let ty = parts.next().context("PLY property without a type")?;
+                let prop = if ty == "list" {
+                    Property::List {
+                        count: Scalar::from_name(parts.next().context("PLY list without types")?)?,
+                        item: Scalar::from_name(parts.next().context("PLY list without types")?)?,
+                    }
+                } else {
+                    Property::Scalar {
+                        ty: Scalar::from_name(ty)?,
+                        name: parts
+                            .next()
+                            .context("PLY property without a name")?
+                            .to_owned(),
+                    }
+                };
+                element.props.push(prop);
+            }
+            Some("end_header") => break,
+            // comment, obj_info, blank lines
+            _ => {}
+        }
+    }
+
+    Ok(Header {
+        format: format.context("PLY header has no format line")?,
+        elements,
+    })
+}
+
+/// Reads one element instance, storing each scalar property's value in `out`
+/// (lists are skipped and stored as 0).
+fn read_instance<R: BufRead>(
+    r: &mut R,
+    format: Format,
+    props: &[Property],
+    line: &mut String,
+    out: &mut Vec<f64>,
+) -> Result<()> {
+    out.clear();
+    match format {
+        Format::Ascii => {
+            line.clear();
+            if r.read_line(line)? == 0 {
+                bail!("PLY body ended early");
+            }
+            let mut tokens = line.split_whitespace();
+            let mut next = || -> Result<f64> {
+                Ok(tokens
+                    .next()
+                    .context("PLY line has too few values")?
+                    .parse()?)
+            };
+            for prop in props {
+                match prop {
+                    Property::Scalar { .. } => out.push(next()?),
+                    Property::List { .. } => {
+                        for _ in 0..next()? as usize {
+                            next()?;
+                        }
+                        out.push(0.0);
+                    }
+                }
+            }
+        }
+        Format::BinaryLe => {
+            let mut b = [0u8; 8];
+            for prop in props {
+                match *prop {
+                    Property::Scalar { ty, .. } => {
+                        r.read_exact(&mut b[..ty.size()])?;
+                        out.push(ty.decode_le(&b));
+                    }
+                    Property::List { count, item } => {
+                        r.read_exact(&mut b[..count.size()])?;
+                        let n = count.decode_le(&b) as u64;
+                        let skip = n * item.size() as u64;
+                        let mut rest = Read::take(&mut *r, skip);
+                        if std::io::copy(&mut rest, &mut std::io::sink())? != skip {
+                            bail!("PLY body ended early");
+                        }
+                        out.push(0.0);
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Read the vertex positions from a PLY file, with `red/green/blue` colors if
+/// present and, if `read_normals` is set, `nx/ny/nz` normals.
+///
+/// Integer colors are taken as 0–255 and float colors as 0–1. Vertices with a
+/// non-finite position are dropped together with their color and normal.
+pub(crate) fn parse_ply_vertices<R: BufRead>(mut r: R, read_normals: bool) -> Result<MeshVertices> {
+    let header = parse_header(&mut r)?;
+
+    let find = |props: &[Property], want: &str| {
+        props
+            .iter()
+            .position(|p| matches!(p, Property::Scalar { name, .. } if name == want))
+    };
+    let scalar_type = |props: &[Property], i: usize| match props[i] {
+        Property::Scalar { ty, .. } => ty,
+        Property::List { .. } => unreachable!(),
+    };
+
+    let mut line = String::new();
+    let mut values = Vec::new();
+    for element in &header.elements {
+        let props = &element.props;
+        if element.name != "vertex" {
+            for _ in 0..element.count {
+                read_instance(&mut r, header.format, props, &mut line, &mut values)?;
+            }
+            continue;
+        }
+
+        let xyz_idx = [
+            find(props, "x").context("PLY vertex has no x property")?,
+            find(props, "y").context("PLY vertex has no y property")?,
+            find(props, "z").context("PLY vertex has no z property")?,
+        ];
+        let rgb_idx = match (
+            find(props, "red"),
+            find(props, "green"),
+            find(props, "blue"),
+        ) {
+            (Some(r), Some(g), Some(b)) => Some([r, g, b]),
+            _ => None,
+        };
+        let normal_idx = match (find(props, "nx"), find(props, "ny"), find(props, "nz")) {
+            (Some(x), Some(y), Some(z)) if read_normals => Some([x, y, z]),
+            _ => None,
+        };
+        let color_scale = match rgb_idx {
+            Some(idx) if scalar_type(props, idx[0]).is_float() => 255.0,
+            _ => 1.0,
+        };
+
+        let capacity = element.count.min(1 << 20);
+        let mut xyz = Vec::with_capacity(capacity);
+        let mut colors = rgb_idx.map(|_| Vec::with_capacity(capacity));
+        let mut normals = normal_idx.map(|_| Vec::with_capacity(capacity));
+        for _ in 0..element.count {
+            read_instance(&mut r, header.format, props, &mut line, &mut values)?;
+            xyz.push(xyz_idx.map(|i| values[i]));
+            if let (Some(colors), Some(idx)) = (colors.as_mut(), rgb_idx) {
+                colors.push(idx.map(|i| (values[i] * color_scale).round().clamp(0.0, 255.0) as u8));
+            }
+            if let (Some(normals), Some(idx)) = (normals.as_mut(), normal_idx) {
+                normals.push(idx.map(|i| values[i] as f32));
+            }
+        }
+
+        return Ok(MeshVertices::finite(xyz, colors, normals));
+    }
+
+    bail!("PLY file has no vertex element")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VERTICES: [([f64; 3], [f32; 3], [f32; 3]); 2] = [
+        ([1.5, -2.0, 3.25], [0.0, 0.0, 1.0], [1.0, 0.5, 0.0]),
+        ([-4.0, 5.5, 0.125], [0.0, 1.0, 0.0], [0.2, 0.4, 0.6]),
+    ];
+
+    fn header(format: &str) -> String {
+        format!(
+            "ply\nformat {} 1.0\nelement vertex 2\n\
+             property double x\nproperty double y\nproperty double z\n\
+             property float nx\nproperty float ny\nproperty float nz\n\
+             property float red\nproperty float green\nproperty float blue\n\
+             element face 1\nproperty list uchar int vertex_indices\nend_header\n",
+            format
+        )
+    }
+
+    #[test]
+    fn ascii_and_binary_bodies_read_alike() {
+        let mut ascii = header("ascii");
+        for (p, n, c) in VERTICES {
+            let row: Vec<String> = p
+                .iter()
+                .map(f64::to_string)
+                .chain(n.iter().chain(&c).map(f32::to_string))
+                .collect();
+            ascii += &(row.join(" ") + "\n");
+        }
+        ascii += "3 0 1 0\n";
+
+        let mut binary = header("binary_little_endian").into_bytes();
+        for (p, n, c) in VERTICES {
+            for v in p {
+                binary.extend(v.to_le_bytes());
+            }
+            for v in n.iter().chain(&c) {
+                binary.extend(v.to_le_bytes());
+            }
+        }
+        binary.push(3);
+        for i in [0i32, 1, 0] {
+            binary.extend(i.to_le_bytes());
+        }
+
+        for body in [ascii.as_bytes(), &binary] {
+            let mesh = parse_ply_vertices(body, true).unwrap();
+            assert_eq!(mesh.xyz, VERTICES.map(|v| v.0));
+            assert_eq!(mesh.normals.unwrap(), VERTICES.map(|v| v.1));
+            // Float colors are scaled from 0–1 to 0–255.
+            assert_eq!(mesh.colors.unwrap(), [[255, 128, 0], [51, 102, 153]]);
+        }
+    }
+
+    #[test]
+    fn missing_vertex_element_is_an_error() {
+        let ply = "ply\nformat ascii 1.0\nelement face 0\n\
+                   property list uchar int vertex_indices\nend_header\n";
+        let Err(err) = parse_ply_vertices(ply.as_bytes(), false) else {
+            panic!("parsed a PLY without vertices");
+        };
+        assert!(err.to_string().contains("no vertex element"), "{}", err);
+    }
+}
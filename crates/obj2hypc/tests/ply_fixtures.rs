@@ -0,0 +1,158 @@
+//! Runs the `obj2hypc` binary on the PLY inputs in `fixtures/`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+fn fixtures_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures")
+}
+
+/// A fresh, empty scratch directory for one test.
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("obj2hypc-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Converts every file in `input_dir` into `output_dir`, failing on a non-zero exit.
+fn convert(input_dir: &Path, output_dir: &Path, extra_args: &[&str]) {
+    let output = Command::new(env!("CARGO_BIN_EXE_obj2hypc"))
+        .arg("--input-dir")
+        .arg(input_dir)
+        .arg("--output-dir")
+        .arg(output_dir)
+        .args(extra_args)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "obj2hypc failed:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn ascii_and_binary_match_the_goldens() {
+    let out = scratch_dir("golden");
+    convert(
+        &fixtures_dir().join("ply"),
+        &out,
+        &["--input-cs", "ecef", "--overwrite"],
+    );
+
+    // The two inputs hold the same vertices, so only the tile key, taken from
+    // the file name, tells their tiles apart.
+    let [ascii, binary] = ["ascii.hypc", "binary.hypc"].map(|name| {
+        let mut tile = hypc::read_file(out.join(name)).unwrap();
+        tile.tile_key = None;
+        tile
+    });
+    assert_eq!(ascii, binary);
+
+    // And each is byte-identical to its committed golden.
+    for name in ["ascii.hypc", "binary.hypc"] {
+        assert!(
+            fs::read(out.join(name)).unwrap()
+                == fs::read(fixtures_dir().join("expected").join(name)).unwrap(),
+            "{} differs from fixtures/expected",
+            name
+        );
+    }
+
+    fs::remove_dir_all(&out).unwrap();
+}
+
+#[test]
+fn infinite_vertex_is_dropped() {
+    let out = scratch_dir("nonfinite");
+    convert(
+        &fixtures_dir().join("ply_nonfinite"),
+        &out,
+        &["--input-cs", "ecef"],
+    );
+
+    // Seven of the eight vertices are finite, and the anchor stays among them
+    // rather than being dragged off by the eighth.
+    let tile = hypc::read_file(out.join("inf.hypc")).unwrap();
+    assert_eq!(tile.points_units.len(), 7);
+    let anchor_m = tile
+        .anchor_ecef_units
+        .map(|u| u as f64 / tile.units_per_meter as f64);
+    for p in tile.points_ecef_m() {
+        let d2: f64 = (0..3).map(|k| (p[k] - anchor_m[k]).powi(2)).sum();
+        assert!(
+            d2.sqrt() < 5.0,
+            "{:?} is {} m from the anchor",
+            p,
+            d2.sqrt()
+        );
+    }
+
+    // Its color went with it.
+    let colors = tile.colors.unwrap();
+    assert_eq!(colors.len(), 7);
+    assert!(!colors.contains(&[120, 175, 44]));
+
+    fs::remove_dir_all(&out).unwrap();
+}
+
+#[test]
+fn single_thread_runs_are_byte_identical() {
+    let runs = ["a", "b"].map(|run| {
+        let out = scratch_dir(&format!("repro-{}", run));
+        convert(&fixtures_dir().join("ply"), &out, &["--single-thread"]);
+        out
+    });
+
+    let files = |dir: &Path| -> Vec<PathBuf> {
+        let mut names: Vec<PathBuf> = fs::read_dir(dir)
+            .unwrap()
+            .map(|e| PathBuf::from(e.unwrap().file_name()))
+            .collect();
+        names.sort();
+        names
+    };
+    let names = files(&runs[0]);
+    assert!(names.iter().any(|n| n.extension() == Some("hypc".as_ref())));
+    assert_eq!(names, files(&runs[1]));
+    for name in &names {
+        assert!(
+            fs::read(runs[0].join(name)).unwrap() == fs::read(runs[1].join(name)).unwrap(),
+            "{} differs between runs",
+            name.display()
+        );
+    }
+
+    for out in runs {
+        fs::remove_dir_all(out).unwrap();
+    }
+}
+
+#[test]
+fn rerun_without_overwrite_keeps_every_tile_in_the_manifest() {
+    let out = scratch_dir("rerun-manifest");
+    let manifest_files = || -> Vec<String> {
+        let manifest: serde_json::Value =
+            serde_json::from_slice(&fs::read(out.join("manifest.json")).unwrap()).unwrap();
+        let mut files: Vec<String> = manifest["tiles"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| t["file"].as_str().unwrap().to_owned())
+            .collect();
+        files.sort();
+        files
+    };
+
+    convert(&fixtures_dir().join("ply"), &out, &[]);
+    let first = manifest_files();
+    assert!(!first.is_empty());
+
+    // Every tile now exists, so the second run skips them all.
+    convert(&fixtures_dir().join("ply"), &out, &[]);
+    assert_eq!(manifest_files(), first);
+
+    fs::remove_dir_all(&out).unwrap();
+}
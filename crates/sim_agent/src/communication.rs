@@ -41,13 +41,16 @@ impl Comm {
     }
 
     /// Performs the unary `RegisterAgent` RPC call.
-    pub async fn register(&mut self, session_id: &str) -> Result<u64, Status> {
+    ///
+    /// An empty `dataset_id` lets the orchestrator use the dataset it spawned the agent for.
+    pub async fn register(&mut self, session_id: &str, dataset_id: &str) -> Result<u64, Status> {
         let resp = self
             .client
             .register_agent(Request::new(RegisterAgentRequest {
                 session_id: session_id.into(),
                 sw_version: "dev".into(),
                 hw_profile: "sim".into(),
+                dataset_id: dataset_id.into(),
             }))
             .await?
             .into_inner();
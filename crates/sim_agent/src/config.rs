@@ -23,10 +23,54 @@ pub struct Config {
     #[arg(long, env = "AGENT_METRICS_LISTEN_ADDR")]
     pub metrics_listen_addr: String,
 
-    /// The filesystem path to the `.hypc` point cloud data file.
+    /// The filesystem path to a `.hypc` point cloud tile, or to a directory
+    /// whose `.hypc` tiles are all loaded.
     ///
-    /// This file is loaded into GPU memory at startup and is used by the
-    /// perception system to simulate LiDAR scans.
+    /// The points are loaded into GPU memory at startup and are used by the
+    /// perception system to simulate LiDAR scans. Tiles in a directory are
+    /// numbered in file-name order, which the orchestrator must match.
     #[arg(long, env = "POINT_CLOUD_PATH")]
     pub point_cloud_path: PathBuf,
+
+    /// The dataset this agent surveys on a multi-dataset orchestrator.
+    ///
+    /// Set by the orchestrator when it spawns the agent; empty selects the
+    /// orchestrator's default dataset.
+    #[arg(long, env = "AGENT_DATASET_ID", default_value = "")]
+    pub dataset_id: String,
+
+    /// Speed at which the agent flies straight to its assigned waypoint, in m/s.
+    #[arg(long, env = "AGENT_MAX_SPEED_MPS", default_value_t = 10.0)]
+    pub max_speed_mps: f64,
+
+    /// Full field-of-view angle of the LiDAR, in degrees.
+    ///
+    /// Points are only revealed inside a cone of this angle around the agent's
+    /// forward axis (body +X, rotated by its pose). 360 scans a full sphere.
+    #[arg(long, env = "AGENT_FOV_DEG", default_value_t = 360.0)]
+    pub fov_deg: f32,
+
+    /// Hide points behind nearer ones (line-of-sight occlusion).
+    ///
+    /// Doubles the per-scan GPU work: a first pass records the nearest point in
+    /// each angular bin around the agent, the second only reveals points near
+    /// that depth.
+    #[arg(long, env = "AGENT_OCCLUSION")]
+    pub occlusion: bool,
+
+    /// Angular resolution of the occlusion depth map, in degrees per bin.
+    ///
+    /// Points are only compared against others in the same azimuth/elevation
+    /// bin, so finer bins resolve smaller gaps (and thinner occluders hide
+    /// less) at the cost of a larger map: 360/bin x 180/bin cells of 4 bytes.
+    /// Only used with `--occlusion`.
+    #[arg(long, env = "AGENT_OCCLUSION_BIN_DEG", default_value_t = 0.5)]
+    pub occlusion_bin_deg: f32,
+
+    /// Compute workgroup size for the perception shader (e.g. 64, 128 or 256).
+    ///
+    /// Must fit the GPU's compute limits. When unset, the agent benchmarks the
+    /// common sizes at startup and picks the fastest for the current GPU.
+    #[arg(long, env = "AGENT_WORKGROUP_SIZE")]
+    pub workgroup_size: Option<u32>,
 }
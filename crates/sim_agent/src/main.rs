@@ -32,17 +32,23 @@ async fn main() -> anyhow::Result<()> {
     let session_id = uuid::Uuid::new_v4().to_string();
 
     // Initialize perception system (this can take a moment for GPU setup)
-    let perception_system =
-        PerceptionSystem::new(AGENT_SCAN_RANGE_M, &config.point_cloud_path).await?;
+    let perception_system = PerceptionSystem::new(
+        AGENT_SCAN_RANGE_M,
+        config.fov_deg,
+        config.occlusion.then_some(config.occlusion_bin_deg),
+        &config.point_cloud_path,
+        config.workgroup_size,
+    )
+    .await?;
 
     // Connect and register with the orchestrator
     let mut comm = communication::Comm::connect(&config.orchestrator_grpc_addr).await?;
-    let agent_id = comm.register(&session_id).await?;
+    let agent_id = comm.register(&session_id, &config.dataset_id).await?;
     tracing::info!(agent_id, session_id, "Agent registered successfully");
 
     // Initialize metrics and state machine
     let metrics = Arc::new(AgentMetrics::new(agent_id));
-    let mut agent_machine = AgentMachine::new(agent_id);
+    let mut agent_machine = AgentMachine::new(agent_id, config.max_speed_mps);
 
     // --- 2. Start Metrics Server ---
     let metrics_router = metrics.clone().router();
@@ -95,13 +101,16 @@ async fn main() -> anyhow::Result<()> {
                 let now = Instant::now();
                 let dt = now.duration_since(last_tick);
                 last_tick = now;
+                metrics.record_tick_jitter(
+                    (dt.as_secs_f64() - 1.0 / AGENT_TICK_RATE_HZ as f64).abs(),
+                );
 
                 // Update agent physics and state machine
                 agent_machine.tick(dt);
 
                 // If the agent is in a state to perceive, run the LiDAR scan
                 if agent_machine.mode == state::Mode::Perceiving {
-                    match perception_system.run_lidar_scan(&agent_machine.pose) {
+                    match perception_system.run_lidar_scan(&agent_machine.pose).await {
                         Ok(discovered) => {
                             if !discovered.is_empty() {
                                 agent_machine.discovery_buffer |= &discovered;
@@ -134,6 +143,11 @@ async fn main() -> anyhow::Result<()> {
         }
     }
 
-    tracing::info!("Agent shutting down.");
+    tracing::info!(
+        max_tick_jitter_s = metrics.tick_jitter_max_seconds.get(),
+        ticks = metrics.tick_jitter_histogram.get_sample_count(),
+        "Agent shutting down."
+    );
+    perception_system.shutdown().await;
     Ok(())
 }
@@ -1,5 +1,9 @@
 use axum::{response::IntoResponse, routing::get, Router};
-use prometheus::{Encoder, Gauge, Registry, TextEncoder};
+use prometheus::{Encoder, Gauge, Histogram, HistogramOpts, Registry, TextEncoder};
+
+/// Histogram buckets for control tick jitter, in seconds: from 1 ms up to
+/// ten whole ticks at 10 Hz.
+const TICK_JITTER_BUCKETS: [f64; 10] = [0.001, 0.002, 0.005, 0.01, 0.02, 0.05, 0.1, 0.2, 0.5, 1.0];
 
 /// A container for all Prometheus metrics exposed by the agent.
 ///
@@ -10,6 +14,10 @@ pub struct AgentMetrics {
     pub planning_loop_duration_seconds: Gauge,
     pub points_discovered_per_report: Gauge,
     pub grpc_connection_status: Gauge,
+    pub tick_jitter_seconds: Gauge,
+    /// Worst control tick jitter since the agent started.
+    pub tick_jitter_max_seconds: Gauge,
+    pub tick_jitter_histogram: Histogram,
 }
 
 impl AgentMetrics {
@@ -43,6 +51,27 @@ impl AgentMetrics {
                 "agent_grpc_connection_status",
                 "1 for connected, 0 for disconnected."
             ),
+            tick_jitter_seconds: reg_gauge!(
+                "agent_tick_jitter_seconds",
+                "Deviation of the last control tick from its nominal period, in seconds."
+            ),
+            tick_jitter_max_seconds: reg_gauge!(
+                "agent_tick_jitter_max_seconds",
+                "Worst deviation of a control tick from its nominal period so far, in seconds."
+            ),
+            tick_jitter_histogram: {
+                let histogram = Histogram::with_opts(
+                    HistogramOpts::new(
+                        "agent_tick_jitter_histogram_seconds",
+                        "Deviation of each control tick from its nominal period, in seconds.",
+                    )
+                    .const_label("agent_id", &agent_id_str)
+                    .buckets(TICK_JITTER_BUCKETS.to_vec()),
+                )
+                .unwrap();
+                registry.register(Box::new(histogram.clone())).unwrap();
+                histogram
+            },
             registry,
         }
     }
@@ -76,6 +105,16 @@ impl AgentMetrics {
         self.planning_loop_duration_seconds.set(duration_secs);
     }
 
+    /// Records the jitter of one control tick: the last-tick gauge, the
+    /// worst case so far and the histogram.
+    pub fn record_tick_jitter(&self, jitter_secs: f64) {
+        self.tick_jitter_seconds.set(jitter_secs);
+        if jitter_secs > self.tick_jitter_max_seconds.get() {
+            self.tick_jitter_max_seconds.set(jitter_secs);
+        }
+        self.tick_jitter_histogram.observe(jitter_secs);
+    }
+
     /// Sets the points discovered per report metric.
     pub fn set_points_discovered_in_report(&self, count: u64) {
         self.points_discovered_per_report.set(count as f64);
@@ -1,47 +1,126 @@
 use anyhow::Context;
 use bytemuck::{Pod, Zeroable};
-use nalgebra::Isometry3;
+use nalgebra::{Isometry3, Vector3};
 use roaring::RoaringBitmap;
-use std::fs::File;
-use std::io::Read;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread::JoinHandle;
 use std::time::Instant;
 use wgpu::util::DeviceExt;
 
-const WORKGROUP_SIZE: u32 = 256;
+/// The `WORKGROUP_SIZE` declaration in `shader.wgsl`, replaced at pipeline creation.
+const SHADER_WORKGROUP_DECL: &str = "const WORKGROUP_SIZE: u32 = 256u;";
+
+/// Workgroup sizes tried by the startup auto-tune; GPUs differ in which one is fastest.
+const WORKGROUP_SIZE_CANDIDATES: [u32; 3] = [64, 128, 256];
+
+/// Timed dispatches per candidate during auto-tune (after one warm-up dispatch).
+const AUTOTUNE_ITERATIONS: u32 = 3;
 
 /// A CPU-side struct that mirrors the `AgentPose` uniform structure in the WGSL shader.
 ///
-/// It must be aligned to 16 bytes (`vec4`), so we add padding.
+/// WGSL packs each trailing `f32` into the last lane of the preceding `vec3`, so
+/// the layout needs no explicit padding: three 16-byte rows, 48 bytes in total.
 /// Derives `Pod` and `Zeroable` to allow for safe, zero-cost casting to a byte slice.
 #[repr(C)]
 #[derive(Clone, Copy, Debug, Pod, Zeroable)]
 struct AgentPoseUniform {
     position: [f32; 3],
-    _padding1: f32,
     scan_range_sq: f32,
-    _padding2: [f32; 3],
+    /// Unit vector the sensor looks along, in ECEF.
+    forward: [f32; 3],
+    /// Cosine of half the field of view; -1 (a 360° FOV) disables the cone test.
+    cos_half_fov: f32,
+    /// Occlusion depth map size in bins; 0 columns disables occlusion.
+    depth_cols: u32,
+    depth_rows: u32,
+    bins_per_rad: f32,
+    depth_tolerance_m: f32,
+}
+
+/// How far behind the nearest point of its angular bin a point may lie and still
+/// count as visible, so that the several points a surface puts in one bin are
+/// all revealed.
+const OCCLUSION_DEPTH_TOLERANCE_M: f32 = 1.0;
+
+/// Size of the occlusion depth map for `bin_deg`-degree bins, as (azimuth
+/// columns, elevation rows).
+fn depth_map_dims(bin_deg: f32) -> (u32, u32) {
+    (
+        (360.0 / bin_deg).ceil() as u32,
+        (180.0 / bin_deg).ceil() as u32,
+    )
 }
 
+/// The sensor's boresight in the agent's body frame; the pose rotation takes it
+/// to ECEF.
+pub(crate) const SENSOR_FORWARD_BODY: Vector3<f64> = Vector3::new(1.0, 0.0, 0.0);
+
 /// Manages the headless wgpu context and resources for GPU-based perception simulation.
 pub struct PerceptionSystem {
-    device: wgpu::Device,
+    device: Arc<wgpu::Device>,
     queue: wgpu::Queue,
     pipeline: wgpu::ComputePipeline,
+    /// Occlusion stage 1 (`build_depth`); `None` when occlusion is off.
+    depth_pipeline: Option<wgpu::ComputePipeline>,
     bind_group: wgpu::BindGroup,
     point_cloud_buffer: wgpu::Buffer,
     result_buffer: wgpu::Buffer,
     staging_buffer: wgpu::Buffer,
     pose_uniform_buffer: wgpu::Buffer,
+    depth_map_buffer: wgpu::Buffer,
     num_points: u64,
     scan_range_m: f32,
+    /// Cosine of half the configured field of view.
+    cos_half_fov: f32,
+    /// Occlusion bin size in degrees, if occlusion is on.
+    occlusion_bin_deg: Option<f32>,
+    /// Invocations per workgroup the pipeline was compiled with.
+    workgroup_size: u32,
+    /// Wakes the readback poll thread after each submission.
+    poll_tx: mpsc::Sender<()>,
+    /// The readback poll thread, joined on `shutdown`.
+    poll_thread: JoinHandle<()>,
+    /// Set while a readback map is requested or held, so `shutdown` knows whether
+    /// a scan was abandoned mid-readback and the staging buffer needs unmapping.
+    staging_mapped: AtomicBool,
 }
 
 impl PerceptionSystem {
     /// Creates a new `PerceptionSystem`, initializing the wgpu device and pipeline.
     ///
+    /// `fov_deg` is the full cone angle around the sensor's forward axis, in
+    /// (0, 360]; 360 reveals every point in range.
+    ///
+    /// `occlusion_bin_deg` turns on line-of-sight occlusion with angular bins of
+    /// that size: each scan first records the nearest visible point per bin, then
+    /// reveals only points at (about) that depth. Smaller bins let more points
+    /// through gaps and cost more memory (a 0.5° map is 720 x 360 cells).
+    ///
+    /// `workgroup_size` is validated against the device limits; `None` benchmarks
+    /// `WORKGROUP_SIZE_CANDIDATES` on this GPU and keeps the fastest.
+    ///
     /// This function is asynchronous as GPU initialization is non-blocking.
-    pub async fn new(scan_range_m: f32, point_cloud_path: &Path) -> anyhow::Result<Self> {
+    pub async fn new(
+        scan_range_m: f32,
+        fov_deg: f32,
+        occlusion_bin_deg: Option<f32>,
+        point_cloud_path: &Path,
+        workgroup_size: Option<u32>,
+    ) -> anyhow::Result<Self> {
+        anyhow::ensure!(
+            fov_deg > 0.0 && fov_deg <= 360.0,
+            "Field of view must be in (0, 360] degrees, got {}",
+            fov_deg
+        );
+        if let Some(bin_deg) = occlusion_bin_deg {
+            anyhow::ensure!(
+                bin_deg > 0.0 && bin_deg <= 90.0,
+                "Occlusion bin size must be in (0, 90] degrees, got {}",
+                bin_deg
+            );
+        }
         let startup_instant = Instant::now();
         tracing::info!("Initializing PerceptionSystem...");
 
@@ -73,6 +152,7 @@ impl PerceptionSystem {
             )
             .await
             .context("Failed to get wgpu device.")?;
+        let device = Arc::new(device);
 
         // --- 2. Load Point Cloud Data ---
         let (num_points, point_cloud_data) = Self::load_point_cloud(point_cloud_path)?;
@@ -82,6 +162,14 @@ impl PerceptionSystem {
             "Loaded point cloud data"
         );
 
+        let max_binding = u64::from(device.limits().max_storage_buffer_binding_size);
+        anyhow::ensure!(
+            point_cloud_data.len() as u64 <= max_binding,
+            "Point cloud needs a {} byte storage buffer; the device allows {}",
+            point_cloud_data.len(),
+            max_binding
+        );
+
         // --- 3. Create Buffers ---
         let point_cloud_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Point Cloud Buffer"),
@@ -102,7 +190,9 @@ impl PerceptionSystem {
         let result_buffer = device.create_buffer(&wgpu::BufferDescriptor {
             label: Some("Discovered Points Result Buffer"),
             size: result_buffer_size,
-            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::COPY_SRC
+                | wgpu::BufferUsages::COPY_DST,
             mapped_at_creation: false,
         });
 
@@ -114,12 +204,26 @@ impl PerceptionSystem {
             mapped_at_creation: false,
         });
 
-        // --- 4. Create Shader and Pipeline ---
-        let shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("Perception Shader"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("./shader.wgsl").into()),
+        // With occlusion off the shader never touches the map, but the binding
+        // still needs a buffer.
+        let depth_cells = occlusion_bin_deg.map_or(1, |bin_deg| {
+            let (cols, rows) = depth_map_dims(bin_deg);
+            u64::from(cols) * u64::from(rows)
+        });
+        anyhow::ensure!(
+            depth_cells * 4 <= max_binding,
+            "Occlusion depth map needs a {} byte storage buffer; the device allows {}",
+            depth_cells * 4,
+            max_binding
+        );
+        let depth_map_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Occlusion Depth Map Buffer"),
+            size: depth_cells * 4,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
         });
 
+        // --- 4. Create Bind Group and Pipeline Layout ---
         let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             label: Some("Perception Bind Group Layout"),
             entries: &[
@@ -153,6 +257,16 @@ impl PerceptionSystem {
                     },
                     count: None,
                 },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
             ],
         });
 
@@ -172,6 +286,10 @@ impl PerceptionSystem {
                     binding: 2,
                     resource: result_buffer.as_entire_binding(),
                 },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: depth_map_buffer.as_entire_binding(),
+                },
             ],
         });
 
@@ -181,14 +299,42 @@ impl PerceptionSystem {
             push_constant_ranges: &[],
         });
 
-        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
-            label: Some("Perception Pipeline"),
-            layout: Some(&pipeline_layout),
-            module: &shader_module,
-            entry_point: "main",
-            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        // --- 5. Choose the Workgroup Size and Build the Pipeline ---
+        let workgroup_size = match workgroup_size {
+            Some(size) => {
+                Self::check_workgroup_size(&device.limits(), size, num_points)?;
+                tracing::info!(workgroup_size = size, "Using configured workgroup size");
+                size
+            }
+            None => Self::autotune_workgroup_size(
+                &device,
+                &queue,
+                &pipeline_layout,
+                &bind_group,
+                num_points,
+            )?,
+        };
+        let pipeline = Self::create_pipeline(&device, &pipeline_layout, workgroup_size, "main");
+        let depth_pipeline = occlusion_bin_deg.map(|bin_deg| {
+            tracing::info!(bin_deg, "Occlusion enabled");
+            Self::create_pipeline(&device, &pipeline_layout, workgroup_size, "build_depth")
         });
 
+        // --- 6. Spawn the Readback Poll Thread ---
+        // `device.poll(Wait)` blocks until the GPU drains its queue, so it runs on a
+        // dedicated OS thread instead of the Tokio worker driving the control loop.
+        let (poll_tx, poll_rx) = mpsc::channel::<()>();
+        let poll_device = device.clone();
+        let poll_thread = std::thread::Builder::new()
+            .name("perception-poll".into())
+            .spawn(move || {
+                // Exits once the `PerceptionSystem` (and its sender) is dropped.
+                while poll_rx.recv().is_ok() {
+                    poll_device.poll(wgpu::Maintain::Wait);
+                }
+            })
+            .context("Failed to spawn perception poll thread.")?;
+
         tracing::info!(
             duration_ms = startup_instant.elapsed().as_millis(),
             "PerceptionSystem initialized successfully"
@@ -198,25 +344,127 @@ impl PerceptionSystem {
             device,
             queue,
             pipeline,
+            depth_pipeline,
             bind_group,
             point_cloud_buffer,
             result_buffer,
             staging_buffer,
             pose_uniform_buffer,
+            depth_map_buffer,
             num_points,
             scan_range_m,
+            // Exactly -1 at 360° so the shader can skip the cone test.
+            cos_half_fov: if fov_deg >= 360.0 {
+                -1.0
+            } else {
+                (fov_deg / 2.0).to_radians().cos()
+            },
+            occlusion_bin_deg,
+            workgroup_size,
+            poll_tx,
+            poll_thread,
+            staging_mapped: AtomicBool::new(false),
         })
     }
 
+    /// Tears down the GPU context deterministically.
+    ///
+    /// Stops the poll thread, waits for any in-flight submission to finish, unmaps
+    /// the staging buffer if a scan was abandoned mid-readback, then destroys the
+    /// buffers and the device in that order instead of relying on drop order.
+    pub async fn shutdown(self) {
+        let Self {
+            device,
+            queue,
+            pipeline,
+            depth_pipeline,
+            bind_group,
+            point_cloud_buffer,
+            result_buffer,
+            staging_buffer,
+            pose_uniform_buffer,
+            depth_map_buffer,
+            poll_tx,
+            poll_thread,
+            staging_mapped,
+            ..
+        } = self;
+        tracing::info!("Shutting down PerceptionSystem...");
+
+        // --- 1. Stop the Poll Thread ---
+        // Dropping the sender ends its loop once the current poll (if any) returns.
+        drop(poll_tx);
+        match tokio::task::spawn_blocking(move || poll_thread.join()).await {
+            Ok(Ok(())) => tracing::info!("Perception poll thread stopped"),
+            _ => tracing::warn!("Perception poll thread panicked or could not be joined"),
+        }
+
+        // --- 2. Wait for Pending GPU Work ---
+        let wait_device = device.clone();
+        let _ = tokio::task::spawn_blocking(move || wait_device.poll(wgpu::Maintain::Wait)).await;
+        tracing::info!("Pending GPU submissions drained");
+
+        // --- 3. Unmap the Staging Buffer ---
+        if staging_mapped.swap(false, Ordering::AcqRel) {
+            staging_buffer.unmap();
+            tracing::info!("Unmapped staging buffer left by an interrupted scan");
+        }
+
+        // --- 4. Release Buffers and Pipeline ---
+        drop(bind_group);
+        drop((pipeline, depth_pipeline));
+        for buffer in [
+            &point_cloud_buffer,
+            &result_buffer,
+            &staging_buffer,
+            &pose_uniform_buffer,
+            &depth_map_buffer,
+        ] {
+            buffer.destroy();
+        }
+        drop((
+            point_cloud_buffer,
+            result_buffer,
+            staging_buffer,
+            pose_uniform_buffer,
+            depth_map_buffer,
+        ));
+        tracing::info!("Released perception buffers and pipeline");
+
+        // --- 5. Destroy the Device ---
+        drop(queue);
+        device.destroy();
+        drop(device);
+        tracing::info!("PerceptionSystem shut down");
+    }
+
     /// Runs a simulated LiDAR scan from the agent's current pose.
-    pub fn run_lidar_scan(&self, pose: &Isometry3<f64>) -> anyhow::Result<RoaringBitmap> {
+    ///
+    /// Points are revealed if they are within the scan range and inside the
+    /// field-of-view cone around the pose's forward (body +X) axis, and, with
+    /// occlusion on, not hidden behind a nearer point in the same angular bin.
+    ///
+    /// The returned bitmap holds global point indices across all loaded tiles
+    /// (see `load_point_cloud`).
+    ///
+    /// The readback is awaited asynchronously; the blocking device poll happens on
+    /// the perception poll thread so the caller's runtime thread stays free.
+    pub async fn run_lidar_scan(&self, pose: &Isometry3<f64>) -> anyhow::Result<RoaringBitmap> {
         // --- 1. Update Uniform Buffer ---
         let position = pose.translation.vector;
+        let forward = pose.rotation * SENSOR_FORWARD_BODY;
+        let (depth_cols, depth_rows) = self.occlusion_bin_deg.map_or((0, 0), depth_map_dims);
         let uniform = AgentPoseUniform {
             position: [position.x as f32, position.y as f32, position.z as f32],
             scan_range_sq: self.scan_range_m * self.scan_range_m,
-            _padding1: 0.0,
-            _padding2: [0.0; 3],
+            forward: [forward.x as f32, forward.y as f32, forward.z as f32],
+            cos_half_fov: self.cos_half_fov,
+            depth_cols,
+            depth_rows,
+            bins_per_rad: self
+                .occlusion_bin_deg
+                .map_or(0.0, |bin_deg| 1.0 / bin_deg.to_radians()),
+            depth_tolerance_m: OCCLUSION_DEPTH_TOLERANCE_M,
         };
         self.queue
             .write_buffer(&self.pose_uniform_buffer, 0, bytemuck::bytes_of(&uniform));
@@ -231,6 +479,26 @@ impl PerceptionSystem {
                 label: Some("Perception Command Encoder"),
             });
 
+        let n = u64::try_from(self.num_points).unwrap();
+
+        anyhow::ensure!(n <= u64::from(u32::MAX), "num_points exceeds u32::MAX for dispatch");
+
+        let workgroups = (n as u32).div_ceil(self.workgroup_size);
+
+        // Occlusion stage 1 fills the depth map the main pass tests against; a
+        // separate pass so its writes are complete before the main pass reads.
+        if let Some(depth_pipeline) = &self.depth_pipeline {
+            encoder.clear_buffer(&self.depth_map_buffer, 0, None);
+
+            let mut depth_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Occlusion Depth Pass"),
+                timestamp_writes: None,
+            });
+            depth_pass.set_pipeline(depth_pipeline);
+            depth_pass.set_bind_group(0, &self.bind_group, &[]);
+            depth_pass.dispatch_workgroups(workgroups, 1, 1);
+        }
+
         {
             let mut compute_pass =
                 encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
@@ -241,12 +509,6 @@ impl PerceptionSystem {
             compute_pass.set_pipeline(&self.pipeline);
             compute_pass.set_bind_group(0, &self.bind_group, &[]);
 
-            let n = u64::try_from(self.num_points).unwrap();
-
-            anyhow::ensure!(n <= u64::from(u32::MAX), "num_points exceeds u32::MAX for dispatch");
-
-            let workgroups = ((n as u32) + WORKGROUP_SIZE - 1) / WORKGROUP_SIZE;
-
             compute_pass.dispatch_workgroups(workgroups, 1, 1);
         }
 
@@ -263,14 +525,17 @@ impl PerceptionSystem {
         // --- 3. Await GPU and Read Results ---
         let buffer_slice = self.staging_buffer.slice(..);
         let (sender, receiver) = futures::channel::oneshot::channel();
+        self.staging_mapped.store(true, Ordering::Release);
         buffer_slice.map_async(wgpu::MapMode::Read, move |result| {
-            sender.send(result).unwrap();
+            // The receiver is gone if the scan was abandoned; `shutdown` unmaps.
+            let _ = sender.send(result);
         });
 
-        // Poll the device to make sure the submission is processed.
-        // `pollster::block_on` will drive the future to completion.
-        self.device.poll(wgpu::Maintain::Wait);
-        pollster::block_on(receiver)??;
+        // Hand the wait off to the poll thread, which fires the map callback.
+        self.poll_tx
+            .send(())
+            .context("Perception poll thread has exited.")?;
+        receiver.await??;
 
         let mut discovered_points = RoaringBitmap::new();
         {
@@ -287,40 +552,276 @@ impl PerceptionSystem {
             discovered_points.extend(&indices[..count as usize]);
         }
         self.staging_buffer.unmap();
+        self.staging_mapped.store(false, Ordering::Release);
 
         Ok(discovered_points)
     }
 
-    /// Loads point cloud from a .hypc file.
-    /// Format: u64 num_points, followed by tightly packed f32 xyz coordinates.
-    /// Pads the data to vec4 alignment for the GPU.
-    fn load_point_cloud(path: &Path) -> anyhow::Result<(u64, Vec<u8>)> {
-        let mut file = File::open(path)
-            .with_context(|| format!("Failed to open point cloud file: {:?}", path))?;
+    /// Compiles the perception shader's `entry_point` for a given workgroup size.
+    fn create_pipeline(
+        device: &wgpu::Device,
+        layout: &wgpu::PipelineLayout,
+        workgroup_size: u32,
+        entry_point: &str,
+    ) -> wgpu::ComputePipeline {
+        let source = include_str!("./shader.wgsl");
+        debug_assert!(source.contains(SHADER_WORKGROUP_DECL));
+        let source = source.replace(
+            SHADER_WORKGROUP_DECL,
+            &format!("const WORKGROUP_SIZE: u32 = {}u;", workgroup_size),
+        );
 
-        let mut count_buf = [0u8; 8];
-        file.read_exact(&mut count_buf)?;
-        let num_points = u64::from_le_bytes(count_buf);
+        let shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Perception Shader"),
+            source: wgpu::ShaderSource::Wgsl(source.into()),
+        });
 
-        let mut xyz_data = Vec::new();
-        file.read_to_end(&mut xyz_data)?;
+        device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Perception Pipeline"),
+            layout: Some(layout),
+            module: &shader_module,
+            entry_point,
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        })
+    }
 
-        let expected_size = num_points as usize * 3 * 4;
+    /// Checks a workgroup size against the device's compute limits, including the
+    /// dispatch size needed to cover `num_points`.
+    fn check_workgroup_size(
+        limits: &wgpu::Limits,
+        workgroup_size: u32,
+        num_points: u64,
+    ) -> anyhow::Result<()> {
+        anyhow::ensure!(workgroup_size > 0, "Workgroup size must be non-zero");
+        anyhow::ensure!(
+            workgroup_size <= limits.max_compute_workgroup_size_x
+                && workgroup_size <= limits.max_compute_invocations_per_workgroup,
+            "Workgroup size {} exceeds device limits (x: {}, invocations: {})",
+            workgroup_size,
+            limits.max_compute_workgroup_size_x,
+            limits.max_compute_invocations_per_workgroup
+        );
+        let workgroups = num_points.div_ceil(u64::from(workgroup_size));
         anyhow::ensure!(
-            xyz_data.len() == expected_size,
-            "Point cloud file size mismatch. Expected {} bytes for {} points, got {}",
-            expected_size,
+            workgroups <= u64::from(limits.max_compute_workgroups_per_dimension),
+            "Workgroup size {} needs {} workgroups for {} points (device max {})",
+            workgroup_size,
+            workgroups,
             num_points,
-            xyz_data.len()
+            limits.max_compute_workgroups_per_dimension
         );
+        Ok(())
+    }
 
-        // Pad the vec3 data to vec4 for 16-byte alignment on the GPU.
-        let points_f32: &[f32] = bytemuck::cast_slice(&xyz_data);
-        let mut padded_data = Vec::<f32>::with_capacity(num_points as usize * 4);
-        for p in points_f32.chunks_exact(3) {
-            padded_data.extend_from_slice(&[p[0], p[1], p[2], 0.0]);
+    /// Benchmarks each valid candidate workgroup size with a full-cloud dispatch and
+    /// returns the fastest.
+    ///
+    /// Runs once at startup and blocks on the device; the scan range is zero so the
+    /// timing measures the point sweep rather than result writes.
+    fn autotune_workgroup_size(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        layout: &wgpu::PipelineLayout,
+        bind_group: &wgpu::BindGroup,
+        num_points: u64,
+    ) -> anyhow::Result<u32> {
+        let limits = device.limits();
+        let candidates: Vec<u32> = WORKGROUP_SIZE_CANDIDATES
+            .into_iter()
+            .filter(|&size| Self::check_workgroup_size(&limits, size, num_points).is_ok())
+            .collect();
+        anyhow::ensure!(
+            !candidates.is_empty(),
+            "No workgroup size in {:?} fits the device limits for {} points",
+            WORKGROUP_SIZE_CANDIDATES,
+            num_points
+        );
+        if candidates.len() == 1 || num_points == 0 {
+            tracing::info!(workgroup_size = candidates[0], "Skipping workgroup auto-tune");
+            return Ok(candidates[0]);
         }
 
+        let mut best = (candidates[0], f64::INFINITY);
+        for size in candidates {
+            let pipeline = Self::create_pipeline(device, layout, size, "main");
+            let workgroups = num_points.div_ceil(u64::from(size)) as u32;
+
+            let mut elapsed_ms = 0.0;
+            for iteration in 0..=AUTOTUNE_ITERATIONS {
+                let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("Perception Auto-tune Encoder"),
+                });
+                {
+                    let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                        label: Some("Perception Auto-tune Pass"),
+                        timestamp_writes: None,
+                    });
+                    pass.set_pipeline(&pipeline);
+                    pass.set_bind_group(0, bind_group, &[]);
+                    pass.dispatch_workgroups(workgroups, 1, 1);
+                }
+
+                let start = Instant::now();
+                queue.submit(std::iter::once(encoder.finish()));
+                device.poll(wgpu::Maintain::Wait);
+                // The first dispatch warms up the pipeline and is not counted.
+                if iteration > 0 {
+                    elapsed_ms += start.elapsed().as_secs_f64() * 1e3;
+                }
+            }
+            let avg_ms = elapsed_ms / f64::from(AUTOTUNE_ITERATIONS);
+
+            tracing::debug!(workgroup_size = size, avg_ms, "Workgroup auto-tune sample");
+            if avg_ms < best.1 {
+                best = (size, avg_ms);
+            }
+        }
+
+        tracing::info!(
+            workgroup_size = best.0,
+            avg_ms = best.1,
+            "Auto-tuned perception workgroup size"
+        );
+        Ok(best.0)
+    }
+
+    /// Loads the point cloud at `path`: a single HYPC tile, or every `.hypc`
+    /// file directly inside a directory.
+    ///
+    /// Tiles are concatenated in file-name order, so a point's global index (the
+    /// index reported in scan bitmaps) is its index within its tile plus the
+    /// point count of every tile sorting before it. The index space is stable for
+    /// as long as the set of tiles is.
+    /// Pads the data to vec4 alignment for the GPU.
+    fn load_point_cloud(path: &Path) -> anyhow::Result<(u64, Vec<u8>)> {
+        let paths = if path.is_dir() {
+            tile_paths(path)?
+        } else {
+            vec![path.to_path_buf()]
+        };
+        anyhow::ensure!(!paths.is_empty(), "No .hypc tiles in {:?}", path);
+
+        let mut padded_data = Vec::<f32>::new();
+        for path in &paths {
+            let first_index = padded_data.len() / 4;
+            Self::load_tile(path, &mut padded_data)?;
+            tracing::debug!(
+                tile = %path.display(),
+                first_index,
+                num_points = padded_data.len() / 4 - first_index,
+                "Loaded point cloud tile"
+            );
+        }
+
+        let num_points = (padded_data.len() / 4) as u64;
+        anyhow::ensure!(
+            num_points <= u64::from(u32::MAX),
+            "{} points across {} tiles exceed the u32 point index space",
+            num_points,
+            paths.len()
+        );
+
         Ok((num_points, bytemuck::cast_slice(&padded_data).to_vec()))
     }
+
+    /// Appends the points of one HYPC tile to `padded_data` as absolute ECEF
+    /// metres (anchor plus offset, with any XFRM correction applied), padded to
+    /// vec4.
+    fn load_tile(path: &Path, padded_data: &mut Vec<f32>) -> anyhow::Result<()> {
+        let tile = hypc::read_file(path).map_err(|e| {
+            let bad_magic = e
+                .get_ref()
+                .and_then(|inner| inner.downcast_ref::<hypc::HypcError>())
+                .is_some_and(|inner| matches!(inner, hypc::HypcError::BadMagic));
+            if bad_magic {
+                anyhow::anyhow!("{:?} is not a HYPC tile (bad magic)", path)
+            } else {
+                anyhow::Error::new(e)
+                    .context(format!("Failed to read point cloud file: {:?}", path))
+            }
+        })?;
+
+        // Pad the vec3 data to vec4 for 16-byte alignment on the GPU.
+        let points = tile.iter_points_ecef_m();
+        padded_data.reserve(points.len() * 4);
+        for p in points {
+            padded_data.extend_from_slice(&[p[0] as f32, p[1] as f32, p[2] as f32, 0.0]);
+        }
+        Ok(())
+    }
+}
+
+/// The `.hypc` files directly inside `dir`, sorted by file name.
+fn tile_paths(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for entry in
+        std::fs::read_dir(dir).with_context(|| format!("Failed to list tiles in {:?}", dir))?
+    {
+        let path = entry?.path();
+        if path.is_file() && path.extension().and_then(|e| e.to_str()) == Some("hypc") {
+            paths.push(path);
+        }
+    }
+    paths.sort_by(|a, b| a.file_name().cmp(&b.file_name()));
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tile(units_per_meter: u32, points_units: Vec<[i32; 3]>) -> hypc::HypcTile {
+        hypc::HypcTile {
+            units_per_meter,
+            anchor_ecef_units: [4_177_000, 855_000, 4_727_000].map(|m| m * units_per_meter as i64),
+            tile_key: None,
+            points_units,
+            points_encoding: hypc::PointsEncoding::Delta,
+            points_codec: hypc::PointsCodec::None,
+            labels: None,
+            colors: None,
+            geot: None,
+            smc1: None,
+            transform: None,
+            crc: false,
+        }
+    }
+
+    #[test]
+    fn load_point_cloud_reads_hypc_tiles() {
+        let dir = std::env::temp_dir().join(format!("sim_agent_load_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        // Written out of order; loaded by file name. Other files are skipped.
+        hypc::write_file(dir.join("b.hypc"), &tile(100, vec![[10, -20, 30]])).unwrap();
+        hypc::write_file(
+            dir.join("a.hypc"),
+            &tile(1000, vec![[1500, -2000, 250], [0, 0, 0]]),
+        )
+        .unwrap();
+        std::fs::write(dir.join("notes.txt"), "not a tile").unwrap();
+
+        let (num_points, data) = PerceptionSystem::load_point_cloud(&dir).unwrap();
+        assert_eq!(num_points, 3);
+        let data: Vec<f32> = data
+            .chunks_exact(4)
+            .map(|b| f32::from_ne_bytes(b.try_into().unwrap()))
+            .collect();
+        let vec4 = |x: f64, y: f64, z: f64| [x as f32, y as f32, z as f32, 0.0];
+        let expected: Vec<f32> = [
+            vec4(4_177_001.5, 854_998.0, 4_727_000.25),
+            vec4(4_177_000.0, 855_000.0, 4_727_000.0),
+            vec4(4_177_000.1, 854_999.8, 4_727_000.3),
+        ]
+        .concat();
+        assert_eq!(data, expected);
+
+        let (num_points, _) = PerceptionSystem::load_point_cloud(&dir.join("b.hypc")).unwrap();
+        assert_eq!(num_points, 1);
+
+        std::fs::write(dir.join("c.hypc"), b"NOPE\x03\0\0\0").unwrap();
+        let err = PerceptionSystem::load_point_cloud(&dir).unwrap_err();
+        assert!(err.to_string().contains("bad magic"), "{:#}", err);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
@@ -15,6 +15,14 @@ var<storage, read> point_cloud: array<vec4<f32>>;
 @group(0) @binding(2)
 var<storage, read_write> discovered_points: DiscoveredPoints;
 
+// Occlusion depth map: one cell per angular bin around the agent, holding the
+// distance to the nearest in-view point in that bin. Distances are stored as
+// 0xFFFFFFFF minus their f32 bits, so that the host clearing the buffer to zero
+// means "empty" and atomicMax keeps the nearest. Cleared before every scan;
+// unused (a single cell) when occlusion is off.
+@group(0) @binding(3)
+var<storage, read_write> depth_map: array<atomic<u32>>;
+
 // --- Struct Definitions ---
 
 // Corresponds to the AgentPose uniform buffer object on the CPU side.
@@ -24,6 +32,18 @@ struct AgentPose {
     // The squared scan range in meters^2. Squaring is done on the CPU
     // to avoid a sqrt() operation per-point in the shader.
     scan_range_sq: f32,
+    // Unit vector along the sensor's boresight, in ECEF.
+    forward: vec3<f32>,
+    // Cosine of half the field of view. -1 means a full 360 degree FOV.
+    cos_half_fov: f32,
+    // Depth map size in azimuth x elevation bins. 0 columns disables occlusion.
+    depth_cols: u32,
+    depth_rows: u32,
+    // Angular bins per radian, on both axes.
+    bins_per_rad: f32,
+    // How far behind the nearest point of its bin a point may lie and still
+    // be visible; points on the same surface rarely share one exact depth.
+    depth_tolerance_m: f32,
 };
 
 // Corresponds to the output buffer on the CPU side.
@@ -36,9 +56,58 @@ struct DiscoveredPoints {
 
 // --- Compute Shader ---
 
+// Points processed per workgroup. The host rewrites this line with the size chosen
+// for the current GPU (configured or auto-tuned); 256 is the fallback default.
+const WORKGROUP_SIZE: u32 = 256u;
+
+// Whether a point at `offset` from the agent (`distance_sq` = |offset|^2) is
+// within the scan range and the field-of-view cone, ignoring occlusion.
+fn in_view(offset: vec3<f32>, distance_sq: f32) -> bool {
+    // Reject points outside the scan radius.
+    if (distance_sq > agent_pose.scan_range_sq) {
+        return false;
+    }
+
+    // Reject points outside the field-of-view cone. The angle between the
+    // offset and the boresight is within the half-angle when
+    // dot(offset, forward) >= |offset| * cos(half-angle); the sqrt is only paid
+    // for points already in range, and not at all with a full FOV.
+    return agent_pose.cos_half_fov <= -1.0 ||
+        dot(offset, agent_pose.forward) >= agent_pose.cos_half_fov * sqrt(distance_sq);
+}
+
+// Depth map cell for a point at `offset` and `distance` > 0 from the agent,
+// binned by ECEF azimuth (around Z) and elevation (from the XY plane).
+fn depth_bin(offset: vec3<f32>, distance: f32) -> u32 {
+    let azimuth = atan2(offset.y, offset.x) + PI;
+    let elevation = asin(clamp(offset.z / distance, -1.0, 1.0)) + 0.5 * PI;
+    let col = min(u32(azimuth * agent_pose.bins_per_rad), agent_pose.depth_cols - 1u);
+    let row = min(u32(elevation * agent_pose.bins_per_rad), agent_pose.depth_rows - 1u);
+    return row * agent_pose.depth_cols + col;
+}
+
+const PI: f32 = 3.14159265358979;
+
+// Occlusion stage 1: records the nearest in-view distance in each angular bin.
+@compute @workgroup_size(WORKGROUP_SIZE)
+fn build_depth(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let point_index = global_id.x;
+    if (point_index >= arrayLength(&point_cloud)) {
+        return;
+    }
+
+    let offset = point_cloud[point_index].xyz - agent_pose.position;
+    let distance_sq = dot(offset, offset);
+    if (distance_sq == 0.0 || !in_view(offset, distance_sq)) {
+        return;
+    }
+
+    let distance = sqrt(distance_sq);
+    atomicMax(&depth_map[depth_bin(offset, distance)], 0xFFFFFFFFu - bitcast<u32>(distance));
+}
+
 // The entry point for the compute shader.
-// We process 256 points per workgroup, a common size for good performance.
-@compute @workgroup_size(256)
+@compute @workgroup_size(WORKGROUP_SIZE)
 fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
     let point_index = global_id.x;
     let num_points = arrayLength(&point_cloud);
@@ -60,13 +129,25 @@ fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
     // actual distance as it avoids a square root operation.
     let distance_sq = dot(offset, offset);
 
-    // If the point is within the scan radius...
-    if (distance_sq <= agent_pose.scan_range_sq) {
-        // ...atomically increment the discovery counter and get the index
-        // at which to store our result. This prevents race conditions.
-        let storage_index = atomicAdd(&discovered_points.count, 1u);
+    if (!in_view(offset, distance_sq)) {
+        return;
+    }
 
-        // Store the index of the discovered point in the output buffer.
-        discovered_points.indices[storage_index] = point_index;
+    // Occlusion stage 2: reject points behind the nearest one in their bin
+    // (filled in by `build_depth`).
+    if (agent_pose.depth_cols > 0u && distance_sq > 0.0) {
+        let distance = sqrt(distance_sq);
+        let cell = atomicLoad(&depth_map[depth_bin(offset, distance)]);
+        let nearest = bitcast<f32>(0xFFFFFFFFu - cell);
+        if (distance > nearest + agent_pose.depth_tolerance_m) {
+            return;
+        }
     }
+
+    // Atomically increment the discovery counter and get the index at which
+    // to store our result. This prevents race conditions.
+    let storage_index = atomicAdd(&discovered_points.count, 1u);
+
+    // Store the index of the discovered point in the output buffer.
+    discovered_points.indices[storage_index] = point_index;
 }
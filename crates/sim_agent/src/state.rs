@@ -2,11 +2,13 @@ use api::gen::api::v1::{
     AgentMode as ApiAgentMode, AgentReport, AgentState as ApiAgentState, Task, UnitQuaternion, Vec3m,
     Vec3mps,
 };
-use nalgebra::{Isometry3, Point3, UnitQuaternion as NalgebraUnitQuaternion, Vector3};
+use crate::perception::SENSOR_FORWARD_BODY;
+use nalgebra::{
+    Isometry3, Matrix3, Point3, Rotation3, UnitQuaternion as NalgebraUnitQuaternion, Vector3,
+};
 use roaring::RoaringBitmap;
 use std::time::Duration;
 
-const AGENT_SPEED_MPS: f64 = 10.0; // 10 m/s
 const WAYPOINT_PROXIMITY_M: f64 = 1.0; // 1 meter
 
 /// The operational mode of the agent, representing its current state.
@@ -37,12 +39,14 @@ pub struct AgentMachine {
     pub current_task: Option<Task>,
     pub current_plan: Option<Plan>,
     pub discovery_buffer: RoaringBitmap,
+    /// Speed along the path while navigating, m/s.
+    max_speed_mps: f64,
     sequence_number: u32,
 }
 
 impl AgentMachine {
     /// Creates a new `AgentMachine` in an initial state.
-    pub fn new(agent_id: u64) -> Self {
+    pub fn new(agent_id: u64, max_speed_mps: f64) -> Self {
         Self {
             agent_id,
             pose: Isometry3::identity(),
@@ -51,6 +55,7 @@ impl AgentMachine {
             current_task: None,
             current_plan: None,
             discovery_buffer: RoaringBitmap::new(),
+            max_speed_mps,
             sequence_number: 0,
         }
     }
@@ -87,36 +92,48 @@ impl AgentMachine {
                 }
             }
             Mode::Navigating => {
-                if let Some(plan) = &self.current_plan {
-                    if let Some(target) = plan.waypoints.first() {
-                        let current_pos = self.pose.translation.vector;
-                        let direction = target - Point3::from(current_pos);
-                        let distance = direction.magnitude();
-
-                        if distance < WAYPOINT_PROXIMITY_M {
-                            // Reached waypoint
-                            tracing::info!("Waypoint reached. Task complete.");
-                            self.current_plan = None;
-                            self.current_task = None;
-                            self.mode = Mode::AwaitingTask;
-                            self.velocity = Vector3::zeros();
-                        } else {
-                            // Move towards waypoint
-                            self.velocity = direction.normalize() * AGENT_SPEED_MPS;
-                            self.pose.translation.vector += self.velocity * dt_secs;
-                            // TODO: Update orientation to face velocity vector
-                            self.mode = Mode::Perceiving;
-                        }
-                    }
-                } else {
+                let Some(target) = self.current_plan.as_ref().and_then(|p| p.waypoints.first())
+                else {
                     // No plan, revert to awaiting.
                     self.mode = Mode::AwaitingTask;
+                    return;
+                };
+
+                // Straight at the waypoint, without overshooting it.
+                let offset = target - Point3::from(self.pose.translation.vector);
+                let mut remaining_m = offset.magnitude();
+                if remaining_m > WAYPOINT_PROXIMITY_M && dt_secs > 0.0 {
+                    let direction = offset / remaining_m;
+                    let step_m = (self.max_speed_mps * dt_secs).min(remaining_m);
+                    self.velocity = direction * (step_m / dt_secs);
+                    self.pose.translation.vector += direction * step_m;
+                    self.pose.rotation = Self::facing(direction, self.pose.translation.vector);
+                    remaining_m -= step_m;
+                }
+
+                if remaining_m <= WAYPOINT_PROXIMITY_M {
+                    tracing::info!("Waypoint reached, perceiving");
+                    self.velocity = Vector3::zeros();
+                    self.mode = Mode::Perceiving;
                 }
             }
             Mode::Perceiving => {
-                // The actual perception (GPU scan) is done in the main loop.
-                // This state immediately transitions back to Navigating to continue movement.
-                self.mode = Mode::Navigating;
+                // The main loop scanned on the tick that entered this mode, so the
+                // waypoint is done.
+                let more = self.current_plan.as_mut().is_some_and(|plan| {
+                    if !plan.waypoints.is_empty() {
+                        plan.waypoints.remove(0);
+                    }
+                    !plan.waypoints.is_empty()
+                });
+                if more {
+                    self.mode = Mode::Navigating;
+                } else {
+                    tracing::info!("Waypoint surveyed. Task complete.");
+                    self.current_plan = None;
+                    self.current_task = None;
+                    self.mode = Mode::AwaitingTask;
+                }
             }
             Mode::Shutdown => {
                 // Agent is shutting down.
@@ -164,6 +181,26 @@ impl AgentMachine {
         }
     }
 
+    /// The body rotation whose forward axis (the sensor boresight) points along
+    /// `direction`, level with the local horizon at `position_ecef` where it can
+    /// be: body +Z stays on the side of the geocentric up.
+    fn facing(direction: Vector3<f64>, position_ecef: Vector3<f64>) -> NalgebraUnitQuaternion<f64> {
+        let side = position_ecef
+            .try_normalize(f64::EPSILON)
+            .and_then(|up| up.cross(&direction).try_normalize(1e-9));
+        match side {
+            Some(y) => {
+                let m = Matrix3::from_columns(&[direction, y, direction.cross(&y)]);
+                NalgebraUnitQuaternion::from_rotation_matrix(&Rotation3::from_matrix_unchecked(m))
+            }
+            // Straight up or down, or at the origin: any roll will do.
+            None => NalgebraUnitQuaternion::rotation_between(&SENSOR_FORWARD_BODY, &direction)
+                .unwrap_or_else(|| {
+                    NalgebraUnitQuaternion::from_axis_angle(&Vector3::z_axis(), std::f64::consts::PI)
+                }),
+        }
+    }
+
     // --- Conversion Helpers ---
 
     fn api_to_nalgebra_point(v: &Vec3m) -> Point3<f64> {
@@ -195,3 +232,48 @@ impl AgentMachine {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tick_reaches_waypoint_in_expected_ticks() {
+        // 10 m/s at 10 Hz: 1 m per tick, 50 m to go.
+        let start = Vector3::new(4_177_000.0, 855_000.0, 4_727_000.0);
+        let target = start + Vector3::new(30.0, -40.0, 0.0);
+        let dt = Duration::from_millis(100);
+        let mut agent = AgentMachine::new(7, 10.0);
+        agent.pose.translation.vector = start;
+        agent.assign_task(Task {
+            target_waypoint_ecef_m: Some(Vec3m {
+                x: target.x,
+                y: target.y,
+                z: target.z,
+            }),
+        });
+
+        // One tick to plan, then one per metre until within the proximity radius.
+        let max_ticks = 1 + 50;
+        let mut ticks = 0;
+        while agent.mode != Mode::Perceiving {
+            assert!(
+                ticks < max_ticks,
+                "still {:?} after {} ticks",
+                agent.mode,
+                ticks
+            );
+            agent.tick(dt);
+            ticks += 1;
+        }
+        assert!(ticks >= max_ticks - 1, "arrived after only {} ticks", ticks);
+        let remaining = (target - agent.pose.translation.vector).magnitude();
+        assert!(remaining <= WAYPOINT_PROXIMITY_M, "{} m short", remaining);
+        assert_eq!(agent.velocity, Vector3::zeros());
+
+        // Surveying the only waypoint completes the task.
+        agent.tick(dt);
+        assert_eq!(agent.mode, Mode::AwaitingTask);
+        assert!(agent.current_task.is_none() && agent.current_plan.is_none());
+    }
+}
@@ -1,5 +1,5 @@
 // symtex/crates/sim_orchestrator/src/agent_manager.rs
-use crate::state::{AgentRuntimeInfo, CanonicalState};
+use crate::state::{AgentRuntimeInfo, CanonicalState, PendingAgent};
 use anyhow::Context;
 use std::sync::{
     atomic::{AtomicU16, Ordering},
@@ -16,12 +16,17 @@ use tokio::{
 /// Configuration for the AgentManager.
 #[derive(Debug, Clone)]
 pub struct AgentManagerConfig {
+    /// Agents to spawn per dataset.
     pub num_agents: u32,
     pub agent_binary_path: String,
     pub orchestrator_public_grpc_addr: String,
     pub agent_metrics_port_range_start: u16,
     pub health_check_interval: Duration,
     pub agent_health_timeout: Duration,
+    /// Delay between consecutive agent spawns during startup.
+    pub spawn_stagger: Duration,
+    /// Maximum number of spawned-but-unregistered agents before spawning pauses (0 = unlimited).
+    pub max_pending_registrations: usize,
 }
 
 /// Manages the lifecycle of `sim_agent` child processes.
@@ -77,10 +82,31 @@ impl AgentManager {
     }
 
     /// Runs the initial agent spawning and the health check loop.
+    ///
+    /// Each dataset gets `num_agents` agents; spawns are interleaved across datasets
+    /// so parallel scenarios come up together.
     async fn run(&self) -> anyhow::Result<()> {
-        for i in 0..self.config.num_agents {
-            if let Err(e) = self.spawn_agent().await {
-                tracing::error!(agent_index = i, error = %e, "Failed to spawn initial agent");
+        let dataset_ids = self.state.dataset_ids();
+        let total = self.config.num_agents * dataset_ids.len() as u32;
+
+        for i in 0..total {
+            self.wait_for_pending_capacity().await;
+
+            let dataset_id = &dataset_ids[i as usize % dataset_ids.len()];
+            if let Err(e) = self.spawn_agent(dataset_id).await {
+                tracing::error!(agent_index = i, dataset_id, error = %e, "Failed to spawn initial agent");
+            }
+
+            tracing::info!(
+                spawned = i + 1,
+                total,
+                pending = self.state.pending_registrations.len(),
+                "Agent spawn progress"
+            );
+
+            // Pace the registration burst so the gRPC server isn't hit all at once.
+            if i + 1 < total && !self.config.spawn_stagger.is_zero() {
+                sleep(self.config.spawn_stagger).await;
             }
         }
 
@@ -88,14 +114,43 @@ impl AgentManager {
         Ok(())
     }
 
-    /// Spawns a single `sim_agent` child process.
-    async fn spawn_agent(&self) -> anyhow::Result<()> {
+    /// Waits until the number of pending registrations drops below the configured cap.
+    ///
+    /// Gives up after `agent_health_timeout` so an agent that never registers
+    /// cannot stall the rest of the fleet.
+    async fn wait_for_pending_capacity(&self) {
+        let cap = self.config.max_pending_registrations;
+        if cap == 0 {
+            return;
+        }
+
+        let started = tokio::time::Instant::now();
+        while self.state.pending_registrations.len() >= cap {
+            if started.elapsed() > self.config.agent_health_timeout {
+                tracing::warn!(
+                    pending = self.state.pending_registrations.len(),
+                    cap,
+                    "Pending registrations did not drain in time; continuing spawn."
+                );
+                return;
+            }
+            sleep(Duration::from_millis(100)).await;
+        }
+    }
+
+    /// Spawns a single `sim_agent` child process for the given dataset.
+    ///
+    /// Agents of a dataset configured by path load that path's tiles, so the
+    /// point indices they report match the dataset's reveal mask and task grid.
+    /// Agents of a dataset configured by point count inherit the orchestrator's
+    /// own `POINT_CLOUD_PATH`.
+    async fn spawn_agent(&self, dataset_id: &str) -> anyhow::Result<()> {
         let session_id = uuid::Uuid::new_v4().to_string();
         let metrics_port = self
             .next_metrics_port
             .fetch_add(1, Ordering::Relaxed);
 
-        tracing::info!(session_id, metrics_port, "Spawning new agent process");
+        tracing::info!(session_id, metrics_port, dataset_id, "Spawning new agent process");
 
         let mut command = Command::new(&self.config.agent_binary_path);
         command
@@ -104,9 +159,17 @@ impl AgentManager {
                 &self.config.orchestrator_public_grpc_addr,
             )
             .env("AGENT_SESSION_ID", &session_id)
+            .env("AGENT_DATASET_ID", dataset_id)
             .env("AGENT_METRICS_PORT", metrics_port.to_string())
             .env("RUST_LOG", "info,h2=warn,hyper=warn,tower=warn") // Sensible defaults
             .kill_on_drop(true);
+        if let Some(source) = self
+            .state
+            .dataset(dataset_id)
+            .and_then(|d| d.point_cloud_metadata.source.as_ref())
+        {
+            command.env("POINT_CLOUD_PATH", source);
+        }
 
         let child = command
             .spawn()
@@ -114,7 +177,13 @@ impl AgentManager {
 
         // Insert the process handle into the pending map. The gRPC service will move it
         // to the main agents map upon successful registration.
-        self.state.pending_registrations.insert(session_id, child);
+        self.state.pending_registrations.insert(
+            session_id,
+            PendingAgent {
+                dataset_id: dataset_id.into(),
+                process_handle: child,
+            },
+        );
 
         Ok(())
     }
@@ -159,9 +228,14 @@ impl AgentManager {
 
             // Remove the dead/stale agents from the main state map
             for agent_id in agents_to_remove {
-                if self.state.agents.remove(&agent_id).is_some() {
+                if let Some((_, info)) = self.state.agents.remove(&agent_id) {
                     tracing::info!(agent_id, "Removed agent from state.");
-                    self.state.broadcast_world_state();
+                    if let Some(dataset) = self.state.dataset(&info.dataset_id) {
+                        if let Some(tasking) = &dataset.tasking {
+                            tasking.release(agent_id);
+                        }
+                        self.state.broadcast_world_state(dataset);
+                    }
                 }
             }
         }
@@ -0,0 +1,98 @@
+// symtex/crates/sim_orchestrator/src/coverage.rs
+use roaring::RoaringBitmap;
+
+/// The survey area's lon/lat bounding box split into an `n`×`n` grid, with the
+/// points of each cell, so coverage can be broken down by region.
+///
+/// Rows run south to north and columns west to east. Unlike `TaskGrid`, every
+/// cell exists even when it holds no points, so cell labels are stable.
+pub struct CoverageGrid {
+    n: usize,
+    /// Global indices (as reported by agents) of each cell's points, row-major.
+    cells: Vec<RoaringBitmap>,
+}
+
+/// Collects the positions of a dataset's points for a [`CoverageGrid`].
+///
+/// Tiles must be added in agent load order, as for `TaskGridBuilder`.
+pub struct CoverageGridBuilder {
+    n: usize,
+    /// Lon/lat of every point; f32 is ~1 m, plenty to place points in cells.
+    lon_lat: Vec<[f32; 2]>,
+}
+
+impl CoverageGridBuilder {
+    /// Starts an empty `n`×`n` grid.
+    pub fn new(n: usize) -> anyhow::Result<Self> {
+        anyhow::ensure!(n > 0, "coverage grid size must be positive");
+        Ok(Self {
+            n,
+            lon_lat: Vec::new(),
+        })
+    }
+
+    /// Adds the next tile's points.
+    pub fn add_tile(&mut self, tile: &hypc::HypcTile) {
+        self.lon_lat.extend(
+            tile.iter_points_geodetic()
+                .map(|(lat, lon, _h)| [lon as f32, lat as f32]),
+        );
+    }
+
+    /// Splits the points' bounding box into cells and sorts the points into them.
+    pub fn finish(self) -> anyhow::Result<CoverageGrid> {
+        let Self { n, lon_lat } = self;
+        anyhow::ensure!(
+            u32::try_from(lon_lat.len()).is_ok(),
+            "point count exceeds the u32 index space"
+        );
+
+        let mut min = [f32::INFINITY; 2];
+        let mut max = [f32::NEG_INFINITY; 2];
+        for p in &lon_lat {
+            for k in 0..2 {
+                min[k] = min[k].min(p[k]);
+                max[k] = max[k].max(p[k]);
+            }
+        }
+
+        // Maps a coordinate to its row or column; the max edge goes to the last one.
+        let slot = |v: f32, k: usize| -> usize {
+            let extent = max[k] - min[k];
+            if extent > 0.0 {
+                (((v - min[k]) / extent * n as f32) as usize).min(n - 1)
+            } else {
+                0
+            }
+        };
+
+        let mut cells = vec![RoaringBitmap::new(); n * n];
+        for (index, p) in lon_lat.iter().enumerate() {
+            cells[slot(p[1], 1) * n + slot(p[0], 0)].insert(index as u32);
+        }
+        Ok(CoverageGrid { n, cells })
+    }
+}
+
+impl CoverageGrid {
+    /// Cells along each axis.
+    pub fn n(&self) -> usize {
+        self.n
+    }
+
+    /// Revealed fraction of each cell's points as `((row, col), ratio)`, in
+    /// row-major order; cells without points are skipped.
+    pub fn cell_coverage<'a>(
+        &'a self,
+        reveal_mask: &'a RoaringBitmap,
+    ) -> impl Iterator<Item = ((usize, usize), f64)> + 'a {
+        self.cells
+            .iter()
+            .enumerate()
+            .filter(|(_, points)| !points.is_empty())
+            .map(move |(i, points)| {
+                let ratio = reveal_mask.intersection_len(points) as f64 / points.len() as f64;
+                ((i / self.n, i % self.n), ratio)
+            })
+    }
+}
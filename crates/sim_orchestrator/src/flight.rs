@@ -1,5 +1,8 @@
 // symtex/crates/sim_orchestrator/src/flight.rs
-use crate::{metrics::Metrics, state::CanonicalState};
+use crate::{
+    metrics::Metrics,
+    state::{ticket_dataset_id, CanonicalState},
+};
 use arrow::record_batch::RecordBatch;
 use arrow_array::{ArrayRef, LargeBinaryArray};
 use arrow_flight::{
@@ -13,6 +16,13 @@ use futures::Stream;
 use std::{pin::Pin, sync::Arc};
 use tonic::{Request, Response, Status};
 
+/// Field metadata value identifying the roaring serialization flavour.
+///
+/// `RoaringBitmap::serialize_into` emits the portable format shared with the
+/// CRoaring family, so non-Rust clients (e.g. Python's `pyroaring`) must use
+/// their portable deserializer (`BitMap.deserialize`), not a native/frozen one.
+const ROARING_FORMAT: &str = "portable";
+
 /// Implements the Apache Arrow Flight service for serving reveal mask data.
 pub struct FlightSvc {
     state: Arc<CanonicalState>,
@@ -26,6 +36,11 @@ impl FlightService for FlightSvc {
 
     /// Handles a client request to retrieve a data stream. In this service, it's used
     /// exclusively to fetch the reveal mask bitmap associated with a given ticket.
+    ///
+    /// The single `roaring_portable` column carries field metadata:
+    /// - `content_type`: `application/x-roaring`
+    /// - `version`: schema version of this payload (currently `1`)
+    /// - `roaring_format`: always `portable` (see [`ROARING_FORMAT`])
     async fn do_get(
         &self,
         req: Request<Ticket>,
@@ -34,12 +49,15 @@ impl FlightService for FlightSvc {
 
         let ticket_bytes = req.into_inner().ticket;
 
-        // 1. Validate the ticket and retrieve the corresponding data snapshot.
+        // 1. Route the ticket to its dataset, then validate it and retrieve the
+        //    corresponding data snapshot.
+        let dataset = ticket_dataset_id(&ticket_bytes)
+            .and_then(|id| self.state.dataset(id))
+            .ok_or_else(|| Status::not_found("Invalid or expired ticket"))?;
         let reveal_mask_snapshot = {
-            let tickets = self.state.valid_flight_tickets.read();
+            let tickets = dataset.valid_flight_tickets.read();
             tickets
                 .get(ticket_bytes.as_ref())
-                .cloned()
                 .ok_or_else(|| Status::not_found("Invalid or expired ticket"))?
         };
 
@@ -48,6 +66,9 @@ impl FlightService for FlightSvc {
         reveal_mask_snapshot
             .serialize_into(&mut buffer)
             .map_err(|e| Status::internal(format!("Failed to serialize bitmap: {}", e)))?;
+        // The portable format has a deterministic size; a mismatch means the
+        // serializer changed flavour and clients would silently misread it.
+        debug_assert_eq!(buffer.len(), reveal_mask_snapshot.serialized_size());
 
         // 3. Define the Arrow Schema for the data.
         let schema = Arc::new(Schema::new(vec![Field::new(
@@ -59,6 +80,7 @@ impl FlightService for FlightSvc {
             [
                 ("content_type".to_string(), "application/x-roaring".to_string()),
                 ("version".to_string(), "1".to_string()),
+                ("roaring_format".to_string(), ROARING_FORMAT.to_string()),
             ]
             .into(),
         )]));
@@ -79,6 +101,7 @@ impl FlightService for FlightSvc {
 
         tracing::debug!(
             ticket_len = ticket_bytes.len(),
+            dataset_id = dataset.id,
             points = reveal_mask_snapshot.len(),
             "Served Flight ticket"
         );
@@ -170,3 +193,66 @@ pub fn make_server(
 ) -> FlightServiceServer<FlightSvc> {
     FlightServiceServer::new(FlightSvc { state, metrics })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::FlightTicketLimits;
+    use arrow_flight::utils::flight_data_to_batches;
+    use futures::TryStreamExt;
+    use roaring::RoaringBitmap;
+    use std::{collections::HashMap, time::Duration};
+
+    #[tokio::test]
+    async fn do_get_serves_the_ticket_snapshot_in_portable_format() {
+        let state = CanonicalState::new(
+            &[("survey".into(), 1_000_000)],
+            HashMap::new(),
+            HashMap::new(),
+            HashMap::new(),
+            FlightTicketLimits {
+                ttl: Duration::from_secs(60),
+                max_tickets: 8,
+            },
+        );
+        let dataset = state.dataset("survey").unwrap().clone();
+        // Array, bitmap and run containers alike.
+        let mask: RoaringBitmap = (0..100)
+            .chain((70_000..140_000).step_by(2))
+            .chain(300_000..400_000)
+            .collect();
+        let mut discovered = Vec::new();
+        mask.serialize_into(&mut discovered).unwrap();
+        dataset.merge_discovered_points(&discovered).unwrap();
+        let ticket = dataset.create_flight_ticket();
+        // Reveals after the ticket is issued are not part of its snapshot.
+        dataset.reveal_mask.write().insert(999_999);
+
+        let svc = FlightSvc {
+            state,
+            metrics: Arc::new(Metrics::new()),
+        };
+        let response = svc
+            .do_get(Request::new(Ticket {
+                ticket: ticket.into(),
+            }))
+            .await
+            .unwrap();
+        let flight_data: Vec<FlightData> = response.into_inner().try_collect().await.unwrap();
+        let batches = flight_data_to_batches(&flight_data).unwrap();
+        assert_eq!(batches.len(), 1);
+
+        let field = batches[0].schema().field(0).clone();
+        assert_eq!(
+            field.metadata().get("roaring_format").map(String::as_str),
+            Some(ROARING_FORMAT)
+        );
+        let column = batches[0]
+            .column(0)
+            .as_any()
+            .downcast_ref::<LargeBinaryArray>()
+            .unwrap();
+        let served = RoaringBitmap::deserialize_from(column.value(0)).unwrap();
+        assert_eq!(served, mask);
+    }
+}
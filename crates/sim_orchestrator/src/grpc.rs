@@ -1,6 +1,7 @@
 use crate::{
     metrics::Metrics,
-    state::{AgentRuntimeInfo, CanonicalState, WorldStateSnapshot},
+    state::{AgentRuntimeInfo, CanonicalState, DatasetState, WorldStateSnapshot},
+    tasking,
 };
 use api::gen::api::v1::{
     simulation_c2_server::{SimulationC2, SimulationC2Server},
@@ -18,6 +19,55 @@ pub struct C2Svc {
     metrics: Arc<Metrics>,
 }
 
+impl C2Svc {
+    /// Resolves a request's dataset ID (empty = default).
+    fn dataset(&self, dataset_id: &str) -> Option<Arc<DatasetState>> {
+        self.state.dataset(dataset_id).cloned()
+    }
+
+    /// Applies a pause/resume, updating the gauge and notifying viewers on change.
+    fn set_paused(&self, dataset: &DatasetState, paused: bool) {
+        if dataset.set_paused(paused) {
+            self.metrics.update_paused(&dataset.id, paused);
+            self.state.broadcast_world_state(dataset);
+        }
+    }
+
+    /// Resumes the dataset and claims a survey cell for each of its idle agents.
+    /// The tasks reach the agents in reply to their next report, since
+    /// `allocate_task` repeats a claimed waypoint until the agent arrives.
+    ///
+    /// Returns the `IssueCommandResponse` message, naming how many agents were tasked.
+    fn start_survey(&self, dataset: &DatasetState) -> String {
+        if dataset.tasking.is_none() {
+            return format!(
+                "Dataset '{}' has no task grid; agents are not tasked",
+                dataset.id
+            );
+        }
+        self.set_paused(dataset, false);
+
+        let tasked = self
+            .state
+            .dataset_agents(&dataset.id)
+            .iter()
+            .filter(|agent| tasking::allocate_task(dataset, agent).is_some())
+            .count();
+        tracing::info!(dataset_id = dataset.id, tasked, "Survey started");
+        format!("Survey started: {} agents tasked", tasked)
+    }
+
+    /// Clears the dataset's discoveries, tickets and task claims, zeroes its
+    /// coverage gauge and sends viewers the emptied world state.
+    fn reset(&self, dataset: &DatasetState) {
+        dataset.reset();
+        self.metrics
+            .update_coverage(&dataset.id, dataset.get_coverage_ratio());
+        self.metrics.update_cell_coverage(dataset);
+        self.state.broadcast_world_state(dataset);
+    }
+}
+
 #[tonic::async_trait]
 impl SimulationC2 for C2Svc {
     /// RPC for a `sim_agent` to register with the orchestrator.
@@ -33,7 +83,7 @@ impl SimulationC2 for C2Svc {
 
         // Phase 2: Finalize registration.
         // Atomically remove the pending registration to prevent race conditions.
-        let child_handle = match self.state.pending_registrations.remove(&session_id) {
+        let pending = match self.state.pending_registrations.remove(&session_id) {
             Some(entry) => entry.1, // entry is a (key, value) tuple
             None => {
                 tracing::warn!(
@@ -44,17 +94,33 @@ impl SimulationC2 for C2Svc {
             }
         };
 
+        // The orchestrator decided the dataset at spawn time; an agent may echo it
+        // but not switch to another one.
+        if !req_inner.dataset_id.is_empty() && req_inner.dataset_id != pending.dataset_id {
+            tracing::warn!(
+                session_id,
+                requested = req_inner.dataset_id,
+                assigned = pending.dataset_id,
+                "Agent registration failed: dataset mismatch."
+            );
+            return Err(Status::invalid_argument(format!(
+                "Agent was spawned for dataset '{}'",
+                pending.dataset_id
+            )));
+        }
+
         let agent_id = self.state.next_agent_id();
-        tracing::info!(agent_id, session_id, "Registering agent");
+        tracing::info!(agent_id, session_id, dataset_id = pending.dataset_id, "Registering agent");
 
         let runtime_info = AgentRuntimeInfo {
+            dataset_id: pending.dataset_id,
             last_seen: Instant::now(),
             current_state: AgentState {
                 agent_id,
                 mode: AgentMode::AwaitingTask as i32,
                 ..Default::default()
             },
-            process_handle: Some(child_handle),
+            process_handle: Some(pending.process_handle),
         };
 
         self.state.agents.insert(agent_id, runtime_info);
@@ -102,30 +168,39 @@ impl SimulationC2 for C2Svc {
                                     tracing::info!(agent_id, "Established report stream.");
                                 }
 
-                                // Update agent state
+                                // Update agent state, and task the agent if it is idle
+                                let mut assigned_task = None;
                                 if let Some(agent_state) = report.state {
+                                    if let Some(dataset) = state.agent_dataset(agent_id) {
+                                        assigned_task = tasking::allocate_task(&dataset, &agent_state);
+                                    }
                                     state.update_agent_state(agent_id, agent_state);
                                 }
 
-                                // Process discovered points
+                                // Process discovered points into the agent's own dataset
                                 if !report.discovered_point_ids_portable.is_empty() {
-                                    match state.merge_discovered_points(&report.discovered_point_ids_portable) {
-                                        Ok(new_points) => {
-                                            if new_points > 0 {
-                                                metrics.points_revealed_total.inc_by(new_points);
-                                                metrics.update_coverage(state.get_coverage_ratio());
-                                                state.broadcast_world_state();
+                                    match state.agent_dataset(agent_id) {
+                                        Some(dataset) => match dataset.merge_discovered_points(&report.discovered_point_ids_portable) {
+                                            Ok(new_points) => {
+                                                if new_points > 0 {
+                                                    metrics.points_revealed_total.inc_by(new_points);
+                                                    metrics.update_coverage(&dataset.id, dataset.get_coverage_ratio());
+                                                    metrics.update_cell_coverage(&dataset);
+                                                    state.broadcast_world_state(&dataset);
+                                                }
                                             }
-                                        }
-                                        Err(e) => {
-                                            tracing::warn!(error = %e, agent_id, "Failed to process discovered points");
+                                            Err(e) => {
+                                                tracing::warn!(error = %e, agent_id, "Failed to process discovered points");
+                                            }
+                                        },
+                                        None => {
+                                            tracing::warn!(agent_id, "Dropping discoveries from an unregistered agent");
                                         }
                                     }
                                 }
 
-                                // TODO: Implement task allocation logic
                                 let resp = ReportStateResponse {
-                                    assigned_task: None,
+                                    assigned_task,
                                     schema_version: 1,
                                 };
 
@@ -167,13 +242,16 @@ impl SimulationC2 for C2Svc {
     /// Long-lived server-streaming RPC for a viewer to receive updates on the world state.
     async fn subscribe_world_state(
         &self,
-        _req: Request<SubscribeWorldStateRequest>,
+        req: Request<SubscribeWorldStateRequest>,
     ) -> Result<Response<Self::SubscribeWorldStateStream>, Status> {
         self.metrics.grpc_requests_total.inc();
-        tracing::info!("New world state subscriber connected.");
+        let dataset_id = req.into_inner().dataset_id;
+        let dataset = self
+            .dataset(&dataset_id)
+            .ok_or_else(|| unknown_dataset(&dataset_id))?;
+        tracing::info!(dataset_id = dataset.id, "New world state subscriber connected.");
 
-        let rx = self.state.world_state_tx.subscribe();
-        let state_clone = self.state.clone();
+        let rx = dataset.world_state_tx.subscribe();
 
         let stream = tokio_stream::wrappers::WatchStream::new(rx).map(
             move |snap: WorldStateSnapshot| {
@@ -181,7 +259,9 @@ impl SimulationC2 for C2Svc {
                     timestamp_ms: snap.timestamp_ms,
                     agents: snap.agents,
                     reveal_mask_ticket: snap.reveal_mask_flight_ticket,
-                    map_coverage_ratio: state_clone.get_coverage_ratio(),
+                    map_coverage_ratio: dataset.get_coverage_ratio(),
+                    paused: snap.paused,
+                    dataset_id: dataset.id.clone(),
                     schema_version: 1,
                 })
             },
@@ -198,30 +278,49 @@ impl SimulationC2 for C2Svc {
         req: Request<IssueCommandRequest>,
     ) -> Result<Response<IssueCommandResponse>, Status> {
         self.metrics.grpc_requests_total.inc();
+        let req = req.into_inner();
         let cmd = req
-            .into_inner()
             .command
             .ok_or_else(|| Status::invalid_argument("Command is missing"))?;
+        let dataset = self
+            .dataset(&req.dataset_id)
+            .ok_or_else(|| unknown_dataset(&req.dataset_id))?;
 
-        match cmd {
+        let message = match cmd {
             issue_command_request::Command::StartSurvey(_) => {
-                tracing::info!("Received StartSurvey command.");
-                // TODO: Trigger tasking module
+                tracing::info!(dataset_id = dataset.id, "Received StartSurvey command.");
+                self.start_survey(&dataset)
             }
             issue_command_request::Command::ResetSimulation(_) => {
-                tracing::info!("Received ResetSimulation command.");
-                // TODO: Implement simulation reset logic
+                tracing::info!(dataset_id = dataset.id, "Received ResetSimulation command.");
+                self.reset(&dataset);
+                format!("Dataset '{}' reset", dataset.id)
             }
-        }
+            issue_command_request::Command::PauseSimulation(_) => {
+                tracing::info!(dataset_id = dataset.id, "Received PauseSimulation command.");
+                self.set_paused(&dataset, true);
+                "Command acknowledged".into()
+            }
+            issue_command_request::Command::ResumeSimulation(_) => {
+                tracing::info!(dataset_id = dataset.id, "Received ResumeSimulation command.");
+                self.set_paused(&dataset, false);
+                "Command acknowledged".into()
+            }
+        };
 
         Ok(Response::new(IssueCommandResponse {
             acknowledged: true,
-            message: "Command acknowledged".into(),
+            message,
             schema_version: 1,
         }))
     }
 }
 
+/// The `NOT_FOUND` status returned for a dataset ID this orchestrator does not serve.
+fn unknown_dataset(dataset_id: &str) -> Status {
+    Status::not_found(format!("Unknown dataset '{}'", dataset_id))
+}
+
 /// Configures and runs the main gRPC server.
 pub async fn serve_grpc(
     state: Arc<CanonicalState>,
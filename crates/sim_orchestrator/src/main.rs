@@ -1,16 +1,26 @@
 // symtex/crates/sim_orchestrator/src/main.rs
 mod agent_manager;
+mod coverage;
 mod flight;
 mod grpc;
 mod metrics;
+mod rest;
 mod state;
 mod tasking;
 
 use crate::agent_manager::{AgentManager, AgentManagerConfig};
+use crate::coverage::{CoverageGrid, CoverageGridBuilder};
 use crate::metrics::Metrics;
-use crate::state::CanonicalState;
+use crate::state::{CanonicalState, DatasetSource, FlightTicketLimits, TileMetadata};
+use crate::tasking::{TaskGrid, TaskGridBuilder};
 use anyhow::Context;
-use std::{net::SocketAddr, sync::Arc, time::Duration};
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
 use tokio::sync::watch;
 use tracing_subscriber::{fmt, EnvFilter};
 
@@ -25,12 +35,28 @@ struct Config {
     num_agents: u32,
     agent_health_timeout: Duration,
     agent_metrics_port_range_start: u16,
-    point_cloud_total_points: u64,
+    agent_spawn_stagger: Duration,
+    agent_max_pending_registrations: usize,
+    /// Datasets to serve as `(dataset_id, total_points)`.
+    datasets: Vec<(String, u64)>,
+    /// Paths and tiles of the datasets configured by path, used to task agents.
+    dataset_sources: DatasetSources,
+    /// Edge length of the cubes survey areas are split into for tasking.
+    task_cell_size_m: f64,
+    /// Cells along each axis of the per-cell coverage grid; 0 disables it.
+    coverage_grid_n: usize,
+    /// Lifetime and per-dataset cap of Arrow Flight tickets.
+    ticket_limits: FlightTicketLimits,
 }
 
 impl Config {
     /// Parses configuration from environment variables.
     fn from_env() -> anyhow::Result<Self> {
+        let (datasets, dataset_sources) = parse_datasets(
+            &std::env::var("ORCHESTRATOR_DATASETS")
+                .unwrap_or_else(|_| format!("{}=1000000", state::DEFAULT_DATASET_ID)),
+        )
+        .context("Failed to parse ORCHESTRATOR_DATASETS")?;
         Ok(Self {
             grpc_listen_addr: std::env::var("ORCHESTRATOR_GRPC_LISTEN_ADDR")
                 .unwrap_or_else(|_| "0.0.0.0:50051".into())
@@ -62,12 +88,153 @@ impl Config {
                 .unwrap_or_else(|_| "9100".into())
                 .parse()
                 .context("Failed to parse AGENT_METRICS_PORT_RANGE_START")?,
-            // TODO: Load this from .hypc header per specification.
-            point_cloud_total_points: 1_000_000,
+            agent_spawn_stagger: Duration::from_millis(
+                std::env::var("AGENT_SPAWN_STAGGER_MS")
+                    .unwrap_or_else(|_| "250".into())
+                    .parse()
+                    .context("Failed to parse AGENT_SPAWN_STAGGER_MS")?,
+            ),
+            agent_max_pending_registrations: std::env::var("AGENT_MAX_PENDING_REGISTRATIONS")
+                .unwrap_or_else(|_| "8".into())
+                .parse()
+                .context("Failed to parse AGENT_MAX_PENDING_REGISTRATIONS")?,
+            datasets,
+            dataset_sources,
+            task_cell_size_m: std::env::var("ORCHESTRATOR_TASK_CELL_M")
+                .unwrap_or_else(|_| "100".into())
+                .parse()
+                .context("Failed to parse ORCHESTRATOR_TASK_CELL_M")?,
+            coverage_grid_n: std::env::var("ORCHESTRATOR_COVERAGE_GRID")
+                .unwrap_or_else(|_| "8".into())
+                .parse()
+                .context("Failed to parse ORCHESTRATOR_COVERAGE_GRID")?,
+            ticket_limits: FlightTicketLimits {
+                ttl: Duration::from_millis(
+                    std::env::var("ORCHESTRATOR_TICKET_TTL_MS")
+                        .unwrap_or_else(|_| "60000".into())
+                        .parse()
+                        .context("Failed to parse ORCHESTRATOR_TICKET_TTL_MS")?,
+                ),
+                max_tickets: std::env::var("ORCHESTRATOR_MAX_TICKETS")
+                    .unwrap_or_else(|_| "1024".into())
+                    .parse()
+                    .context("Failed to parse ORCHESTRATOR_MAX_TICKETS")?,
+            },
         })
     }
 }
 
+/// Sources of the datasets configured by path, by dataset ID.
+type DatasetSources = HashMap<String, DatasetSource>;
+
+/// Parses `ORCHESTRATOR_DATASETS`: comma-separated `<dataset_id>=<source>` entries,
+/// where `<source>` is either a point count or the path of the `.hypc` tile (or
+/// directory of tiles) the dataset's agents load, counted from the tile headers.
+///
+/// Returns the `(dataset_id, total_points)` list and, for datasets given by
+/// path, that path and their tiles in agent load order.
+fn parse_datasets(spec: &str) -> anyhow::Result<(Vec<(String, u64)>, DatasetSources)> {
+    let mut datasets: Vec<(String, u64)> = Vec::new();
+    let mut sources = HashMap::new();
+    for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let (id, total) = entry
+            .split_once('=')
+            .with_context(|| format!("expected <dataset_id>=<source>, got '{}'", entry))?;
+        let id = id.trim();
+        anyhow::ensure!(!id.is_empty(), "empty dataset id in '{}'", entry);
+        anyhow::ensure!(
+            datasets.iter().all(|(d, _)| d != id),
+            "duplicate dataset id '{}'",
+            id
+        );
+        let source = total.trim();
+        let total = match source.parse() {
+            Ok(total) => total,
+            Err(_) => {
+                let paths = tile_paths(Path::new(source))
+                    .with_context(|| format!("listing tiles for dataset '{}'", id))?;
+                let meta = read_tile_headers(Path::new(source), paths)
+                    .with_context(|| format!("counting points for dataset '{}'", id))?;
+                let total = meta.iter().map(|t| u64::from(t.points_count)).sum();
+                sources.insert(
+                    id.to_string(),
+                    DatasetSource {
+                        path: PathBuf::from(source),
+                        tiles: meta,
+                    },
+                );
+                total
+            }
+        };
+        datasets.push((id.to_string(), total));
+    }
+    anyhow::ensure!(!datasets.is_empty(), "at least one dataset is required");
+    Ok((datasets, sources))
+}
+
+/// The `.hypc` tile at `path`, or the `.hypc` files directly inside a directory
+/// sorted by file name; the set, and order, the agent's perception system
+/// concatenates.
+fn tile_paths(path: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+
+    let mut paths = Vec::new();
+    for entry in std::fs::read_dir(path).with_context(|| format!("listing {}", path.display()))? {
+        let p = entry?.path();
+        if p.is_file() && p.extension().and_then(|e| e.to_str()) == Some("hypc") {
+            paths.push(p);
+        }
+    }
+    anyhow::ensure!(!paths.is_empty(), "no .hypc tiles in {}", path.display());
+    paths.sort_by(|a, b| a.file_name().cmp(&b.file_name()));
+    Ok(paths)
+}
+
+/// The headers of `paths`, the tiles of the dataset at `source`.
+fn read_tile_headers(source: &Path, paths: Vec<PathBuf>) -> anyhow::Result<Vec<TileMetadata>> {
+    let mut tiles = Vec::with_capacity(paths.len());
+    let mut total = 0u64;
+    for p in paths {
+        let header = hypc::read_header(&p).with_context(|| format!("reading {}", p.display()))?;
+        total += u64::from(header.points_count);
+        tiles.push(TileMetadata::from_header(p, &header));
+    }
+    tracing::info!(
+        source = %source.display(),
+        tiles = tiles.len(),
+        total_points = total,
+        "Counted dataset points from tile headers"
+    );
+    Ok(tiles)
+}
+
+/// The task grid of a dataset's `tiles` and, if `coverage_grid_n > 0`, its
+/// coverage grid, built in one pass that reads each tile once.
+fn load_grids(
+    tiles: &[TileMetadata],
+    task_cell_size_m: f64,
+    coverage_grid_n: usize,
+) -> anyhow::Result<(TaskGrid, Option<CoverageGrid>)> {
+    let mut task = TaskGridBuilder::new(task_cell_size_m)?;
+    let mut coverage = (coverage_grid_n > 0)
+        .then(|| CoverageGridBuilder::new(coverage_grid_n))
+        .transpose()?;
+    for t in tiles {
+        let tile =
+            hypc::read_file(&t.path).with_context(|| format!("reading {}", t.path.display()))?;
+        task.add_tile(&tile)?;
+        if let Some(coverage) = &mut coverage {
+            coverage.add_tile(&tile);
+        }
+    }
+    Ok((
+        task.finish(),
+        coverage.map(CoverageGridBuilder::finish).transpose()?,
+    ))
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     fmt()
@@ -81,7 +248,42 @@ async fn main() -> anyhow::Result<()> {
     let (shutdown_tx, shutdown_rx) = watch::channel(());
 
     let metrics = Arc::new(Metrics::new());
-    let (state, _world_state_rx) = CanonicalState::new(config.point_cloud_total_points);
+    let mut task_grids = HashMap::new();
+    let mut coverage_grids = HashMap::new();
+    for (id, source) in &config.dataset_sources {
+        let (task_grid, coverage_grid) =
+            load_grids(&source.tiles, config.task_cell_size_m, config.coverage_grid_n)
+                .with_context(|| format!("building grids for dataset '{}'", id))?;
+        tracing::info!(dataset_id = id, cells = task_grid.len(), "Built task grid");
+        task_grids.insert(id.clone(), task_grid);
+
+        if let Some(grid) = coverage_grid {
+            tracing::info!(dataset_id = id, n = grid.n(), "Built coverage grid");
+            coverage_grids.insert(id.clone(), grid);
+        }
+    }
+    for (id, _) in &config.datasets {
+        if !task_grids.contains_key(id) {
+            tracing::warn!(
+                dataset_id = id,
+                "Dataset configured by point count only; its agents will not be tasked \
+                 and load the orchestrator's own POINT_CLOUD_PATH"
+            );
+        }
+    }
+    let state = CanonicalState::new(
+        &config.datasets,
+        task_grids,
+        coverage_grids,
+        config.dataset_sources.clone(),
+        config.ticket_limits,
+    );
+    // Publish every cell from the start, so unexplored ones show up at 0.
+    for id in state.dataset_ids() {
+        if let Some(dataset) = state.dataset(&id) {
+            metrics.update_cell_coverage(dataset);
+        }
+    }
 
     // Spawn the Agent Manager
     let agent_manager_config = AgentManagerConfig {
@@ -91,6 +293,8 @@ async fn main() -> anyhow::Result<()> {
         agent_metrics_port_range_start: config.agent_metrics_port_range_start,
         health_check_interval: Duration::from_secs(5),
         agent_health_timeout: config.agent_health_timeout,
+        spawn_stagger: config.agent_spawn_stagger,
+        max_pending_registrations: config.agent_max_pending_registrations,
     };
     let agent_manager_handle =
         AgentManager::spawn(agent_manager_config, state.clone(), shutdown_rx.clone());
@@ -116,9 +320,9 @@ async fn main() -> anyhow::Result<()> {
         })
     };
 
-    // Spawn the metrics server
+    // Spawn the metrics server, which also serves the read-only REST API
     let metrics_handle = {
-        let router = metrics.router();
+        let router = metrics.router().merge(rest::router(state.clone()));
         let addr = config.metrics_listen_addr;
         tokio::spawn(async move {
             let listener = tokio::net::TcpListener::bind(addr).await?;
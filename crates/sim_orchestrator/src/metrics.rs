@@ -1,5 +1,6 @@
+use crate::state::DatasetState;
 use axum::{response::IntoResponse, routing::get, Router};
-use prometheus::{Encoder, Gauge, IntCounter, IntGauge, Registry, TextEncoder};
+use prometheus::{Encoder, GaugeVec, IntCounter, IntGauge, IntGaugeVec, Opts, Registry, TextEncoder};
 
 /// A container for all Prometheus metric collectors for the sim_orchestrator.
 ///
@@ -13,12 +14,16 @@ pub struct Metrics {
     pub agents_active: IntGauge,
     /// Total number of unique points revealed across the entire simulation.
     pub points_revealed_total: IntCounter,
-    /// The current ratio of revealed points to total points (0.0 to 1.0).
-    pub map_coverage_ratio: Gauge,
+    /// The current ratio of revealed points to total points (0.0 to 1.0), per dataset.
+    pub map_coverage_ratio: GaugeVec,
+    /// Coverage ratio per lon/lat cell (`"row,col"`, from the south-west), per dataset.
+    pub map_coverage_ratio_cell: GaugeVec,
     /// Total number of gRPC requests handled by the C2 service.
     pub grpc_requests_total: IntCounter,
     /// Total number of Arrow Flight requests handled.
     pub flight_requests_total: IntCounter,
+    /// 1 while a dataset is paused, 0 otherwise.
+    pub simulation_paused: IntGaugeVec,
 }
 
 impl Metrics {
@@ -55,9 +60,20 @@ impl Metrics {
                 "Total number of unique points revealed by all agents"
             )
             .unwrap()),
-            map_coverage_ratio: reg!(Gauge::new(
-                "map_coverage_ratio",
-                "The ratio of revealed points to total points in the point cloud"
+            map_coverage_ratio: reg!(GaugeVec::new(
+                Opts::new(
+                    "map_coverage_ratio",
+                    "The ratio of revealed points to total points in the point cloud"
+                ),
+                &["dataset"]
+            )
+            .unwrap()),
+            map_coverage_ratio_cell: reg!(GaugeVec::new(
+                Opts::new(
+                    "map_coverage_ratio_cell",
+                    "The ratio of revealed points to total points in one cell of the survey area's lon/lat grid"
+                ),
+                &["dataset", "cell"]
             )
             .unwrap()),
             grpc_requests_total: reg!(IntCounter::new(
@@ -70,6 +86,14 @@ impl Metrics {
                 "Total number of Arrow Flight DoGet requests received"
             )
             .unwrap()),
+            simulation_paused: reg!(IntGaugeVec::new(
+                Opts::new(
+                    "simulation_paused",
+                    "Whether the simulation is currently paused (1) or running (0)"
+                ),
+                &["dataset"]
+            )
+            .unwrap()),
             registry,
         }
     }
@@ -96,9 +120,32 @@ impl Metrics {
         )
     }
 
-    /// Sets the value of the map coverage gauge.
-    pub fn update_coverage(&self, coverage_ratio: f64) {
-        self.map_coverage_ratio.set(coverage_ratio);
+    /// Sets the value of the map coverage gauge for a dataset.
+    pub fn update_coverage(&self, dataset_id: &str, coverage_ratio: f64) {
+        self.map_coverage_ratio
+            .with_label_values(&[dataset_id])
+            .set(coverage_ratio);
+    }
+
+    /// Sets the per-cell coverage gauges of a dataset that has a coverage grid.
+    pub fn update_cell_coverage(&self, dataset: &DatasetState) {
+        let Some(grid) = &dataset.coverage_grid else {
+            return;
+        };
+        let reveal_mask = dataset.reveal_mask.read();
+        for ((row, col), ratio) in grid.cell_coverage(&reveal_mask) {
+            let cell = format!("{},{}", row, col);
+            self.map_coverage_ratio_cell
+                .with_label_values(&[&dataset.id, &cell])
+                .set(ratio);
+        }
+    }
+
+    /// Sets the value of the paused gauge for a dataset.
+    pub fn update_paused(&self, dataset_id: &str, paused: bool) {
+        self.simulation_paused
+            .with_label_values(&[dataset_id])
+            .set(paused as i64);
     }
 
     /// Sets the value of the active agents gauge.
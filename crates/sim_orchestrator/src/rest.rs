@@ -0,0 +1,168 @@
+// symtex/crates/sim_orchestrator/src/rest.rs
+//! Read-only HTTP JSON view of the world state, for dashboards and other
+//! clients that don't speak gRPC. Served next to `/metrics`.
+//!
+//! - `GET /agents`: the latest reported state of every registered agent
+//! - `GET /coverage`: reveal progress per dataset
+//! - `GET /tiles`: tile header metadata per dataset
+//!
+//! Each route takes an optional `?dataset=<id>` filter. Commands (pause, reset)
+//! stay on gRPC.
+
+use crate::state::{CanonicalState, DatasetState};
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    routing::get,
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+pub fn router(state: Arc<CanonicalState>) -> Router {
+    Router::new()
+        .route("/agents", get(get_agents))
+        .route("/coverage", get(get_coverage))
+        .route("/tiles", get(get_tiles))
+        .with_state(state)
+}
+
+#[derive(Deserialize)]
+struct DatasetFilter {
+    dataset: Option<String>,
+}
+
+#[derive(Serialize)]
+struct AgentDto {
+    agent_id: u64,
+    dataset_id: String,
+    /// `AgentMode` name, e.g. `NAVIGATING`.
+    mode: &'static str,
+    timestamp_ms: i64,
+    /// Time since the agent's last report.
+    last_seen_ms: u64,
+    position_ecef_m: Option<[f64; 3]>,
+    velocity_ecef_mps: Option<[f64; 3]>,
+    /// `[w, x, y, z]`.
+    orientation_ecef: Option<[f64; 4]>,
+    sequence: u32,
+}
+
+#[derive(Serialize)]
+struct CoverageDto {
+    dataset_id: String,
+    revealed_points: u64,
+    total_points: u64,
+    coverage_ratio: f64,
+    paused: bool,
+}
+
+#[derive(Serialize)]
+struct DatasetTilesDto {
+    dataset_id: String,
+    tiles: Vec<TileDto>,
+}
+
+#[derive(Serialize)]
+struct TileDto {
+    path: String,
+    points_count: u32,
+    units_per_meter: u32,
+    anchor_ecef_m: [f64; 3],
+    /// Lowercase hex.
+    tile_key: Option<String>,
+}
+
+/// The datasets `filter` selects, in ID order, or 404 for an unknown ID.
+fn datasets(
+    state: &CanonicalState,
+    filter: &DatasetFilter,
+) -> Result<Vec<Arc<DatasetState>>, (StatusCode, String)> {
+    match &filter.dataset {
+        Some(id) => state
+            .dataset(id)
+            .map(|d| vec![d.clone()])
+            .ok_or_else(|| (StatusCode::NOT_FOUND, format!("unknown dataset '{}'", id))),
+        None => Ok(state
+            .dataset_ids()
+            .iter()
+            .filter_map(|id| state.dataset(id).cloned())
+            .collect()),
+    }
+}
+
+async fn get_agents(
+    State(state): State<Arc<CanonicalState>>,
+    Query(filter): Query<DatasetFilter>,
+) -> Result<Json<Vec<AgentDto>>, (StatusCode, String)> {
+    let dataset_ids: Vec<String> = datasets(&state, &filter)?
+        .iter()
+        .map(|d| d.id.clone())
+        .collect();
+
+    let mut agents: Vec<AgentDto> = state
+        .agents
+        .iter()
+        .filter(|entry| dataset_ids.contains(&entry.dataset_id))
+        .map(|entry| {
+            let s = &entry.current_state;
+            AgentDto {
+                agent_id: *entry.key(),
+                dataset_id: entry.dataset_id.clone(),
+                mode: s.mode().as_str_name(),
+                timestamp_ms: s.timestamp_ms,
+                last_seen_ms: entry.last_seen.elapsed().as_millis() as u64,
+                position_ecef_m: s.position_ecef_m.as_ref().map(|v| [v.x, v.y, v.z]),
+                velocity_ecef_mps: s.velocity_ecef_mps.as_ref().map(|v| [v.x, v.y, v.z]),
+                orientation_ecef: s.orientation_ecef.as_ref().map(|q| [q.w, q.x, q.y, q.z]),
+                sequence: s.sequence,
+            }
+        })
+        .collect();
+    agents.sort_by_key(|a| a.agent_id);
+    Ok(Json(agents))
+}
+
+async fn get_coverage(
+    State(state): State<Arc<CanonicalState>>,
+    Query(filter): Query<DatasetFilter>,
+) -> Result<Json<Vec<CoverageDto>>, (StatusCode, String)> {
+    let coverage = datasets(&state, &filter)?
+        .iter()
+        .map(|d| CoverageDto {
+            dataset_id: d.id.clone(),
+            revealed_points: d.reveal_mask.read().len(),
+            total_points: d.point_cloud_metadata.total_points,
+            coverage_ratio: d.get_coverage_ratio(),
+            paused: d.is_paused(),
+        })
+        .collect();
+    Ok(Json(coverage))
+}
+
+async fn get_tiles(
+    State(state): State<Arc<CanonicalState>>,
+    Query(filter): Query<DatasetFilter>,
+) -> Result<Json<Vec<DatasetTilesDto>>, (StatusCode, String)> {
+    let tiles = datasets(&state, &filter)?
+        .iter()
+        .map(|d| DatasetTilesDto {
+            dataset_id: d.id.clone(),
+            tiles: d
+                .point_cloud_metadata
+                .tiles
+                .iter()
+                .map(|t| TileDto {
+                    path: t.path.display().to_string(),
+                    points_count: t.points_count,
+                    units_per_meter: t.units_per_meter,
+                    anchor_ecef_m: t.anchor_ecef_m,
+                    tile_key: t
+                        .tile_key
+                        .map(|k| k.iter().map(|b| format!("{:02x}", b)).collect()),
+                })
+                .collect(),
+        })
+        .collect();
+    Ok(Json(tiles))
+}
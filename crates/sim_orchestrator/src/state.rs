@@ -1,37 +1,143 @@
 // symtex/crates/sim_orchestrator/src/state.rs
+use crate::coverage::CoverageGrid;
+use crate::tasking::{TaskGrid, Tasking};
 use api::gen::api::v1 as pb;
 use dashmap::DashMap;
 use parking_lot::RwLock;
 use roaring::RoaringBitmap;
-use std::{collections::HashMap, sync::Arc, time::Instant};
+use std::{
+    collections::{HashMap, VecDeque},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
 use tokio::sync::watch;
 
+/// Dataset used when a request or agent does not name one.
+pub const DEFAULT_DATASET_ID: &str = "default";
+
 /// The single, authoritative source of truth for the simulation.
 ///
 /// This struct is wrapped in an `Arc` and shared across all concurrent tasks
 /// within the orchestrator. It uses thread-safe interior mutability patterns.
+///
+/// Agents are global, but each belongs to exactly one dataset; the per-survey
+/// state (reveal mask, tickets, pause flag, viewer channel) lives in `DatasetState`.
 pub struct CanonicalState {
     /// A map of registered and active agents, keyed by their unique `agent_id`.
     pub agents: DashMap<u64, AgentRuntimeInfo>,
     /// A temporary holding map for agents that have been spawned but have not yet
     /// completed their gRPC registration. Keyed by a unique session ID (UUID string).
-    pub pending_registrations: DashMap<String, tokio::process::Child>,
-    /// The global, unified map of all discovered points, represented as a compressed bitmap.
+    pub pending_registrations: DashMap<String, PendingAgent>,
+    /// Independent surveys served by this orchestrator, keyed by dataset ID.
+    /// Fixed at startup; always contains at least one entry.
+    datasets: HashMap<String, Arc<DatasetState>>,
+    /// An atomic counter to generate unique, monotonic IDs for new agents.
+    next_agent_id: std::sync::atomic::AtomicU64,
+}
+
+/// The state of one survey: its point cloud, reveal mask and viewer broadcast channel.
+pub struct DatasetState {
+    /// The dataset ID this state is registered under.
+    pub id: String,
+    /// The unified map of all discovered points in this dataset, represented as a compressed bitmap.
     pub reveal_mask: RwLock<RoaringBitmap>,
     /// Static metadata about the point cloud, such as the total number of points.
     pub point_cloud_metadata: PointCloudMetadata,
     /// The sender side of a watch channel used to broadcast `WorldStateSnapshot` updates
-    /// to all subscribed viewers.
+    /// to all viewers subscribed to this dataset.
     pub world_state_tx: watch::Sender<WorldStateSnapshot>,
-    /// An atomic counter to generate unique, monotonic IDs for new agents.
-    next_agent_id: std::sync::atomic::AtomicU64,
-    /// A map of currently valid Arrow Flight tickets to their corresponding reveal mask snapshots.
+    /// Set while the dataset is paused by an operator; tasking is suspended.
+    paused: AtomicBool,
+    /// Survey cells and agent claims; `None` when the dataset was configured by
+    /// point count only, so there is no geometry to task agents with.
+    pub tasking: Option<Tasking>,
+    /// Points per lon/lat cell, for the per-cell coverage gauge; `None` when the
+    /// dataset was configured by point count only.
+    pub coverage_grid: Option<CoverageGrid>,
+    /// Currently valid Arrow Flight tickets and their reveal mask snapshots.
     /// This prevents clients from using old tickets to access new data.
-    pub valid_flight_tickets: RwLock<HashMap<Vec<u8>, Arc<RoaringBitmap>>>,
+    pub valid_flight_tickets: RwLock<FlightTickets>,
+}
+
+/// How long Flight tickets stay valid and how many a dataset keeps.
+#[derive(Debug, Clone, Copy)]
+pub struct FlightTicketLimits {
+    /// Tickets older than this are rejected and evicted.
+    pub ttl: Duration,
+    /// At most this many tickets are retained; the oldest are evicted first.
+    pub max_tickets: usize,
+}
+
+/// Flight tickets issued for one dataset, each with the reveal mask snapshot it
+/// resolves to. Bounded by [`FlightTicketLimits`], pruned as tickets are added.
+pub struct FlightTickets {
+    limits: FlightTicketLimits,
+    snapshots: HashMap<Vec<u8>, (Instant, Arc<RoaringBitmap>)>,
+    /// Tickets in issue order, so the oldest are at the front.
+    order: VecDeque<Vec<u8>>,
+}
+
+impl FlightTickets {
+    pub fn new(limits: FlightTicketLimits) -> Self {
+        Self {
+            limits,
+            snapshots: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    /// The snapshot for `ticket`, unless it is unknown or has expired.
+    pub fn get(&self, ticket: &[u8]) -> Option<Arc<RoaringBitmap>> {
+        let (issued, snapshot) = self.snapshots.get(ticket)?;
+        (issued.elapsed() <= self.limits.ttl).then(|| snapshot.clone())
+    }
+
+    /// Registers a new ticket, first evicting expired tickets and then the
+    /// oldest ones until there is room under the cap.
+    pub fn insert(&mut self, ticket: Vec<u8>, snapshot: Arc<RoaringBitmap>) {
+        let now = Instant::now();
+        while let Some(oldest) = self.order.front() {
+            let expired = self
+                .snapshots
+                .get(oldest)
+                .is_none_or(|(issued, _)| now.duration_since(*issued) > self.limits.ttl);
+            if !expired && self.order.len() < self.limits.max_tickets {
+                break;
+            }
+            if let Some(oldest) = self.order.pop_front() {
+                self.snapshots.remove(&oldest);
+            }
+        }
+        if self.limits.max_tickets == 0 {
+            return;
+        }
+        self.order.push_back(ticket.clone());
+        self.snapshots.insert(ticket, (now, snapshot));
+    }
+
+    /// Invalidates every outstanding ticket.
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+        self.order.clear();
+    }
+}
+
+/// An agent process that has been spawned but has not registered yet.
+pub struct PendingAgent {
+    /// The dataset the agent was spawned for.
+    pub dataset_id: String,
+    /// A handle to the agent's OS child process.
+    pub process_handle: tokio::process::Child,
 }
 
 /// Holds all runtime information for a single agent, including its OS process handle.
 pub struct AgentRuntimeInfo {
+    /// The dataset this agent is surveying.
+    pub dataset_id: String,
     /// The last time the orchestrator received a report from this agent. Used for health checks.
     pub last_seen: Instant,
     /// The most recent state reported by the agent.
@@ -47,31 +153,112 @@ pub struct WorldStateSnapshot {
     pub timestamp_ms: i64,
     pub agents: Vec<pb::AgentState>,
     pub reveal_mask_flight_ticket: Vec<u8>,
+    pub paused: bool,
 }
 
 /// Static metadata about the point cloud.
 pub struct PointCloudMetadata {
     pub total_points: u64,
+    /// The `.hypc` tile or directory of tiles the dataset was configured with,
+    /// passed to its agents as `POINT_CLOUD_PATH`; `None` when the dataset was
+    /// configured by point count only.
+    pub source: Option<PathBuf>,
+    /// The dataset's tiles in agent load order; empty when the dataset was
+    /// configured by point count only.
+    pub tiles: Vec<TileMetadata>,
+}
+
+/// A dataset configured by path: the tile or directory given, and the tiles
+/// read from it in agent load order.
+#[derive(Debug, Clone)]
+pub struct DatasetSource {
+    pub path: PathBuf,
+    pub tiles: Vec<TileMetadata>,
+}
+
+/// Header fields of one dataset tile, read at startup.
+#[derive(Debug, Clone)]
+pub struct TileMetadata {
+    pub path: PathBuf,
+    pub points_count: u32,
+    pub units_per_meter: u32,
+    pub anchor_ecef_m: [f64; 3],
+    pub tile_key: Option<[u8; 32]>,
+}
+
+impl TileMetadata {
+    pub fn from_header(path: PathBuf, header: &hypc::HypcHeader) -> Self {
+        let upm = header.units_per_meter as f64;
+        Self {
+            path,
+            points_count: header.points_count,
+            units_per_meter: header.units_per_meter,
+            anchor_ecef_m: header.anchor_ecef_units.map(|u| u as f64 / upm),
+            tile_key: header.tile_key,
+        }
+    }
 }
 
 impl CanonicalState {
-    /// Creates a new, empty `CanonicalState` and the receiver for its broadcast channel.
-    pub fn new(total_points: u64) -> (Arc<Self>, watch::Receiver<WorldStateSnapshot>) {
-        let (tx, rx) = watch::channel(WorldStateSnapshot {
-            timestamp_ms: 0,
-            agents: Vec::new(),
-            reveal_mask_flight_ticket: Vec::new(),
-        });
-        let this = Arc::new(Self {
+    /// Creates a new, empty `CanonicalState` with one `DatasetState` per
+    /// `(dataset_id, total_points)` entry. Datasets with an entry in `task_grids`
+    /// get agent tasking, those with one in `coverage_grids` per-cell coverage,
+    /// and those with one in `sources` keep it as metadata.
+    ///
+    /// An empty list yields the single `DEFAULT_DATASET_ID` dataset with no points.
+    pub fn new(
+        datasets: &[(String, u64)],
+        mut task_grids: HashMap<String, TaskGrid>,
+        mut coverage_grids: HashMap<String, CoverageGrid>,
+        mut sources: HashMap<String, DatasetSource>,
+        ticket_limits: FlightTicketLimits,
+    ) -> Arc<Self> {
+        let mut map: HashMap<String, Arc<DatasetState>> = datasets
+            .iter()
+            .map(|(id, total_points)| {
+                let tasking = task_grids.remove(id).map(Tasking::new);
+                let coverage_grid = coverage_grids.remove(id);
+                let source = sources.remove(id);
+                let metadata = PointCloudMetadata {
+                    total_points: *total_points,
+                    source: source.as_ref().map(|s| s.path.clone()),
+                    tiles: source.map(|s| s.tiles).unwrap_or_default(),
+                };
+                (
+                    id.clone(),
+                    Arc::new(DatasetState::new(
+                        id,
+                        metadata,
+                        tasking,
+                        coverage_grid,
+                        ticket_limits,
+                    )),
+                )
+            })
+            .collect();
+        if map.is_empty() {
+            map.insert(
+                DEFAULT_DATASET_ID.into(),
+                Arc::new(DatasetState::new(
+                    DEFAULT_DATASET_ID,
+                    PointCloudMetadata {
+                        total_points: 0,
+                        source: None,
+                        tiles: Vec::new(),
+                    },
+                    None,
+                    None,
+                    ticket_limits,
+                )),
+            );
+        }
+
+        Arc::new(Self {
             agents: DashMap::new(),
             pending_registrations: DashMap::new(),
-            reveal_mask: RwLock::new(RoaringBitmap::new()),
-            point_cloud_metadata: PointCloudMetadata { total_points },
-            world_state_tx: tx,
+            datasets: map,
             next_agent_id: std::sync::atomic::AtomicU64::new(1),
-            valid_flight_tickets: RwLock::new(HashMap::new()),
-        });
-        (this, rx)
+        })
     }
 
     /// Atomically generates and returns a new, unique agent ID.
@@ -80,6 +267,45 @@ impl CanonicalState {
             .fetch_add(1, std::sync::atomic::Ordering::Relaxed)
     }
 
+    /// Looks up a dataset by ID; an empty ID selects the default dataset.
+    pub fn dataset(&self, dataset_id: &str) -> Option<&Arc<DatasetState>> {
+        if dataset_id.is_empty() {
+            self.default_dataset()
+        } else {
+            self.datasets.get(dataset_id)
+        }
+    }
+
+    /// Returns the default dataset: `DEFAULT_DATASET_ID` if configured, otherwise
+    /// the first dataset by ID.
+    pub fn default_dataset(&self) -> Option<&Arc<DatasetState>> {
+        self.datasets
+            .get(DEFAULT_DATASET_ID)
+            .or_else(|| self.datasets.iter().min_by(|a, b| a.0.cmp(b.0)).map(|(_, d)| d))
+    }
+
+    /// Returns all dataset IDs in sorted order.
+    pub fn dataset_ids(&self) -> Vec<String> {
+        let mut ids: Vec<String> = self.datasets.keys().cloned().collect();
+        ids.sort();
+        ids
+    }
+
+    /// Returns the dataset the given agent belongs to, if the agent is known.
+    pub fn agent_dataset(&self, agent_id: u64) -> Option<Arc<DatasetState>> {
+        let dataset_id = self.agents.get(&agent_id)?.dataset_id.clone();
+        self.datasets.get(&dataset_id).cloned()
+    }
+
+    /// Returns the latest reported state of each agent in a dataset.
+    pub fn dataset_agents(&self, dataset_id: &str) -> Vec<pb::AgentState> {
+        self.agents
+            .iter()
+            .filter(|entry| entry.dataset_id == dataset_id)
+            .map(|entry| entry.current_state.clone())
+            .collect()
+    }
+
     /// Safely updates the state of a known agent based on a new report.
     ///
     /// This performs an in-place update to avoid overwriting the `process_handle`.
@@ -95,7 +321,73 @@ impl CanonicalState {
         }
     }
 
-    /// Merges a bitmap of discovered points from an agent into the global reveal mask.
+    /// Gathers the current state of one dataset, creates a snapshot, and broadcasts
+    /// it to that dataset's subscribers.
+    pub fn broadcast_world_state(&self, dataset: &DatasetState) {
+        let agents = self.dataset_agents(&dataset.id);
+
+        let ticket = dataset.create_flight_ticket();
+
+        let snapshot = WorldStateSnapshot {
+            timestamp_ms: chrono::Utc::now().timestamp_millis(),
+            agents,
+            reveal_mask_flight_ticket: ticket,
+            paused: dataset.is_paused(),
+        };
+
+        // Sending on a watch channel never fails.
+        let _ = dataset.world_state_tx.send(snapshot);
+    }
+}
+
+impl DatasetState {
+    /// Creates an empty dataset over the given point cloud.
+    fn new(
+        id: &str,
+        point_cloud_metadata: PointCloudMetadata,
+        tasking: Option<Tasking>,
+        coverage_grid: Option<CoverageGrid>,
+        ticket_limits: FlightTicketLimits,
+    ) -> Self {
+        let (tx, _rx) = watch::channel(WorldStateSnapshot {
+            timestamp_ms: 0,
+            agents: Vec::new(),
+            reveal_mask_flight_ticket: Vec::new(),
+            paused: false,
+        });
+        Self {
+            id: id.into(),
+            reveal_mask: RwLock::new(RoaringBitmap::new()),
+            point_cloud_metadata,
+            world_state_tx: tx,
+            paused: AtomicBool::new(false),
+            tasking,
+            coverage_grid,
+            valid_flight_tickets: RwLock::new(FlightTickets::new(ticket_limits)),
+        }
+    }
+
+    /// Returns whether this dataset is currently paused.
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Acquire)
+    }
+
+    /// Sets the paused flag. Returns `true` if the value changed.
+    pub fn set_paused(&self, paused: bool) -> bool {
+        self.paused.swap(paused, Ordering::AcqRel) != paused
+    }
+
+    /// Returns the dataset to its unsurveyed state: clears the reveal mask,
+    /// invalidates all Flight tickets and drops every task claim.
+    pub fn reset(&self) {
+        self.reveal_mask.write().clear();
+        self.valid_flight_tickets.write().clear();
+        if let Some(tasking) = &self.tasking {
+            tasking.reset();
+        }
+    }
+
+    /// Merges a bitmap of discovered points from an agent into the dataset's reveal mask.
     ///
     /// Returns the number of newly discovered points.
     pub fn merge_discovered_points(&self, discovered: &[u8]) -> Result<u64, String> {
@@ -115,37 +407,21 @@ impl CanonicalState {
     }
 
     /// Creates a new, unique ticket for Arrow Flight and associates it with a
-    /// snapshot of the current reveal mask.
+    /// snapshot of the current reveal mask. Expired and excess older tickets are
+    /// evicted (see `FlightTickets::insert`).
+    ///
+    /// The ticket is 16 random bytes followed by the UTF-8 dataset ID, so the
+    /// Flight service can route it without a global lookup (see `ticket_dataset_id`).
     pub fn create_flight_ticket(&self) -> Vec<u8> {
-        let ticket = uuid::Uuid::new_v4().as_bytes().to_vec();
+        let mut ticket = uuid::Uuid::new_v4().as_bytes().to_vec();
+        ticket.extend_from_slice(self.id.as_bytes());
         let reveal_mask_snapshot = self.reveal_mask.read().clone();
         self.valid_flight_tickets
             .write()
             .insert(ticket.clone(), Arc::new(reveal_mask_snapshot));
-        // TODO: Add logic to prune old tickets from the map.
         ticket
     }
 
-    /// Gathers the current state, creates a snapshot, and broadcasts it to all subscribers.
-    pub fn broadcast_world_state(&self) {
-        let agents: Vec<pb::AgentState> = self
-            .agents
-            .iter()
-            .map(|entry| entry.current_state.clone())
-            .collect();
-
-        let ticket = self.create_flight_ticket();
-
-        let snapshot = WorldStateSnapshot {
-            timestamp_ms: chrono::Utc::now().timestamp_millis(),
-            agents,
-            reveal_mask_flight_ticket: ticket,
-        };
-
-        // Sending on a watch channel never fails.
-        let _ = self.world_state_tx.send(snapshot);
-    }
-
     /// Calculates the current map coverage ratio.
     pub fn get_coverage_ratio(&self) -> f64 {
         let revealed = self.reveal_mask.read().len();
@@ -156,3 +432,104 @@ impl CanonicalState {
         }
     }
 }
+
+/// Extracts the dataset ID embedded in a Flight ticket by `create_flight_ticket`.
+pub fn ticket_dataset_id(ticket: &[u8]) -> Option<&str> {
+    ticket.get(16..).and_then(|id| std::str::from_utf8(id).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tickets(ttl: Duration, max_tickets: usize) -> FlightTickets {
+        FlightTickets::new(FlightTicketLimits { ttl, max_tickets })
+    }
+
+    fn snapshot(points: &[u32]) -> Arc<RoaringBitmap> {
+        Arc::new(points.iter().copied().collect())
+    }
+
+    #[test]
+    fn ticket_insert_past_cap_evicts_oldest() {
+        let mut tickets = tickets(Duration::from_secs(60), 2);
+        tickets.insert(b"a".to_vec(), snapshot(&[1]));
+        tickets.insert(b"b".to_vec(), snapshot(&[2]));
+        tickets.insert(b"c".to_vec(), snapshot(&[3]));
+
+        assert!(tickets.get(b"a").is_none());
+        assert_eq!(tickets.get(b"b"), Some(snapshot(&[2])));
+        assert_eq!(tickets.get(b"c"), Some(snapshot(&[3])));
+        assert_eq!(tickets.order.len(), 2);
+    }
+
+    #[test]
+    fn ticket_expires_after_ttl() {
+        let mut tickets = tickets(Duration::from_millis(20), 8);
+        tickets.insert(b"old".to_vec(), snapshot(&[1]));
+        assert!(tickets.get(b"old").is_some());
+
+        std::thread::sleep(Duration::from_millis(40));
+        assert!(tickets.get(b"old").is_none());
+
+        // The next insert prunes it, though under the cap.
+        tickets.insert(b"new".to_vec(), snapshot(&[2]));
+        assert!(!tickets.snapshots.contains_key(b"old".as_slice()));
+        assert!(tickets.get(b"new").is_some());
+    }
+
+    #[test]
+    fn reset_clears_coverage_tickets_and_claims() {
+        // Four points within a metre of each other: one task cell.
+        let tile = hypc::HypcTile {
+            units_per_meter: 1000,
+            anchor_ecef_units: [4_177_000_000, 855_000_000, 4_727_000_000],
+            tile_key: None,
+            points_units: vec![[0, 0, 0], [100, 0, 0], [0, 100, 0], [0, 0, 100]],
+            points_encoding: hypc::PointsEncoding::Raw,
+            points_codec: hypc::PointsCodec::None,
+            labels: None,
+            colors: None,
+            geot: None,
+            smc1: None,
+            transform: None,
+            crc: false,
+        };
+        let mut grid = crate::tasking::TaskGridBuilder::new(10.0).unwrap();
+        grid.add_tile(&tile).unwrap();
+        let dataset = DatasetState::new(
+            "test",
+            PointCloudMetadata {
+                total_points: 4,
+                source: None,
+                tiles: Vec::new(),
+            },
+            Some(Tasking::new(grid.finish())),
+            None,
+            FlightTicketLimits {
+                ttl: Duration::from_secs(60),
+                max_tickets: 8,
+            },
+        );
+        let idle_agent = |agent_id| pb::AgentState {
+            agent_id,
+            mode: pb::AgentMode::AwaitingTask as i32,
+            ..Default::default()
+        };
+
+        let mut discovered = Vec::new();
+        snapshot(&[0, 1]).serialize_into(&mut discovered).unwrap();
+        assert_eq!(dataset.merge_discovered_points(&discovered), Ok(2));
+        assert_eq!(dataset.get_coverage_ratio(), 0.5);
+        let ticket = dataset.create_flight_ticket();
+        assert!(dataset.valid_flight_tickets.read().get(&ticket).is_some());
+        // Agent 1 claims the only cell, so there is nothing left for agent 2.
+        assert!(crate::tasking::allocate_task(&dataset, &idle_agent(1)).is_some());
+        assert!(crate::tasking::allocate_task(&dataset, &idle_agent(2)).is_none());
+
+        dataset.reset();
+        assert_eq!(dataset.get_coverage_ratio(), 0.0);
+        assert!(dataset.valid_flight_tickets.read().get(&ticket).is_none());
+        assert!(crate::tasking::allocate_task(&dataset, &idle_agent(2)).is_some());
+    }
+}
@@ -1,34 +1,256 @@
 // symtex/crates/sim_orchestrator/src/tasking.rs
-use crate::state::CanonicalState;
+use crate::state::DatasetState;
+use anyhow::Context;
 use api::gen::api::v1 as pb;
-use std::collections::HashMap;
+use parking_lot::Mutex;
+use roaring::RoaringBitmap;
+use std::collections::{HashMap, HashSet};
 
-/// Analyzes the current world state and allocates new tasks to agents.
+/// A cell counts as surveyed, and is no longer assigned, at this coverage.
+const CELL_COMPLETE_COVERAGE: f64 = 0.95;
+
+/// An idle agent this close to its claimed waypoint has finished the task.
+/// Agents report idle once within 1 m of their waypoint; the slack covers the
+/// report lagging the pose.
+const TASK_ARRIVAL_RADIUS_M: f64 = 5.0;
+
+/// One region of the survey: the points in an axis-aligned ECEF cube.
+struct TaskCell {
+    /// Mean position of the cell's points; the waypoint agents are sent to.
+    centroid_m: [f64; 3],
+    /// Global indices (as reported by agents) of the cell's points.
+    points: RoaringBitmap,
+}
+
+/// The survey area split into cells, for handing out waypoints.
 ///
-/// This function represents the "brains" of the mission planning. It is responsible
-/// for deciding what agents should do next based on the overall mission objectives
-/// and the current state of the simulation.
+/// Only cells that contain points exist, so a thin surface in a large bounding
+/// box does not produce mostly empty cells.
+pub struct TaskGrid {
+    cells: Vec<TaskCell>,
+}
+
+/// Accumulates the points of a dataset's tiles into [`TaskGrid`] cells.
 ///
-/// # Arguments
+/// Tiles must be added in the order an agent loads them, so that point indices
+/// match the ones agents report (see `sim_agent`'s `load_point_cloud`).
+pub struct TaskGridBuilder {
+    cell_size_m: f64,
+    /// Coordinate sum and points of each cell, by cell key.
+    cells: HashMap<[i64; 3], ([f64; 3], RoaringBitmap)>,
+    /// Global index of the next point.
+    index: u32,
+}
+
+impl TaskGridBuilder {
+    /// Starts an empty grid of `cell_size_m` cubes aligned to the ECEF axes.
+    pub fn new(cell_size_m: f64) -> anyhow::Result<Self> {
+        anyhow::ensure!(
+            cell_size_m.is_finite() && cell_size_m > 0.0,
+            "task cell size must be positive, got {}",
+            cell_size_m
+        );
+        Ok(Self {
+            cell_size_m,
+            cells: HashMap::new(),
+            index: 0,
+        })
+    }
+
+    /// Adds the next tile's points.
+    pub fn add_tile(&mut self, tile: &hypc::HypcTile) -> anyhow::Result<()> {
+        for p in tile.iter_points_ecef_m() {
+            let key = p.map(|c| (c / self.cell_size_m).floor() as i64);
+            let (sum, points) = self.cells.entry(key).or_default();
+            for (s, c) in sum.iter_mut().zip(p) {
+                *s += c;
+            }
+            points.insert(self.index);
+            self.index = self
+                .index
+                .checked_add(1)
+                .context("point count exceeds the u32 index space")?;
+        }
+        Ok(())
+    }
+
+    /// The grid of every cell that received a point.
+    pub fn finish(self) -> TaskGrid {
+        // Sort by cell key so ties in `select_cell` break the same way every run.
+        let mut cells: Vec<_> = self.cells.into_iter().collect();
+        cells.sort_unstable_by_key(|(key, _)| *key);
+        let cells = cells
+            .into_iter()
+            .map(|(_, (sum, points))| TaskCell {
+                centroid_m: sum.map(|s| s / points.len() as f64),
+                points,
+            })
+            .collect();
+        TaskGrid { cells }
+    }
+}
+
+impl TaskGrid {
+    /// Number of non-empty cells.
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// Picks the cell to survey next from `from_m`: the least covered cell that
+    /// is not yet complete and not `excluded`, the nearest one on a tie.
+    fn select_cell(
+        &self,
+        reveal_mask: &RoaringBitmap,
+        from_m: [f64; 3],
+        excluded: impl Fn(usize) -> bool,
+    ) -> Option<usize> {
+        self.cells
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| !excluded(i))
+            .map(|(i, cell)| {
+                let coverage =
+                    reveal_mask.intersection_len(&cell.points) as f64 / cell.points.len() as f64;
+                (i, coverage, distance_sq(cell.centroid_m, from_m))
+            })
+            .filter(|&(_, coverage, _)| coverage < CELL_COMPLETE_COVERAGE)
+            .min_by(|a, b| a.1.total_cmp(&b.1).then(a.2.total_cmp(&b.2)))
+            .map(|(i, _, _)| i)
+    }
+}
+
+/// Per-dataset task bookkeeping: the grid and which agent works on which cell.
+pub struct Tasking {
+    grid: TaskGrid,
+    assignments: Mutex<Assignments>,
+}
+
+#[derive(Default)]
+struct Assignments {
+    /// The cell each agent is currently sent to, by `agent_id`.
+    claims: HashMap<u64, usize>,
+    /// Cells an agent has reached. Not reassigned even if still short of
+    /// complete, since another visit would see the same points.
+    visited: HashSet<usize>,
+}
+
+impl Tasking {
+    pub fn new(grid: TaskGrid) -> Self {
+        Self {
+            grid,
+            assignments: Mutex::new(Assignments::default()),
+        }
+    }
+
+    /// Drops an agent's claim so its cell can be handed to another agent.
+    pub fn release(&self, agent_id: u64) {
+        self.assignments.lock().claims.remove(&agent_id);
+    }
+
+    /// Forgets all claims and visited cells, as after a simulation reset.
+    pub fn reset(&self) {
+        *self.assignments.lock() = Assignments::default();
+    }
+}
+
+/// Decides what an agent should do next, given the state it just reported.
 ///
-/// * `_state` - A read-only reference to the `CanonicalState` of the simulation.
+/// Only idle (`AWAITING_TASK`) agents are tasked. An idle agent that has not yet
+/// reached its claimed cell is sent there again, so a task lost in flight is
+/// repeated rather than replaced. Once it arrives, the cell is marked visited
+/// and the agent gets the least covered unclaimed, unvisited cell (see
+/// `TaskGrid::select_cell`), whose point centroid becomes the waypoint.
 ///
 /// # Returns
 ///
-/// A `HashMap` where the key is the `agent_id` and the value is the `Task`
-/// assigned to that agent. Agents not present in the map are not assigned a new task.
-///
-/// # Implementation Note
-///
-/// As per the project specification, this is a placeholder implementation. The focus
-/// is on the architecture that enables tasking, not the complexity of the tasking
-/// algorithm itself. Future work could involve implementing algorithms such as:
-///
-/// - Frontier-based exploration (finding the edges of the known map).
-/// - Greedy allocation (assigning agents to the nearest unexplored area).
-/// - Coverage planning algorithms.
-/// - Dynamic tasking based on operator commands.
-pub fn allocate_tasks(_state: &CanonicalState) -> HashMap<u64, pb::Task> {
-    // Placeholder implementation: No tasks are allocated at this time.
-    HashMap::new()
+/// The `Task` to send the agent, or `None` if it is busy, the dataset is paused
+/// or has no task grid, or every cell is complete, visited or claimed.
+pub fn allocate_task(dataset: &DatasetState, agent: &pb::AgentState) -> Option<pb::Task> {
+    if dataset.is_paused() || agent.mode() != pb::AgentMode::AwaitingTask {
+        return None;
+    }
+    let tasking = dataset.tasking.as_ref()?;
+    let position = agent
+        .position_ecef_m
+        .as_ref()
+        .map_or([0.0; 3], |p| [p.x, p.y, p.z]);
+
+    let mut assignments = tasking.assignments.lock();
+    if let Some(&cell) = assignments.claims.get(&agent.agent_id) {
+        let waypoint = tasking.grid.cells[cell].centroid_m;
+        if distance_sq(waypoint, position) > TASK_ARRIVAL_RADIUS_M * TASK_ARRIVAL_RADIUS_M {
+            return Some(waypoint_task(waypoint));
+        }
+        assignments.visited.insert(cell);
+        assignments.claims.remove(&agent.agent_id);
+    }
+
+    let reveal_mask = dataset.reveal_mask.read();
+    let Assignments { claims, visited } = &mut *assignments;
+    let cell = tasking.grid.select_cell(&reveal_mask, position, |i| {
+        visited.contains(&i) || claims.values().any(|&c| c == i)
+    })?;
+    claims.insert(agent.agent_id, cell);
+
+    let waypoint = tasking.grid.cells[cell].centroid_m;
+    tracing::info!(
+        agent_id = agent.agent_id,
+        dataset_id = dataset.id,
+        cell,
+        ?waypoint,
+        "Assigned survey cell"
+    );
+    Some(waypoint_task(waypoint))
+}
+
+fn waypoint_task(waypoint_m: [f64; 3]) -> pb::Task {
+    pb::Task {
+        target_waypoint_ecef_m: Some(pb::Vec3m {
+            x: waypoint_m[0],
+            y: waypoint_m[1],
+            z: waypoint_m[2],
+        }),
+    }
+}
+
+fn distance_sq(a: [f64; 3], b: [f64; 3]) -> f64 {
+    (0..3).map(|i| (a[i] - b[i]) * (a[i] - b[i])).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Three 10-point cells 100 m apart along the x axis.
+    fn grid() -> TaskGrid {
+        TaskGrid {
+            cells: (0..3u32)
+                .map(|i| TaskCell {
+                    centroid_m: [100.0 * i as f64, 0.0, 0.0],
+                    points: (10 * i..10 * i + 10).collect(),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn select_cell_prefers_least_covered_then_nearest() {
+        let grid = grid();
+        let none = |_: usize| false;
+
+        // Cell 0 complete, cell 1 half revealed, cell 2 at 30%.
+        let mut mask: RoaringBitmap = (0..10).chain(10..15).chain(20..23).collect();
+        assert_eq!(grid.select_cell(&mask, [0.0; 3], none), Some(2));
+        assert_eq!(grid.select_cell(&mask, [0.0; 3], |i| i == 2), Some(1));
+        assert_eq!(grid.select_cell(&mask, [0.0; 3], |i| i != 0), None);
+
+        // Cells 1 and 2 tied at 50%: the nearer one wins.
+        mask.extend(23..25);
+        assert_eq!(grid.select_cell(&mask, [0.0; 3], none), Some(1));
+        assert_eq!(grid.select_cell(&mask, [250.0, 0.0, 0.0], none), Some(2));
+
+        // Nothing left once every cell reaches CELL_COMPLETE_COVERAGE.
+        mask.extend(0..30);
+        assert_eq!(grid.select_cell(&mask, [0.0; 3], none), None);
+    }
 }